@@ -0,0 +1,13 @@
+#![no_main]
+
+use devlogs_feed::utils::bluesky::parse_bluesky_url;
+use devlogs_feed::utils::ids::AtUri;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let Some(uri) = parse_bluesky_url(input) else {
+        return;
+    };
+    assert_eq!(uri.as_str().parse::<AtUri>().ok(), Some(uri.clone()));
+    let _ = (uri.did(), uri.collection(), uri.rkey(), uri.permalink());
+});
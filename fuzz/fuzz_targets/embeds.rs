@@ -0,0 +1,14 @@
+#![no_main]
+
+use devlogs_feed::embeds;
+use devlogs_feed::scoring::MediaInfo;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+    MediaInfo::from_embed_view(Some(&value));
+    let embed = embeds::from_record(value);
+    MediaInfo::from_embed(embed.as_ref());
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use devlogs_feed::scoring::MediaInfo;
+use devlogs_feed::utils::bluesky::{
+    extract_facet_links, extract_facet_mentions, extract_facet_tags, Facet,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(facets) = serde_json::from_slice::<Option<Vec<Facet>>>(data) else {
+        return;
+    };
+    let links = extract_facet_links(&facets);
+    extract_facet_mentions(&facets);
+    extract_facet_tags(&facets);
+
+    let media = MediaInfo::default().with_facets(&facets);
+    assert_eq!(media.facet_links, links);
+});
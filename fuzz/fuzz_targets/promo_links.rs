@@ -0,0 +1,12 @@
+#![no_main]
+
+use devlogs_feed::scoring::content::{count_links, has_links, is_promo_domain};
+use devlogs_feed::scoring::MediaInfo;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let (total, promo) = count_links(text);
+    assert!(promo <= total);
+    has_links(text, &MediaInfo::default());
+    is_promo_domain(text);
+});
@@ -0,0 +1,5 @@
+CREATE TABLE post_embeddings (
+    post_uri TEXT PRIMARY KEY NOT NULL,
+    embedding BLOB NOT NULL,
+    FOREIGN KEY (post_uri) REFERENCES posts(uri) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+CREATE TABLE post_embeddings_old (
+    post_uri TEXT PRIMARY KEY NOT NULL,
+    embedding BLOB NOT NULL,
+    FOREIGN KEY (post_uri) REFERENCES posts(uri) ON DELETE CASCADE
+);
+
+INSERT INTO post_embeddings_old (post_uri, embedding)
+SELECT post_uri, embedding FROM post_embeddings
+WHERE post_uri IN (SELECT uri FROM posts);
+
+DROP TABLE post_embeddings;
+ALTER TABLE post_embeddings_old RENAME TO post_embeddings;
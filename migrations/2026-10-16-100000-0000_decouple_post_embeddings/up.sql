@@ -0,0 +1,13 @@
+CREATE TABLE post_embeddings_new (
+    post_uri TEXT PRIMARY KEY NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at BIGINT NOT NULL DEFAULT 0
+);
+
+INSERT INTO post_embeddings_new (post_uri, embedding)
+SELECT post_uri, embedding FROM post_embeddings;
+
+DROP TABLE post_embeddings;
+ALTER TABLE post_embeddings_new RENAME TO post_embeddings;
+
+CREATE INDEX idx_post_embeddings_created_at ON post_embeddings(created_at);
@@ -0,0 +1,5 @@
+CREATE TABLE handles (
+    did TEXT PRIMARY KEY NOT NULL,
+    handle TEXT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE handles DROP COLUMN bio_score;
+ALTER TABLE handles DROP COLUMN description;
@@ -0,0 +1,2 @@
+ALTER TABLE handles ADD COLUMN description TEXT;
+ALTER TABLE handles ADD COLUMN bio_score REAL NOT NULL DEFAULT 0;
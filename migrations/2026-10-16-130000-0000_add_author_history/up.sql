@@ -0,0 +1,8 @@
+CREATE TABLE author_history (
+    post_uri TEXT PRIMARY KEY NOT NULL,
+    author_did TEXT NOT NULL,
+    accepted_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_author_history_author ON author_history(author_did, accepted_at);
+CREATE INDEX idx_author_history_accepted_at ON author_history(accepted_at);
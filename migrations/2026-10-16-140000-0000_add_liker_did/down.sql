@@ -0,0 +1,2 @@
+DROP INDEX idx_likes_liker_did;
+ALTER TABLE likes DROP COLUMN liker_did;
@@ -0,0 +1,7 @@
+ALTER TABLE likes ADD COLUMN liker_did TEXT;
+
+UPDATE likes
+SET liker_did = substr(like_uri, 6, instr(substr(like_uri, 6), '/') - 1)
+WHERE like_uri LIKE 'at://%/%';
+
+CREATE INDEX idx_likes_liker_did ON likes(liker_did);
@@ -0,0 +1,9 @@
+CREATE TABLE language_stats (
+    lang TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    rejected INTEGER NOT NULL DEFAULT 0,
+    relevant INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (lang, day)
+);
+
+CREATE INDEX idx_language_stats_day ON language_stats(day);
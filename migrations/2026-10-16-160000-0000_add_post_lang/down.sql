@@ -0,0 +1,2 @@
+DROP INDEX idx_posts_lang;
+ALTER TABLE posts DROP COLUMN lang;
@@ -0,0 +1,3 @@
+ALTER TABLE posts ADD COLUMN lang TEXT NOT NULL DEFAULT 'en';
+
+CREATE INDEX idx_posts_lang ON posts(lang, timestamp);
@@ -0,0 +1,9 @@
+CREATE TABLE coverage_gaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    started_at BIGINT NOT NULL,
+    ended_at BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    backfilled_at BIGINT
+);
+
+CREATE INDEX idx_coverage_gaps_pending ON coverage_gaps(backfilled_at, ended_at);
@@ -0,0 +1 @@
+ALTER TABLE posts DROP COLUMN mention_count;
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN mention_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN thread_length INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,15 @@
+CREATE VIRTUAL TABLE posts_fts USING fts5(uri UNINDEXED, text, tokenize = 'porter unicode61');
+
+INSERT INTO posts_fts (uri, text) SELECT uri, text FROM posts;
+
+CREATE TRIGGER posts_fts_insert AFTER INSERT ON posts BEGIN
+    INSERT INTO posts_fts (uri, text) VALUES (new.uri, new.text);
+END;
+
+CREATE TRIGGER posts_fts_delete AFTER DELETE ON posts BEGIN
+    DELETE FROM posts_fts WHERE uri = old.uri;
+END;
+
+CREATE TRIGGER posts_fts_update AFTER UPDATE OF text ON posts BEGIN
+    UPDATE posts_fts SET text = new.text WHERE uri = old.uri;
+END;
@@ -0,0 +1,16 @@
+CREATE TABLE archive_posts (
+    uri TEXT PRIMARY KEY NOT NULL,
+    text_hash TEXT NOT NULL,
+    author_did TEXT,
+    lang TEXT NOT NULL DEFAULT 'en',
+    timestamp BIGINT NOT NULL,
+    priority FLOAT NOT NULL,
+    confidence TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    like_count INTEGER NOT NULL DEFAULT 0,
+    reply_count INTEGER NOT NULL DEFAULT 0,
+    repost_count INTEGER NOT NULL DEFAULT 0,
+    archived_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_archive_posts_archived_at ON archive_posts(archived_at);
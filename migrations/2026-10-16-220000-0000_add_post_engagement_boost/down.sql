@@ -0,0 +1 @@
+ALTER TABLE posts DROP COLUMN engagement_boost;
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN engagement_boost REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+CREATE TABLE admin_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    actor TEXT NOT NULL,
+    role TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_admin_audit_created_at ON admin_audit(created_at);
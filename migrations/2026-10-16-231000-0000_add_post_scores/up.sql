@@ -0,0 +1,7 @@
+CREATE TABLE post_scores (
+    uri TEXT PRIMARY KEY NOT NULL,
+    explanation TEXT NOT NULL,
+    scored_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_post_scores_scored_at ON post_scores(scored_at);
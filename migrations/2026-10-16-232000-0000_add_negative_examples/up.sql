@@ -0,0 +1,9 @@
+CREATE TABLE negative_examples (
+    post_uri TEXT PRIMARY KEY NOT NULL,
+    text TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    request_less_count INTEGER NOT NULL,
+    harvested_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_negative_examples_harvested_at ON negative_examples(harvested_at);
@@ -0,0 +1,13 @@
+CREATE TABLE author_stats (
+    author_did TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    assessed INTEGER NOT NULL DEFAULT 0,
+    accepted INTEGER NOT NULL DEFAULT 0,
+    score_sum REAL NOT NULL DEFAULT 0,
+    request_more INTEGER NOT NULL DEFAULT 0,
+    request_less INTEGER NOT NULL DEFAULT 0,
+    spam_flags INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (author_did, day)
+);
+
+CREATE INDEX idx_author_stats_day ON author_stats(day);
@@ -0,0 +1,40 @@
+CREATE TABLE author_stats_old (
+    author_did TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    assessed INTEGER NOT NULL DEFAULT 0,
+    accepted INTEGER NOT NULL DEFAULT 0,
+    score_sum REAL NOT NULL DEFAULT 0,
+    request_more INTEGER NOT NULL DEFAULT 0,
+    request_less INTEGER NOT NULL DEFAULT 0,
+    spam_flags INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (author_did, day)
+);
+INSERT INTO author_stats_old
+    SELECT author_did, day, SUM(assessed), SUM(accepted), SUM(score_sum), SUM(request_more),
+        SUM(request_less), SUM(spam_flags)
+    FROM author_stats
+    GROUP BY author_did, day;
+DROP TABLE author_stats;
+ALTER TABLE author_stats_old RENAME TO author_stats;
+CREATE INDEX idx_author_stats_day ON author_stats(day);
+
+CREATE TABLE language_stats_old (
+    lang TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    rejected INTEGER NOT NULL DEFAULT 0,
+    relevant INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (lang, day)
+);
+INSERT INTO language_stats_old
+    SELECT lang, day, SUM(rejected), SUM(relevant)
+    FROM language_stats
+    GROUP BY lang, day;
+DROP TABLE language_stats;
+ALTER TABLE language_stats_old RENAME TO language_stats;
+CREATE INDEX idx_language_stats_day ON language_stats(day);
+
+ALTER TABLE archive_posts DROP COLUMN config_id;
+ALTER TABLE post_scores DROP COLUMN config_id;
+ALTER TABLE posts DROP COLUMN config_id;
+
+DROP TABLE config_history;
@@ -0,0 +1,49 @@
+CREATE TABLE config_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    hash TEXT NOT NULL,
+    settings TEXT NOT NULL,
+    profile TEXT,
+    activated_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_config_history_hash ON config_history(hash);
+
+ALTER TABLE posts ADD COLUMN config_id INTEGER;
+ALTER TABLE post_scores ADD COLUMN config_id INTEGER;
+ALTER TABLE archive_posts ADD COLUMN config_id INTEGER;
+
+-- Daily stats are kept per config, with 0 for rows recorded before config history existed.
+CREATE TABLE language_stats_new (
+    lang TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    config_id INTEGER NOT NULL DEFAULT 0,
+    rejected INTEGER NOT NULL DEFAULT 0,
+    relevant INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (lang, day, config_id)
+);
+INSERT INTO language_stats_new (lang, day, rejected, relevant)
+    SELECT lang, day, rejected, relevant FROM language_stats;
+DROP TABLE language_stats;
+ALTER TABLE language_stats_new RENAME TO language_stats;
+CREATE INDEX idx_language_stats_day ON language_stats(day);
+
+CREATE TABLE author_stats_new (
+    author_did TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    config_id INTEGER NOT NULL DEFAULT 0,
+    assessed INTEGER NOT NULL DEFAULT 0,
+    accepted INTEGER NOT NULL DEFAULT 0,
+    score_sum REAL NOT NULL DEFAULT 0,
+    request_more INTEGER NOT NULL DEFAULT 0,
+    request_less INTEGER NOT NULL DEFAULT 0,
+    spam_flags INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (author_did, day, config_id)
+);
+INSERT INTO author_stats_new (
+    author_did, day, assessed, accepted, score_sum, request_more, request_less, spam_flags
+)
+    SELECT author_did, day, assessed, accepted, score_sum, request_more, request_less, spam_flags
+    FROM author_stats;
+DROP TABLE author_stats;
+ALTER TABLE author_stats_new RENAME TO author_stats;
+CREATE INDEX idx_author_stats_day ON author_stats(day);
@@ -0,0 +1,7 @@
+-- How far back and forward each backfill search query has been walked, so restarts resume.
+CREATE TABLE backfill_state (
+    query TEXT PRIMARY KEY NOT NULL,
+    oldest_at BIGINT NOT NULL,
+    newest_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
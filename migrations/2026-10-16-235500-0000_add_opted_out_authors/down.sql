@@ -0,0 +1 @@
+DROP TABLE opted_out_authors;
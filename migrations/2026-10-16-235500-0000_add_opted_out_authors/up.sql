@@ -0,0 +1,6 @@
+-- Authors who tagged a post with an opt-out hashtag; none of their posts are stored again.
+CREATE TABLE opted_out_authors (
+    did TEXT PRIMARY KEY NOT NULL,
+    post_uri TEXT NOT NULL,
+    opted_out_at BIGINT NOT NULL
+);
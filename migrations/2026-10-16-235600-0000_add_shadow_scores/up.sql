@@ -0,0 +1,12 @@
+-- What the shadow scoring profile would have done with each scored firehose post, next to the
+-- live verdict. Kept for `feed.cutoff_hours`.
+CREATE TABLE shadow_scores (
+    uri TEXT PRIMARY KEY NOT NULL,
+    profile TEXT NOT NULL,
+    live_priority REAL NOT NULL,
+    shadow_priority REAL NOT NULL,
+    live_accepted INTEGER NOT NULL,
+    shadow_accepted INTEGER NOT NULL,
+    scored_at BIGINT NOT NULL
+);
+CREATE INDEX idx_shadow_scores_profile ON shadow_scores(profile, scored_at);
@@ -0,0 +1 @@
+DROP TABLE like_affinities;
@@ -0,0 +1,11 @@
+-- How much each feed user has liked each topic and author, decayed by `feed.like_affinity`
+-- half-life. `kind` is `topic` or `author`.
+CREATE TABLE like_affinities (
+    user_did TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    score REAL NOT NULL,
+    updated_at BIGINT NOT NULL,
+    PRIMARY KEY (user_did, kind, value)
+);
+CREATE INDEX idx_like_affinities_updated_at ON like_affinities(updated_at);
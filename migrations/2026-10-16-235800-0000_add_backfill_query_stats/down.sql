@@ -0,0 +1 @@
+DROP TABLE backfill_query_stats;
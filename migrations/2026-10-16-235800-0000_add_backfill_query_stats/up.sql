@@ -0,0 +1,8 @@
+-- Posts each backfill search query fetched and how many of them were accepted, per UTC day.
+CREATE TABLE backfill_query_stats (
+    query TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    searched INTEGER NOT NULL,
+    accepted INTEGER NOT NULL,
+    PRIMARY KEY (query, day)
+);
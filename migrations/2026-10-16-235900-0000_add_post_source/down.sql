@@ -0,0 +1,2 @@
+ALTER TABLE archive_posts DROP COLUMN source;
+ALTER TABLE posts DROP COLUMN source;
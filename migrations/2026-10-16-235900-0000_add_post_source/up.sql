@@ -0,0 +1,4 @@
+-- How each post got into the database: `firehose`, `backfill`, `search-poller` (gap backfill)
+-- or `manual` (snapshot import). Rows from before this was tracked are `unknown`.
+ALTER TABLE posts ADD COLUMN source TEXT NOT NULL DEFAULT 'unknown';
+ALTER TABLE archive_posts ADD COLUMN source TEXT NOT NULL DEFAULT 'unknown';
@@ -0,0 +1,3 @@
+-- When each like was received, so the brigading guard can look at recent likes only. Likes
+-- from before this was tracked read as 0 and fall outside every burst window.
+ALTER TABLE likes ADD COLUMN liked_at BIGINT NOT NULL DEFAULT 0;
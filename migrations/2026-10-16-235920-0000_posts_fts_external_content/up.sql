@@ -0,0 +1,27 @@
+-- Index posts.text in place of a copy keyed by an unindexed uri column, which made every post
+-- delete scan the whole index. Rows are tied to posts by rowid.
+DROP TRIGGER posts_fts_update;
+DROP TRIGGER posts_fts_delete;
+DROP TRIGGER posts_fts_insert;
+DROP TABLE posts_fts;
+CREATE VIRTUAL TABLE posts_fts USING fts5(
+    text,
+    content = 'posts',
+    content_rowid = 'rowid',
+    tokenize = 'porter unicode61'
+);
+
+INSERT INTO posts_fts (posts_fts) VALUES ('rebuild');
+
+CREATE TRIGGER posts_fts_insert AFTER INSERT ON posts BEGIN
+    INSERT INTO posts_fts (rowid, text) VALUES (new.rowid, new.text);
+END;
+
+CREATE TRIGGER posts_fts_delete AFTER DELETE ON posts BEGIN
+    INSERT INTO posts_fts (posts_fts, rowid, text) VALUES ('delete', old.rowid, old.text);
+END;
+
+CREATE TRIGGER posts_fts_update AFTER UPDATE OF text ON posts BEGIN
+    INSERT INTO posts_fts (posts_fts, rowid, text) VALUES ('delete', old.rowid, old.text);
+    INSERT INTO posts_fts (rowid, text) VALUES (new.rowid, new.text);
+END;
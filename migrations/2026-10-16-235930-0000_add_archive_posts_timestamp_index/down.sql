@@ -0,0 +1 @@
+DROP INDEX idx_archive_posts_timestamp;
@@ -0,0 +1 @@
+CREATE INDEX idx_archive_posts_timestamp ON archive_posts(timestamp);
@@ -0,0 +1,779 @@
+use crate::config_history;
+use crate::db::{
+    self, AuditEntry, ConfigSnapshot, DbPool, NewAuditEntry, NewBlockedAuthor, Post, ShadowScore,
+    ShadowSummary,
+};
+use crate::engagement::EngagementTracker;
+use crate::explain::ScoreExplanation;
+use crate::negatives::NegativeSet;
+use crate::scoring::{
+    author_reputation, calculate_priority, extract_content_signals, hashtag_matches_for,
+    keyword_matches_for, weighted_keyword_score, MLHandle, MediaInfo, PrioritySignals,
+    SharedVectorIndex, TopicLabel,
+};
+use crate::settings::{settings, AdminRole, AdminToken};
+use crate::utils::ids::Did;
+use crate::utils::logs;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{delete, get, post};
+use axum::{Extension, Json, Router};
+use chrono::Utc;
+use diesel::sqlite::SqliteConnection;
+use diesel::Connection;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+pub const ADMIN_TOKEN_ENV: &str = "ADMIN_TOKEN";
+
+const AUDIT_DEFAULT_LIMIT: i64 = 100;
+const AUDIT_MAX_LIMIT: i64 = 500;
+const SHADOW_DEFAULT_HOURS: i64 = 24;
+
+#[derive(Clone)]
+pub struct AdminState {
+    pool: DbPool,
+    ml_handle: MLHandle,
+    vector_index: SharedVectorIndex,
+}
+
+/// The token holder making a request, as resolved by `authorize`.
+#[derive(Debug, Clone)]
+pub struct Admin {
+    pub name: String,
+    pub role: AdminRole,
+}
+
+impl AdminState {
+    pub fn new(pool: DbPool, ml_handle: MLHandle, vector_index: SharedVectorIndex) -> Self {
+        Self {
+            pool,
+            ml_handle,
+            vector_index,
+        }
+    }
+
+    /// Drops deleted posts from the vector index so related-post lookups stop returning them.
+    fn forget_posts(&self, uris: &[String]) {
+        if let Ok(mut index) = self.vector_index.write() {
+            for uri in uris {
+                index.remove(uri);
+            }
+        }
+    }
+
+    /// Records an action in the audit log and prints it.
+    fn audit(&self, admin: &Admin, action: &str, target: &str) {
+        logs::log_admin_action(&admin.name, action, target);
+        if let Ok(mut conn) = self.pool.get() {
+            let _ = db::insert_audit_entry(
+                &mut conn,
+                NewAuditEntry {
+                    actor: admin.name.clone(),
+                    role: admin.role.to_string(),
+                    action: action.to_string(),
+                    target: target.to_string(),
+                    created_at: Utc::now().timestamp(),
+                },
+            );
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockAuthorRequest {
+    pub did: String,
+    /// The post that prompted the block, kept for reference.
+    pub post_uri: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockAuthorResponse {
+    pub did: String,
+    pub deleted_posts: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockAuthorsRequest {
+    pub dids: Vec<Did>,
+    pub post_uri: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockAuthorsResponse {
+    /// Authors that weren't already blocked.
+    pub blocked: usize,
+    pub deleted_posts: usize,
+}
+
+/// Posts must match every filter given, and at least one is required.
+#[derive(Debug, Deserialize)]
+pub struct DeleteMatchingRequest {
+    /// Text the post contains, ignoring ASCII case.
+    pub pattern: Option<String>,
+    /// A topic label as shown in the feed context, like `"sharing a link"`.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeDomainRequest {
+    pub domain: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostRequest {
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedResponse {
+    pub deleted: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RescoreResponse {
+    pub uri: String,
+    pub previous_priority: f32,
+    pub priority: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExplainQuery {
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExplainResponse {
+    pub uri: String,
+    /// Whether the post is in the feed now, which it may have left since it was scored.
+    pub in_feed: bool,
+    /// How the post was ingested, while it's in the feed.
+    pub source: Option<String>,
+    pub scored_at: i64,
+    pub explanation: ScoreExplanation,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub limit: Option<i64>,
+    /// Only entries older than this id, to page back through the log.
+    pub before: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditResponse {
+    pub entries: Vec<AuditEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigHistoryResponse {
+    pub configs: Vec<ConfigSnapshot>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShadowQuery {
+    pub hours: Option<i64>,
+    /// How many of the posts the profiles disagreed on to list.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShadowResponse {
+    pub enabled: bool,
+    pub profile: String,
+    pub since: i64,
+    pub summary: ShadowSummary,
+    pub disagreements: Vec<ShadowScore>,
+}
+
+/// Serves the admin API on its own listener, apart from the public API.
+pub async fn serve(state: AdminState, addr: SocketAddr) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    logs::log_admin_api_listening(&addr);
+    axum::serve(listener, router(state)).await
+}
+
+pub fn router(state: AdminState) -> Router {
+    let analyst = Router::new()
+        .route("/admin/v1/audit", get(audit_log))
+        .route("/admin/v1/config-history", get(config_history))
+        .route("/admin/v1/posts/explain", get(explain_post))
+        .route("/admin/v1/shadow", get(shadow_report));
+    let moderator = Router::new()
+        .route("/admin/v1/authors/block", post(block_author))
+        .route("/admin/v1/spammers/{did}", delete(unflag_spammer))
+        .route("/admin/v1/opt-outs/{did}", delete(remove_opt_out))
+        .route("/admin/v1/posts/delete", post(delete_post))
+        .route("/admin/v1/posts/rescore", post(rescore_post));
+    let owner = Router::new()
+        .route("/admin/v1/authors/block-batch", post(block_authors))
+        .route(
+            "/admin/v1/posts/delete-matching",
+            post(delete_matching_posts),
+        )
+        .route("/admin/v1/posts/purge-domain", post(purge_domain));
+
+    Router::new()
+        .merge(require_role(AdminRole::Analyst, analyst))
+        .merge(require_role(AdminRole::Moderator, moderator))
+        .merge(require_role(AdminRole::Owner, owner))
+        .with_state(state)
+}
+
+fn require_role(role: AdminRole, routes: Router<AdminState>) -> Router<AdminState> {
+    routes.route_layer(middleware::from_fn_with_state(role, authorize))
+}
+
+/// The tokens in `api.admin_tokens`, plus `ADMIN_TOKEN` as an owner. Read for each request, so
+/// a settings reload adds or revokes tokens without a restart. With none, every request is
+/// refused.
+fn configured_tokens() -> Vec<AdminToken> {
+    let mut tokens = settings().api.admin_tokens.clone();
+    if let Ok(token) = std::env::var(ADMIN_TOKEN_ENV) {
+        tokens.push(AdminToken {
+            name: ADMIN_TOKEN_ENV.to_string(),
+            token,
+            role: AdminRole::Owner,
+        });
+    }
+    tokens
+}
+
+fn authenticate(tokens: &[AdminToken], provided: &str) -> Option<Admin> {
+    tokens
+        .iter()
+        .filter(|t| !t.token.is_empty())
+        .find(|t| token_matches(&t.token, provided))
+        .map(|t| Admin {
+            name: t.name.clone(),
+            role: t.role,
+        })
+}
+
+/// Compares without short-circuiting so response times don't leak how much of a guess matched.
+fn token_matches(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Rejects requests without a known token (401) or whose token's role is below `required` (403),
+/// and hands the [`Admin`] on to the handler.
+async fn authorize(
+    State(required): State<AdminRole>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let admin = authenticate(&configured_tokens(), provided).ok_or(StatusCode::UNAUTHORIZED)?;
+    if admin.role < required {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    request.extensions_mut().insert(admin);
+    Ok(next.run(request).await)
+}
+
+/// Why a post was accepted or rejected when it was last scored.
+async fn explain_post(
+    State(state): State<AdminState>,
+    Query(query): Query<ExplainQuery>,
+) -> Result<Json<ExplainResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let (explanation, scored_at) = db::get_post_score(&mut conn, &query.uri)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let explanation =
+        serde_json::from_str(&explanation).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let source = db::get_posts_by_uris(&mut conn, std::slice::from_ref(&query.uri))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .next()
+        .map(|post| post.source);
+
+    Ok(Json(ExplainResponse {
+        uri: query.uri,
+        in_feed: source.is_some(),
+        source,
+        scored_at,
+        explanation,
+    }))
+}
+
+async fn audit_log(
+    State(state): State<AdminState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let limit = query
+        .limit
+        .unwrap_or(AUDIT_DEFAULT_LIMIT)
+        .clamp(1, AUDIT_MAX_LIMIT);
+    let entries = db::get_audit_entries(&mut conn, limit, query.before)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(AuditResponse { entries }))
+}
+
+/// Settings that have been active, newest first, paged like the audit log.
+async fn config_history(
+    State(state): State<AdminState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<ConfigHistoryResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let limit = query
+        .limit
+        .unwrap_or(AUDIT_DEFAULT_LIMIT)
+        .clamp(1, AUDIT_MAX_LIMIT);
+    let configs = db::get_config_history(&mut conn, limit, query.before)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ConfigHistoryResponse { configs }))
+}
+
+/// How the shadow scoring profile's verdicts compare with the live ones over the last `hours`.
+async fn shadow_report(
+    State(state): State<AdminState>,
+    Query(query): Query<ShadowQuery>,
+) -> Result<Json<ShadowResponse>, StatusCode> {
+    let shadow = settings().scoring.shadow.clone();
+    let hours = query.hours.unwrap_or(SHADOW_DEFAULT_HOURS).max(1);
+    let since = Utc::now().timestamp() - hours * 3600;
+    let limit = query
+        .limit
+        .unwrap_or(AUDIT_DEFAULT_LIMIT)
+        .clamp(1, AUDIT_MAX_LIMIT);
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let summary = db::shadow_summary(&mut conn, &shadow.profile, since)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disagreements = db::shadow_disagreements(&mut conn, &shadow.profile, since, limit)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ShadowResponse {
+        enabled: shadow.enabled,
+        profile: shadow.profile,
+        since,
+        summary,
+        disagreements,
+    }))
+}
+
+async fn block_author(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Json(request): Json<BlockAuthorRequest>,
+) -> Result<Json<BlockAuthorResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    db::block_author(
+        &mut conn,
+        NewBlockedAuthor {
+            did: request.did.clone(),
+            post_uri: request.post_uri.unwrap_or_default(),
+            blocked_at: Utc::now().timestamp(),
+        },
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let deleted = db::delete_posts_by_author(&mut conn, &request.did)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.forget_posts(&deleted);
+
+    state.audit(&admin, "blocked author", &request.did);
+    Ok(Json(BlockAuthorResponse {
+        did: request.did,
+        deleted_posts: deleted.len(),
+    }))
+}
+
+async fn unflag_spammer(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Path(did): Path<String>,
+) -> Result<Json<DeletedResponse>, StatusCode> {
+    let deleted = EngagementTracker::new(state.pool.clone())
+        .unflag_spammer(&did)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    state.audit(&admin, "unflagged spammer", &did);
+    Ok(Json(DeletedResponse { deleted }))
+}
+
+/// Lets an author's posts into the feed again after they opted out, at their request.
+async fn remove_opt_out(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Path(did): Path<String>,
+) -> Result<Json<DeletedResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let deleted = db::remove_opted_out_author(&mut conn, &did)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    state.audit(&admin, "removed opt-out", &did);
+    Ok(Json(DeletedResponse { deleted }))
+}
+
+async fn delete_post(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Json(request): Json<PostRequest>,
+) -> Result<Json<DeletedResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let deleted =
+        db::delete_post(&mut conn, &request.uri).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.forget_posts(std::slice::from_ref(&request.uri));
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    state.audit(&admin, "deleted post", &request.uri);
+    Ok(Json(DeletedResponse { deleted }))
+}
+
+async fn block_authors(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Json(request): Json<BlockAuthorsRequest>,
+) -> Result<Json<BlockAuthorsResponse>, StatusCode> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let dids: Vec<String> = request.dids.into_iter().map(String::from).collect();
+    let post_uri = request.post_uri.unwrap_or_default();
+    let blocked_at = Utc::now().timestamp();
+    let (blocked, deleted_posts) = conn
+        .transaction(|conn| {
+            let blocked = db::block_authors(
+                conn,
+                dids.iter()
+                    .map(|did| NewBlockedAuthor {
+                        did: did.clone(),
+                        post_uri: post_uri.clone(),
+                        blocked_at,
+                    })
+                    .collect(),
+            )?;
+            Ok::<_, diesel::result::Error>((blocked, db::delete_posts_by_authors(conn, &dids)?))
+        })
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.forget_posts(&deleted_posts);
+
+    state.audit(&admin, "blocked authors", &dids.join(", "));
+    Ok(Json(BlockAuthorsResponse {
+        blocked,
+        deleted_posts: deleted_posts.len(),
+    }))
+}
+
+async fn delete_matching_posts(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Json(request): Json<DeleteMatchingRequest>,
+) -> Result<Json<DeletedResponse>, StatusCode> {
+    let pattern = request.pattern.filter(|p| !p.trim().is_empty());
+    let label = request
+        .label
+        .map(|label| label.parse::<TopicLabel>())
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if pattern.is_none() && label.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let uris = db::get_post_uris_matching(&mut conn, pattern.as_deref(), label)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let deleted = delete_posts(&state, &mut conn, &uris)?;
+
+    let filters = [
+        pattern.map(|pattern| format!("pattern {pattern:?}")),
+        label.map(|label| format!("label {:?}", label.to_string())),
+    ];
+    state.audit(
+        &admin,
+        &format!("deleted {deleted} posts matching"),
+        &filters.into_iter().flatten().collect::<Vec<_>>().join(", "),
+    );
+    Ok(Json(DeletedResponse { deleted }))
+}
+
+/// Deletes posts linking to `domain` or its subdomains. Link targets aren't stored, so this
+/// goes by the URLs written out in the post text.
+async fn purge_domain(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Json(request): Json<PurgeDomainRequest>,
+) -> Result<Json<DeletedResponse>, StatusCode> {
+    let domain = request
+        .domain
+        .trim()
+        .trim_start_matches("www.")
+        .to_lowercase();
+    if !domain.contains('.') || domain.contains(['/', ':']) || domain.contains(char::is_whitespace)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let candidates = db::get_posts_containing(&mut conn, &domain)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let uris = matching_uris(candidates, |post| {
+        post.link_count > 0 && links_to_domain(&post.text, &domain)
+    });
+    let deleted = delete_posts(&state, &mut conn, &uris)?;
+
+    state.audit(&admin, &format!("purged {deleted} posts linking"), &domain);
+    Ok(Json(DeletedResponse { deleted }))
+}
+
+fn matching_uris(posts: Vec<Post>, matches: impl Fn(&Post) -> bool) -> Vec<String> {
+    posts
+        .into_iter()
+        .filter(|post| matches(post))
+        .map(|post| post.uri)
+        .collect()
+}
+
+fn delete_posts(
+    state: &AdminState,
+    conn: &mut SqliteConnection,
+    uris: &[String],
+) -> Result<usize, StatusCode> {
+    if uris.is_empty() {
+        return Ok(0);
+    }
+    let deleted = db::delete_posts(conn, uris).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.forget_posts(uris);
+    Ok(deleted)
+}
+
+/// Whether any link in `text` points at `domain` or one of its subdomains. Links may be written
+/// with or without a scheme, as Bluesky shortens them in the displayed text.
+fn links_to_domain(text: &str, domain: &str) -> bool {
+    text.split_whitespace().any(|word| {
+        let word = word.to_lowercase();
+        let link = word
+            .split_once("://")
+            .map_or(word.as_str(), |(_, rest)| rest);
+        let host = link
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or_default()
+            .trim_matches(|c: char| !c.is_ascii_alphanumeric());
+        host == domain
+            || host
+                .strip_suffix(domain)
+                .is_some_and(|subdomain| subdomain.ends_with('.'))
+    })
+}
+
+/// Similarity of a stored post to the closest negative example, as ingestion scores it, using
+/// the post's embedding from the vector index when it has one.
+async fn negative_similarity(state: &AdminState, post: &Post) -> Result<f32, StatusCode> {
+    if !settings().scoring.negative_examples.enabled {
+        return Ok(0.0);
+    }
+    let negatives = NegativeSet::default();
+    {
+        let mut conn = state
+            .pool
+            .get()
+            .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+        negatives
+            .reload(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if negatives.is_empty() {
+        return Ok(0.0);
+    }
+    let stored = state
+        .vector_index
+        .read()
+        .ok()
+        .and_then(|index| index.embedding(&post.uri).cloned());
+    let embedding = match stored {
+        Some(embedding) => embedding,
+        None => state.ml_handle.embed(post.text.clone()).await,
+    };
+    Ok(negatives.closest(&embedding))
+}
+
+/// Scores a stored post again with the current settings and models. Media is rebuilt from the
+/// stored counts, since the original embed isn't kept. Alt text and link cards aren't stored
+/// either, so their relevance is carried over from the post's last explanation.
+async fn rescore_post(
+    State(state): State<AdminState>,
+    Extension(admin): Extension<Admin>,
+    Json(request): Json<PostRequest>,
+) -> Result<Json<RescoreResponse>, StatusCode> {
+    let post = {
+        let mut conn = state
+            .pool
+            .get()
+            .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+        db::get_posts_by_uris(&mut conn, std::slice::from_ref(&request.uri))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .pop()
+            .ok_or(StatusCode::NOT_FOUND)?
+    };
+
+    let media = MediaInfo {
+        image_count: post.image_count.clamp(0, u8::MAX as i32) as u8,
+        has_video: post.has_media != 0 && post.image_count == 0,
+        has_alt_text: post.has_alt_text != 0,
+        ..MediaInfo::default()
+    };
+    let quality = state.ml_handle.score(post.text.clone()).await;
+    let content = extract_content_signals(&post.text, &media);
+    let mut signals = PrioritySignals::new(&quality, &content);
+    signals.negative_similarity = negative_similarity(&state, &post).await?;
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    if let Some(did) = &post.author_did {
+        let since = Utc::now().timestamp() - (settings().authors.history_window_days * 86400);
+        signals.bio_relevance = db::get_bio_score(&mut conn, did).unwrap_or(0.0);
+        signals.prior_accepted = db::count_author_history(&mut conn, did, since)
+            .unwrap_or(0)
+            .saturating_sub(1) as u32;
+        let now = Utc::now().timestamp();
+        let since_day =
+            now - now.rem_euclid(86400) - settings().authors.reputation.window_days * 86400;
+        signals.author_reputation = db::get_author_record(&mut conn, did, since_day)
+            .map(|record| author_reputation(&record))
+            .unwrap_or(0.0);
+    }
+    signals.alt_text_relevance = db::get_post_score(&mut conn, &post.uri)
+        .ok()
+        .flatten()
+        .and_then(|(explanation, _)| serde_json::from_str::<ScoreExplanation>(&explanation).ok())
+        .map_or(0.0, |explanation| explanation.signals.alt_text_relevance);
+    let lang = Some(post.lang.as_str());
+    let keyword_matches = keyword_matches_for(&post.text, lang);
+    signals.keyword_score = weighted_keyword_score(&keyword_matches);
+    let breakdown = calculate_priority(&signals);
+    let priority = breakdown.priority;
+    db::update_post_priority(&mut conn, &post.uri, priority)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let now = Utc::now().timestamp();
+    let config_id = config_history::active_config_id(&state.pool, now);
+    let matches = keyword_matches
+        .into_iter()
+        .chain(hashtag_matches_for(&post.text, None, lang))
+        .collect();
+    if let Some(row) = ScoreExplanation::new(&quality, &signals, &breakdown)
+        .with_matches(matches)
+        .to_row(&post.uri, now, config_id)
+    {
+        let _ = db::upsert_post_scores(&mut conn, vec![row]);
+    }
+
+    state.audit(&admin, "rescored post", &post.uri);
+    Ok(Json(RescoreResponse {
+        uri: post.uri,
+        previous_priority: post.priority,
+        priority,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cret", "s3creT"));
+        assert!(!token_matches("s3cret", "s3cre"));
+        assert!(!token_matches("s3cret", ""));
+    }
+
+    #[test]
+    fn test_authenticate_skips_empty_tokens() {
+        let tokens = [
+            AdminToken {
+                name: "unset".to_string(),
+                token: String::new(),
+                role: AdminRole::Owner,
+            },
+            AdminToken {
+                name: "mod".to_string(),
+                token: "s3cret".to_string(),
+                role: AdminRole::Moderator,
+            },
+        ];
+        assert!(authenticate(&tokens, "").is_none());
+        assert!(authenticate(&tokens, "nope").is_none());
+        let admin = authenticate(&tokens, "s3cret").unwrap();
+        assert_eq!(
+            (admin.name.as_str(), admin.role),
+            ("mod", AdminRole::Moderator)
+        );
+    }
+
+    #[test]
+    fn test_roles_include_lower_roles() {
+        let roles: Vec<AdminRole> = ["analyst", "moderator", "owner"]
+            .iter()
+            .map(|role| role.parse().unwrap())
+            .collect();
+        assert!(roles.is_sorted());
+        assert_eq!(roles[1], AdminRole::Moderator);
+        assert!("admin".parse::<AdminRole>().is_err());
+    }
+
+    #[test]
+    fn test_links_to_domain() {
+        assert!(links_to_domain(
+            "wishlist at https://spam.example/buy",
+            "spam.example"
+        ));
+        assert!(links_to_domain("go to www.spam.example!", "spam.example"));
+        assert!(links_to_domain("shop.spam.example/deal...", "spam.example"));
+        assert!(!links_to_domain("notspam.example/deal", "spam.example"));
+        assert!(!links_to_domain("spam.example.org", "spam.example"));
+        assert!(links_to_domain("(spam.example)", "spam.example"));
+        assert!(!links_to_domain("no links here", "spam.example"));
+    }
+}
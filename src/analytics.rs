@@ -0,0 +1,221 @@
+use crate::db::PostActivity;
+use crate::settings::settings;
+use crate::utils::time;
+use chrono::{Datelike, Timelike};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HourBucket {
+    pub day_of_week: u8,
+    pub hour: u8,
+    pub accepted: i64,
+    pub engagement: f32,
+}
+
+/// Accepted posts grouped by mention count; the last bucket holds everything from
+/// `MENTION_BUCKETS - 1` mentions up.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MentionBucket {
+    pub mentions: u8,
+    pub accepted: i64,
+    pub avg_engagement: f32,
+}
+
+const MENTION_BUCKETS: usize = 4;
+
+/// Accepted posts grouped by how they were ingested.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceBucket {
+    pub source: String,
+    pub accepted: i64,
+    pub avg_priority: f32,
+    pub avg_engagement: f32,
+}
+
+fn weighted_engagement(post: &PostActivity) -> f32 {
+    let s = settings();
+    post.reply_count.unwrap_or(0) as f32 * s.engagement.weights.reply
+        + post.repost_count.unwrap_or(0) as f32 * s.engagement.weights.repost
+        + post.like_count.unwrap_or(0) as f32 * s.engagement.weights.like
+}
+
+pub fn local_slot(timestamp: i64, tz_offset_minutes: i32) -> (u8, u8) {
+    let shifted = timestamp + i64::from(tz_offset_minutes) * 60;
+    time::from_timestamp(shifted)
+        .map(|dt| (dt.weekday().num_days_from_monday() as u8, dt.hour() as u8))
+        .unwrap_or_default()
+}
+
+pub fn posting_hours(activity: &[PostActivity], tz_offset_minutes: i32) -> Vec<HourBucket> {
+    let mut buckets: Vec<HourBucket> = (0..7 * 24)
+        .map(|slot| HourBucket {
+            day_of_week: (slot / 24) as u8,
+            hour: (slot % 24) as u8,
+            ..Default::default()
+        })
+        .collect();
+
+    for post in activity {
+        let (day, hour) = local_slot(post.timestamp, tz_offset_minutes);
+        let bucket = &mut buckets[day as usize * 24 + hour as usize];
+        bucket.accepted += 1;
+        bucket.engagement += weighted_engagement(post);
+    }
+
+    buckets
+}
+
+pub fn mention_engagement(activity: &[PostActivity]) -> Vec<MentionBucket> {
+    let mut buckets: Vec<MentionBucket> = (0..MENTION_BUCKETS)
+        .map(|mentions| MentionBucket {
+            mentions: mentions as u8,
+            ..Default::default()
+        })
+        .collect();
+
+    for post in activity {
+        let index = (post.mention_count.max(0) as usize).min(MENTION_BUCKETS - 1);
+        let bucket = &mut buckets[index];
+        bucket.accepted += 1;
+        bucket.avg_engagement += weighted_engagement(post);
+    }
+
+    for bucket in &mut buckets {
+        if bucket.accepted > 0 {
+            bucket.avg_engagement /= bucket.accepted as f32;
+        }
+    }
+
+    buckets
+}
+
+/// Sources ordered by how many posts they brought in.
+pub fn source_engagement(activity: &[PostActivity]) -> Vec<SourceBucket> {
+    let mut buckets: Vec<SourceBucket> = Vec::new();
+    for post in activity {
+        let index = match buckets.iter().position(|b| b.source == post.source) {
+            Some(index) => index,
+            None => {
+                buckets.push(SourceBucket {
+                    source: post.source.clone(),
+                    ..Default::default()
+                });
+                buckets.len() - 1
+            }
+        };
+        let bucket = &mut buckets[index];
+        bucket.accepted += 1;
+        bucket.avg_priority += post.priority;
+        bucket.avg_engagement += weighted_engagement(post);
+    }
+
+    for bucket in &mut buckets {
+        bucket.avg_priority /= bucket.accepted as f32;
+        bucket.avg_engagement /= bucket.accepted as f32;
+    }
+    buckets.sort_by(|a, b| b.accepted.cmp(&a.accepted).then(a.source.cmp(&b.source)));
+    buckets
+}
+
+pub fn hour_balance_multipliers(timestamps: &[i64], boost: f32) -> [f32; 24] {
+    let mut multipliers = [1.0; 24];
+    if boost <= 0.0 || timestamps.is_empty() {
+        return multipliers;
+    }
+
+    let mut counts = [0usize; 24];
+    for &timestamp in timestamps {
+        let (_, hour) = local_slot(timestamp, 0);
+        counts[hour as usize] += 1;
+    }
+
+    let expected = timestamps.len() as f32 / 24.0;
+    for (multiplier, &count) in multipliers.iter_mut().zip(counts.iter()) {
+        let share = count as f32 / expected;
+        if share < 1.0 {
+            *multiplier = 1.0 + boost * (1.0 - share);
+        }
+    }
+
+    multipliers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_slot_applies_offset() {
+        // 2026-10-12 is a Monday, 23:30 UTC
+        let timestamp = 1_791_847_800;
+        assert_eq!(local_slot(timestamp, 0), (0, 23));
+        assert_eq!(local_slot(timestamp, 60), (1, 0));
+        assert_eq!(local_slot(timestamp, -180), (0, 20));
+    }
+
+    #[test]
+    fn test_hour_balance_boosts_quiet_hours() {
+        let busy_hour = 1_791_847_800;
+        let mut timestamps = vec![busy_hour; 47];
+        timestamps.push(busy_hour + 6 * 3600);
+
+        let multipliers = hour_balance_multipliers(&timestamps, 0.2);
+        let (_, busy) = local_slot(busy_hour, 0);
+        let (_, quiet) = local_slot(busy_hour + 6 * 3600, 0);
+
+        assert_eq!(multipliers[busy as usize], 1.0);
+        assert!(multipliers[quiet as usize] > 1.0);
+        assert!(multipliers.iter().all(|m| *m <= 1.2));
+    }
+
+    #[test]
+    fn test_mention_engagement_buckets() {
+        let post = |mention_count, like_count| PostActivity {
+            timestamp: 0,
+            mention_count,
+            priority: 1.0,
+            source: "firehose".to_string(),
+            like_count: Some(like_count),
+            reply_count: None,
+            repost_count: None,
+        };
+        let activity = [post(0, 4), post(0, 2), post(5, 0), post(3, 1)];
+
+        let buckets = mention_engagement(&activity);
+        assert_eq!(buckets.len(), MENTION_BUCKETS);
+        assert_eq!(buckets[0].accepted, 2);
+        assert_eq!(buckets[1].accepted, 0);
+        assert_eq!(buckets[3].accepted, 2);
+        assert!(buckets[0].avg_engagement > buckets[3].avg_engagement);
+    }
+
+    #[test]
+    fn test_source_engagement() {
+        let post = |source: &str, priority, like_count| PostActivity {
+            timestamp: 0,
+            mention_count: 0,
+            priority,
+            source: source.to_string(),
+            like_count: Some(like_count),
+            reply_count: None,
+            repost_count: None,
+        };
+        let activity = [
+            post("backfill", 1.0, 0),
+            post("firehose", 2.0, 4),
+            post("firehose", 4.0, 2),
+        ];
+
+        let buckets = source_engagement(&activity);
+        let sources: Vec<&str> = buckets.iter().map(|b| b.source.as_str()).collect();
+        assert_eq!(sources, ["firehose", "backfill"]);
+        assert_eq!(buckets[0].accepted, 2);
+        assert_eq!(buckets[0].avg_priority, 3.0);
+        assert!(buckets[0].avg_engagement > buckets[1].avg_engagement);
+    }
+
+    #[test]
+    fn test_hour_balance_disabled() {
+        assert_eq!(hour_balance_multipliers(&[0, 3600], 0.0), [1.0; 24]);
+    }
+}
@@ -0,0 +1,811 @@
+use crate::analytics::{self, HourBucket, MentionBucket, SourceBucket};
+use crate::archive;
+use crate::buffers::{BufferMonitor, BufferStatus};
+use crate::cleanup::{CleanupMonitor, CleanupStatus};
+use crate::db::{self, DbPool};
+use crate::firehose::{FirehoseMonitor, FirehoseStatus};
+use crate::ranking::{rank_feed, RankContext, Reader};
+use crate::scoring::{ConfidenceTier, MLHandle, QueueStatus, SharedVectorIndex};
+use crate::settings::settings;
+use crate::utils::ids::AtUri;
+use crate::utils::logs;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+
+#[derive(Clone)]
+pub struct ApiState {
+    pool: DbPool,
+    vector_index: SharedVectorIndex,
+    firehose: FirehoseMonitor,
+    buffers: BufferMonitor,
+    cleanup: CleanupMonitor,
+    ml_handle: MLHandle,
+}
+
+impl ApiState {
+    pub fn new(
+        pool: DbPool,
+        vector_index: SharedVectorIndex,
+        firehose: FirehoseMonitor,
+        buffers: BufferMonitor,
+        cleanup: CleanupMonitor,
+        ml_handle: MLHandle,
+    ) -> Self {
+        Self {
+            pool,
+            vector_index,
+            firehose,
+            buffers,
+            cleanup,
+            ml_handle,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelatedQuery {
+    pub uri: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelatedPost {
+    pub uri: String,
+    pub similarity: f32,
+    pub author_did: Option<String>,
+    pub author_handle: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelatedResponse {
+    pub uri: String,
+    pub related: Vec<RelatedPost>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub uri: String,
+    pub text: String,
+    pub author_did: Option<String>,
+    pub author_handle: Option<String>,
+    pub timestamp: i64,
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HoursQuery {
+    pub tz_offset_minutes: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HoursResponse {
+    pub tz_offset_minutes: i32,
+    pub buckets: Vec<HourBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MentionsResponse {
+    pub buckets: Vec<MentionBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourcesResponse {
+    pub sources: Vec<SourceBucket>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LanguagesQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageCount {
+    pub lang: String,
+    pub rejected: i64,
+    pub relevant: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguagesResponse {
+    pub days: i64,
+    pub languages: Vec<LanguageCount>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryYieldsQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryYield {
+    pub query: String,
+    pub searched: i64,
+    pub accepted: i64,
+    /// Accepted posts per searched post.
+    pub acceptance_rate: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryYieldsResponse {
+    pub days: i64,
+    pub queries: Vec<QueryYield>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InteractionsQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InteractionsResponse {
+    pub days: i64,
+    /// Interactions per type, such as `seen`, `clickthrough` or `share`.
+    pub counts: BTreeMap<String, i64>,
+    /// Click-throughs per post seen.
+    pub clickthrough_rate: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveQuery {
+    /// UTC day, as `YYYY-MM-DD`.
+    pub day: String,
+    /// 1-based page number.
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveEntry {
+    pub uri: String,
+    /// The post on bsky.app.
+    pub permalink: Option<String>,
+    pub author_did: Option<String>,
+    pub lang: String,
+    pub timestamp: i64,
+    pub priority: f32,
+    /// Confidence tier: `STRONG`, `MODERATE` or `WEAK`.
+    pub tier: String,
+    /// Topic label, such as `sharing work` or `dev update`.
+    pub label: String,
+    pub like_count: i32,
+    pub reply_count: i32,
+    pub repost_count: i32,
+    /// How the post was ingested: `firehose`, `backfill`, `search-poller`, `manual` or
+    /// `unknown`.
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveResponse {
+    pub day: String,
+    pub page: usize,
+    /// Accepted posts published that day, across all pages.
+    pub total: usize,
+    pub next_page: Option<usize>,
+    pub posts: Vec<ArchiveEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewFormat {
+    #[default]
+    Json,
+    Html,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewQuery {
+    pub limit: Option<usize>,
+    /// Feed language code; `en` when left out.
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub format: PreviewFormat,
+    /// Hex shuffle seed. Defaults to 0, so the same ranking can be compared before and after a
+    /// settings change.
+    pub seed: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewEntry {
+    pub uri: String,
+    pub permalink: Option<String>,
+    pub author_did: Option<String>,
+    pub excerpt: String,
+    pub priority: f32,
+    /// Priority after serve-time adjustments, which sets the order within a time bucket.
+    pub adjusted_priority: f32,
+    /// Confidence tier: `STRONG`, `MODERATE` or `WEAK`.
+    pub tier: String,
+    /// Topic label, such as `sharing work` or `dev update`.
+    pub label: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewResponse {
+    pub lang: String,
+    pub seed: String,
+    pub posts: Vec<PreviewEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClustersResponse {
+    pub clusters: Vec<Vec<String>>,
+}
+
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/v1/related", get(related_posts))
+        .route("/api/v1/clusters", get(clusters))
+        .route("/api/v1/search", get(search))
+        .route("/api/v1/analytics/hours", get(posting_hours))
+        .route("/api/v1/analytics/mentions", get(mention_engagement))
+        .route("/api/v1/analytics/sources", get(source_engagement))
+        .route("/api/v1/analytics/languages", get(rejected_languages))
+        .route("/api/v1/analytics/interactions", get(interaction_counts))
+        .route(
+            "/api/v1/analytics/backfill-queries",
+            get(backfill_query_yields),
+        )
+        .route("/api/v1/archive", get(archive_day))
+        .route("/api/v1/preview", get(preview))
+        .route("/api/v1/firehose", get(firehose_status))
+        .route("/api/v1/buffers", get(buffer_status))
+        .route("/api/v1/cleanup", get(cleanup_status))
+        .route("/api/v1/ml-queue", get(ml_queue_status))
+        .with_state(state)
+}
+
+pub async fn serve(state: ApiState, addr: SocketAddr) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    logs::log_api_listening(&addr);
+    axum::serve(listener, router(state)).await
+}
+
+async fn related_posts(
+    State(state): State<ApiState>,
+    Query(query): Query<RelatedQuery>,
+) -> Result<Json<RelatedResponse>, StatusCode> {
+    let s = settings();
+    let limit = query
+        .limit
+        .unwrap_or(s.api.related_default_limit)
+        .min(s.api.related_max_limit);
+
+    let stored = state
+        .vector_index
+        .read()
+        .ok()
+        .and_then(|index| index.embedding(&query.uri).cloned());
+    let target = match stored {
+        Some(embedding) => embedding,
+        None => {
+            let mut conn = state
+                .pool
+                .get()
+                .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+            db::get_post_embedding(&mut conn, &query.uri).ok_or(StatusCode::NOT_FOUND)?
+        }
+    };
+
+    let neighbors: Vec<(String, f32)> = {
+        let index = state
+            .vector_index
+            .read()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        index
+            .search(&target, limit + 1)
+            .into_iter()
+            .filter(|(uri, _)| *uri != query.uri)
+            .take(limit)
+            .collect()
+    };
+
+    let uris: Vec<String> = neighbors.iter().map(|(uri, _)| uri.clone()).collect();
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    // Posts deleted by another process, such as `moderate`, stay in the index until the next
+    // cleanup run, so only posts still stored are returned.
+    let authors: HashMap<String, (Option<String>, Option<String>)> =
+        db::get_post_authors(&mut conn, &uris)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .map(|author| (author.uri, (author.did, author.handle)))
+            .collect();
+
+    let related = neighbors
+        .into_iter()
+        .filter_map(|(uri, similarity)| {
+            let (author_did, author_handle) = authors.get(&uri).cloned()?;
+            Some(RelatedPost {
+                uri,
+                similarity,
+                author_did,
+                author_handle,
+            })
+        })
+        .collect();
+
+    Ok(Json(RelatedResponse {
+        uri: query.uri,
+        related,
+    }))
+}
+
+/// Text relevance scaled by priority, never dropping a match below a tenth of its relevance.
+fn search_score(relevance: f64, priority: f32) -> f32 {
+    let weight = settings().api.search_priority_weight;
+    relevance as f32 * (1.0 + weight * priority).max(0.1)
+}
+
+async fn search(
+    State(state): State<ApiState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, StatusCode> {
+    let s = settings();
+    let limit = query
+        .limit
+        .unwrap_or(s.api.search_default_limit)
+        .min(s.api.search_max_limit);
+    let candidates = (limit * s.api.search_candidates_per_result) as i64;
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let hits = db::search_posts(&mut conn, &query.q, candidates).map_err(|e| {
+        if db::is_fts_query_error(&e) {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    let mut results: Vec<SearchResult> = hits
+        .into_iter()
+        .map(|hit| SearchResult {
+            score: search_score(hit.relevance, hit.priority),
+            uri: hit.uri,
+            text: hit.text,
+            author_did: hit.author_did,
+            author_handle: hit.author_handle,
+            timestamp: hit.timestamp,
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(limit);
+
+    Ok(Json(SearchResponse {
+        query: query.q,
+        results,
+    }))
+}
+
+async fn posting_hours(
+    State(state): State<ApiState>,
+    Query(query): Query<HoursQuery>,
+) -> Result<Json<HoursResponse>, StatusCode> {
+    let s = settings();
+    let tz_offset_minutes = query
+        .tz_offset_minutes
+        .unwrap_or(0)
+        .clamp(-14 * 60, 14 * 60);
+    let cutoff = chrono::Utc::now().timestamp() - (s.feed.cutoff_hours * 3600);
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let activity =
+        db::get_post_activity(&mut conn, cutoff).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(HoursResponse {
+        tz_offset_minutes,
+        buckets: analytics::posting_hours(&activity, tz_offset_minutes),
+    }))
+}
+
+async fn mention_engagement(
+    State(state): State<ApiState>,
+) -> Result<Json<MentionsResponse>, StatusCode> {
+    let s = settings();
+    let cutoff = chrono::Utc::now().timestamp() - (s.feed.cutoff_hours * 3600);
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let activity =
+        db::get_post_activity(&mut conn, cutoff).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(MentionsResponse {
+        buckets: analytics::mention_engagement(&activity),
+    }))
+}
+
+async fn source_engagement(
+    State(state): State<ApiState>,
+) -> Result<Json<SourcesResponse>, StatusCode> {
+    let s = settings();
+    let cutoff = chrono::Utc::now().timestamp() - (s.feed.cutoff_hours * 3600);
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let activity =
+        db::get_post_activity(&mut conn, cutoff).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(SourcesResponse {
+        sources: analytics::source_engagement(&activity),
+    }))
+}
+
+async fn interaction_counts(
+    State(state): State<ApiState>,
+    Query(query): Query<InteractionsQuery>,
+) -> Result<Json<InteractionsResponse>, StatusCode> {
+    let days = query.days.unwrap_or(7).clamp(1, 365);
+    let since = chrono::Utc::now().timestamp() - days * 86400;
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let counts: BTreeMap<String, i64> = db::get_interaction_counts(&mut conn, since)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .collect();
+
+    let count = |kind: &str| counts.get(kind).copied().unwrap_or(0);
+    let seen = count(db::INTERACTION_SEEN);
+    let clickthrough_rate = if seen > 0 {
+        count(db::INTERACTION_CLICKTHROUGH) as f32 / seen as f32
+    } else {
+        0.0
+    };
+
+    Ok(Json(InteractionsResponse {
+        days,
+        counts,
+        clickthrough_rate,
+    }))
+}
+
+async fn rejected_languages(
+    State(state): State<ApiState>,
+    Query(query): Query<LanguagesQuery>,
+) -> Result<Json<LanguagesResponse>, StatusCode> {
+    let days = query.days.unwrap_or(7).clamp(1, 365);
+    let now = chrono::Utc::now().timestamp();
+    let since_day = now - now.rem_euclid(86400) - (days - 1) * 86400;
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let totals = db::get_language_stats(&mut conn, since_day)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut languages: Vec<LanguageCount> = totals
+        .into_iter()
+        .map(|total| LanguageCount {
+            lang: total.lang,
+            rejected: total.rejected.unwrap_or(0),
+            relevant: total.relevant.unwrap_or(0),
+        })
+        .collect();
+    languages.sort_by(|a, b| {
+        b.relevant
+            .cmp(&a.relevant)
+            .then(b.rejected.cmp(&a.rejected))
+    });
+
+    Ok(Json(LanguagesResponse { days, languages }))
+}
+
+async fn backfill_query_yields(
+    State(state): State<ApiState>,
+    Query(query): Query<QueryYieldsQuery>,
+) -> Result<Json<QueryYieldsResponse>, StatusCode> {
+    let days = query.days.unwrap_or(7).clamp(1, 365);
+    let now = chrono::Utc::now().timestamp();
+    let since_day = now - now.rem_euclid(86400) - (days - 1) * 86400;
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let totals =
+        db::get_query_stats(&mut conn, since_day).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut queries: Vec<QueryYield> = totals
+        .into_iter()
+        .map(|total| {
+            let searched = total.searched.unwrap_or(0);
+            let accepted = total.accepted.unwrap_or(0);
+            QueryYield {
+                query: total.query,
+                searched,
+                accepted,
+                acceptance_rate: if searched > 0 {
+                    accepted as f32 / searched as f32
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect();
+    // Worst first, since the point is finding queries to drop.
+    queries.sort_by(|a, b| {
+        a.acceptance_rate
+            .total_cmp(&b.acceptance_rate)
+            .then(b.searched.cmp(&a.searched))
+    });
+
+    Ok(Json(QueryYieldsResponse { days, queries }))
+}
+
+/// Start of a `YYYY-MM-DD` UTC day, as a unix timestamp.
+fn parse_day(day: &str) -> Option<i64> {
+    let date = chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+async fn archive_day(
+    State(state): State<ApiState>,
+    Query(query): Query<ArchiveQuery>,
+) -> Result<Json<ArchiveResponse>, StatusCode> {
+    let s = settings();
+    let day_start = parse_day(&query.day).ok_or(StatusCode::BAD_REQUEST)?;
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query
+        .limit
+        .unwrap_or(s.api.archive_default_limit)
+        .clamp(1, s.api.archive_max_limit);
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let offset = (page - 1).saturating_mul(limit);
+    let (total, day_posts) = archive::day_posts(
+        &mut conn,
+        day_start,
+        i64::try_from(offset).unwrap_or(i64::MAX),
+        limit as i64,
+        chrono::Utc::now().timestamp(),
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let total = total as usize;
+
+    let posts = day_posts
+        .into_iter()
+        .map(|post| ArchiveEntry {
+            permalink: post
+                .uri
+                .parse::<AtUri>()
+                .ok()
+                .and_then(|uri| uri.permalink()),
+            uri: post.uri,
+            author_did: post.author_did,
+            lang: post.lang,
+            timestamp: post.timestamp,
+            priority: post.priority,
+            tier: post.confidence,
+            label: post.topic,
+            like_count: post.like_count,
+            reply_count: post.reply_count,
+            repost_count: post.repost_count,
+            source: post.source,
+        })
+        .collect();
+
+    Ok(Json(ArchiveResponse {
+        day: query.day,
+        page,
+        total,
+        next_page: (offset.saturating_add(limit) < total).then_some(page + 1),
+        posts,
+    }))
+}
+
+/// The top of the feed as an anonymous reader would get it, without needing a Bluesky client.
+async fn preview(
+    State(state): State<ApiState>,
+    Query(query): Query<PreviewQuery>,
+) -> Result<Response, StatusCode> {
+    let s = settings();
+    let limit = query
+        .limit
+        .unwrap_or(s.api.preview_default_limit)
+        .clamp(1, s.api.preview_max_limit);
+    let seed = match query.seed.as_deref() {
+        Some(seed) => u64::from_str_radix(seed, 16).map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => 0,
+    };
+    let lang = query.lang.unwrap_or_else(|| "en".to_string());
+
+    let now = chrono::Utc::now().timestamp();
+    let cutoff = now - s.feed.cutoff_hours * 3600;
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let posts = db::get_language_feed(&mut conn, cutoff, &lang)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ctx = RankContext::new(&posts, now, seed);
+    let posts = rank_feed(&posts, &Reader::default(), &ctx)
+        .into_iter()
+        .take(limit)
+        .map(|(post, adjusted_priority)| PreviewEntry {
+            uri: post.uri.clone(),
+            permalink: post
+                .uri
+                .parse::<AtUri>()
+                .ok()
+                .and_then(|uri| uri.permalink()),
+            author_did: post.author_did.clone(),
+            excerpt: excerpt(&post.text, s.api.preview_excerpt_chars),
+            priority: post.priority,
+            adjusted_priority,
+            tier: ConfidenceTier::from_priority(post.priority).to_string(),
+            label: post.topic().to_string(),
+        })
+        .collect();
+
+    let response = PreviewResponse {
+        lang,
+        seed: format!("{seed:x}"),
+        posts,
+    };
+    Ok(match query.format {
+        PreviewFormat::Json => Json(response).into_response(),
+        PreviewFormat::Html => Html(preview_html(&response)).into_response(),
+    })
+}
+
+/// A post's text on one line, cut to `max_chars`.
+fn excerpt(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut excerpt: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    excerpt.push('…');
+    excerpt
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn preview_html(preview: &PreviewResponse) -> String {
+    let mut rows = String::new();
+    for (i, post) in preview.posts.iter().enumerate() {
+        let link = post.permalink.as_deref().unwrap_or(&post.uri);
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{}</td><td>{}</td>\
+             <td><a href=\"{}\">{}</a></td></tr>\n",
+            i + 1,
+            post.priority,
+            post.adjusted_priority,
+            post.tier,
+            escape_html(&post.label),
+            escape_html(link),
+            escape_html(&post.excerpt),
+        ));
+    }
+    format!(
+        "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>Feed preview</title></head>\n\
+         <body><p>{} feed, seed {}</p>\n<table>\n\
+         <tr><th>#</th><th>priority</th><th>adjusted</th><th>tier</th><th>label</th><th>post</th></tr>\n\
+         {rows}</table></body></html>\n",
+        escape_html(&preview.lang),
+        preview.seed,
+    )
+}
+
+async fn firehose_status(State(state): State<ApiState>) -> Json<FirehoseStatus> {
+    Json(state.firehose.status())
+}
+
+async fn buffer_status(State(state): State<ApiState>) -> Json<BufferStatus> {
+    Json(state.buffers.status())
+}
+
+async fn cleanup_status(State(state): State<ApiState>) -> Json<CleanupStatus> {
+    Json(state.cleanup.status())
+}
+
+async fn ml_queue_status(State(state): State<ApiState>) -> Json<QueueStatus> {
+    Json(state.ml_handle.queue_status())
+}
+
+async fn clusters(State(state): State<ApiState>) -> Result<Json<ClustersResponse>, StatusCode> {
+    let s = settings();
+    let index = state
+        .vector_index
+        .read()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let clusters = index.clusters(s.semantic.cluster_similarity, s.semantic.cluster_min_size);
+
+    Ok(Json(ClustersResponse { clusters }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_score_favors_priority() {
+        assert!(search_score(2.0, 1.0) > search_score(2.0, 0.0));
+        assert!(search_score(2.0, 0.0) > search_score(1.0, 0.0));
+        assert!(search_score(1.0, -10.0) > 0.0);
+    }
+
+    #[test]
+    fn test_parse_day() {
+        assert_eq!(parse_day("1970-01-02"), Some(86400));
+        assert_eq!(parse_day("2026-10-16"), Some(1_792_108_800));
+        assert_eq!(parse_day("2026-02-30"), None);
+        assert_eq!(parse_day("16/10/2026"), None);
+    }
+
+    #[test]
+    fn test_preview_html_escapes_text() {
+        assert_eq!(excerpt("made a\n\nboss  fight", 20), "made a boss fight");
+        assert_eq!(excerpt("made a boss fight", 7), "made a…");
+
+        let preview = PreviewResponse {
+            lang: "en".to_string(),
+            seed: "0".to_string(),
+            posts: vec![PreviewEntry {
+                uri: "at://did:plc:a/app.bsky.feed.post/1".to_string(),
+                permalink: None,
+                author_did: None,
+                excerpt: "<script>alert(1)</script> & more".to_string(),
+                priority: 1.5,
+                adjusted_priority: 1.25,
+                tier: "STRONG".to_string(),
+                label: "dev update".to_string(),
+            }],
+        };
+        let html = preview_html(&preview);
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt; &amp; more"));
+        assert!(html.contains("<td>1.50</td><td>1.25</td><td>STRONG</td>"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_fts_query_quotes_terms() {
+        assert_eq!(
+            db::fts_query("godot \"shader* OR"),
+            Some("\"godot\" \"\"\"shader*\" \"OR\"".to_string())
+        );
+        assert_eq!(db::fts_query("   "), None);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::db::{self, ArchivedPost, EngagementCounts, Post};
+use crate::scoring::ConfidenceTier;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+/// Hex SHA-256 of the post text, enough to dedupe and join against re-fetched records
+/// without keeping the text around after the post leaves the feed.
+pub fn text_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn archived_post(post: Post, engagement: Option<EngagementCounts>, now: i64) -> ArchivedPost {
+    let tier = ConfidenceTier::from_priority(post.priority);
+    let topic = post.topic();
+    let (like_count, reply_count, repost_count) = engagement.unwrap_or_default();
+
+    ArchivedPost {
+        uri: post.uri,
+        text_hash: text_hash(&post.text),
+        author_did: post.author_did,
+        lang: post.lang,
+        timestamp: post.timestamp,
+        priority: post.priority,
+        confidence: tier.to_string(),
+        topic: topic.to_string(),
+        like_count,
+        reply_count,
+        repost_count,
+        archived_at: now,
+        config_id: post.config_id,
+        source: post.source,
+    }
+}
+
+/// Copies the posts the cleanup job is about to delete into `archive_posts`.
+pub fn archive_expiring_posts(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+    max_posts: i64,
+    now: i64,
+) -> QueryResult<usize> {
+    let entries: Vec<ArchivedPost> = db::get_expiring_posts(conn, cutoff_timestamp, max_posts)?
+        .into_iter()
+        .map(|(post, engagement)| archived_post(post, engagement, now))
+        .collect();
+    if entries.is_empty() {
+        return Ok(0);
+    }
+    db::insert_archived_posts(conn, entries)
+}
+
+/// One page of the accepted posts published on the UTC day starting at `day_start`, oldest
+/// first, and how many there are in all. Posts still in the feed are read live, so their
+/// engagement is current; the rest come from `archive_posts`. Blocked and opted-out authors are
+/// left out.
+pub fn day_posts(
+    conn: &mut SqliteConnection,
+    day_start: i64,
+    offset: i64,
+    limit: i64,
+    now: i64,
+) -> QueryResult<(i64, Vec<ArchivedPost>)> {
+    let day_end = day_start + 86400;
+    let total = db::count_day_posts(conn, day_start, day_end)?;
+    let uris = db::get_day_post_uris(conn, day_start, day_end, offset, limit)?;
+    if uris.is_empty() {
+        return Ok((total, Vec::new()));
+    }
+    let live = db::get_posts_with_engagement(conn, &uris)?
+        .into_iter()
+        .map(|(post, engagement)| archived_post(post, engagement, now))
+        .collect();
+    let archived = db::get_archived_posts_by_uris(conn, &uris)?;
+    Ok((total, merge_day(live, archived)))
+}
+
+fn merge_day(live: Vec<ArchivedPost>, archived: Vec<ArchivedPost>) -> Vec<ArchivedPost> {
+    let live_uris: HashSet<String> = live.iter().map(|post| post.uri.clone()).collect();
+    let mut posts: Vec<ArchivedPost> = live
+        .into_iter()
+        .chain(
+            archived
+                .into_iter()
+                .filter(|post| !live_uris.contains(&post.uri)),
+        )
+        .collect();
+    posts.sort_by(|a, b| {
+        a.timestamp
+            .cmp(&b.timestamp)
+            .then_with(|| a.uri.cmp(&b.uri))
+    });
+    posts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_hash() {
+        assert_eq!(
+            text_hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_archived_post_keeps_scores_not_text() {
+        let post = Post {
+            uri: "at://did:plc:dev/app.bsky.feed.post/1".to_string(),
+            text: "Shipped the new level editor!".to_string(),
+            timestamp: 1000,
+            priority: 5.0,
+            has_media: 1,
+            is_first_person: 1,
+            author_did: Some("did:plc:dev".to_string()),
+            image_count: 2,
+            has_alt_text: 1,
+            link_count: 0,
+            promo_link_count: 0,
+            lang: "en".to_string(),
+            mention_count: 0,
+            thread_length: 1,
+            config_id: Some(3),
+            source: "backfill".to_string(),
+        };
+        let hash = text_hash(&post.text);
+
+        let archived = archived_post(post, Some((12, 3, 4)), 2000);
+        assert_eq!(archived.text_hash, hash);
+        assert_eq!(archived.confidence, "STRONG");
+        assert_eq!(archived.topic, "sharing work");
+        assert_eq!(
+            (
+                archived.like_count,
+                archived.reply_count,
+                archived.repost_count
+            ),
+            (12, 3, 4)
+        );
+        assert_eq!(archived.archived_at, 2000);
+        assert_eq!(archived.config_id, Some(3));
+        assert_eq!(archived.source, "backfill");
+    }
+
+    #[test]
+    fn test_merge_day_prefers_live_posts() {
+        let entry = |uri: &str, timestamp: i64, like_count: i32| ArchivedPost {
+            uri: uri.to_string(),
+            text_hash: String::new(),
+            author_did: None,
+            lang: "en".to_string(),
+            timestamp,
+            priority: 1.0,
+            confidence: "MODERATE".to_string(),
+            topic: "discussion".to_string(),
+            like_count,
+            reply_count: 0,
+            repost_count: 0,
+            archived_at: 0,
+            config_id: None,
+            source: "firehose".to_string(),
+        };
+
+        let merged = merge_day(
+            vec![entry("at://b", 20, 9), entry("at://c", 10, 0)],
+            vec![entry("at://a", 10, 1), entry("at://b", 20, 2)],
+        );
+        let order: Vec<(&str, i32)> = merged
+            .iter()
+            .map(|post| (post.uri.as_str(), post.like_count))
+            .collect();
+        assert_eq!(order, [("at://a", 1), ("at://c", 0), ("at://b", 9)]);
+    }
+}
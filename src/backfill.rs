@@ -1,47 +1,439 @@
-use crate::db::{self, is_blocked_author, DbPool, NewPost};
+use crate::config_history;
+use crate::db::{
+    self, is_blocked_author, BackfillState, DbPool, NewOptedOutAuthor, NewPost, NewQueryStat,
+    PostSource,
+};
+use crate::embeddings::EmbeddingStore;
 use crate::scoring::{
-    apply_filters, calculate_priority, extract_content_signals, has_hashtags, has_keywords,
-    FilterResult, MLHandle, MediaInfo, PrioritySignals,
+    apply_filters, calculate_priority, extract_content_signals, hashtag_matches_for,
+    keyword_matches_for, weighted_keyword_score, Filter, FilterResult, MLHandle, MediaInfo,
+    PrioritySignals,
 };
 use crate::settings::settings;
-use crate::utils::bluesky::{create_session, extract_facet_links, search_posts, SearchPost};
+use crate::utils::bluesky::{create_session, search_posts, SearchPost, SearchSort};
+use crate::utils::ids::{AtUri, Did};
 use crate::utils::logs::{self, PostAssessment};
+use crate::utils::time;
 use chrono::Utc;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// The stretch of a query's results that has been walked, newest first, without holes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Coverage {
+    oldest_at: i64,
+    newest_at: i64,
+}
+
+impl From<BackfillState> for Coverage {
+    fn from(state: BackfillState) -> Self {
+        Self {
+            oldest_at: state.oldest_at,
+            newest_at: state.newest_at,
+        }
+    }
+}
+
+/// Results of paging one query newest-first from `until` back towards `since`.
+struct Walk {
+    posts: Vec<SearchPost>,
+    /// `since` once the walk got there, otherwise the oldest `indexed_at` it reached.
+    oldest_at: i64,
+    reached_since: bool,
+}
+
+const ACCEPTED: &str = "accepted";
+const DUPLICATE: &str = "duplicate";
+const MALFORMED: &str = "malformed";
+const NO_RELEVANCE: &str = "no-relevance";
+
+/// What a backfill did with each searched post, or with `dry_run`, would have done.
+#[derive(Debug, Default, Serialize)]
+pub struct BackfillReport {
+    pub dry_run: bool,
+    pub searched: usize,
+    pub duplicates: usize,
+    pub filtered: usize,
+    pub no_relevance: usize,
+    pub accepted: usize,
+    /// How many new posts each query found and how many of those were accepted.
+    pub queries: BTreeMap<String, QueryYield>,
+    /// Posts per outcome: `accepted`, `duplicate`, `no-relevance`, `malformed` or the filter
+    /// that rejected them.
+    pub outcomes: BTreeMap<String, usize>,
+    pub posts: Vec<ReportedPost>,
+}
 
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct QueryYield {
+    pub searched: usize,
+    pub accepted: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportedPost {
+    pub uri: String,
+    /// The configured query that found the post.
+    pub query: String,
+    pub outcome: String,
+    /// Set once the post got as far as scoring.
+    pub priority: Option<f32>,
+    pub text: String,
+}
+
+impl BackfillReport {
+    fn record(&mut self, query: &str, post: &SearchPost, outcome: &str, priority: Option<f32>) {
+        *self.outcomes.entry(outcome.to_string()).or_default() += 1;
+        if outcome == ACCEPTED {
+            self.accepted += 1;
+            self.queries.entry(query.to_string()).or_default().accepted += 1;
+        }
+        self.posts.push(ReportedPost {
+            uri: post.uri.clone(),
+            query: query.to_string(),
+            outcome: outcome.to_string(),
+            priority,
+            text: post.record.text.clone(),
+        });
+    }
+}
+
+/// Walks each search query back from now and records how far it got in `backfill_state`. After
+/// a restart only what's newer than the last run is searched before carrying on further back,
+/// until `backfill.hours` ago is covered.
 pub async fn run_backfill(pool: DbPool, ml_handle: &MLHandle) {
     let s = settings();
     logs::log_backfill_start();
 
+    let started_at = Utc::now().timestamp();
+    let floor = started_at - s.backfill.hours * 3600;
+    let client = reqwest::Client::new();
+    let Some(access_token) = session(&client).await else {
+        return;
+    };
+
+    let mut all_posts: Vec<(String, SearchPost)> = Vec::new();
+    let mut states = Vec::new();
+
+    for query in &s.backfill.queries {
+        if all_posts.len() >= s.backfill.limit {
+            break;
+        }
+        let previous = pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::get_backfill_state(&mut conn, query).ok().flatten())
+            .map(Coverage::from);
+        let max_posts = s.backfill.limit - all_posts.len();
+
+        let Some((posts, coverage)) = resume_query(
+            &client,
+            &access_token,
+            query,
+            previous,
+            floor,
+            started_at,
+            max_posts,
+        )
+        .await
+        else {
+            continue;
+        };
+        logs::log_backfill_query(query, posts.len());
+        all_posts.extend(posts.into_iter().map(|post| (query.clone(), post)));
+        states.push(BackfillState {
+            query: query.to_string(),
+            oldest_at: coverage.oldest_at,
+            newest_at: coverage.newest_at,
+            updated_at: started_at,
+        });
+    }
+
+    // Only move the cursors once the posts they cover have been stored.
+    if store_posts(&pool, ml_handle, &all_posts, PostSource::Backfill, false)
+        .await
+        .is_some()
+    {
+        if let Ok(mut conn) = pool.get() {
+            for state in &states {
+                let _ = db::upsert_backfill_state(&mut conn, state);
+            }
+        }
+    }
+}
+
+/// Searches the last `backfill.hours` for each of `queries` and scores the results like the
+/// startup backfill, without storing posts or moving the backfill cursors. Returns `None` when
+/// the search or the database couldn't be reached.
+#[allow(dead_code)]
+pub async fn dry_run(
+    pool: &DbPool,
+    ml_handle: &MLHandle,
+    queries: &[String],
+) -> Option<BackfillReport> {
+    let s = settings();
+    logs::log_backfill_start();
+
+    let now = Utc::now().timestamp();
+    let since = now - s.backfill.hours * 3600;
     let client = reqwest::Client::new();
+    let access_token = session(&client).await?;
+
+    let mut all_posts: Vec<(String, SearchPost)> = Vec::new();
+    for query in queries {
+        if all_posts.len() >= s.backfill.limit {
+            break;
+        }
+        let max_posts = s.backfill.limit - all_posts.len();
+        match search_back(&client, &access_token, query, since, now, max_posts).await {
+            Ok(walk) => {
+                logs::log_backfill_query(query, walk.posts.len());
+                all_posts.extend(walk.posts.into_iter().map(|post| (query.clone(), post)));
+            }
+            Err(e) => logs::log_backfill_query_failed(query, &e),
+        }
+    }
+
+    all_posts.truncate(s.backfill.limit);
+    store_posts(pool, ml_handle, &all_posts, PostSource::Backfill, true).await
+}
+
+/// `query` with each of `exclude_terms` it doesn't already exclude appended as a negative term,
+/// quoted when it's more than one word.
+pub fn build_query(query: &str, exclude_terms: &[String]) -> String {
+    let mut built = query.trim().to_string();
+    for term in exclude_terms {
+        let term = term.trim();
+        if term.is_empty() {
+            continue;
+        }
+        let negated = if term.contains(char::is_whitespace) {
+            format!("-\"{term}\"")
+        } else {
+            format!("-{term}")
+        };
+        let padded = format!(" {} ", built.to_lowercase());
+        if !padded.contains(&format!(" {} ", negated.to_lowercase())) {
+            built.push(' ');
+            built.push_str(&negated);
+        }
+    }
+    built
+}
 
-    let access_token = match create_session(&client).await {
-        Ok(token) => token,
+/// Searches what's been posted since `previous` was walked, then carries on back towards
+/// `floor` from wherever the query's coverage ends. Returns `None` when the query couldn't be
+/// searched at all.
+async fn resume_query(
+    client: &reqwest::Client,
+    access_token: &str,
+    query: &str,
+    previous: Option<Coverage>,
+    floor: i64,
+    started_at: i64,
+    max_posts: usize,
+) -> Option<(Vec<SearchPost>, Coverage)> {
+    let previous = previous.filter(|coverage| coverage.newest_at > floor);
+    let since = previous.map_or(floor, |coverage| coverage.newest_at);
+
+    let newer = match search_back(client, access_token, query, since, started_at, max_posts).await {
+        Ok(walk) => walk,
         Err(e) => {
-            logs::log_backfill_auth_failed(&e);
-            return;
+            logs::log_backfill_query_failed(query, &e);
+            return None;
         }
     };
+    let mut coverage = after_newer_walk(previous, &newer, started_at);
+    let mut posts = newer.posts;
 
-    let search_queries = vec!["gamedev", "indiedev", "devlog", "game development"];
-    let since = (Utc::now() - chrono::Duration::hours(s.backfill.hours))
-        .format("%Y-%m-%dT%H:%M:%SZ")
-        .to_string();
+    if coverage.oldest_at > floor && posts.len() < max_posts {
+        match search_back(
+            client,
+            access_token,
+            query,
+            floor,
+            coverage.oldest_at,
+            max_posts - posts.len(),
+        )
+        .await
+        {
+            Ok(older) => {
+                coverage.oldest_at = older.oldest_at;
+                posts.extend(older.posts);
+            }
+            Err(e) => logs::log_backfill_query_failed(query, &e),
+        }
+    }
+    Some((posts, coverage))
+}
 
-    let mut all_posts: Vec<SearchPost> = Vec::new();
+/// Coverage after walking back from `started_at`. If the walk didn't reach what was covered
+/// before, the hole in between means only the new stretch counts, and the old one is walked
+/// again on the way back.
+fn after_newer_walk(previous: Option<Coverage>, newer: &Walk, started_at: i64) -> Coverage {
+    match previous {
+        Some(previous) if newer.reached_since => Coverage {
+            oldest_at: previous.oldest_at,
+            newest_at: started_at,
+        },
+        _ => Coverage {
+            oldest_at: newer.oldest_at,
+            newest_at: started_at,
+        },
+    }
+}
 
-    for query in &search_queries {
+/// Pages through a query newest-first, moving `until` back to the oldest post of each full page,
+/// for up to `backfill.max_pages_per_query` pages or `max_posts` posts.
+async fn search_back(
+    client: &reqwest::Client,
+    access_token: &str,
+    query: &str,
+    since: i64,
+    until: i64,
+    max_posts: usize,
+) -> Result<Walk, String> {
+    let s = settings();
+    let search_query = build_query(query, &s.backfill.exclude_terms);
+    let since_param = time::format_rfc3339(since).map_err(|e| e.to_string())?;
+    let mut until = until;
+    let mut posts = Vec::new();
+
+    for page in 0..s.backfill.max_pages_per_query {
+        let until_param = time::format_rfc3339(until).map_err(|e| e.to_string())?;
+        let batch = match search_posts(
+            client,
+            access_token,
+            &search_query,
+            s.backfill.search_limit,
+            Some(&since_param),
+            Some(&until_param),
+            SearchSort::Latest,
+        )
+        .await
+        {
+            Ok(batch) => batch,
+            Err(e) if page == 0 => return Err(e),
+            Err(e) => {
+                logs::log_backfill_query_failed(query, &e);
+                break;
+            }
+        };
+
+        let full = batch.len() >= s.backfill.search_limit as usize;
+        let oldest = batch
+            .iter()
+            .filter_map(|post| time::parse_rfc3339(&post.indexed_at).ok())
+            .map(|indexed_at| indexed_at.timestamp())
+            .min();
+        posts.extend(batch);
+
+        if !full {
+            return Ok(Walk {
+                posts,
+                oldest_at: since,
+                reached_since: true,
+            });
+        }
+        match oldest {
+            Some(oldest) if oldest < until => until = oldest,
+            _ => break,
+        }
+        if posts.len() >= max_posts {
+            break;
+        }
+    }
+
+    Ok(Walk {
+        posts,
+        oldest_at: until,
+        reached_since: false,
+    })
+}
+
+/// Searches for the firehose coverage gaps recorded by the handler, oldest first, and marks
+/// each one done once its time range has been backfilled.
+pub async fn run_gap_backfill(pool: DbPool, ml_handle: &MLHandle) {
+    let s = settings();
+    let now = Utc::now().timestamp();
+    let since = now - s.backfill.hours * 3600;
+
+    let gaps = match pool.get() {
+        Ok(mut conn) => {
+            let _ = db::cleanup_coverage_gaps(&mut conn, since);
+            db::get_pending_coverage_gaps(&mut conn, since).unwrap_or_default()
+        }
+        Err(_) => return,
+    };
+
+    for gap in gaps {
+        logs::log_gap_backfill_start(gap.started_at, gap.ended_at, &gap.reason);
+        if backfill_range(
+            &pool,
+            ml_handle,
+            gap.started_at.max(since),
+            Some(gap.ended_at),
+        )
+        .await
+        {
+            if let Ok(mut conn) = pool.get() {
+                let _ = db::mark_coverage_gap_backfilled(&mut conn, gap.id, Utc::now().timestamp());
+            }
+        }
+    }
+}
+
+async fn session(client: &reqwest::Client) -> Option<String> {
+    match create_session(client).await {
+        Ok(token) => Some(token),
+        Err(e) => {
+            logs::log_backfill_auth_failed(&e);
+            None
+        }
+    }
+}
+
+/// Runs the search queries over posts created between `since` and `until` and stores the
+/// accepted ones. Returns false when the search could not be performed at all.
+async fn backfill_range(
+    pool: &DbPool,
+    ml_handle: &MLHandle,
+    since: i64,
+    until: Option<i64>,
+) -> bool {
+    let s = settings();
+    let (since, until) = match (
+        time::format_rfc3339(since),
+        until.map(time::format_rfc3339).transpose(),
+    ) {
+        (Ok(since), Ok(until)) => (since, until),
+        (Err(e), _) | (_, Err(e)) => {
+            logs::log_backfill_query_failed("time range", &e.to_string());
+            return false;
+        }
+    };
+    let client = reqwest::Client::new();
+    let Some(access_token) = session(&client).await else {
+        return false;
+    };
+
+    let mut all_posts: Vec<(String, SearchPost)> = Vec::new();
+
+    for query in &s.backfill.queries {
         match search_posts(
             &client,
             &access_token,
-            query,
+            &build_query(query, &s.backfill.exclude_terms),
             s.backfill.search_limit,
             Some(&since),
+            until.as_deref(),
+            SearchSort::Top,
         )
         .await
         {
             Ok(posts) => {
                 logs::log_backfill_query(query, posts.len());
-                all_posts.extend(posts);
+                all_posts.extend(posts.into_iter().map(|post| (query.clone(), post)));
             }
             Err(e) => {
                 logs::log_backfill_query_failed(query, &e);
@@ -53,40 +445,69 @@ pub async fn run_backfill(pool: DbPool, ml_handle: &MLHandle) {
         }
     }
 
+    all_posts.truncate(s.backfill.limit);
+    store_posts(pool, ml_handle, &all_posts, PostSource::SearchPoller, false)
+        .await
+        .is_some()
+}
+
+/// Filters and scores searched posts, each with the query that found it, like the firehose
+/// does and stores the accepted ones, as coming from `source`, and each query's yield, unless
+/// `dry_run`. Returns `None` when the database couldn't be reached.
+async fn store_posts(
+    pool: &DbPool,
+    ml_handle: &MLHandle,
+    all_posts: &[(String, SearchPost)],
+    source: PostSource,
+    dry_run: bool,
+) -> Option<BackfillReport> {
+    let s = settings();
+    let mut report = BackfillReport {
+        dry_run,
+        searched: all_posts.len(),
+        ..BackfillReport::default()
+    };
     if all_posts.is_empty() {
         logs::log_backfill_complete(0, 0);
-        return;
+        return Some(report);
     }
 
-    let mut conn = match pool.get() {
-        Ok(c) => c,
-        Err(_) => return,
-    };
+    let embeddings = EmbeddingStore::new(pool.clone());
+    let mut conn = pool.get().ok()?;
 
-    let total_to_process = all_posts.len().min(s.backfill.limit);
+    let total_to_process = all_posts.len();
     let mut new_posts: Vec<NewPost> = Vec::new();
     let mut current = 0;
     let mut processed = 0;
-    let mut duplicates = 0;
-    let mut filtered = 0;
-    let mut no_relevance = 0;
+    let mut seen = HashSet::new();
 
-    for post in all_posts.iter().take(s.backfill.limit) {
+    for (query, post) in all_posts {
         current += 1;
         logs::log_backfill_progress(current, total_to_process);
 
-        if db::post_exists(&mut conn, &post.uri) {
-            duplicates += 1;
+        if !seen.insert(post.uri.as_str()) || db::post_exists(&mut conn, &post.uri) {
+            report.duplicates += 1;
+            report.record(query, post, DUPLICATE, None);
             continue;
         }
+        // Posts already stored or found by an earlier query don't count against a query's
+        // yield.
+        report.queries.entry(query.clone()).or_default().searched += 1;
 
         if post.record.reply.is_some() {
             continue;
         }
 
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&post.indexed_at)
-            .map(|dt| dt.timestamp())
-            .unwrap_or_else(|_| Utc::now().timestamp());
+        let (Ok(uri), Ok(author_did), Ok(indexed_at)) = (
+            post.uri.parse::<AtUri>(),
+            post.author.did.parse::<Did>(),
+            time::parse_rfc3339(&post.indexed_at),
+        ) else {
+            report.filtered += 1;
+            report.record(query, post, MALFORMED, None);
+            continue;
+        };
+        let timestamp = indexed_at.timestamp();
 
         processed += 1;
 
@@ -100,30 +521,50 @@ pub async fn run_backfill(pool: DbPool, ml_handle: &MLHandle) {
 
         let mut assessment = PostAssessment::new(text);
 
-        let mut media_info = extract_media_from_embed(&post.embed);
-        media_info.facet_links = extract_facet_links(&post.record.facets);
-
-        let filter_result = apply_filters(
-            text,
-            lang,
-            Some(&post.author.did),
-            &media_info,
-            |_| false,
-            |did| is_blocked_author(&mut conn, did),
-        );
+        let media_info =
+            MediaInfo::from_embed_view(post.embed.as_ref()).with_facets(&post.record.facets);
+
+        let filter_result = if db::is_opted_out_author(&mut conn, &post.author.did) {
+            FilterResult::Reject(Filter::AuthorOptOut)
+        } else {
+            apply_filters(
+                text,
+                lang,
+                Some(&post.author.did),
+                &media_info,
+                |_| false,
+                |did| is_blocked_author(&mut conn, did),
+            )
+        };
         assessment.set_filter_result(filter_result.clone());
-        if matches!(filter_result, FilterResult::Reject(_)) {
-            filtered += 1;
+        if filter_result == FilterResult::Reject(Filter::AuthorOptOut) && !dry_run {
+            let entry = NewOptedOutAuthor {
+                did: post.author.did.clone(),
+                post_uri: post.uri.clone(),
+                opted_out_at: Utc::now().timestamp(),
+            };
+            if let Ok(Some(deleted)) = db::opt_out_author(&mut conn, entry) {
+                logs::log_author_opted_out(&post.author.did, None, deleted.len());
+            }
+        }
+        if let FilterResult::Reject(filter) = &filter_result {
+            assessment.log();
+            report.filtered += 1;
+            report.record(query, post, &filter.to_string(), None);
             continue;
         }
 
         let is_influencer = s.filters.influencer_dids.contains(&post.author.did);
 
-        let (found_keywords, _) = has_keywords(text);
-        let (found_hashtags, _) = has_hashtags(text);
-        assessment.set_relevance(found_keywords, found_hashtags);
-        if !found_keywords && !found_hashtags && !is_influencer {
-            no_relevance += 1;
+        let keyword_matches = keyword_matches_for(text, lang);
+        let hashtag_matches = hashtag_matches_for(text, media_info.facet_tags.as_deref(), lang);
+        let relevant = !keyword_matches.is_empty() || !hashtag_matches.is_empty();
+        let keyword_score = weighted_keyword_score(&keyword_matches);
+        assessment.set_relevance(keyword_matches, hashtag_matches);
+        if !relevant && !is_influencer {
+            assessment.log();
+            report.no_relevance += 1;
+            report.record(query, post, NO_RELEVANCE, None);
             continue;
         }
 
@@ -132,144 +573,129 @@ pub async fn run_backfill(pool: DbPool, ml_handle: &MLHandle) {
         let content = extract_content_signals(text, &media_info);
         assessment.set_content(content.clone(), media_info.clone());
 
-        let signals = PrioritySignals::new(&quality, &content);
+        let mut signals = PrioritySignals::new(&quality, &content);
+        signals.keyword_score = keyword_score;
         let priority = calculate_priority(&signals);
         assessment.set_priority(quality, signals, priority.clone());
-        assessment.print();
+        assessment.log();
 
         if priority.priority < settings().scoring.rejection.min_priority {
-            filtered += 1;
+            report.filtered += 1;
+            let low_priority = Filter::LowPriority.to_string();
+            report.record(query, post, &low_priority, Some(priority.priority));
             continue;
         }
+        report.record(query, post, ACCEPTED, Some(priority.priority));
+        if dry_run {
+            continue;
+        }
+
+        embeddings.get_or_embed(ml_handle, &post.uri, text).await;
 
         let new_post = NewPost::new(
-            post.uri.clone(),
+            uri,
             text.clone(),
             timestamp,
             priority.priority,
             &media_info,
             &content,
-            Some(post.author.did.clone()),
-        );
+            Some(author_did),
+        )
+        .with_lang(s.filters.feed_language(lang))
+        .with_source(source)
+        .with_config_id(config_history::active_config_id(
+            pool,
+            Utc::now().timestamp(),
+        ));
 
         new_posts.push(new_post);
-
-        if new_posts.len() >= s.backfill.limit {
-            break;
-        }
     }
 
-    let accepted = new_posts.len();
-    logs::log_backfill_stats(duplicates, filtered, no_relevance);
+    logs::log_backfill_stats(report.duplicates, report.filtered, report.no_relevance);
+    for (query, yielded) in &report.queries {
+        logs::log_backfill_query_yield(query, yielded.searched, yielded.accepted);
+    }
+    if !dry_run {
+        let now = Utc::now().timestamp();
+        let stats = report
+            .queries
+            .iter()
+            .map(|(query, yielded)| NewQueryStat {
+                query: query.clone(),
+                day: now - now.rem_euclid(86400),
+                searched: yielded.searched as i32,
+                accepted: yielded.accepted as i32,
+            })
+            .collect();
+        let _ = db::record_query_stats(&mut conn, stats);
+    }
     if !new_posts.is_empty() {
         let _ = db::insert_posts(&mut conn, new_posts);
     }
 
-    logs::log_backfill_complete(accepted, processed);
+    logs::log_backfill_complete(report.accepted, processed);
+    Some(report)
 }
 
-fn extract_media_from_embed(embed: &Option<serde_json::Value>) -> MediaInfo {
-    let Some(embed) = embed else {
-        return MediaInfo::default();
-    };
-
-    let embed_type = embed.get("$type").and_then(|t| t.as_str()).unwrap_or("");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    match embed_type {
-        "app.bsky.embed.video#view" => MediaInfo {
-            image_count: 0,
-            has_video: true,
-            has_alt_text: false,
-            external_uri: None,
-            facet_links: Vec::new(),
-        },
-        "app.bsky.embed.images#view" => {
-            let images = embed.get("images").and_then(|i| i.as_array());
-            let count = images.map(|arr| arr.len()).unwrap_or(0).min(255) as u8;
-            let has_alt = images
-                .map(|arr| {
-                    arr.iter().any(|img| {
-                        img.get("alt")
-                            .and_then(|a| a.as_str())
-                            .map(|s| !s.is_empty())
-                            .unwrap_or(false)
-                    })
-                })
-                .unwrap_or(false);
-            MediaInfo {
-                image_count: count,
-                has_video: false,
-                has_alt_text: has_alt,
-                external_uri: None,
-                facet_links: Vec::new(),
-            }
+    fn walk(oldest_at: i64, reached_since: bool) -> Walk {
+        Walk {
+            posts: Vec::new(),
+            oldest_at,
+            reached_since,
         }
-        "app.bsky.embed.external#view" => {
-            let uri = embed
-                .get("external")
-                .and_then(|e| e.get("uri"))
-                .and_then(|u| u.as_str())
-                .map(|s| s.to_string());
-            MediaInfo {
-                image_count: 0,
-                has_video: false,
-                has_alt_text: false,
-                external_uri: uri,
-                facet_links: Vec::new(),
+    }
+
+    #[test]
+    fn test_after_newer_walk() {
+        let previous = Coverage {
+            oldest_at: 1_000,
+            newest_at: 5_000,
+        };
+
+        // Caught up with the last run: the old coverage is extended to now.
+        let joined = after_newer_walk(Some(previous), &walk(5_000, true), 9_000);
+        assert_eq!(
+            joined,
+            Coverage {
+                oldest_at: 1_000,
+                newest_at: 9_000
             }
-        }
-        "app.bsky.embed.recordWithMedia#view" => {
-            if let Some(media) = embed.get("media") {
-                let media_type = media.get("$type").and_then(|t| t.as_str()).unwrap_or("");
-                match media_type {
-                    "app.bsky.embed.video#view" => MediaInfo {
-                        image_count: 0,
-                        has_video: true,
-                        has_alt_text: false,
-                        external_uri: None,
-                        facet_links: Vec::new(),
-                    },
-                    "app.bsky.embed.images#view" => {
-                        let images = media.get("images").and_then(|i| i.as_array());
-                        let count = images.map(|arr| arr.len()).unwrap_or(0).min(255) as u8;
-                        let has_alt = images
-                            .map(|arr| {
-                                arr.iter().any(|img| {
-                                    img.get("alt")
-                                        .and_then(|a| a.as_str())
-                                        .map(|s| !s.is_empty())
-                                        .unwrap_or(false)
-                                })
-                            })
-                            .unwrap_or(false);
-                        MediaInfo {
-                            image_count: count,
-                            has_video: false,
-                            has_alt_text: has_alt,
-                            external_uri: None,
-                            facet_links: Vec::new(),
-                        }
-                    }
-                    "app.bsky.embed.external#view" => {
-                        let uri = media
-                            .get("external")
-                            .and_then(|e| e.get("uri"))
-                            .and_then(|u| u.as_str())
-                            .map(|s| s.to_string());
-                        MediaInfo {
-                            image_count: 0,
-                            has_video: false,
-                            has_alt_text: false,
-                            external_uri: uri,
-                            facet_links: Vec::new(),
-                        }
-                    }
-                    _ => MediaInfo::default(),
-                }
-            } else {
-                MediaInfo::default()
+        );
+
+        // Ran out of pages before reaching it: only the new stretch is trusted.
+        let gapped = after_newer_walk(Some(previous), &walk(7_000, false), 9_000);
+        assert_eq!(
+            gapped,
+            Coverage {
+                oldest_at: 7_000,
+                newest_at: 9_000
             }
-        }
-        _ => MediaInfo::default(),
+        );
+
+        let first_run = after_newer_walk(None, &walk(2_000, true), 9_000);
+        assert_eq!(first_run.oldest_at, 2_000);
+    }
+
+    #[test]
+    fn test_build_query() {
+        let exclude = vec![
+            "giveaway".to_string(),
+            "steam keys".to_string(),
+            " ".to_string(),
+        ];
+        assert_eq!(
+            build_query("#devlog", &exclude),
+            "#devlog -giveaway -\"steam keys\""
+        );
+        // Terms the query already excludes aren't repeated.
+        assert_eq!(
+            build_query("gamedev -Giveaway", &exclude),
+            "gamedev -Giveaway -\"steam keys\""
+        );
+        assert_eq!(build_query(" indiedev ", &[]), "indiedev");
     }
 }
@@ -0,0 +1,93 @@
+use devlogs_feed::backfill;
+use devlogs_feed::db::establish_pool;
+use devlogs_feed::scoring::MLHandle;
+use devlogs_feed::settings::{override_settings, settings};
+use devlogs_feed::utils::logs::{self, dim};
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str =
+    "usage: backfill [--dry-run [--query <query>]... [--report <file>]] [--with <path>=<value>]...";
+
+fn value(args: &mut impl Iterator<Item = String>) -> String {
+    args.next().unwrap_or_else(|| {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+
+    let mut dry_run = false;
+    let mut queries = Vec::new();
+    let mut report_path = None;
+    let mut overrides = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" | "-n" => dry_run = true,
+            "--query" | "-q" => queries.push(value(&mut args)),
+            "--report" | "-r" => report_path = Some(value(&mut args)),
+            "--with" | "-w" => overrides.push(value(&mut args)),
+            _ => {
+                eprintln!("{USAGE}");
+                process::exit(1);
+            }
+        }
+    }
+
+    // Without --dry-run this is the startup backfill, which searches its own queries and keeps
+    // no report.
+    if !dry_run && (!queries.is_empty() || report_path.is_some()) {
+        eprintln!("error: --query and --report need --dry-run");
+        process::exit(1);
+    }
+    if let Err(e) = override_settings(&overrides) {
+        eprintln!("error: invalid --with: {}", e);
+        process::exit(1);
+    }
+    for assignment in &overrides {
+        println!("{}", dim().apply_to(format!("with {assignment}")));
+    }
+    if queries.is_empty() {
+        queries = settings().backfill.queries.clone();
+    }
+
+    let database_url =
+        env::var("DATABASE_URL").unwrap_or_else(|_| settings().server.database_url.clone());
+    let pool = establish_pool(&database_url);
+
+    logs::log_ml_loading();
+    let ml_handle = match MLHandle::spawn() {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("error: failed to spawn ml handle: {}", e);
+            process::exit(1);
+        }
+    };
+    logs::log_ml_ready();
+
+    if !dry_run {
+        backfill::run_backfill(pool, &ml_handle).await;
+        return;
+    }
+
+    let Some(report) = backfill::dry_run(&pool, &ml_handle, &queries).await else {
+        eprintln!("error: backfill search or database unavailable");
+        process::exit(1);
+    };
+    logs::log_backfill_dry_run(&report.outcomes);
+
+    if let Some(path) = report_path {
+        let json = serde_json::to_string_pretty(&report).expect("Failed to serialize report");
+        if let Err(e) = fs::write(&path, json) {
+            eprintln!("error: failed to write {}: {}", path, e);
+            process::exit(1);
+        }
+        logs::log_backfill_report_written(&path);
+    }
+}
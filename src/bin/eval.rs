@@ -0,0 +1,94 @@
+use devlogs_feed::eval::{evaluate, EvalReport, Label, LabeledPost, Outcome};
+use devlogs_feed::scoring::MLHandle;
+use devlogs_feed::utils::logs::{self, dim, pad_label};
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "usage: eval <dataset.jsonl> [--misses|-m]";
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let show_misses = args.iter().any(|a| a == "--misses" || a == "-m");
+    let Some(path) = args.iter().find(|a| !a.starts_with('-')) else {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    };
+
+    let contents = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("error: failed to read {}: {}", path, e);
+        process::exit(1);
+    });
+    let posts: Vec<LabeledPost> = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).unwrap_or_else(|e| {
+                eprintln!("error: {}:{}: {}", path, index + 1, e);
+                process::exit(1);
+            })
+        })
+        .collect();
+
+    logs::log_ml_loading();
+    let ml_handle = MLHandle::spawn().unwrap_or_else(|e| {
+        eprintln!("error: failed to spawn ml handle: {}", e);
+        process::exit(1);
+    });
+    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    logs::log_ml_ready();
+
+    let mut report = EvalReport::default();
+    let mut misses: Vec<(&LabeledPost, Outcome)> = Vec::new();
+    for post in &posts {
+        let outcome = evaluate(post, &ml_handle).await;
+        report.record(post.label, &outcome);
+        if (post.label == Label::Accept) != matches!(outcome, Outcome::Accepted { .. }) {
+            misses.push((post, outcome));
+        }
+    }
+
+    if show_misses {
+        for (post, outcome) in &misses {
+            let expected = match post.label {
+                Label::Accept => "accept",
+                Label::Reject => "reject",
+            };
+            println!(
+                "{} {} \"{}\"",
+                dim().apply_to(format!("expected {expected}, got")),
+                outcome,
+                post.text.replace('\n', " ")
+            );
+        }
+        println!();
+    }
+
+    print_report(&report);
+}
+
+fn print_report(report: &EvalReport) {
+    println!("{}{}", pad_label("posts", 0), report.total());
+    println!("{}{:.3}", pad_label("precision", 0), report.precision());
+    println!("{}{:.3}", pad_label("recall", 0), report.recall());
+    println!("{}{:.3}", pad_label("f1", 0), report.f1());
+    println!();
+    println!(
+        "{}{:>10}{:>10}",
+        pad_label("outcome", 0),
+        "accept",
+        "reject"
+    );
+    for (reason, counts) in &report.by_reason {
+        println!(
+            "{}{:>10}{:>10}",
+            pad_label(reason, 0),
+            counts.labeled_accept,
+            counts.labeled_reject
+        );
+    }
+}
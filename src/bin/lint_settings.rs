@@ -0,0 +1,18 @@
+use devlogs_feed::lint::lint_filters;
+use devlogs_feed::settings::settings;
+use devlogs_feed::utils::logs;
+use std::process;
+
+fn main() {
+    dotenvy::dotenv().ok();
+
+    let warnings = lint_filters(&settings().filters);
+    for warning in &warnings {
+        logs::log_lint_warning(&warning.list, &warning.entry, &warning.message);
+    }
+    logs::log_lint_summary(warnings.len());
+
+    if !warnings.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::Utc;
+use devlogs_feed::db::{self, establish_pool, NewAuditEntry, NewBlockedAuthor};
+use devlogs_feed::engagement::EngagementTracker;
+use devlogs_feed::settings::{settings, AdminRole};
+use devlogs_feed::utils::ids::{AtUri, Did};
+use devlogs_feed::utils::logs;
+use diesel::prelude::*;
+use std::env;
+use std::process;
+
+const USAGE: &str =
+    "usage: moderate <block <did|url>|unblock <did>|list-spammers|list-blocked|purge-author <did>>";
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {message}");
+    process::exit(1);
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(1);
+}
+
+fn parse_did(value: &str) -> Did {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("{value:?} is not a DID")))
+}
+
+/// The author behind a DID, an `at://` URI or a bsky.app post URL, and the post it named.
+/// Handles are looked up in the handle cache.
+fn resolve_author(conn: &mut SqliteConnection, target: &str) -> (Did, Option<AtUri>) {
+    if let Ok(did) = target.parse::<Did>() {
+        return (did, None);
+    }
+    let uri = target
+        .parse::<AtUri>()
+        .or_else(|_| AtUri::from_permalink(target))
+        .unwrap_or_else(|_| fail(format!("{target:?} is neither a DID nor a post URI or URL")));
+    let did = uri
+        .did()
+        .or_else(|| db::get_did_for_handle(conn, uri.authority())?.parse().ok())
+        .unwrap_or_else(|| {
+            fail(format!(
+                "unknown handle {}, pass the author's DID instead",
+                uri.authority()
+            ))
+        });
+    (did, Some(uri))
+}
+
+/// Logs the action like an admin API call and records it in the audit log.
+fn audit(conn: &mut SqliteConnection, action: &str, target: &str) {
+    let actor = env::var("USER").unwrap_or_else(|_| "moderate".to_string());
+    logs::log_admin_action(&actor, action, target);
+    let entry = NewAuditEntry {
+        actor,
+        role: AdminRole::Owner.to_string(),
+        action: action.to_string(),
+        target: target.to_string(),
+        created_at: Utc::now().timestamp(),
+    };
+    if let Err(e) = db::insert_audit_entry(conn, entry) {
+        eprintln!("warning: failed to record the audit entry: {}", e);
+    }
+}
+
+fn main() {
+    dotenvy::dotenv().ok();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (command, target) = match args.as_slice() {
+        [command] => (command.as_str(), None),
+        [command, target] => (command.as_str(), Some(target.as_str())),
+        _ => usage(),
+    };
+
+    let database_url =
+        env::var("DATABASE_URL").unwrap_or_else(|_| settings().server.database_url.clone());
+    let pool = establish_pool(&database_url);
+    let mut conn = pool.get().expect("Failed to get connection");
+
+    match (command, target) {
+        ("block", Some(target)) => {
+            let (did, post_uri) = resolve_author(&mut conn, target);
+            let blocked = NewBlockedAuthor {
+                did: did.to_string(),
+                post_uri: post_uri.map(|uri| uri.to_string()).unwrap_or_default(),
+                blocked_at: Utc::now().timestamp(),
+            };
+            let (inserted, deleted) = conn
+                .transaction(|conn| {
+                    let inserted = db::block_author(conn, blocked)?;
+                    Ok::<_, diesel::result::Error>((
+                        inserted,
+                        db::delete_posts_by_author(conn, did.as_str())?.len(),
+                    ))
+                })
+                .unwrap_or_else(|e| fail(format!("failed to block {did}: {e}")));
+            if inserted == 0 {
+                println!("{did} was already blocked");
+            }
+            audit(&mut conn, "blocked author", did.as_str());
+            logs::log_author_posts_deleted(did.as_str(), deleted);
+        }
+        ("unblock", Some(target)) => {
+            let did = parse_did(target);
+            let deleted = db::unblock_author(&mut conn, did.as_str())
+                .unwrap_or_else(|e| fail(format!("failed to unblock {did}: {e}")));
+            if deleted == 0 {
+                fail(format!("{did} is not blocked"));
+            }
+            audit(&mut conn, "unblocked author", did.as_str());
+        }
+        ("purge-author", Some(target)) => {
+            let did = parse_did(target);
+            let deleted = db::delete_posts_by_author(&mut conn, did.as_str())
+                .unwrap_or_else(|e| fail(format!("failed to delete posts by {did}: {e}")))
+                .len();
+            audit(&mut conn, "purged author", did.as_str());
+            logs::log_author_posts_deleted(did.as_str(), deleted);
+        }
+        ("list-spammers", None) => {
+            let spammers = EngagementTracker::new(pool.clone())
+                .get_spammers()
+                .unwrap_or_else(|e| fail(format!("failed to read spammers: {e}")));
+            for spammer in &spammers {
+                let detection = if spammer.auto_detected != 0 {
+                    "auto"
+                } else {
+                    "manual"
+                };
+                logs::log_listed_author(
+                    spammer.flagged_at,
+                    &spammer.did,
+                    &format!("{detection:<6}  {}", spammer.reason),
+                );
+            }
+            println!("{} spammers", spammers.len());
+        }
+        ("list-blocked", None) => {
+            let blocked = db::get_blocked_authors(&mut conn)
+                .unwrap_or_else(|e| fail(format!("failed to read blocked authors: {e}")));
+            for author in &blocked {
+                logs::log_listed_author(author.blocked_at, &author.did, &author.post_uri);
+            }
+            println!("{} blocked authors", blocked.len());
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,267 @@
+use devlogs_feed::cursor::{FeedCursor, RankKey};
+use devlogs_feed::db::{self, establish_pool, Post};
+use devlogs_feed::ranking::{
+    bucket_seconds, order_reason, rank_feed, rank_post, within_tier_ttl, OrderReason,
+    RankBreakdown, RankContext, Reader,
+};
+use devlogs_feed::settings::settings;
+use devlogs_feed::utils::bluesky::fetch_follows;
+use devlogs_feed::utils::logs::{dim, pad_label};
+use std::collections::HashMap;
+use std::env;
+use std::process;
+use std::sync::Arc;
+
+const USAGE: &str = "usage: rank-diff <uri-a> <uri-b> [--user <did>] [--seed <hex|cursor>]";
+
+/// One post's side of the comparison.
+struct Side<'a> {
+    post: &'a Post,
+    engagement_boost: f32,
+    breakdown: RankBreakdown,
+    seen: bool,
+    expired: bool,
+    position: Option<usize>,
+}
+
+impl Side<'_> {
+    fn key(&self) -> RankKey<'_> {
+        RankKey {
+            timestamp: self.post.timestamp,
+            priority: self.breakdown.adjusted_priority,
+            uri: &self.post.uri,
+        }
+    }
+
+    fn placement(&self) -> String {
+        match self.position {
+            Some(index) => format!("#{}", index + 1),
+            None if self.seen => "seen".to_string(),
+            None if self.expired => "tier expired".to_string(),
+            None => "sampled out".to_string(),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut uris = Vec::new();
+    let mut user = None;
+    let mut seed = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--user" => user = iter.next().cloned(),
+            "--seed" => seed = iter.next().map(|value| parse_seed(value)),
+            _ => uris.push(arg.clone()),
+        }
+    }
+    let [uri_a, uri_b] = uris.as_slice() else {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    };
+
+    let s = settings();
+    let database_url = env::var("DATABASE_URL").unwrap_or_else(|_| s.server.database_url.clone());
+    let pool = establish_pool(&database_url);
+    let mut conn = pool.get().expect("Failed to get connection");
+
+    let now = chrono::Utc::now().timestamp();
+    let cutoff = now - (s.feed.cutoff_hours * 3600);
+
+    let (lang_a, lang_b) = (
+        feed_lang(&mut conn, uri_a, cutoff),
+        feed_lang(&mut conn, uri_b, cutoff),
+    );
+    if lang_a != lang_b {
+        eprintln!("error: the posts are in different feeds ({lang_a}, {lang_b})");
+        process::exit(1);
+    }
+
+    let posts = db::get_language_feed(&mut conn, cutoff, &lang_a).unwrap_or_else(|e| {
+        eprintln!("error: failed to read the feed: {}", e);
+        process::exit(1);
+    });
+    let mut reader = user
+        .as_deref()
+        .map(|did| Reader::load(&mut conn, did, cutoff, now))
+        .unwrap_or_default();
+    if let (Some(did), true) = (&user, s.feed.follows.enabled) {
+        let client = reqwest::Client::new();
+        match fetch_follows(&client, did, s.feed.follows.max_follows).await {
+            Ok(follows) => reader.follows = Arc::new(follows.into_iter().collect()),
+            Err(e) => eprintln!("warning: failed to fetch follows, not applying them: {}", e),
+        }
+    }
+    if reader.interests.is_some() {
+        let embeddings: HashMap<String, Vec<f32>> = db::get_feed_embeddings(&mut conn, cutoff)
+            .map(|rows| rows.into_iter().collect())
+            .unwrap_or_default();
+        reader.match_interests(&posts, |uri| embeddings.get(uri).map(Vec::as_slice));
+    }
+    let ctx = RankContext::new(&posts, now, seed.unwrap_or_else(rand::random));
+    let ranked = rank_feed(&posts, &reader, &ctx);
+
+    let side = |uri: &str, conn: &mut _| {
+        let post = posts
+            .iter()
+            .find(|p| p.uri == uri)
+            .expect("post is in the feed");
+        Side {
+            post,
+            engagement_boost: db::get_engagement_boost(conn, uri).unwrap_or(0.0),
+            breakdown: rank_post(post, &reader, &ctx),
+            seen: reader.seen.contains(uri),
+            expired: !within_tier_ttl(post, now),
+            position: ranked.iter().position(|(p, _)| p.uri == uri),
+        }
+    };
+    let a = side(uri_a, &mut conn);
+    let b = side(uri_b, &mut conn);
+
+    print_diff(&a, &b, &lang_a, user.as_deref(), &ctx);
+}
+
+/// A bare hex seed, or a feed cursor to reuse the seed of the session it came from.
+fn parse_seed(value: &str) -> u64 {
+    value
+        .parse::<FeedCursor>()
+        .map(|cursor| cursor.seed)
+        .or_else(|_| u64::from_str_radix(value, 16))
+        .unwrap_or_else(|_| {
+            eprintln!("error: {value:?} is neither a hex seed nor a feed cursor");
+            process::exit(1);
+        })
+}
+
+/// The language feed a post is served in, exiting if it isn't in any feed right now.
+fn feed_lang(conn: &mut diesel::SqliteConnection, uri: &str, cutoff: i64) -> String {
+    match db::get_posts_by_uris(conn, &[uri.to_string()]).map(|mut p| p.pop()) {
+        Ok(Some(post)) if post.timestamp > cutoff => post.lang,
+        Ok(Some(_)) => {
+            eprintln!("error: {uri} is past the feed cutoff");
+            process::exit(1);
+        }
+        Ok(None) => {
+            eprintln!("error: {uri} is not in the feed");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("error: failed to read {uri}: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn print_diff(a: &Side, b: &Side, lang: &str, user: Option<&str>, ctx: &RankContext) {
+    let bucket_seconds = bucket_seconds();
+    let age = |side: &Side| format!("{:.1}h", (ctx.now - side.post.timestamp) as f32 / 3600.0);
+    let bucket = |side: &Side| {
+        format!(
+            "-{}",
+            ctx.now / bucket_seconds.max(1) - side.post.timestamp / bucket_seconds.max(1)
+        )
+    };
+    let signed = |value: f32| format!("{value:+.3}");
+    let times = |value: f32| format!("×{value:.3}");
+
+    let (ab, bb) = (&a.breakdown, &b.breakdown);
+    let rows = [
+        ("position", a.placement(), b.placement()),
+        ("age", age(a), age(b)),
+        ("time bucket", bucket(a), bucket(b)),
+        (
+            "base score",
+            signed(ab.stored_priority - a.engagement_boost),
+            signed(bb.stored_priority - b.engagement_boost),
+        ),
+        (
+            "engagement boost",
+            signed(a.engagement_boost),
+            signed(b.engagement_boost),
+        ),
+        (
+            "thread boost",
+            signed(ab.thread_boost),
+            signed(bb.thread_boost),
+        ),
+        (
+            "bounded",
+            signed(ab.bounded_priority),
+            signed(bb.bounded_priority),
+        ),
+        ("preference", times(ab.preference), times(bb.preference)),
+        ("follow", times(ab.follow), times(bb.follow)),
+        ("interest", times(ab.interest), times(bb.interest)),
+        ("affinity", times(ab.affinity), times(bb.affinity)),
+        (
+            "hour balance",
+            times(ab.hour_balance),
+            times(bb.hour_balance),
+        ),
+        ("decay", times(ab.decay), times(bb.decay)),
+        ("jitter", times(1.0 + ab.jitter), times(1.0 + bb.jitter)),
+        (
+            "adjusted priority",
+            signed(ab.adjusted_priority),
+            signed(bb.adjusted_priority),
+        ),
+    ];
+
+    println!("A  {}", a.post.uri);
+    println!("B  {}", b.post.uri);
+    println!(
+        "{}",
+        dim().apply_to(format!(
+            "feed {lang} · reader {} · seed {:x} · buckets of {}h",
+            user.unwrap_or("anonymous"),
+            ctx.seed,
+            bucket_seconds / 3600
+        ))
+    );
+    println!();
+    println!("{}{:>14}{:>14}", pad_label("", 0), "A", "B");
+    for (label, left, right) in rows {
+        println!("{}{left:>14}{right:>14}", pad_label(label, 0));
+    }
+    println!();
+    println!("{}", verdict(a, b, bucket_seconds));
+}
+
+fn verdict(a: &Side, b: &Side, bucket_seconds: i64) -> String {
+    let (Some(pos_a), Some(pos_b)) = (a.position, b.position) else {
+        let missing: Vec<&str> = [("A", a), ("B", b)]
+            .iter()
+            .filter(|(_, side)| side.position.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+        return format!("{} not in this reader's feed", missing.join(" and "));
+    };
+    let (above, below, names) = if pos_a < pos_b {
+        (a, b, ("A", "B"))
+    } else {
+        (b, a, ("B", "A"))
+    };
+
+    match order_reason(above.key(), below.key(), bucket_seconds) {
+        Some(OrderReason::NewerBucket) => format!(
+            "{} ranks above {}: it's in a newer time bucket, so priority isn't compared",
+            names.0, names.1
+        ),
+        Some(OrderReason::HigherPriority) => format!(
+            "{} ranks above {}: same time bucket, higher adjusted priority ({:.3} vs {:.3})",
+            names.0, names.1, above.breakdown.adjusted_priority, below.breakdown.adjusted_priority
+        ),
+        Some(OrderReason::UriTiebreak) => format!(
+            "{} ranks above {}: same bucket and priority, tie broken by URI",
+            names.0, names.1
+        ),
+        None => format!(
+            "{} ranks above {}: the author cap deferred {} past the window's limit",
+            names.0, names.1, names.1
+        ),
+    }
+}
@@ -1,68 +1,67 @@
 use devlogs_feed::scoring::{
-    apply_filters, calculate_priority, extract_content_signals, has_hashtags, has_keywords,
-    FilterResult, MLHandle, MediaInfo, PrioritySignals,
+    apply_filters, calculate_priority, extract_content_signals, hashtag_matches_for,
+    keyword_matches_for, weighted_keyword_score, FilterResult, MLHandle, MediaInfo,
+    PrioritySignals,
 };
+use devlogs_feed::settings::{override_settings, settings};
 use devlogs_feed::utils::bluesky::{fetch_post, parse_bluesky_url};
-use devlogs_feed::utils::logs::{self, PostAssessment};
+use devlogs_feed::utils::logs::{self, dim, PostAssessment};
 use std::env;
 use std::process;
 
+const USAGE: &str =
+    "usage: score-post <url_or_text> [--media|-m] [--video|-v] [--alt|-a] [--with <path>=<value>]...";
+
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
-    let has_media_flag = args.iter().any(|a| a == "--media" || a == "-m");
-    let has_video_flag = args.iter().any(|a| a == "--video" || a == "-v");
-    let has_alt_flag = args.iter().any(|a| a == "--alt" || a == "-a");
-
-    let text_args: Vec<&String> = args
-        .iter()
-        .skip(1)
-        .filter(|a| {
-            *a != "--media"
-                && *a != "-m"
-                && *a != "--video"
-                && *a != "-v"
-                && *a != "--alt"
-                && *a != "-a"
-        })
-        .collect();
+    let mut has_media_flag = false;
+    let mut has_video_flag = false;
+    let mut has_alt_flag = false;
+    let mut overrides = Vec::new();
+    let mut text_args = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--media" | "-m" => has_media_flag = true,
+            "--video" | "-v" => has_video_flag = true,
+            "--alt" | "-a" => has_alt_flag = true,
+            "--with" | "-w" => match args.next() {
+                Some(assignment) => overrides.push(assignment),
+                None => {
+                    eprintln!("{USAGE}");
+                    process::exit(1);
+                }
+            },
+            _ => text_args.push(arg),
+        }
+    }
 
     if text_args.is_empty() {
-        eprintln!("usage: score-post <url_or_text> [--media|-m] [--video|-v] [--alt|-a]");
+        eprintln!("{USAGE}");
         process::exit(1);
     }
 
-    let input = text_args
-        .iter()
-        .map(|s| s.as_str())
-        .collect::<Vec<_>>()
-        .join(" ");
+    if let Err(e) = override_settings(&overrides) {
+        eprintln!("error: invalid --with: {}", e);
+        process::exit(1);
+    }
+    for assignment in &overrides {
+        println!("{}", dim().apply_to(format!("with {assignment}")));
+    }
+
+    let input = text_args.join(" ");
 
     let (text, media_info) = if let Some(at_uri) = parse_bluesky_url(&input) {
-        match fetch_post(&at_uri).await {
-            Ok(post) => {
-                let media = MediaInfo {
-                    image_count: post.image_count.min(255) as u8,
-                    has_video: post.has_video,
-                    has_alt_text: false,
-                    external_uri: post.external_uri,
-                    facet_links: post.facet_links,
-                };
-                (post.text, media)
-            }
+        match fetch_post(at_uri.as_str()).await {
+            Ok(post) => (post.text, post.media),
             Err(e) => {
                 eprintln!("error: failed to fetch post: {}", e);
                 process::exit(1);
             }
         }
     } else {
-        let media = MediaInfo {
-            image_count: if has_media_flag { 1 } else { 0 },
-            has_video: has_video_flag,
-            has_alt_text: has_alt_flag,
-            external_uri: None,
-            facet_links: Vec::new(),
-        };
+        let media = MediaInfo::from_flags(has_media_flag, has_video_flag, has_alt_flag);
         (input, media)
     };
 
@@ -91,11 +90,13 @@ async fn score_post(text: &str, media: &MediaInfo, ml_handle: &MLHandle) {
         return;
     }
 
-    let (found_keywords, _) = has_keywords(text);
-    let (found_hashtags, _) = has_hashtags(text);
-    assessment.set_relevance(found_keywords, found_hashtags);
+    let keyword_matches = keyword_matches_for(text, None);
+    let hashtag_matches = hashtag_matches_for(text, media.facet_tags.as_deref(), None);
+    let relevant = !keyword_matches.is_empty() || !hashtag_matches.is_empty();
+    let keyword_score = weighted_keyword_score(&keyword_matches);
+    assessment.set_relevance(keyword_matches, hashtag_matches);
 
-    if !found_keywords && !found_hashtags {
+    if !relevant {
         assessment.print();
         return;
     }
@@ -105,17 +106,22 @@ async fn score_post(text: &str, media: &MediaInfo, ml_handle: &MLHandle) {
     let content = extract_content_signals(text, media);
     assessment.set_content(content.clone(), media.clone());
 
-    let signals = PrioritySignals::new(&quality, &content);
+    let mut signals = PrioritySignals::new(&quality, &content);
+    signals.keyword_score = keyword_score;
     let priority = calculate_priority(&signals);
+    let below_minimum = priority.priority < settings().scoring.rejection.min_priority;
     assessment.set_priority(quality, signals, priority);
+    if below_minimum {
+        assessment.reject_low_priority();
+    }
     assessment.print();
 }
 
 #[cfg(test)]
 mod tests {
     use devlogs_feed::scoring::{
-        apply_filters, extract_content_signals, has_hashtags, has_keywords, FilterResult, MLHandle,
-        MediaInfo, PrioritySignals,
+        apply_filters, extract_content_signals, has_hashtags_for, has_keywords, FilterResult,
+        MLHandle, PrioritySignals,
     };
     use devlogs_feed::utils::bluesky::{fetch_post, parse_bluesky_url};
 
@@ -127,17 +133,11 @@ mod tests {
 
     async fn evaluate_url(url: &str, ml_handle: &MLHandle) -> bool {
         let at_uri = parse_bluesky_url(url).expect("Invalid URL format");
-        let post = fetch_post(&at_uri)
+        let post = fetch_post(at_uri.as_str())
             .await
             .unwrap_or_else(|e| panic!("Failed to fetch {}: {}", url, e));
 
-        let media = MediaInfo {
-            image_count: post.image_count.min(255) as u8,
-            has_video: post.has_video,
-            has_alt_text: false,
-            external_uri: post.external_uri.clone(),
-            facet_links: post.facet_links.clone(),
-        };
+        let media = post.media;
 
         let filter_result =
             apply_filters(&post.text, Some("en"), None, &media, |_| false, |_| false);
@@ -146,7 +146,7 @@ mod tests {
         }
 
         let (found_keywords, _) = has_keywords(&post.text);
-        let (found_hashtags, _) = has_hashtags(&post.text);
+        let (found_hashtags, _) = has_hashtags_for(&post.text, media.facet_tags.as_deref(), None);
         if !found_keywords && !found_hashtags {
             return false;
         }
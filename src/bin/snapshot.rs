@@ -0,0 +1,81 @@
+use devlogs_feed::db::establish_pool;
+use devlogs_feed::settings::settings;
+use devlogs_feed::snapshot::{self, Snapshot, SNAPSHOT_VERSION};
+use devlogs_feed::utils::logs;
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    dotenvy::dotenv().ok();
+
+    let args: Vec<String> = env::args().collect();
+    let (command, path) = match (args.get(1), args.get(2)) {
+        (Some(command), Some(path)) => (command.as_str(), path.as_str()),
+        _ => {
+            eprintln!("usage: snapshot <export|import|export-archive> <file>");
+            process::exit(1);
+        }
+    };
+
+    let database_url =
+        env::var("DATABASE_URL").unwrap_or_else(|_| settings().server.database_url.clone());
+    let pool = establish_pool(&database_url);
+    let mut conn = pool.get().expect("Failed to get connection");
+
+    match command {
+        "export" => {
+            let cutoff = chrono::Utc::now().timestamp() - (settings().feed.cutoff_hours * 3600);
+            let snapshot = snapshot::export(&mut conn, cutoff).unwrap_or_else(|e| {
+                eprintln!("error: failed to read posts: {}", e);
+                process::exit(1);
+            });
+            let count = snapshot.posts.len();
+            let json = serde_json::to_string(&snapshot).expect("Failed to serialize snapshot");
+            if let Err(e) = fs::write(path, json) {
+                eprintln!("error: failed to write {}: {}", path, e);
+                process::exit(1);
+            }
+            logs::log_snapshot_exported(count, path);
+        }
+        "export-archive" => {
+            let archive = snapshot::export_archive(&mut conn).unwrap_or_else(|e| {
+                eprintln!("error: failed to read archived posts: {}", e);
+                process::exit(1);
+            });
+            let count = archive.posts.len();
+            let json = serde_json::to_string(&archive).expect("Failed to serialize archive");
+            if let Err(e) = fs::write(path, json) {
+                eprintln!("error: failed to write {}: {}", path, e);
+                process::exit(1);
+            }
+            logs::log_archive_exported(count, path);
+        }
+        "import" => {
+            let contents = fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("error: failed to read {}: {}", path, e);
+                process::exit(1);
+            });
+            let snapshot: Snapshot = serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("error: invalid snapshot: {}", e);
+                process::exit(1);
+            });
+            if snapshot.version != SNAPSHOT_VERSION {
+                eprintln!(
+                    "error: unsupported snapshot version {} (expected {})",
+                    snapshot.version, SNAPSHOT_VERSION
+                );
+                process::exit(1);
+            }
+            let imported = snapshot::import(&mut conn, snapshot).unwrap_or_else(|e| {
+                eprintln!("error: failed to import posts: {}", e);
+                process::exit(1);
+            });
+            logs::log_snapshot_imported(imported, path);
+        }
+        _ => {
+            eprintln!("usage: snapshot <export|import|export-archive> <file>");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use crate::db::NewInteraction;
+use crate::settings::{settings, Flush};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Serialize)]
+pub struct BufferStatus {
+    pub pending_posts: usize,
+    pub pending_likes: usize,
+    pub max_pending_posts: usize,
+    pub max_pending_likes: usize,
+    /// Fullest either buffer is, as a fraction of its limit.
+    pub occupancy: f32,
+    pub peak_posts: usize,
+    pub peak_likes: usize,
+    pub forced_flushes: u64,
+    /// Flushes skipped because no database connection was free; the events stay buffered.
+    pub skipped_flushes: u64,
+    /// Entries written by the last periodic flush.
+    pub last_flush_entries: usize,
+    pub dropped_posts: u64,
+    pub dropped_likes: u64,
+    pub pending_interactions: usize,
+    /// Interactions dropped for going over the per-request or pending limit.
+    pub dropped_interactions: u64,
+    /// Interactions already waiting for the same user, post and type.
+    pub duplicate_interactions: u64,
+    /// Interactions lost to failed inserts.
+    pub failed_interactions: u64,
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    pending_posts: AtomicUsize,
+    pending_likes: AtomicUsize,
+    peak_posts: AtomicUsize,
+    peak_likes: AtomicUsize,
+    forced_flushes: AtomicU64,
+    skipped_flushes: AtomicU64,
+    last_flush_entries: AtomicUsize,
+    dropped_posts: AtomicU64,
+    dropped_likes: AtomicU64,
+    pending_interactions: AtomicUsize,
+    dropped_interactions: AtomicU64,
+    duplicate_interactions: AtomicU64,
+    failed_interactions: AtomicU64,
+}
+
+/// Occupancy of the handler's pending buffers, updated as events are buffered and flushed.
+#[derive(Debug, Clone, Default)]
+pub struct BufferMonitor {
+    inner: Arc<MonitorState>,
+}
+
+impl BufferMonitor {
+    pub fn observe(&self, posts: usize, likes: usize) {
+        self.inner.pending_posts.store(posts, Ordering::Relaxed);
+        self.inner.pending_likes.store(likes, Ordering::Relaxed);
+        self.inner.peak_posts.fetch_max(posts, Ordering::Relaxed);
+        self.inner.peak_likes.fetch_max(likes, Ordering::Relaxed);
+    }
+
+    pub fn record_forced_flush(&self) {
+        self.inner.forced_flushes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_skipped_flush(&self) {
+        self.inner.skipped_flushes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_flush(&self, entries: usize) {
+        self.inner
+            .last_flush_entries
+            .store(entries, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_posts(&self, count: usize) {
+        self.inner
+            .dropped_posts
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_likes(&self, count: usize) {
+        self.inner
+            .dropped_likes
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn observe_interactions(&self, pending: usize) {
+        self.inner
+            .pending_interactions
+            .store(pending, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_interactions(&self, count: usize) {
+        self.inner
+            .dropped_interactions
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_duplicate_interactions(&self, count: usize) {
+        self.inner
+            .duplicate_interactions
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_failed_interactions(&self, count: usize) {
+        self.inner
+            .failed_interactions
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> BufferStatus {
+        let s = settings();
+        let pending_posts = self.inner.pending_posts.load(Ordering::Relaxed);
+        let pending_likes = self.inner.pending_likes.load(Ordering::Relaxed);
+        let fill = |pending: usize, max: usize| pending as f32 / max.max(1) as f32;
+        BufferStatus {
+            pending_posts,
+            pending_likes,
+            max_pending_posts: s.flush.max_pending_posts,
+            max_pending_likes: s.flush.max_pending_likes,
+            occupancy: fill(pending_posts, s.flush.max_pending_posts)
+                .max(fill(pending_likes, s.flush.max_pending_likes)),
+            peak_posts: self.inner.peak_posts.load(Ordering::Relaxed),
+            peak_likes: self.inner.peak_likes.load(Ordering::Relaxed),
+            forced_flushes: self.inner.forced_flushes.load(Ordering::Relaxed),
+            skipped_flushes: self.inner.skipped_flushes.load(Ordering::Relaxed),
+            last_flush_entries: self.inner.last_flush_entries.load(Ordering::Relaxed),
+            dropped_posts: self.inner.dropped_posts.load(Ordering::Relaxed),
+            dropped_likes: self.inner.dropped_likes.load(Ordering::Relaxed),
+            pending_interactions: self.inner.pending_interactions.load(Ordering::Relaxed),
+            dropped_interactions: self.inner.dropped_interactions.load(Ordering::Relaxed),
+            duplicate_interactions: self.inner.duplicate_interactions.load(Ordering::Relaxed),
+            failed_interactions: self.inner.failed_interactions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Feed interactions waiting for the next flush, at most one per user, post and type.
+#[derive(Debug, Default)]
+pub struct PendingInteractions {
+    keys: HashSet<(String, String, String)>,
+    items: Vec<NewInteraction>,
+}
+
+/// What happened to a batch of interactions offered to the pending buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueuedInteractions {
+    pub queued: usize,
+    pub duplicates: usize,
+    pub dropped: usize,
+}
+
+impl PendingInteractions {
+    /// Queues interactions until `max_pending` are waiting, skipping ones already queued.
+    pub fn extend(
+        &mut self,
+        interactions: impl IntoIterator<Item = NewInteraction>,
+        max_pending: usize,
+    ) -> QueuedInteractions {
+        let mut result = QueuedInteractions::default();
+        for interaction in interactions {
+            let key = (
+                interaction.user_did.clone(),
+                interaction.post_uri.clone(),
+                interaction.interaction_type.clone(),
+            );
+            if self.keys.contains(&key) {
+                result.duplicates += 1;
+            } else if self.items.len() >= max_pending {
+                result.dropped += 1;
+            } else {
+                self.keys.insert(key);
+                self.items.push(interaction);
+                result.queued += 1;
+            }
+        }
+        result
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn drain(&mut self) -> Vec<NewInteraction> {
+        self.keys.clear();
+        std::mem::take(&mut self.items)
+    }
+}
+
+/// Size a full buffer is trimmed to, leaving headroom so a busy firehose doesn't trim on
+/// every event.
+pub fn drop_target(max: usize) -> usize {
+    max - max / 10
+}
+
+/// Removes the lowest-priority entries, oldest first among equals, until `keep` remain.
+/// Returns the removed entries in their original order.
+pub fn drop_lowest<T>(items: &mut Vec<T>, keep: usize, priority: impl Fn(&T) -> f32) -> Vec<T> {
+    if items.len() <= keep {
+        return Vec::new();
+    }
+
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|a, b| priority(&items[*a]).total_cmp(&priority(&items[*b])));
+    let mut dropped = vec![false; items.len()];
+    for index in &order[..items.len() - keep] {
+        dropped[*index] = true;
+    }
+
+    let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(items)
+        .into_iter()
+        .zip(dropped)
+        .partition(|(_, dropped)| *dropped);
+    *items = kept.into_iter().map(|(item, _)| item).collect();
+    removed.into_iter().map(|(item, _)| item).collect()
+}
+
+/// Wait before the next periodic flush: doubles after flushes that wrote at most
+/// `flush.idle_max_entries`, up to `max_interval_ms`, and resets to `min_interval_ms` as soon
+/// as a flush writes more.
+pub fn next_flush_interval(current: Duration, flushed: usize, flush: &Flush) -> Duration {
+    let min = Duration::from_millis(flush.min_interval_ms);
+    let max = Duration::from_millis(flush.max_interval_ms).max(min);
+    if flushed <= flush.idle_max_entries {
+        current.saturating_mul(2).clamp(min, max)
+    } else {
+        min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_lowest_keeps_highest_priority() {
+        let mut items = vec![("a", 1.0), ("b", -1.0), ("c", 3.0), ("d", -1.0), ("e", 2.0)];
+        let removed = drop_lowest(&mut items, 3, |(_, priority)| *priority);
+
+        assert_eq!(removed, vec![("b", -1.0), ("d", -1.0)]);
+        assert_eq!(items, vec![("a", 1.0), ("c", 3.0), ("e", 2.0)]);
+        assert!(drop_lowest(&mut items, 5, |(_, priority)| *priority).is_empty());
+        assert_eq!(drop_target(20000), 18000);
+    }
+
+    #[test]
+    fn test_flush_interval_stretches_when_idle() {
+        let flush = crate::settings::Settings::default().flush;
+        let min = Duration::from_millis(flush.min_interval_ms);
+        let max = Duration::from_millis(flush.max_interval_ms);
+
+        assert_eq!(next_flush_interval(min, 0, &flush), min * 2);
+        assert_eq!(next_flush_interval(max, 0, &flush), max);
+        assert_eq!(
+            next_flush_interval(max, flush.idle_max_entries + 1, &flush),
+            min
+        );
+        assert_eq!(next_flush_interval(Duration::ZERO, 0, &flush), min);
+    }
+
+    #[test]
+    fn test_pending_interactions_dedupe_and_cap() {
+        let interaction = |post: &str, kind: &str| NewInteraction {
+            user_did: "did:plc:reader".to_string(),
+            post_uri: post.to_string(),
+            interaction_type: kind.to_string(),
+            created_at: 0,
+        };
+        let mut pending = PendingInteractions::default();
+        let result = pending.extend(
+            vec![
+                interaction("at://a", "seen"),
+                interaction("at://a", "seen"),
+                interaction("at://a", "request_more"),
+                interaction("at://b", "seen"),
+            ],
+            2,
+        );
+
+        assert_eq!(
+            result,
+            QueuedInteractions {
+                queued: 2,
+                duplicates: 1,
+                dropped: 1
+            }
+        );
+        assert_eq!(pending.drain().len(), 2);
+        assert!(pending.is_empty());
+        assert_eq!(
+            pending
+                .extend(vec![interaction("at://a", "seen")], 2)
+                .queued,
+            1
+        );
+    }
+
+    #[test]
+    fn test_monitor_tracks_peaks() {
+        let monitor = BufferMonitor::default();
+        monitor.observe(10, 500);
+        monitor.observe(0, 0);
+        monitor.record_dropped_likes(40);
+
+        let status = monitor.status();
+        assert_eq!((status.pending_posts, status.pending_likes), (0, 0));
+        assert_eq!((status.peak_posts, status.peak_likes), (10, 500));
+        assert_eq!(status.dropped_likes, 40);
+        assert_eq!(status.occupancy, 0.0);
+    }
+}
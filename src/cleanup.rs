@@ -0,0 +1,149 @@
+use crate::archive;
+use crate::db;
+use crate::engagement::EngagementTracker;
+use crate::settings::settings;
+use diesel::result::Error;
+use diesel::sqlite::SqliteConnection;
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+
+const ORPHAN_EMBEDDING_GRACE_SECS: i64 = 3600;
+/// Like affinities untouched for this many half-lives are down to a thousandth and dropped.
+const AFFINITY_TTL_HALF_LIVES: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    pub posts_deleted: usize,
+    pub engagement_deleted: usize,
+    /// Likes, replies, reposts and cached engagement left behind by already deleted posts.
+    pub orphans_removed: usize,
+}
+
+impl CleanupReport {
+    pub fn total(&self) -> usize {
+        self.posts_deleted + self.engagement_deleted + self.orphans_removed
+    }
+}
+
+/// Removes posts that fell out of `feed.cutoff_hours` or past `feed.max_stored_posts`,
+/// archiving them first when enabled, along with old replies and reposts, orphaned engagement
+/// and embeddings, score explanations, shadow scores, faded like affinities and expired author
+/// history. Likes and cached engagement go with their posts through `ON DELETE CASCADE`.
+pub fn cleanup_expired(
+    conn: &mut SqliteConnection,
+    engagement: &EngagementTracker,
+    now: i64,
+) -> Result<CleanupReport, Error> {
+    let s = settings();
+    let cutoff = now - (s.feed.cutoff_hours * 3600);
+
+    if s.archive.enabled {
+        archive::archive_expiring_posts(conn, cutoff, s.feed.max_stored_posts, now)?;
+        if s.archive.retention_days > 0 {
+            db::cleanup_archive(conn, now - (s.archive.retention_days * 86400))?;
+        }
+    }
+
+    let engagement_deleted = engagement.cleanup_old_engagement(cutoff).unwrap_or(0);
+    let posts_deleted = db::cleanup_old_posts(conn, cutoff, s.feed.max_stored_posts)?;
+    let orphans_removed = db::cleanup_orphan_engagement(conn)?;
+
+    db::cleanup_orphan_embeddings(conn, now - ORPHAN_EMBEDDING_GRACE_SECS)?;
+    db::cleanup_post_scores(conn, cutoff)?;
+    db::cleanup_shadow_scores(conn, cutoff)?;
+    let affinity_ttl = s.feed.like_affinity.half_life_hours * AFFINITY_TTL_HALF_LIVES;
+    db::cleanup_like_affinities(conn, now - (affinity_ttl * 3600.0) as i64)?;
+    db::cleanup_author_history(conn, now - (s.authors.history_window_days * 86400))?;
+    let today = now - now.rem_euclid(86400);
+    db::cleanup_author_stats(conn, today - s.authors.reputation.window_days * 86400)?;
+
+    Ok(CleanupReport {
+        posts_deleted,
+        engagement_deleted,
+        orphans_removed,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct CleanupStatus {
+    pub last_run_at: Option<i64>,
+    pub runs: u64,
+    /// Runs skipped because no database connection was free.
+    pub skipped_runs: u64,
+    pub posts_deleted: u64,
+    pub engagement_deleted: u64,
+    pub orphans_removed: u64,
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    last_run_at: AtomicI64,
+    runs: AtomicU64,
+    skipped_runs: AtomicU64,
+    posts_deleted: AtomicU64,
+    engagement_deleted: AtomicU64,
+    orphans_removed: AtomicU64,
+}
+
+/// Totals across cleanup runs since startup. A steady climb in `orphans_removed` means rows
+/// are being written without foreign keys enforced.
+#[derive(Debug, Clone, Default)]
+pub struct CleanupMonitor {
+    inner: Arc<MonitorState>,
+}
+
+impl CleanupMonitor {
+    pub fn record(&self, report: &CleanupReport, now: i64) {
+        let add = |counter: &AtomicU64, count: usize| {
+            counter.fetch_add(count as u64, Ordering::Relaxed);
+        };
+        self.inner.last_run_at.store(now, Ordering::Relaxed);
+        add(&self.inner.runs, 1);
+        add(&self.inner.posts_deleted, report.posts_deleted);
+        add(&self.inner.engagement_deleted, report.engagement_deleted);
+        add(&self.inner.orphans_removed, report.orphans_removed);
+    }
+
+    pub fn record_skipped(&self) {
+        self.inner.skipped_runs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> CleanupStatus {
+        CleanupStatus {
+            last_run_at: Some(self.inner.last_run_at.load(Ordering::Relaxed)).filter(|t| *t > 0),
+            runs: self.inner.runs.load(Ordering::Relaxed),
+            skipped_runs: self.inner.skipped_runs.load(Ordering::Relaxed),
+            posts_deleted: self.inner.posts_deleted.load(Ordering::Relaxed),
+            engagement_deleted: self.inner.engagement_deleted.load(Ordering::Relaxed),
+            orphans_removed: self.inner.orphans_removed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_accumulates_runs() {
+        let monitor = CleanupMonitor::default();
+        assert_eq!(monitor.status().last_run_at, None);
+
+        let report = CleanupReport {
+            posts_deleted: 3,
+            engagement_deleted: 2,
+            orphans_removed: 5,
+        };
+        monitor.record(&report, 100);
+        monitor.record(&CleanupReport::default(), 160);
+        monitor.record_skipped();
+
+        let status = monitor.status();
+        assert_eq!(report.total(), 10);
+        assert_eq!(status.last_run_at, Some(160));
+        assert_eq!(status.runs, 2);
+        assert_eq!(status.skipped_runs, 1);
+        assert_eq!(status.orphans_removed, 5);
+    }
+}
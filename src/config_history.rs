@@ -0,0 +1,52 @@
+use crate::archive::text_hash;
+use crate::db::{self, DbPool, NewConfigSnapshot};
+use crate::settings::{active_profile, settings, settings_generation};
+use diesel::result::QueryResult;
+use diesel::sqlite::SqliteConnection;
+use ron::ser::PrettyConfig;
+use std::sync::Mutex;
+
+/// The settings generation last written to `config_history`, and the row it was written as.
+static RECORDED: Mutex<Option<(u64, i32)>> = Mutex::new(None);
+
+fn recorded_id(generation: u64) -> Option<i32> {
+    let Ok(recorded) = RECORDED.lock() else {
+        return None;
+    };
+    recorded.filter(|(g, _)| *g == generation).map(|(_, id)| id)
+}
+
+/// Writes the settings in effect to `config_history`, unless this generation of them already
+/// is, and returns the row id that stats and accepted posts are tagged with.
+pub fn record_active_config(conn: &mut SqliteConnection, now: i64) -> QueryResult<i32> {
+    let generation = settings_generation();
+    if let Some(id) = recorded_id(generation) {
+        return Ok(id);
+    }
+
+    let snapshot = ron::ser::to_string_pretty(&settings().redacted(), PrettyConfig::default())
+        .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+    let id = db::record_config_snapshot(
+        conn,
+        NewConfigSnapshot {
+            hash: text_hash(&snapshot),
+            settings: snapshot,
+            profile: active_profile(),
+            activated_at: now,
+        },
+    )?;
+    if let Ok(mut recorded) = RECORDED.lock() {
+        *recorded = Some((generation, id));
+    }
+    Ok(id)
+}
+
+/// The active config's id, recording it first if the settings changed since it last was.
+/// `None` if that fails, so a database hiccup never holds up ingest.
+pub fn active_config_id(pool: &DbPool, now: i64) -> Option<i32> {
+    if let Some(id) = recorded_id(settings_generation()) {
+        return Some(id);
+    }
+    let mut conn = pool.get().ok()?;
+    record_active_config(&mut conn, now).ok()
+}
@@ -0,0 +1,219 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A post's place in the feed order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankKey<'a> {
+    pub timestamp: i64,
+    /// Priority after serve-time adjustments, shuffle jitter included.
+    pub priority: f32,
+    pub uri: &'a str,
+}
+
+/// Feed order: newest `bucket_seconds` time bucket first, then highest priority, then URI so
+/// ties always break the same way.
+pub fn rank_order(a: RankKey, b: RankKey, bucket_seconds: i64) -> Ordering {
+    let bucket_seconds = bucket_seconds.max(1);
+    (b.timestamp / bucket_seconds)
+        .cmp(&(a.timestamp / bucket_seconds))
+        .then_with(|| b.priority.total_cmp(&a.priority))
+        .then_with(|| a.uri.cmp(b.uri))
+}
+
+fn uri_hash(uri: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    uri.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Where a feed page ended, plus the session's shuffle seed so later pages are ranked the same
+/// way. Holds a hash of the last post served and the rank key pages resume after when that
+/// post has left the feed. Encoded as `seed:last:timestamp:priority:uri`, with the seed, hash
+/// and priority bits in hex.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedCursor {
+    pub seed: u64,
+    /// Hash of the last post served.
+    pub last: u64,
+    pub timestamp: i64,
+    pub priority: f32,
+    pub uri: String,
+}
+
+impl FeedCursor {
+    /// Cursor for a page of a feed in rank order, ending at `last`.
+    pub fn after(seed: u64, last: RankKey) -> Self {
+        Self {
+            seed,
+            last: uri_hash(last.uri),
+            timestamp: last.timestamp,
+            priority: last.priority,
+            uri: last.uri.to_string(),
+        }
+    }
+
+    /// Cursor for a page of a feed the author cap took out of rank order. `served` is the
+    /// capped feed up to the end of the page and `ranked` the same posts and more in rank
+    /// order. Pages resume after the last post of the longest prefix of `ranked` that was all
+    /// served, so posts the cap deferred past the page aren't skipped.
+    pub fn after_capped<T>(
+        seed: u64,
+        ranked: &[T],
+        served: &[T],
+        key: impl Fn(&T) -> RankKey,
+    ) -> Option<Self> {
+        let last = key(served.last()?);
+        let served: HashSet<&str> = served.iter().map(|item| key(item).uri).collect();
+        let resume = ranked
+            .iter()
+            .take_while(|item| served.contains(key(item).uri))
+            .last()
+            .map_or(last, &key);
+        Some(Self {
+            last: uri_hash(last.uri),
+            ..Self::after(seed, resume)
+        })
+    }
+
+    fn key(&self) -> RankKey<'_> {
+        RankKey {
+            timestamp: self.timestamp,
+            priority: self.priority,
+            uri: &self.uri,
+        }
+    }
+
+    /// Index in `ranked`, which must be in rank order, the next page starts at: just past the
+    /// cursor's post, or, if it has left the feed since, the first post ranked after its key.
+    pub fn resume_index<T>(
+        &self,
+        ranked: &[T],
+        bucket_seconds: i64,
+        key: impl Fn(&T) -> RankKey,
+    ) -> usize {
+        if let Some(index) = ranked.iter().position(|item| key(item).uri == self.uri) {
+            return index + 1;
+        }
+        ranked
+            .iter()
+            .position(|item| rank_order(key(item), self.key(), bucket_seconds) == Ordering::Greater)
+            .unwrap_or(ranked.len())
+    }
+
+    /// Index in `feed` just past the last post served, or `None` if it has left the feed
+    /// since. `uri` gives each item's post URI.
+    pub fn resume_after<T>(&self, feed: &[T], uri: impl Fn(&T) -> &str) -> Option<usize> {
+        feed.iter()
+            .position(|item| uri_hash(uri(item)) == self.last)
+            .map(|index| index + 1)
+    }
+}
+
+impl fmt::Display for FeedCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:x}:{:x}:{}:{:x}:{}",
+            self.seed,
+            self.last,
+            self.timestamp,
+            self.priority.to_bits(),
+            self.uri
+        )
+    }
+}
+
+impl FromStr for FeedCursor {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.splitn(5, ':');
+        let (Some(seed), Some(last), Some(timestamp), Some(priority), Some(uri)) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(());
+        };
+        Ok(Self {
+            seed: u64::from_str_radix(seed, 16).map_err(|_| ())?,
+            last: u64::from_str_radix(last, 16).map_err(|_| ())?,
+            timestamp: timestamp.parse().map_err(|_| ())?,
+            priority: f32::from_bits(u32::from_str_radix(priority, 16).map_err(|_| ())?),
+            uri: uri.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key<'a>(item: &'a (i64, f32, &'static str)) -> RankKey<'a> {
+        RankKey {
+            timestamp: item.0,
+            priority: item.1,
+            uri: item.2,
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = FeedCursor {
+            seed: u64::MAX,
+            last: 0xabc,
+            timestamp: 1_760_000_000,
+            priority: -1.25,
+            uri: "at://did:plc:abc/app.bsky.feed.post/3kabc".to_string(),
+        };
+        assert_eq!(cursor.to_string().parse::<FeedCursor>(), Ok(cursor));
+        assert!("25".parse::<FeedCursor>().is_err());
+    }
+
+    #[test]
+    fn test_resume_survives_feed_changes() {
+        let mut ranked = vec![(7200, 1.0, "a"), (3600, 3.0, "b"), (3600, 2.0, "c")];
+        ranked.sort_by(|a, b| rank_order(key(a), key(b), 3600));
+        let cursor = FeedCursor::after(7, key(&ranked[1]));
+        assert_eq!(cursor.resume_index(&ranked, 3600, key), 2);
+
+        // A new post on top doesn't repeat "b"; removing "b" doesn't skip "c".
+        ranked.insert(0, (9000, 5.0, "new"));
+        assert_eq!(ranked[cursor.resume_index(&ranked, 3600, key)].2, "c");
+        ranked.retain(|item| item.2 != "b");
+        assert_eq!(ranked[cursor.resume_index(&ranked, 3600, key)].2, "c");
+    }
+
+    #[test]
+    fn test_resume_capped_feed() {
+        let ranked = vec![(3, 4.0, "a"), (3, 3.0, "b"), (2, 2.0, "c"), (1, 1.0, "d")];
+        // The author cap deferred "c" below "d", and the page ended at "d".
+        let capped = vec![ranked[0], ranked[1], ranked[3], ranked[2]];
+        fn uri<'a>(item: &'a (i64, f32, &'static str)) -> &'a str {
+            item.2
+        }
+        let cursor = FeedCursor::after_capped(7, &ranked, &capped[..3], key).unwrap();
+        assert_eq!(cursor.resume_after(&capped, uri), Some(3));
+
+        // Once "d" or the whole page leaves the feed, "c" is still served next.
+        let without_d: Vec<_> = ranked
+            .iter()
+            .filter(|item| item.2 != "d")
+            .cloned()
+            .collect();
+        assert_eq!(cursor.resume_after(&without_d, uri), None);
+        assert_eq!(without_d[cursor.resume_index(&without_d, 1, key)].2, "c");
+        let unseen: Vec<_> = ranked
+            .iter()
+            .filter(|item| item.2 == "c")
+            .cloned()
+            .collect();
+        assert_eq!(unseen[cursor.resume_index(&unseen, 1, key)].2, "c");
+    }
+}
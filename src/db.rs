@@ -1,20 +1,116 @@
-use crate::schema::{blocked_authors, likes, posts, user_interactions};
-use crate::scoring::{ContentSignals, MediaInfo};
+use crate::schema::{
+    admin_audit, archive_posts, author_history, author_stats, backfill_query_stats, backfill_state,
+    blocked_authors, config_history, coverage_gaps, engagement_cache, handles, language_stats,
+    like_affinities, likes, negative_examples, opted_out_authors, post_embeddings, post_scores,
+    posts, replies, reposts, shadow_scores, user_interactions,
+};
+use crate::scoring::{
+    decode_embedding, encode_embedding, AuthorRecord, ContentSignals, Embedding, MediaInfo,
+    TopicLabel,
+};
+use crate::settings::settings;
+use crate::utils::ids::{AtUri, Did};
+use crate::utils::logs;
 use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
-use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PooledConnection};
 use diesel::sqlite::SqliteConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use strum::Display;
 
 pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+pub type DbConnection = PooledConnection<ConnectionManager<SqliteConnection>>;
 
-pub fn establish_pool(database_url: &str) -> DbPool {
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// Values bound per statement when a list goes into a query, keeping well under SQLite's
+/// variable limit.
+const MAX_BOUND_VALUES: usize = 500;
+
+/// Applies `configure_connection` to every pooled connection. SQLite pragmas such as
+/// `foreign_keys` are per connection, and without it deleting a post would leave its likes and
+/// engagement behind instead of cascading.
+#[derive(Debug)]
+struct ConfigureConnection {
+    read_only: bool,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConfigureConnection {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        configure_connection(conn).map_err(diesel::r2d2::Error::QueryError)?;
+        if self.read_only {
+            conn.batch_execute("PRAGMA query_only = ON;")
+                .map_err(diesel::r2d2::Error::QueryError)?;
+        }
+        Ok(())
+    }
+}
+
+fn build_pool(database_url: &str, size: u32, read_only: bool) -> DbPool {
     let manager = ConnectionManager::<SqliteConnection>::new(database_url);
     Pool::builder()
-        .max_size(5)
+        .max_size(size.max(1))
+        .connection_timeout(Duration::from_millis(
+            settings().database.connection_timeout_ms,
+        ))
+        .connection_customizer(Box::new(ConfigureConnection { read_only }))
         .build(manager)
         .expect("Failed to create pool")
 }
 
+/// Opens the database for ingestion and other writers, and brings its schema up to date with the
+/// migrations embedded in the binary, so every entry point works against a fresh or older
+/// database file.
+pub fn establish_pool(database_url: &str) -> DbPool {
+    let pool = build_pool(database_url, settings().database.write_pool_size, false);
+    let mut conn = pool.get().expect("Failed to get connection");
+    let applied = run_migrations(&mut conn).expect("Failed to run database migrations");
+    logs::log_migrations_applied(&applied);
+    drop(conn);
+    pool
+}
+
+/// Opens a separate pool of `query_only` connections for serving the feed and stats, so readers
+/// don't wait on the writers' connections. Under WAL they don't block writes either. Open it after
+/// `establish_pool` has migrated the database.
+pub fn establish_read_pool(database_url: &str) -> DbPool {
+    build_pool(database_url, settings().database.read_pool_size, true)
+}
+
+/// A free connection, without waiting for one, for work done under the handler lock that can
+/// wait for its next cycle, such as flushes and cleanup.
+pub fn try_connection(pool: &DbPool) -> Option<DbConnection> {
+    pool.try_get()
+}
+
+/// A connection for background jobs that can wait for their next cycle. While the pool stays
+/// exhausted, tries `database.acquire_retries` more times with a growing backoff.
+pub async fn get_connection(pool: &DbPool) -> Option<DbConnection> {
+    let (retries, backoff_ms) = {
+        let database = &settings().database;
+        (database.acquire_retries, database.retry_backoff_ms)
+    };
+    for retry in 0..=retries {
+        if retry > 0 {
+            tokio::time::sleep(Duration::from_millis(backoff_ms * u64::from(retry))).await;
+        }
+        if let Some(conn) = pool.try_get() {
+            return Some(conn);
+        }
+    }
+    None
+}
+
+/// Runs the embedded migrations the database hasn't had yet, oldest first, and returns their
+/// versions.
+pub fn run_migrations(conn: &mut SqliteConnection) -> diesel::migration::Result<Vec<String>> {
+    let applied = conn.run_pending_migrations(MIGRATIONS)?;
+    Ok(applied.iter().map(ToString::to_string).collect())
+}
+
 pub fn configure_connection(conn: &mut SqliteConnection) -> QueryResult<()> {
     conn.batch_execute("PRAGMA busy_timeout = 2000;")?;
     conn.batch_execute("PRAGMA journal_mode = WAL;")?;
@@ -38,33 +134,69 @@ pub struct Post {
     pub has_alt_text: i32,
     pub link_count: i32,
     pub promo_link_count: i32,
+    pub lang: String,
+    pub mention_count: i32,
+    pub thread_length: i32,
+    /// The `config_history` row active when the post was accepted.
+    pub config_id: Option<i32>,
+    /// A `PostSource`, or `unknown` for posts stored before sources were recorded.
+    pub source: String,
+}
+
+impl Post {
+    pub fn topic(&self) -> TopicLabel {
+        TopicLabel::classify(
+            &self.text,
+            self.has_media != 0,
+            self.is_first_person != 0,
+            self.link_count.clamp(0, u8::MAX as i32) as u8,
+        )
+    }
 }
 
 #[derive(Insertable, Debug, Clone)]
 #[diesel(table_name = posts)]
 pub struct NewPost {
-    pub uri: String,
+    pub uri: AtUri,
     pub text: String,
     pub timestamp: i64,
     pub priority: f32,
     pub has_media: i32,
     pub is_first_person: i32,
-    pub author_did: Option<String>,
+    pub author_did: Option<Did>,
     pub image_count: i32,
     pub has_alt_text: i32,
     pub link_count: i32,
     pub promo_link_count: i32,
+    pub lang: String,
+    pub mention_count: i32,
+    pub thread_length: i32,
+    pub config_id: Option<i32>,
+    pub source: String,
+}
+
+/// How a post got into the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PostSource {
+    Firehose,
+    /// The startup backfill.
+    Backfill,
+    /// The gap backfill, which searches again for what the firehose missed.
+    SearchPoller,
+    /// Snapshot imports.
+    Manual,
 }
 
 impl NewPost {
     pub fn new(
-        uri: String,
+        uri: AtUri,
         text: String,
         timestamp: i64,
         priority: f32,
         media: &MediaInfo,
         content: &ContentSignals,
-        author_did: Option<String>,
+        author_did: Option<Did>,
     ) -> Self {
         Self {
             uri,
@@ -82,15 +214,55 @@ impl NewPost {
             has_alt_text: i32::from(content.has_alt_text),
             link_count: content.link_count as i32,
             promo_link_count: content.promo_link_count as i32,
+            lang: "en".to_string(),
+            mention_count: content.mention_count as i32,
+            thread_length: 1,
+            config_id: None,
+            source: PostSource::Firehose.to_string(),
         }
     }
+
+    pub fn with_lang(mut self, lang: String) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    pub fn with_config_id(mut self, config_id: Option<i32>) -> Self {
+        self.config_id = config_id;
+        self
+    }
+
+    pub fn with_source(mut self, source: PostSource) -> Self {
+        self.source = source.to_string();
+        self
+    }
 }
 
 #[derive(Insertable, Debug, Clone)]
 #[diesel(table_name = likes)]
 pub struct NewLike {
+    pub post_uri: AtUri,
+    pub like_uri: AtUri,
+    pub liker_did: Option<Did>,
+    pub liked_at: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = post_embeddings)]
+pub struct NewPostEmbedding {
     pub post_uri: String,
-    pub like_uri: String,
+    pub embedding: Vec<u8>,
+    pub created_at: i64,
+}
+
+impl NewPostEmbedding {
+    pub fn new(post_uri: String, embedding: &[f32], created_at: i64) -> Self {
+        Self {
+            post_uri,
+            embedding: encode_embedding(embedding),
+            created_at,
+        }
+    }
 }
 
 pub fn insert_posts(conn: &mut SqliteConnection, new_posts: Vec<NewPost>) -> QueryResult<usize> {
@@ -112,14 +284,16 @@ pub fn insert_likes(conn: &mut SqliteConnection, new_likes: Vec<NewLike>) -> Que
 
     let post_uris: Vec<&str> = new_likes.iter().map(|l| l.post_uri.as_str()).collect();
 
-    let existing_posts: Vec<String> = posts
+    let existing_posts: HashSet<String> = posts
         .filter(post_uri_col.eq_any(&post_uris))
         .select(post_uri_col)
-        .load(conn)?;
+        .load::<String>(conn)?
+        .into_iter()
+        .collect();
 
     let valid_likes: Vec<_> = new_likes
         .into_iter()
-        .filter(|l| existing_posts.contains(&l.post_uri))
+        .filter(|l| existing_posts.contains(l.post_uri.as_str()))
         .collect();
 
     if valid_likes.is_empty() {
@@ -131,10 +305,215 @@ pub fn insert_likes(conn: &mut SqliteConnection, new_likes: Vec<NewLike>) -> Que
         .execute(conn)
 }
 
-pub fn delete_post(conn: &mut SqliteConnection, post_uri: &str) -> QueryResult<usize> {
-    use crate::schema::posts::dsl::*;
+pub fn insert_post_embeddings(
+    conn: &mut SqliteConnection,
+    new_embeddings: Vec<NewPostEmbedding>,
+) -> QueryResult<usize> {
+    if new_embeddings.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::replace_into(post_embeddings::table)
+        .values(&new_embeddings)
+        .execute(conn)
+}
+
+pub fn get_post_embedding(conn: &mut SqliteConnection, uri: &str) -> Option<Embedding> {
+    post_embeddings::table
+        .filter(post_embeddings::post_uri.eq(uri))
+        .select(post_embeddings::embedding)
+        .first::<Vec<u8>>(conn)
+        .ok()
+        .map(|bytes| decode_embedding(&bytes))
+}
+
+pub fn get_feed_embeddings(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+) -> QueryResult<Vec<(String, Embedding)>> {
+    let rows: Vec<(String, Vec<u8>)> = post_embeddings::table
+        .inner_join(posts::table.on(posts::uri.eq(post_embeddings::post_uri)))
+        .filter(posts::timestamp.gt(cutoff_timestamp))
+        .select((post_embeddings::post_uri, post_embeddings::embedding))
+        .load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(uri, bytes)| (uri, decode_embedding(&bytes)))
+        .collect())
+}
+
+pub fn cleanup_orphan_embeddings(
+    conn: &mut SqliteConnection,
+    created_before: i64,
+) -> QueryResult<usize> {
+    diesel::delete(
+        post_embeddings::table
+            .filter(post_embeddings::created_at.lt(created_before))
+            .filter(post_embeddings::post_uri.ne_all(posts::table.select(posts::uri))),
+    )
+    .execute(conn)
+}
+
+/// Deletes likes, replies, reposts and cached engagement whose post no longer exists. The
+/// foreign keys cascade these away, so this only finds rows written while they weren't
+/// enforced.
+pub fn cleanup_orphan_engagement(conn: &mut SqliteConnection) -> QueryResult<usize> {
+    let live = || posts::table.select(posts::uri);
+    let likes =
+        diesel::delete(likes::table.filter(likes::post_uri.ne_all(live()))).execute(conn)?;
+    let replies =
+        diesel::delete(replies::table.filter(replies::post_uri.ne_all(live()))).execute(conn)?;
+    let reposts =
+        diesel::delete(reposts::table.filter(reposts::post_uri.ne_all(live()))).execute(conn)?;
+    let cached =
+        diesel::delete(engagement_cache::table.filter(engagement_cache::post_uri.ne_all(live())))
+            .execute(conn)?;
+    Ok(likes + replies + reposts + cached)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = author_history)]
+pub struct NewAuthorHistory {
+    pub post_uri: String,
+    pub author_did: String,
+    pub accepted_at: i64,
+}
+
+pub fn insert_author_history(
+    conn: &mut SqliteConnection,
+    entries: Vec<NewAuthorHistory>,
+) -> QueryResult<usize> {
+    diesel::insert_or_ignore_into(author_history::table)
+        .values(&entries)
+        .execute(conn)
+}
+
+pub fn count_author_history(
+    conn: &mut SqliteConnection,
+    author_did: &str,
+    since: i64,
+) -> QueryResult<i64> {
+    author_history::table
+        .filter(author_history::author_did.eq(author_did))
+        .filter(author_history::accepted_at.ge(since))
+        .count()
+        .get_result(conn)
+}
+
+pub fn cleanup_author_history(conn: &mut SqliteConnection, before: i64) -> QueryResult<usize> {
+    diesel::delete(author_history::table.filter(author_history::accepted_at.lt(before)))
+        .execute(conn)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = author_stats)]
+pub struct NewAuthorStat {
+    pub author_did: String,
+    pub day: i64,
+    pub config_id: i32,
+    pub assessed: i32,
+    pub accepted: i32,
+    pub score_sum: f32,
+    pub request_more: i32,
+    pub request_less: i32,
+    pub spam_flags: i32,
+}
+
+impl NewAuthorStat {
+    pub fn new(author_did: String, day: i64, config_id: i32, record: &AuthorRecord) -> Self {
+        Self {
+            author_did,
+            day,
+            config_id,
+            assessed: record.assessed as i32,
+            accepted: record.accepted as i32,
+            score_sum: record.score_sum,
+            request_more: record.request_more as i32,
+            request_less: record.request_less as i32,
+            spam_flags: record.spam_flags as i32,
+        }
+    }
+}
+
+pub fn record_author_stats(
+    conn: &mut SqliteConnection,
+    stats: Vec<NewAuthorStat>,
+) -> QueryResult<usize> {
+    use author_stats::{accepted, assessed, request_less, request_more, score_sum, spam_flags};
+    use diesel::upsert::excluded;
+
+    let mut count = 0;
+    for stat in stats {
+        count += diesel::insert_into(author_stats::table)
+            .values(&stat)
+            .on_conflict((
+                author_stats::author_did,
+                author_stats::day,
+                author_stats::config_id,
+            ))
+            .do_update()
+            .set((
+                assessed.eq(assessed + excluded(assessed)),
+                accepted.eq(accepted + excluded(accepted)),
+                score_sum.eq(score_sum + excluded(score_sum)),
+                request_more.eq(request_more + excluded(request_more)),
+                request_less.eq(request_less + excluded(request_less)),
+                spam_flags.eq(spam_flags + excluded(spam_flags)),
+            ))
+            .execute(conn)?;
+    }
+    Ok(count)
+}
+
+/// An author's stats summed over the days since `since_day`.
+pub fn get_author_record(
+    conn: &mut SqliteConnection,
+    did: &str,
+    since_day: i64,
+) -> QueryResult<AuthorRecord> {
+    use diesel::dsl::sum;
+
+    let (assessed, accepted, score_sum, request_more, request_less, spam_flags) =
+        author_stats::table
+            .filter(author_stats::author_did.eq(did))
+            .filter(author_stats::day.ge(since_day))
+            .select((
+                sum(author_stats::assessed),
+                sum(author_stats::accepted),
+                sum(author_stats::score_sum),
+                sum(author_stats::request_more),
+                sum(author_stats::request_less),
+                sum(author_stats::spam_flags),
+            ))
+            .first::<(
+                Option<i64>,
+                Option<i64>,
+                Option<f32>,
+                Option<i64>,
+                Option<i64>,
+                Option<i64>,
+            )>(conn)?;
+    Ok(AuthorRecord {
+        assessed: assessed.unwrap_or(0),
+        accepted: accepted.unwrap_or(0),
+        score_sum: score_sum.unwrap_or(0.0),
+        request_more: request_more.unwrap_or(0),
+        request_less: request_less.unwrap_or(0),
+        spam_flags: spam_flags.unwrap_or(0),
+    })
+}
+
+pub fn cleanup_author_stats(conn: &mut SqliteConnection, before_day: i64) -> QueryResult<usize> {
+    diesel::delete(author_stats::table.filter(author_stats::day.lt(before_day))).execute(conn)
+}
 
-    diesel::delete(posts.filter(uri.eq(post_uri))).execute(conn)
+/// Deletes a post from the feed and the public archive.
+pub fn delete_post(conn: &mut SqliteConnection, post_uri: &str) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        diesel::delete(archive_posts::table.find(post_uri)).execute(conn)?;
+        diesel::delete(posts::table.find(post_uri)).execute(conn)
+    })
 }
 
 pub fn delete_like(conn: &mut SqliteConnection, like_uri_val: &str) -> QueryResult<usize> {
@@ -143,163 +522,1560 @@ pub fn delete_like(conn: &mut SqliteConnection, like_uri_val: &str) -> QueryResu
     diesel::delete(likes.filter(like_uri.eq(like_uri_val))).execute(conn)
 }
 
+#[allow(dead_code)]
 pub fn get_feed(conn: &mut SqliteConnection, cutoff_timestamp: i64) -> QueryResult<Vec<Post>> {
     use crate::schema::posts::dsl::*;
 
     posts
         .filter(timestamp.gt(cutoff_timestamp))
         .order((timestamp.desc(), priority.desc()))
-        .load::<Post>(conn)
+        .select(Post::as_select())
+        .load(conn)
 }
 
-pub fn post_exists(conn: &mut SqliteConnection, post_uri: &str) -> bool {
-    use crate::schema::posts::dsl::*;
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = coverage_gaps)]
+pub struct NewCoverageGap {
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub reason: String,
+}
 
-    posts
-        .filter(uri.eq(post_uri))
-        .count()
-        .get_result::<i64>(conn)
-        .unwrap_or(0)
-        > 0
+#[derive(Queryable, Debug, Clone)]
+#[allow(dead_code)]
+pub struct CoverageGap {
+    pub id: i32,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub reason: String,
+    pub backfilled_at: Option<i64>,
 }
 
-pub fn get_post_author(conn: &mut SqliteConnection, post_uri: &str) -> Option<String> {
-    use crate::schema::posts::dsl::*;
+pub fn insert_coverage_gaps(
+    conn: &mut SqliteConnection,
+    gaps: Vec<NewCoverageGap>,
+) -> QueryResult<usize> {
+    diesel::insert_into(coverage_gaps::table)
+        .values(&gaps)
+        .execute(conn)
+}
 
-    posts
-        .filter(uri.eq(post_uri))
-        .select(author_did)
-        .first::<Option<String>>(conn)
-        .ok()
-        .flatten()
+pub fn get_pending_coverage_gaps(
+    conn: &mut SqliteConnection,
+    since: i64,
+) -> QueryResult<Vec<CoverageGap>> {
+    coverage_gaps::table
+        .filter(coverage_gaps::backfilled_at.is_null())
+        .filter(coverage_gaps::ended_at.gt(since))
+        .order(coverage_gaps::started_at.asc())
+        .load(conn)
 }
 
-pub fn cleanup_old_posts(
+pub fn mark_coverage_gap_backfilled(
     conn: &mut SqliteConnection,
-    cutoff_timestamp: i64,
-    max_posts: i64,
+    gap_id: i32,
+    at: i64,
 ) -> QueryResult<usize> {
-    use crate::schema::posts::dsl::*;
+    diesel::update(coverage_gaps::table.find(gap_id))
+        .set(coverage_gaps::backfilled_at.eq(at))
+        .execute(conn)
+}
 
-    let deleted_by_age =
-        diesel::delete(posts.filter(timestamp.lt(cutoff_timestamp))).execute(conn)?;
+pub fn cleanup_coverage_gaps(conn: &mut SqliteConnection, before: i64) -> QueryResult<usize> {
+    diesel::delete(coverage_gaps::table.filter(coverage_gaps::ended_at.lt(before))).execute(conn)
+}
 
-    let count: i64 = posts.count().get_result(conn)?;
-    let mut deleted_by_limit = 0;
-    if count > max_posts {
-        let excess = count - max_posts;
-        let uris_to_delete: Vec<String> = posts
-            .order(timestamp.asc())
-            .limit(excess)
-            .select(uri)
-            .load(conn)?;
+/// The stretch of a backfill search query's results that has already been walked.
+#[derive(Queryable, Insertable, AsChangeset, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = backfill_state)]
+pub struct BackfillState {
+    pub query: String,
+    pub oldest_at: i64,
+    pub newest_at: i64,
+    pub updated_at: i64,
+}
 
-        deleted_by_limit =
-            diesel::delete(posts.filter(uri.eq_any(uris_to_delete))).execute(conn)?;
-    }
+pub fn get_backfill_state(
+    conn: &mut SqliteConnection,
+    search_query: &str,
+) -> QueryResult<Option<BackfillState>> {
+    backfill_state::table
+        .find(search_query)
+        .first(conn)
+        .optional()
+}
 
-    Ok(deleted_by_age + deleted_by_limit)
+pub fn upsert_backfill_state(
+    conn: &mut SqliteConnection,
+    state: &BackfillState,
+) -> QueryResult<usize> {
+    diesel::insert_into(backfill_state::table)
+        .values(state)
+        .on_conflict(backfill_state::query)
+        .do_update()
+        .set(state)
+        .execute(conn)
 }
 
 #[derive(Insertable, Debug, Clone)]
-#[diesel(table_name = user_interactions)]
-pub struct NewInteraction {
-    pub user_did: String,
-    pub post_uri: String,
-    pub interaction_type: String,
-    pub created_at: i64,
+#[diesel(table_name = backfill_query_stats)]
+pub struct NewQueryStat {
+    pub query: String,
+    pub day: i64,
+    pub searched: i32,
+    pub accepted: i32,
 }
 
-pub const INTERACTION_SEEN: &str = "seen";
-pub const INTERACTION_REQUEST_LESS: &str = "request_less";
-pub const INTERACTION_REQUEST_MORE: &str = "request_more";
-
-pub fn insert_interactions(
+pub fn record_query_stats(
     conn: &mut SqliteConnection,
-    interactions: Vec<NewInteraction>,
+    stats: Vec<NewQueryStat>,
 ) -> QueryResult<usize> {
-    use crate::schema::user_interactions::dsl::*;
+    use backfill_query_stats::{accepted, searched};
+    use diesel::upsert::excluded;
 
-    if interactions.is_empty() {
-        return Ok(0);
+    let mut count = 0;
+    for stat in stats {
+        count += diesel::insert_into(backfill_query_stats::table)
+            .values(&stat)
+            .on_conflict((backfill_query_stats::query, backfill_query_stats::day))
+            .do_update()
+            .set((
+                searched.eq(searched + excluded(searched)),
+                accepted.eq(accepted + excluded(accepted)),
+            ))
+            .execute(conn)?;
     }
+    Ok(count)
+}
 
-    diesel::insert_or_ignore_into(user_interactions)
-        .values(&interactions)
-        .execute(conn)
+#[derive(Queryable, Debug)]
+pub struct QueryTotals {
+    pub query: String,
+    pub searched: Option<i64>,
+    pub accepted: Option<i64>,
 }
 
-pub fn get_user_seen_posts(
+pub fn get_query_stats(
     conn: &mut SqliteConnection,
-    did: &str,
-    cutoff_timestamp: i64,
-) -> QueryResult<Vec<String>> {
-    use crate::schema::user_interactions::dsl::*;
+    since_day: i64,
+) -> QueryResult<Vec<QueryTotals>> {
+    use diesel::dsl::sum;
 
-    user_interactions
-        .filter(user_did.eq(did))
-        .filter(interaction_type.eq(INTERACTION_SEEN))
-        .filter(created_at.gt(cutoff_timestamp))
-        .select(post_uri)
+    backfill_query_stats::table
+        .filter(backfill_query_stats::day.ge(since_day))
+        .group_by(backfill_query_stats::query)
+        .select((
+            backfill_query_stats::query,
+            sum(backfill_query_stats::searched),
+            sum(backfill_query_stats::accepted),
+        ))
         .load(conn)
 }
 
-#[derive(Debug, Clone)]
-pub struct UserPreference {
-    pub post_uri: String,
-    pub is_request_more: bool,
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = post_scores)]
+pub struct NewPostScore {
+    pub uri: String,
+    /// A `ScoreExplanation` as JSON.
+    pub explanation: String,
+    pub scored_at: i64,
+    pub config_id: Option<i32>,
 }
 
-pub fn get_user_preferences(
+/// Stores score explanations, replacing any earlier one for the same post.
+pub fn upsert_post_scores(
     conn: &mut SqliteConnection,
-    did: &str,
-) -> QueryResult<Vec<UserPreference>> {
-    use crate::schema::user_interactions::dsl::*;
+    scores: Vec<NewPostScore>,
+) -> QueryResult<usize> {
+    diesel::replace_into(post_scores::table)
+        .values(&scores)
+        .execute(conn)
+}
 
-    let results: Vec<(String, String)> = user_interactions
-        .filter(user_did.eq(did))
-        .filter(
-            interaction_type
-                .eq(INTERACTION_REQUEST_LESS)
-                .or(interaction_type.eq(INTERACTION_REQUEST_MORE)),
-        )
-        .select((post_uri, interaction_type))
-        .load(conn)?;
+/// The stored explanation JSON for a post and when it was scored.
+pub fn get_post_score(
+    conn: &mut SqliteConnection,
+    post_uri: &str,
+) -> QueryResult<Option<(String, i64)>> {
+    post_scores::table
+        .find(post_uri)
+        .select((post_scores::explanation, post_scores::scored_at))
+        .first(conn)
+        .optional()
+}
 
-    Ok(results
-        .into_iter()
-        .map(|(uri, itype)| UserPreference {
-            post_uri: uri,
-            is_request_more: itype == INTERACTION_REQUEST_MORE,
-        })
-        .collect())
+pub fn cleanup_post_scores(conn: &mut SqliteConnection, before: i64) -> QueryResult<usize> {
+    diesel::delete(post_scores::table.filter(post_scores::scored_at.lt(before))).execute(conn)
 }
 
 #[derive(Insertable, Debug, Clone)]
-#[diesel(table_name = blocked_authors)]
-pub struct NewBlockedAuthor {
-    pub did: String,
-    pub post_uri: String,
-    pub blocked_at: i64,
+#[diesel(table_name = shadow_scores)]
+pub struct NewShadowScore {
+    pub uri: String,
+    pub profile: String,
+    pub live_priority: f32,
+    pub shadow_priority: f32,
+    pub live_accepted: i32,
+    pub shadow_accepted: i32,
+    pub scored_at: i64,
 }
 
-pub fn is_blocked_author(conn: &mut SqliteConnection, author_did: &str) -> bool {
-    blocked_authors::table
-        .filter(blocked_authors::did.eq(author_did))
-        .count()
-        .get_result::<i64>(conn)
-        .unwrap_or(0)
-        > 0
+#[derive(Queryable, Debug, Clone, Serialize)]
+pub struct ShadowScore {
+    pub uri: String,
+    pub profile: String,
+    pub live_priority: f32,
+    pub shadow_priority: f32,
+    pub live_accepted: i32,
+    pub shadow_accepted: i32,
+    pub scored_at: i64,
 }
 
-pub fn block_author(conn: &mut SqliteConnection, blocked: NewBlockedAuthor) -> QueryResult<usize> {
-    diesel::insert_or_ignore_into(blocked_authors::table)
-        .values(&blocked)
+/// How the live settings and a shadow profile decided on the same posts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ShadowSummary {
+    pub scored: i64,
+    pub live_accepted: i64,
+    pub shadow_accepted: i64,
+    /// Rejected live, accepted by the shadow profile.
+    pub shadow_only: i64,
+    /// Accepted live, rejected by the shadow profile.
+    pub live_only: i64,
+}
+
+pub fn upsert_shadow_scores(
+    conn: &mut SqliteConnection,
+    scores: Vec<NewShadowScore>,
+) -> QueryResult<usize> {
+    diesel::replace_into(shadow_scores::table)
+        .values(&scores)
         .execute(conn)
 }
 
-pub fn delete_posts_by_author(conn: &mut SqliteConnection, did: &str) -> QueryResult<usize> {
-    use crate::schema::posts::dsl::*;
+pub fn shadow_summary(
+    conn: &mut SqliteConnection,
+    profile: &str,
+    since: i64,
+) -> QueryResult<ShadowSummary> {
+    let verdicts: Vec<(i32, i32)> = shadow_scores::table
+        .filter(shadow_scores::profile.eq(profile))
+        .filter(shadow_scores::scored_at.ge(since))
+        .select((shadow_scores::live_accepted, shadow_scores::shadow_accepted))
+        .load(conn)?;
+    let mut summary = ShadowSummary::default();
+    for (live, shadow) in verdicts {
+        let (live, shadow) = (live != 0, shadow != 0);
+        summary.scored += 1;
+        summary.live_accepted += live as i64;
+        summary.shadow_accepted += shadow as i64;
+        summary.shadow_only += (shadow && !live) as i64;
+        summary.live_only += (live && !shadow) as i64;
+    }
+    Ok(summary)
+}
+
+/// The most recent posts the shadow profile decided differently on, newest first.
+pub fn shadow_disagreements(
+    conn: &mut SqliteConnection,
+    profile: &str,
+    since: i64,
+    limit: i64,
+) -> QueryResult<Vec<ShadowScore>> {
+    shadow_scores::table
+        .filter(shadow_scores::profile.eq(profile))
+        .filter(shadow_scores::scored_at.ge(since))
+        .filter(shadow_scores::live_accepted.ne(shadow_scores::shadow_accepted))
+        .order(shadow_scores::scored_at.desc())
+        .limit(limit)
+        .load(conn)
+}
+
+pub fn cleanup_shadow_scores(conn: &mut SqliteConnection, before: i64) -> QueryResult<usize> {
+    diesel::delete(shadow_scores::table.filter(shadow_scores::scored_at.lt(before))).execute(conn)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = admin_audit)]
+pub struct NewAuditEntry {
+    pub actor: String,
+    pub role: String,
+    pub action: String,
+    pub target: String,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub actor: String,
+    pub role: String,
+    pub action: String,
+    pub target: String,
+    pub created_at: i64,
+}
+
+pub fn insert_audit_entry(conn: &mut SqliteConnection, entry: NewAuditEntry) -> QueryResult<usize> {
+    diesel::insert_into(admin_audit::table)
+        .values(&entry)
+        .execute(conn)
+}
+
+pub fn get_audit_entries(
+    conn: &mut SqliteConnection,
+    limit: i64,
+    before_id: Option<i32>,
+) -> QueryResult<Vec<AuditEntry>> {
+    let mut query = admin_audit::table
+        .order(admin_audit::id.desc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(before_id) = before_id {
+        query = query.filter(admin_audit::id.lt(before_id));
+    }
+    query.load(conn)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = config_history)]
+pub struct NewConfigSnapshot {
+    /// Hex SHA-256 of `settings`.
+    pub hash: String,
+    /// The effective settings as RON, with secrets redacted.
+    pub settings: String,
+    pub profile: Option<String>,
+    pub activated_at: i64,
+}
+
+#[derive(Queryable, Debug, Clone, Serialize)]
+pub struct ConfigSnapshot {
+    pub id: i32,
+    pub hash: String,
+    pub settings: String,
+    pub profile: Option<String>,
+    pub activated_at: i64,
+}
+
+/// Records `snapshot` as the active config and returns its id. If it matches the config that
+/// was active last, as after a reload that changed nothing, that row's id is returned instead.
+pub fn record_config_snapshot(
+    conn: &mut SqliteConnection,
+    snapshot: NewConfigSnapshot,
+) -> QueryResult<i32> {
+    let latest: Option<(i32, String)> = config_history::table
+        .order(config_history::id.desc())
+        .select((config_history::id, config_history::hash))
+        .first(conn)
+        .optional()?;
+    if let Some((id, hash)) = latest {
+        if hash == snapshot.hash {
+            return Ok(id);
+        }
+    }
+    diesel::insert_into(config_history::table)
+        .values(&snapshot)
+        .returning(config_history::id)
+        .get_result(conn)
+}
+
+pub fn get_config_history(
+    conn: &mut SqliteConnection,
+    limit: i64,
+    before_id: Option<i32>,
+) -> QueryResult<Vec<ConfigSnapshot>> {
+    let mut query = config_history::table
+        .order(config_history::id.desc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(before_id) = before_id {
+        query = query.filter(config_history::id.lt(before_id));
+    }
+    query.load(conn)
+}
+
+pub fn latest_post_timestamp(conn: &mut SqliteConnection) -> Option<i64> {
+    posts::table
+        .select(diesel::dsl::max(posts::timestamp))
+        .first::<Option<i64>>(conn)
+        .ok()
+        .flatten()
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = language_stats)]
+pub struct NewLanguageStat {
+    pub lang: String,
+    pub day: i64,
+    pub config_id: i32,
+    pub rejected: i32,
+    pub relevant: i32,
+}
+
+/// Adds self-replies to the thread length of stored root posts; unknown roots are ignored.
+pub fn record_thread_replies(
+    conn: &mut SqliteConnection,
+    replies: HashMap<String, i32>,
+) -> QueryResult<usize> {
+    let mut count = 0;
+    for (root_uri, added) in replies {
+        count += diesel::update(posts::table.filter(posts::uri.eq(root_uri)))
+            .set(posts::thread_length.eq(posts::thread_length + added))
+            .execute(conn)?;
+    }
+    Ok(count)
+}
+
+pub fn record_language_stats(
+    conn: &mut SqliteConnection,
+    stats: Vec<NewLanguageStat>,
+) -> QueryResult<usize> {
+    use diesel::upsert::excluded;
+
+    let mut count = 0;
+    for stat in stats {
+        count += diesel::insert_into(language_stats::table)
+            .values(&stat)
+            .on_conflict((
+                language_stats::lang,
+                language_stats::day,
+                language_stats::config_id,
+            ))
+            .do_update()
+            .set((
+                language_stats::rejected
+                    .eq(language_stats::rejected + excluded(language_stats::rejected)),
+                language_stats::relevant
+                    .eq(language_stats::relevant + excluded(language_stats::relevant)),
+            ))
+            .execute(conn)?;
+    }
+    Ok(count)
+}
+
+#[derive(Queryable, Debug)]
+pub struct LanguageTotals {
+    pub lang: String,
+    pub rejected: Option<i64>,
+    pub relevant: Option<i64>,
+}
+
+pub fn get_language_stats(
+    conn: &mut SqliteConnection,
+    since_day: i64,
+) -> QueryResult<Vec<LanguageTotals>> {
+    use diesel::dsl::sum;
+
+    language_stats::table
+        .filter(language_stats::day.ge(since_day))
+        .group_by(language_stats::lang)
+        .select((
+            language_stats::lang,
+            sum(language_stats::rejected),
+            sum(language_stats::relevant),
+        ))
+        .load(conn)
+}
+
+pub fn get_language_feed(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+    feed_lang: &str,
+) -> QueryResult<Vec<Post>> {
+    use crate::schema::posts::dsl::*;
+
+    posts
+        .filter(timestamp.gt(cutoff_timestamp))
+        .filter(lang.eq(feed_lang))
+        .order((timestamp.desc(), priority.desc()))
+        .select(Post::as_select())
+        .load(conn)
+}
+
+/// Posts newer than `cutoff_timestamp` that have any engagement velocity, with their velocity.
+/// Velocities last computed before `fresh_since` are stale and left out.
+pub fn get_trending_feed(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+    fresh_since: i64,
+) -> QueryResult<Vec<(String, f32)>> {
+    posts::table
+        .inner_join(engagement_cache::table)
+        .filter(posts::timestamp.gt(cutoff_timestamp))
+        .filter(engagement_cache::velocity_score.gt(0.0))
+        .filter(engagement_cache::last_updated.ge(fresh_since))
+        .order((engagement_cache::velocity_score.desc(), posts::uri.asc()))
+        .select((posts::uri, engagement_cache::velocity_score))
+        .load(conn)
+}
+
+pub fn get_posts_by_uris(conn: &mut SqliteConnection, uris: &[String]) -> QueryResult<Vec<Post>> {
+    use crate::schema::posts::dsl::*;
+
+    posts
+        .filter(uri.eq_any(uris))
+        .select(Post::as_select())
+        .load(conn)
+}
+
+pub fn get_post_priorities(
+    conn: &mut SqliteConnection,
+    uris: &[&str],
+) -> QueryResult<HashMap<String, f32>> {
+    let rows: Vec<(String, f32)> = posts::table
+        .filter(posts::uri.eq_any(uris))
+        .select((posts::uri, posts::priority))
+        .load(conn)?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Replaces a post's priority with one scored from scratch. The engagement boost is cleared,
+/// and the next periodic rescore adds it back.
+pub fn update_post_priority(
+    conn: &mut SqliteConnection,
+    post_uri: &str,
+    new_priority: f32,
+) -> QueryResult<usize> {
+    diesel::update(posts::table.filter(posts::uri.eq(post_uri)))
+        .set((
+            posts::priority.eq(new_priority),
+            posts::engagement_boost.eq(0.0),
+        ))
+        .execute(conn)
+}
+
+#[derive(Queryable, Debug)]
+pub struct RescoreCandidate {
+    pub uri: String,
+    pub priority: f32,
+    pub engagement_boost: f32,
+    pub like_count: i32,
+    pub reply_count: i32,
+    pub repost_count: i32,
+    pub velocity_score: f32,
+    pub engagement_updated_at: i64,
+}
+
+/// Posts newer than `since` that have picked up any engagement.
+pub fn get_rescore_candidates(
+    conn: &mut SqliteConnection,
+    since: i64,
+) -> QueryResult<Vec<RescoreCandidate>> {
+    posts::table
+        .inner_join(engagement_cache::table)
+        .filter(posts::timestamp.gt(since))
+        .select((
+            posts::uri,
+            posts::priority,
+            posts::engagement_boost,
+            engagement_cache::like_count,
+            engagement_cache::reply_count,
+            engagement_cache::repost_count,
+            engagement_cache::velocity_score,
+            engagement_cache::last_updated,
+        ))
+        .load(conn)
+}
+
+pub fn update_engagement_boost(
+    conn: &mut SqliteConnection,
+    post_uri: &str,
+    new_priority: f32,
+    boost: f32,
+) -> QueryResult<usize> {
+    diesel::update(posts::table.filter(posts::uri.eq(post_uri)))
+        .set((
+            posts::priority.eq(new_priority),
+            posts::engagement_boost.eq(boost),
+        ))
+        .execute(conn)
+}
+
+/// The engagement boost currently folded into a post's priority.
+#[allow(dead_code)]
+pub fn get_engagement_boost(conn: &mut SqliteConnection, post_uri: &str) -> Option<f32> {
+    posts::table
+        .find(post_uri)
+        .select(posts::engagement_boost)
+        .first(conn)
+        .ok()
+}
+
+#[derive(Queryable, Debug)]
+pub struct PostActivity {
+    pub timestamp: i64,
+    pub mention_count: i32,
+    pub priority: f32,
+    pub source: String,
+    pub like_count: Option<i32>,
+    pub reply_count: Option<i32>,
+    pub repost_count: Option<i32>,
+}
+
+pub fn get_post_activity(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+) -> QueryResult<Vec<PostActivity>> {
+    posts::table
+        .left_join(engagement_cache::table)
+        .filter(posts::timestamp.gt(cutoff_timestamp))
+        .select((
+            posts::timestamp,
+            posts::mention_count,
+            posts::priority,
+            posts::source,
+            engagement_cache::like_count.nullable(),
+            engagement_cache::reply_count.nullable(),
+            engagement_cache::repost_count.nullable(),
+        ))
+        .load(conn)
+}
+
+pub fn get_post_uris(conn: &mut SqliteConnection) -> QueryResult<Vec<String>> {
+    use crate::schema::posts::dsl::*;
+
+    posts.select(uri).load(conn)
+}
+
+pub fn post_exists(conn: &mut SqliteConnection, post_uri: &str) -> bool {
+    use crate::schema::posts::dsl::*;
+
+    posts
+        .filter(uri.eq(post_uri))
+        .count()
+        .get_result::<i64>(conn)
+        .unwrap_or(0)
+        > 0
+}
+
+pub fn get_post_author(conn: &mut SqliteConnection, post_uri: &str) -> Option<String> {
+    use crate::schema::posts::dsl::*;
+
+    posts
+        .filter(uri.eq(post_uri))
+        .select(author_did)
+        .first::<Option<String>>(conn)
+        .ok()
+        .flatten()
+}
+
+/// Likes, replies and reposts cached for a post.
+pub type EngagementCounts = (i32, i32, i32);
+
+/// Posts the next `cleanup_old_posts` deletes, with their engagement so far.
+pub fn get_expiring_posts(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+    max_posts: i64,
+) -> QueryResult<Vec<(Post, Option<EngagementCounts>)>> {
+    let mut uris: Vec<String> = posts::table
+        .filter(posts::timestamp.lt(cutoff_timestamp))
+        .select(posts::uri)
+        .load(conn)?;
+
+    let kept: i64 = posts::table
+        .filter(posts::timestamp.ge(cutoff_timestamp))
+        .count()
+        .get_result(conn)?;
+    if kept > max_posts {
+        let excess: Vec<String> = posts::table
+            .filter(posts::timestamp.ge(cutoff_timestamp))
+            .order(posts::timestamp.asc())
+            .limit(kept - max_posts)
+            .select(posts::uri)
+            .load(conn)?;
+        uris.extend(excess);
+    }
+    if uris.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    posts::table
+        .left_join(engagement_cache::table)
+        .filter(posts::uri.eq_any(uris))
+        .select((
+            Post::as_select(),
+            (
+                engagement_cache::like_count,
+                engagement_cache::reply_count,
+                engagement_cache::repost_count,
+            )
+                .nullable(),
+        ))
+        .load(conn)
+}
+
+/// The posts with the given URIs, with their engagement so far.
+pub fn get_posts_with_engagement(
+    conn: &mut SqliteConnection,
+    uris: &[String],
+) -> QueryResult<Vec<(Post, Option<EngagementCounts>)>> {
+    posts::table
+        .left_join(engagement_cache::table)
+        .filter(posts::uri.eq_any(uris))
+        .select((
+            Post::as_select(),
+            (
+                engagement_cache::like_count,
+                engagement_cache::reply_count,
+                engagement_cache::repost_count,
+            )
+                .nullable(),
+        ))
+        .load(conn)
+}
+
+pub fn cleanup_old_posts(
+    conn: &mut SqliteConnection,
+    cutoff_timestamp: i64,
+    max_posts: i64,
+) -> QueryResult<usize> {
+    use crate::schema::posts::dsl::*;
+
+    let deleted_by_age =
+        diesel::delete(posts.filter(timestamp.lt(cutoff_timestamp))).execute(conn)?;
+
+    let count: i64 = posts.count().get_result(conn)?;
+    let mut deleted_by_limit = 0;
+    if count > max_posts {
+        let excess = count - max_posts;
+        let uris_to_delete: Vec<String> = posts
+            .order(timestamp.asc())
+            .limit(excess)
+            .select(uri)
+            .load(conn)?;
+
+        deleted_by_limit =
+            diesel::delete(posts.filter(uri.eq_any(uris_to_delete))).execute(conn)?;
+    }
+
+    Ok(deleted_by_age + deleted_by_limit)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = user_interactions)]
+pub struct NewInteraction {
+    pub user_did: String,
+    pub post_uri: String,
+    pub interaction_type: String,
+    pub created_at: i64,
+}
+
+pub const INTERACTION_SEEN: &str = "seen";
+pub const INTERACTION_REQUEST_LESS: &str = "request_less";
+pub const INTERACTION_REQUEST_MORE: &str = "request_more";
+pub const INTERACTION_CLICKTHROUGH: &str = "clickthrough";
+pub const INTERACTION_SHARE: &str = "share";
+/// Interactions that show interest in a post without an explicit request for more.
+pub const ENGAGED_INTERACTIONS: [&str; 2] = [INTERACTION_CLICKTHROUGH, INTERACTION_SHARE];
+
+pub fn insert_interactions(
+    conn: &mut SqliteConnection,
+    interactions: Vec<NewInteraction>,
+) -> QueryResult<usize> {
+    use crate::schema::user_interactions::dsl::*;
+
+    if interactions.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::insert_or_ignore_into(user_interactions)
+        .values(&interactions)
+        .execute(conn)
+}
+
+pub fn get_user_seen_posts(
+    conn: &mut SqliteConnection,
+    did: &str,
+    cutoff_timestamp: i64,
+) -> QueryResult<Vec<String>> {
+    use crate::schema::user_interactions::dsl::*;
+
+    user_interactions
+        .filter(user_did.eq(did))
+        .filter(interaction_type.eq(INTERACTION_SEEN))
+        .filter(created_at.gt(cutoff_timestamp))
+        .select(post_uri)
+        .load(conn)
+}
+
+#[derive(Debug, Clone)]
+pub struct UserPreference {
+    pub post_uri: String,
+    pub is_request_more: bool,
+}
+
+pub fn get_user_preferences(
+    conn: &mut SqliteConnection,
+    did: &str,
+) -> QueryResult<Vec<UserPreference>> {
+    use crate::schema::user_interactions::dsl::*;
+
+    let results: Vec<(String, String)> = user_interactions
+        .filter(user_did.eq(did))
+        .filter(
+            interaction_type
+                .eq(INTERACTION_REQUEST_LESS)
+                .or(interaction_type.eq(INTERACTION_REQUEST_MORE)),
+        )
+        .select((post_uri, interaction_type))
+        .load(conn)?;
+
+    Ok(results
+        .into_iter()
+        .map(|(uri, itype)| UserPreference {
+            post_uri: uri,
+            is_request_more: itype == INTERACTION_REQUEST_MORE,
+        })
+        .collect())
+}
+
+/// Posts `did` clicked through or shared.
+pub fn get_user_engaged_posts(conn: &mut SqliteConnection, did: &str) -> QueryResult<Vec<String>> {
+    user_interactions::table
+        .filter(user_interactions::user_did.eq(did))
+        .filter(user_interactions::interaction_type.eq_any(ENGAGED_INTERACTIONS))
+        .select(user_interactions::post_uri)
+        .distinct()
+        .load(conn)
+}
+
+/// Interactions recorded since `since`, per type.
+pub fn get_interaction_counts(
+    conn: &mut SqliteConnection,
+    since: i64,
+) -> QueryResult<Vec<(String, i64)>> {
+    use diesel::dsl::count_star;
+
+    user_interactions::table
+        .filter(user_interactions::created_at.ge(since))
+        .group_by(user_interactions::interaction_type)
+        .select((user_interactions::interaction_type, count_star()))
+        .load(conn)
+}
+
+/// Embeddings of the posts `did` liked, asked to see more of, clicked through or shared,
+/// newest first.
+pub fn get_user_liked_embeddings(
+    conn: &mut SqliteConnection,
+    did: &str,
+    limit: i64,
+) -> QueryResult<Vec<Embedding>> {
+    let requested_more = user_interactions::table
+        .filter(user_interactions::user_did.eq(did))
+        .filter(
+            user_interactions::interaction_type
+                .eq(INTERACTION_REQUEST_MORE)
+                .or(user_interactions::interaction_type.eq_any(ENGAGED_INTERACTIONS)),
+        )
+        .select(user_interactions::post_uri);
+    let liked = likes::table
+        .filter(likes::liker_did.eq(did))
+        .select(likes::post_uri);
+
+    let rows: Vec<Vec<u8>> = post_embeddings::table
+        .filter(
+            post_embeddings::post_uri
+                .eq_any(requested_more)
+                .or(post_embeddings::post_uri.eq_any(liked)),
+        )
+        .order(post_embeddings::created_at.desc())
+        .limit(limit)
+        .select(post_embeddings::embedding)
+        .load(conn)?;
+
+    Ok(rows.iter().map(|bytes| decode_embedding(bytes)).collect())
+}
+
+pub const AFFINITY_TOPIC: &str = "topic";
+pub const AFFINITY_AUTHOR: &str = "author";
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = like_affinities)]
+pub struct LikeAffinity {
+    pub user_did: String,
+    /// `topic` or `author`.
+    pub kind: String,
+    /// The topic label or author DID.
+    pub value: String,
+    pub score: f32,
+    pub updated_at: i64,
+}
+
+impl LikeAffinity {
+    /// `score` as of `now`, halved every `half_life_hours` since it was last updated.
+    pub fn decayed(&self, now: i64, half_life_hours: f32) -> f32 {
+        let hours = (now - self.updated_at).max(0) as f32 / 3600.0;
+        self.score * 0.5f32.powf(hours / half_life_hours.max(f32::EPSILON))
+    }
+}
+
+/// Counts each like by a feed user, anyone who has sent the feed interactions, towards their
+/// affinity for the liked post's topic and author, decaying what was there first. Likes of
+/// posts that aren't in the feed are skipped. Returns how many affinities were updated.
+pub fn record_like_affinities(
+    conn: &mut SqliteConnection,
+    new_likes: &[NewLike],
+    now: i64,
+    half_life_hours: f32,
+) -> QueryResult<usize> {
+    let likers: Vec<&str> = new_likes
+        .iter()
+        .filter_map(|like| like.liker_did.as_ref().map(Did::as_str))
+        .collect();
+    if likers.is_empty() {
+        return Ok(0);
+    }
+    let feed_users: HashSet<String> = user_interactions::table
+        .filter(user_interactions::user_did.eq_any(&likers))
+        .select(user_interactions::user_did)
+        .distinct()
+        .load::<String>(conn)?
+        .into_iter()
+        .collect();
+    let liked: Vec<(&str, &str)> = new_likes
+        .iter()
+        .filter_map(|like| {
+            let liker = like.liker_did.as_ref()?.as_str();
+            feed_users
+                .contains(liker)
+                .then_some((liker, like.post_uri.as_str()))
+        })
+        .collect();
+    if liked.is_empty() {
+        return Ok(0);
+    }
+
+    let uris: Vec<&str> = liked.iter().map(|(_, uri)| *uri).collect();
+    let liked_posts: HashMap<String, Post> = posts::table
+        .filter(posts::uri.eq_any(&uris))
+        .select(Post::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|post| (post.uri.clone(), post))
+        .collect();
+
+    let mut added: HashMap<(String, &str, String), f32> = HashMap::new();
+    for (liker, uri) in liked {
+        let Some(post) = liked_posts.get(uri) else {
+            continue;
+        };
+        *added
+            .entry((liker.to_string(), AFFINITY_TOPIC, post.topic().to_string()))
+            .or_default() += 1.0;
+        if let Some(author) = &post.author_did {
+            *added
+                .entry((liker.to_string(), AFFINITY_AUTHOR, author.clone()))
+                .or_default() += 1.0;
+        }
+    }
+
+    let users: HashSet<&str> = added.keys().map(|(did, _, _)| did.as_str()).collect();
+    let previous: HashMap<(String, String, String), LikeAffinity> = like_affinities::table
+        .filter(like_affinities::user_did.eq_any(users))
+        .select(LikeAffinity::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|affinity| {
+            (
+                (
+                    affinity.user_did.clone(),
+                    affinity.kind.clone(),
+                    affinity.value.clone(),
+                ),
+                affinity,
+            )
+        })
+        .collect();
+    let updated: Vec<LikeAffinity> = added
+        .into_iter()
+        .map(|((did, affinity_kind, affinity_value), likes_added)| {
+            let key = (did, affinity_kind.to_string(), affinity_value);
+            let score = previous
+                .get(&key)
+                .map_or(0.0, |p| p.decayed(now, half_life_hours))
+                + likes_added;
+            let (user_did, kind, value) = key;
+            LikeAffinity {
+                user_did,
+                kind,
+                value,
+                score,
+                updated_at: now,
+            }
+        })
+        .collect();
+    diesel::replace_into(like_affinities::table)
+        .values(&updated)
+        .execute(conn)
+}
+
+/// `did`'s topic and author affinities, decayed to `now`.
+pub fn get_like_affinities(
+    conn: &mut SqliteConnection,
+    did: &str,
+    now: i64,
+    half_life_hours: f32,
+) -> QueryResult<Vec<LikeAffinity>> {
+    let rows: Vec<LikeAffinity> = like_affinities::table
+        .filter(like_affinities::user_did.eq(did))
+        .select(LikeAffinity::as_select())
+        .load(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|affinity| LikeAffinity {
+            score: affinity.decayed(now, half_life_hours),
+            ..affinity
+        })
+        .collect())
+}
+
+/// Deletes affinities not updated since `before`, which have decayed to next to nothing.
+pub fn cleanup_like_affinities(conn: &mut SqliteConnection, before: i64) -> QueryResult<usize> {
+    diesel::delete(like_affinities::table.filter(like_affinities::updated_at.lt(before)))
+        .execute(conn)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = negative_examples)]
+pub struct NewNegativeExample {
+    pub post_uri: String,
+    pub text: String,
+    pub embedding: Vec<u8>,
+    pub request_less_count: i32,
+    pub harvested_at: i64,
+}
+
+/// Keeps posts that at least `min_count` readers asked to see less of as negative examples,
+/// with their text and embedding, so they outlive the post. Returns how many were added.
+pub fn harvest_negative_examples(
+    conn: &mut SqliteConnection,
+    min_count: i64,
+    now: i64,
+) -> QueryResult<usize> {
+    use diesel::dsl::count_star;
+
+    let disliked: HashMap<String, i64> = user_interactions::table
+        .filter(user_interactions::interaction_type.eq(INTERACTION_REQUEST_LESS))
+        .filter(
+            user_interactions::post_uri
+                .ne_all(negative_examples::table.select(negative_examples::post_uri)),
+        )
+        .group_by(user_interactions::post_uri)
+        .having(count_star().ge(min_count))
+        .select((user_interactions::post_uri, count_star()))
+        .load::<(String, i64)>(conn)?
+        .into_iter()
+        .collect();
+    if disliked.is_empty() {
+        return Ok(0);
+    }
+
+    let rows: Vec<(String, String, Vec<u8>)> = posts::table
+        .inner_join(post_embeddings::table.on(post_embeddings::post_uri.eq(posts::uri)))
+        .filter(posts::uri.eq_any(disliked.keys()))
+        .select((posts::uri, posts::text, post_embeddings::embedding))
+        .load(conn)?;
+    let examples: Vec<NewNegativeExample> = rows
+        .into_iter()
+        .filter(|(_, _, embedding)| !embedding.is_empty())
+        .map(|(post_uri, text, embedding)| NewNegativeExample {
+            request_less_count: disliked[&post_uri] as i32,
+            post_uri,
+            text,
+            embedding,
+            harvested_at: now,
+        })
+        .collect();
+    if examples.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::insert_or_ignore_into(negative_examples::table)
+        .values(&examples)
+        .execute(conn)
+}
+
+/// Embeddings of the newest `limit` negative examples.
+pub fn get_negative_embeddings(
+    conn: &mut SqliteConnection,
+    limit: i64,
+) -> QueryResult<Vec<Embedding>> {
+    let rows: Vec<Vec<u8>> = negative_examples::table
+        .order(negative_examples::harvested_at.desc())
+        .limit(limit)
+        .select(negative_examples::embedding)
+        .load(conn)?;
+    Ok(rows.iter().map(|bytes| decode_embedding(bytes)).collect())
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = blocked_authors)]
+pub struct NewBlockedAuthor {
+    pub did: String,
+    pub post_uri: String,
+    pub blocked_at: i64,
+}
+
+pub fn is_blocked_author(conn: &mut SqliteConnection, author_did: &str) -> bool {
+    blocked_authors::table
+        .filter(blocked_authors::did.eq(author_did))
+        .count()
+        .get_result::<i64>(conn)
+        .unwrap_or(0)
+        > 0
+}
+
+/// Which of `dids` are blocked.
+pub fn get_blocked_among(
+    conn: &mut SqliteConnection,
+    dids: &[String],
+) -> QueryResult<HashSet<String>> {
+    let mut blocked = HashSet::new();
+    for chunk in dids.chunks(MAX_BOUND_VALUES) {
+        blocked.extend(
+            blocked_authors::table
+                .filter(blocked_authors::did.eq_any(chunk))
+                .select(blocked_authors::did)
+                .load::<String>(conn)?,
+        );
+    }
+    Ok(blocked)
+}
+
+pub fn block_author(conn: &mut SqliteConnection, blocked: NewBlockedAuthor) -> QueryResult<usize> {
+    diesel::insert_or_ignore_into(blocked_authors::table)
+        .values(&blocked)
+        .execute(conn)
+}
+
+#[derive(Queryable, Selectable, Debug, Clone, Serialize)]
+#[diesel(table_name = blocked_authors)]
+pub struct BlockedAuthor {
+    pub did: String,
+    pub post_uri: String,
+    pub blocked_at: i64,
+}
+
+/// Blocked authors, most recently blocked first.
+#[allow(dead_code)]
+pub fn get_blocked_authors(conn: &mut SqliteConnection) -> QueryResult<Vec<BlockedAuthor>> {
+    blocked_authors::table
+        .order(blocked_authors::blocked_at.desc())
+        .select(BlockedAuthor::as_select())
+        .load(conn)
+}
+
+#[allow(dead_code)]
+pub fn unblock_author(conn: &mut SqliteConnection, author_did: &str) -> QueryResult<usize> {
+    diesel::delete(blocked_authors::table.find(author_did)).execute(conn)
+}
+
+/// Deletes an author's stored and archived posts and returns the URIs of the stored ones.
+pub fn delete_posts_by_author(conn: &mut SqliteConnection, did: &str) -> QueryResult<Vec<String>> {
+    conn.transaction(|conn| {
+        delete_archived_posts_by_author(conn, did)?;
+        diesel::delete(posts::table.filter(posts::author_did.eq(did)))
+            .returning(posts::uri)
+            .get_results(conn)
+    })
+}
+
+/// Deletes an author's rows from the public archive.
+pub fn delete_archived_posts_by_author(
+    conn: &mut SqliteConnection,
+    did: &str,
+) -> QueryResult<usize> {
+    diesel::delete(archive_posts::table.filter(archive_posts::author_did.eq(did))).execute(conn)
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = opted_out_authors)]
+pub struct NewOptedOutAuthor {
+    pub did: String,
+    /// The post carrying the opt-out hashtag.
+    pub post_uri: String,
+    pub opted_out_at: i64,
+}
+
+pub fn is_opted_out_author(conn: &mut SqliteConnection, author_did: &str) -> bool {
+    opted_out_authors::table
+        .find(author_did)
+        .count()
+        .get_result::<i64>(conn)
+        .unwrap_or(0)
+        > 0
+}
+
+/// Records an opt-out and deletes the author's stored and archived posts. Returns the URIs of
+/// the stored posts deleted, or `None` if the author had already opted out.
+pub fn opt_out_author(
+    conn: &mut SqliteConnection,
+    entry: NewOptedOutAuthor,
+) -> QueryResult<Option<Vec<String>>> {
+    conn.transaction(|conn| {
+        let inserted = diesel::insert_or_ignore_into(opted_out_authors::table)
+            .values(&entry)
+            .execute(conn)?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+        Ok(Some(delete_posts_by_author(conn, &entry.did)?))
+    })
+}
+
+pub fn remove_opted_out_author(
+    conn: &mut SqliteConnection,
+    author_did: &str,
+) -> QueryResult<usize> {
+    diesel::delete(opted_out_authors::table.find(author_did)).execute(conn)
+}
+
+pub fn block_authors(
+    conn: &mut SqliteConnection,
+    blocked: Vec<NewBlockedAuthor>,
+) -> QueryResult<usize> {
+    // Each row binds three values.
+    conn.transaction(|conn| {
+        blocked
+            .chunks(MAX_BOUND_VALUES / 3)
+            .map(|chunk| {
+                diesel::insert_or_ignore_into(blocked_authors::table)
+                    .values(chunk)
+                    .execute(conn)
+            })
+            .sum()
+    })
+}
+
+/// Deletes the authors' stored and archived posts and returns the URIs of the stored ones.
+pub fn delete_posts_by_authors(
+    conn: &mut SqliteConnection,
+    dids: &[String],
+) -> QueryResult<Vec<String>> {
+    conn.transaction(|conn| {
+        let mut deleted = Vec::new();
+        for chunk in dids.chunks(MAX_BOUND_VALUES) {
+            diesel::delete(archive_posts::table.filter(archive_posts::author_did.eq_any(chunk)))
+                .execute(conn)?;
+            deleted.extend(
+                diesel::delete(posts::table.filter(posts::author_did.eq_any(chunk)))
+                    .returning(posts::uri)
+                    .get_results::<String>(conn)?,
+            );
+        }
+        Ok(deleted)
+    })
+}
+
+/// Deletes posts from the feed and the public archive.
+pub fn delete_posts(conn: &mut SqliteConnection, uris: &[String]) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let mut deleted = 0;
+        for chunk in uris.chunks(MAX_BOUND_VALUES) {
+            diesel::delete(archive_posts::table.filter(archive_posts::uri.eq_any(chunk)))
+                .execute(conn)?;
+            deleted +=
+                diesel::delete(posts::table.filter(posts::uri.eq_any(chunk))).execute(conn)?;
+        }
+        Ok(deleted)
+    })
+}
+
+/// URIs of posts whose text contains `needle` (ignoring ASCII case, with `%` and `_` matched
+/// literally) and whose topic is `label`. The topic is worked out in SQL the same way as
+/// [`Post::topic`], so no post has to be loaded.
+pub fn get_post_uris_matching(
+    conn: &mut SqliteConnection,
+    needle: Option<&str>,
+    label: Option<TopicLabel>,
+) -> QueryResult<Vec<String>> {
+    use crate::schema::posts::dsl::*;
+    use diesel::dsl::not;
+
+    let mut query = posts.select(uri).into_boxed();
+    if let Some(needle) = needle {
+        query = query.filter(text.like(contains_pattern(needle)).escape('\\'));
+    }
+
+    let sharing_work = || has_media.ne(0).and(is_first_person.ne(0));
+    let question = || text.like("%?%");
+    let linked = || link_count.gt(0);
+    query = match label {
+        None => query,
+        Some(TopicLabel::SharingWork) => query.filter(sharing_work()),
+        Some(TopicLabel::Question) => query.filter(not(sharing_work())).filter(question()),
+        Some(TopicLabel::Link) => query
+            .filter(not(sharing_work()))
+            .filter(not(question()))
+            .filter(linked()),
+        Some(TopicLabel::Update) => query
+            .filter(not(sharing_work()))
+            .filter(not(question()))
+            .filter(not(linked()))
+            .filter(is_first_person.ne(0)),
+        Some(TopicLabel::Discussion) => query
+            .filter(not(sharing_work()))
+            .filter(not(question()))
+            .filter(not(linked()))
+            .filter(is_first_person.eq(0)),
+    };
+    query.load(conn)
+}
+
+/// A `LIKE` pattern matching text that contains `needle`, escaped with a backslash.
+fn contains_pattern(needle: &str) -> String {
+    let escaped = needle
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Posts whose text contains `needle`, ignoring ASCII case. `%` and `_` match literally.
+pub fn get_posts_containing(conn: &mut SqliteConnection, needle: &str) -> QueryResult<Vec<Post>> {
+    use crate::schema::posts::dsl::*;
+
+    posts
+        .filter(text.like(contains_pattern(needle)).escape('\\'))
+        .select(Post::as_select())
+        .load(conn)
+}
+
+#[derive(Insertable, AsChangeset, Debug, Clone)]
+#[diesel(table_name = handles)]
+pub struct NewHandle {
+    pub did: String,
+    pub handle: String,
+    pub updated_at: i64,
+    pub description: Option<String>,
+    pub bio_score: f32,
+}
+
+pub fn upsert_handle(conn: &mut SqliteConnection, entry: NewHandle) -> QueryResult<usize> {
+    diesel::insert_into(handles::table)
+        .values(&entry)
+        .on_conflict(handles::did)
+        .do_update()
+        .set(&entry)
+        .execute(conn)
+}
+
+pub fn get_handle(conn: &mut SqliteConnection, author_did: &str) -> Option<(String, i64)> {
+    handles::table
+        .filter(handles::did.eq(author_did))
+        .select((handles::handle, handles::updated_at))
+        .first::<(String, i64)>(conn)
+        .ok()
+}
+
+/// The DID most recently cached with `handle`, if any.
+#[allow(dead_code)]
+pub fn get_did_for_handle(conn: &mut SqliteConnection, handle: &str) -> Option<String> {
+    handles::table
+        .filter(handles::handle.eq(handle))
+        .order(handles::updated_at.desc())
+        .select(handles::did)
+        .first::<String>(conn)
+        .ok()
+}
+
+/// The cached bio and its score.
+pub fn get_bio(conn: &mut SqliteConnection, author_did: &str) -> Option<(Option<String>, f32)> {
+    handles::table
+        .filter(handles::did.eq(author_did))
+        .select((handles::description, handles::bio_score))
+        .first::<(Option<String>, f32)>(conn)
+        .ok()
+}
+
+pub fn get_bio_score(conn: &mut SqliteConnection, author_did: &str) -> Option<f32> {
+    handles::table
+        .filter(handles::did.eq(author_did))
+        .select(handles::bio_score)
+        .first::<f32>(conn)
+        .ok()
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct SearchHit {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub uri: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub text: String,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub author_did: Option<String>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub author_handle: Option<String>,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub timestamp: i64,
+    #[diesel(sql_type = diesel::sql_types::Float)]
+    pub priority: f32,
+    /// Negated FTS5 bm25 score, higher is more relevant.
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub relevance: f64,
+}
+
+/// Quotes every word of a user query so FTS5 treats it as plain terms that must all match.
+pub fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Whether `error` is FTS5 rejecting the query itself rather than the database failing.
+pub fn is_fts_query_error(error: &diesel::result::Error) -> bool {
+    match error {
+        diesel::result::Error::DatabaseError(_, info) => {
+            let message = info.message();
+            message.starts_with("fts5:") || message.contains("MATCH expression")
+        }
+        _ => false,
+    }
+}
+
+pub fn search_posts(
+    conn: &mut SqliteConnection,
+    query: &str,
+    limit: i64,
+) -> QueryResult<Vec<SearchHit>> {
+    let Some(fts) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    diesel::sql_query(
+        "SELECT posts.uri, posts.text, posts.author_did, handles.handle AS author_handle, \
+         posts.timestamp, posts.priority, -bm25(posts_fts) AS relevance \
+         FROM posts_fts \
+         JOIN posts ON posts.rowid = posts_fts.rowid \
+         LEFT JOIN handles ON handles.did = posts.author_did \
+         WHERE posts_fts MATCH ? \
+         ORDER BY bm25(posts_fts) \
+         LIMIT ?",
+    )
+    .bind::<diesel::sql_types::Text, _>(fts)
+    .bind::<diesel::sql_types::BigInt, _>(limit)
+    .load(conn)
+}
+
+#[derive(Queryable, Debug)]
+pub struct PostAuthor {
+    pub uri: String,
+    pub did: Option<String>,
+    pub handle: Option<String>,
+}
+
+pub fn get_post_authors(
+    conn: &mut SqliteConnection,
+    uris: &[String],
+) -> QueryResult<Vec<PostAuthor>> {
+    posts::table
+        .left_join(handles::table.on(posts::author_did.eq(handles::did.nullable())))
+        .filter(posts::uri.eq_any(uris))
+        .select((posts::uri, posts::author_did, handles::handle.nullable()))
+        .load(conn)
+}
+
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = archive_posts)]
+pub struct ArchivedPost {
+    pub uri: String,
+    pub text_hash: String,
+    pub author_did: Option<String>,
+    pub lang: String,
+    pub timestamp: i64,
+    pub priority: f32,
+    pub confidence: String,
+    pub topic: String,
+    pub like_count: i32,
+    pub reply_count: i32,
+    pub repost_count: i32,
+    pub archived_at: i64,
+    pub config_id: Option<i32>,
+    pub source: String,
+}
+
+pub fn insert_archived_posts(
+    conn: &mut SqliteConnection,
+    entries: Vec<ArchivedPost>,
+) -> QueryResult<usize> {
+    diesel::replace_into(archive_posts::table)
+        .values(&entries)
+        .execute(conn)
+}
+
+#[allow(dead_code)]
+pub fn get_archived_posts(conn: &mut SqliteConnection) -> QueryResult<Vec<ArchivedPost>> {
+    archive_posts::table
+        .order(archive_posts::timestamp.asc())
+        .select(ArchivedPost::as_select())
+        .load(conn)
+}
+
+pub fn get_archived_posts_by_uris(
+    conn: &mut SqliteConnection,
+    uris: &[String],
+) -> QueryResult<Vec<ArchivedPost>> {
+    archive_posts::table
+        .filter(archive_posts::uri.eq_any(uris))
+        .select(ArchivedPost::as_select())
+        .load(conn)
+}
+
+/// Live and archived posts published in `[?1, ?2)` whose authors are neither blocked nor opted
+/// out. A post still live is only listed once.
+const DAY_POSTS: &str = "\
+    SELECT uri, timestamp FROM posts \
+    WHERE timestamp >= ?1 AND timestamp < ?2 AND (author_did IS NULL OR ( \
+        author_did NOT IN (SELECT did FROM blocked_authors) \
+        AND author_did NOT IN (SELECT did FROM opted_out_authors))) \
+    UNION ALL \
+    SELECT uri, timestamp FROM archive_posts \
+    WHERE timestamp >= ?1 AND timestamp < ?2 AND (author_did IS NULL OR ( \
+        author_did NOT IN (SELECT did FROM blocked_authors) \
+        AND author_did NOT IN (SELECT did FROM opted_out_authors))) \
+    AND uri NOT IN (SELECT uri FROM posts)";
+
+#[derive(QueryableByName, Debug)]
+struct DayPostKey {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    uri: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct RowCount {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    count: i64,
+}
+
+/// How many posts the archive lists for `[start, end)`.
+pub fn count_day_posts(conn: &mut SqliteConnection, start: i64, end: i64) -> QueryResult<i64> {
+    diesel::sql_query(format!("SELECT COUNT(*) AS count FROM ({DAY_POSTS})"))
+        .bind::<diesel::sql_types::BigInt, _>(start)
+        .bind::<diesel::sql_types::BigInt, _>(end)
+        .get_result::<RowCount>(conn)
+        .map(|row| row.count)
+}
+
+/// URIs of one page of the posts the archive lists for `[start, end)`, oldest first.
+pub fn get_day_post_uris(
+    conn: &mut SqliteConnection,
+    start: i64,
+    end: i64,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<Vec<String>> {
+    let keys: Vec<DayPostKey> = diesel::sql_query(format!(
+        "{DAY_POSTS} ORDER BY timestamp, uri LIMIT ?3 OFFSET ?4"
+    ))
+    .bind::<diesel::sql_types::BigInt, _>(start)
+    .bind::<diesel::sql_types::BigInt, _>(end)
+    .bind::<diesel::sql_types::BigInt, _>(limit)
+    .bind::<diesel::sql_types::BigInt, _>(offset)
+    .load(conn)?;
+    Ok(keys.into_iter().map(|key| key.uri).collect())
+}
 
-    diesel::delete(posts.filter(author_did.eq(did))).execute(conn)
+pub fn cleanup_archive(conn: &mut SqliteConnection, before: i64) -> QueryResult<usize> {
+    diesel::delete(archive_posts::table.filter(archive_posts::archived_at.lt(before))).execute(conn)
 }
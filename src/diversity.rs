@@ -0,0 +1,88 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Reorders a ranked feed so that no author has more than `max_per_author` posts in any
+/// aligned `window` of positions. Posts over the cap are deferred to the first later window
+/// with room rather than dropped, ahead of lower-ranked posts. Posts without an author are
+/// never capped. Once only over-cap authors are left, they fill the remaining positions in
+/// order.
+pub fn cap_authors<T>(
+    items: Vec<T>,
+    window: usize,
+    max_per_author: usize,
+    author: impl Fn(&T) -> Option<&str>,
+) -> Vec<T> {
+    if window == 0 || max_per_author == 0 || items.len() <= max_per_author {
+        return items;
+    }
+
+    let total = items.len();
+    let mut ranked: VecDeque<T> = items.into();
+    let mut deferred: Vec<T> = Vec::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut ordered = Vec::with_capacity(total);
+
+    let fits = |item: &T, counts: &HashMap<String, usize>| {
+        author(item).is_none_or(|did| counts.get(did).copied().unwrap_or(0) < max_per_author)
+    };
+
+    while ordered.len() < total {
+        if ordered.len() % window == 0 {
+            counts.clear();
+        }
+
+        let next = if let Some(index) = deferred.iter().position(|item| fits(item, &counts)) {
+            deferred.remove(index)
+        } else if let Some(item) = ranked.pop_front() {
+            if !fits(&item, &counts) {
+                deferred.push(item);
+                continue;
+            }
+            item
+        } else {
+            deferred.remove(0)
+        };
+
+        if let Some(did) = author(&next) {
+            *counts.entry(did.to_string()).or_default() += 1;
+        }
+        ordered.push(next);
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authors(items: &[(u32, Option<&'static str>)]) -> Vec<u32> {
+        items.iter().map(|(id, _)| *id).collect()
+    }
+
+    #[test]
+    fn test_cap_authors_defers_overflow() {
+        let feed = vec![
+            (1, Some("a")),
+            (2, Some("a")),
+            (3, Some("a")),
+            (4, Some("b")),
+            (5, None),
+            (6, Some("a")),
+            (7, Some("c")),
+        ];
+        let ordered = cap_authors(feed, 4, 2, |(_, did)| *did);
+
+        // The third and fourth posts by "a" move to the next window, ahead of "c".
+        assert_eq!(authors(&ordered), vec![1, 2, 4, 5, 3, 6, 7]);
+    }
+
+    #[test]
+    fn test_cap_authors_keeps_every_post() {
+        let feed: Vec<_> = (0..6).map(|id| (id, Some("a"))).collect();
+        let ordered = cap_authors(feed.clone(), 2, 1, |(_, did)| *did);
+        assert_eq!(ordered, feed);
+
+        let feed = vec![(1, Some("a")), (2, Some("b"))];
+        assert_eq!(cap_authors(feed.clone(), 0, 2, |(_, did)| *did), feed);
+    }
+}
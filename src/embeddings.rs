@@ -0,0 +1,67 @@
+use crate::db::{self, DbPool, NewPostEmbedding};
+use crate::scoring::{Embedding, EmbeddingSink, MLHandle};
+use crate::settings::settings;
+use chrono::Utc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct EmbeddingStore {
+    pool: DbPool,
+}
+
+impl EmbeddingStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn spawn_writer(&self) -> EmbeddingSink {
+        let (tx, rx) = mpsc::channel::<(String, Embedding)>();
+        let pool = self.pool.clone();
+
+        thread::spawn(move || run_embedding_writer(pool, rx));
+
+        tx
+    }
+
+    pub fn get(&self, uri: &str) -> Option<Embedding> {
+        let mut conn = self.pool.get().ok()?;
+        db::get_post_embedding(&mut conn, uri).filter(|e| !e.is_empty())
+    }
+
+    pub async fn get_or_embed(&self, ml_handle: &MLHandle, uri: &str, text: &str) -> Embedding {
+        if let Some(embedding) = self.get(uri) {
+            return embedding;
+        }
+        ml_handle
+            .embed_post(uri.to_string(), text.to_string())
+            .await
+    }
+}
+
+fn run_embedding_writer(pool: DbPool, rx: mpsc::Receiver<(String, Embedding)>) {
+    let s = settings();
+    let batch_timeout = Duration::from_millis(s.ml.batch_timeout_ms);
+
+    loop {
+        let mut batch: Vec<NewPostEmbedding> = Vec::new();
+        let now = Utc::now().timestamp();
+
+        match rx.recv() {
+            Ok((uri, embedding)) => batch.push(NewPostEmbedding::new(uri, &embedding, now)),
+            Err(_) => break,
+        }
+
+        while batch.len() < s.ml.batch_size {
+            match rx.recv_timeout(batch_timeout) {
+                Ok((uri, embedding)) => batch.push(NewPostEmbedding::new(uri, &embedding, now)),
+                Err(_) => break,
+            }
+        }
+
+        if let Ok(mut conn) = pool.get() {
+            let _ = db::insert_post_embeddings(&mut conn, batch);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use crate::scoring::MediaInfo;
+use serde::Deserialize;
+use skyfeed::{Cid, Embed, ExternalEmbed, ImageEmbed, MediaEmbed, Uri, VideoEmbed};
+
+#[derive(Debug, Deserialize)]
+struct BlobRef {
+    #[serde(rename = "$link")]
+    link: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Blob {
+    #[serde(rename = "ref")]
+    link: Option<BlobRef>,
+    /// Legacy blobs carry the CID directly.
+    cid: Option<String>,
+    #[serde(default)]
+    mime_type: String,
+}
+
+impl Blob {
+    fn cid(&self) -> Cid {
+        Cid(self
+            .link
+            .as_ref()
+            .map(|r| r.link.clone())
+            .or_else(|| self.cid.clone())
+            .unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageRecord {
+    image: Blob,
+    #[serde(default)]
+    alt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalRecord {
+    uri: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    thumb: Option<Blob>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordRef {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuotedRecord {
+    record: RecordRef,
+}
+
+/// An `app.bsky.embed.*` as stored in a post record.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "$type")]
+enum EmbedRecord {
+    #[serde(rename = "app.bsky.embed.images")]
+    Images { images: Vec<ImageRecord> },
+    #[serde(rename = "app.bsky.embed.video")]
+    Video { video: Blob, alt: Option<String> },
+    #[serde(rename = "app.bsky.embed.external")]
+    External { external: ExternalRecord },
+    #[serde(rename = "app.bsky.embed.record")]
+    Record { record: RecordRef },
+    #[serde(rename = "app.bsky.embed.recordWithMedia")]
+    RecordWithMedia {
+        record: QuotedRecord,
+        media: Box<EmbedRecord>,
+    },
+}
+
+fn images(images: Vec<ImageRecord>) -> Vec<ImageEmbed> {
+    images
+        .into_iter()
+        .map(|image| ImageEmbed {
+            cid: image.image.cid(),
+            alt_text: image.alt,
+            mime_type: image.image.mime_type,
+        })
+        .collect()
+}
+
+fn video(video: Blob, alt: Option<String>) -> VideoEmbed {
+    VideoEmbed {
+        cid: video.cid(),
+        alt_text: alt,
+        mime_type: video.mime_type,
+    }
+}
+
+fn external(external: ExternalRecord) -> ExternalEmbed {
+    ExternalEmbed {
+        title: external.title,
+        description: external.description,
+        uri: external.uri,
+        thumbnail: external.thumb.map(|thumb| thumb.cid()),
+    }
+}
+
+fn media(embed: EmbedRecord) -> Option<MediaEmbed> {
+    match embed {
+        EmbedRecord::Images { images: list } => Some(MediaEmbed::Images(images(list))),
+        EmbedRecord::Video { video: blob, alt } => Some(MediaEmbed::Video(video(blob, alt))),
+        EmbedRecord::External { external: record } => Some(MediaEmbed::External(external(record))),
+        EmbedRecord::Record { .. } | EmbedRecord::RecordWithMedia { .. } => None,
+    }
+}
+
+/// The embed of a raw post record, as skyfeed would have decoded it. Unknown or malformed
+/// embeds are dropped rather than failing the whole post.
+pub fn from_record(value: serde_json::Value) -> Option<Embed> {
+    match serde_json::from_value(value).ok()? {
+        EmbedRecord::Images { images: list } => Some(Embed::Images(images(list))),
+        EmbedRecord::Video { video: blob, alt } => Some(Embed::Video(video(blob, alt))),
+        EmbedRecord::External { external: record } => Some(Embed::External(external(record))),
+        EmbedRecord::Record { record } => Some(Embed::Quote(Uri(record.uri))),
+        EmbedRecord::RecordWithMedia { record, media: m } => {
+            Some(Embed::QuoteWithMedia(Uri(record.record.uri), media(*m)?))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageView {
+    #[serde(default)]
+    alt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalView {
+    uri: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// An `app.bsky.embed.*#view` as the AppView hydrates it. Only the parts scoring reads are
+/// kept; quoted posts and unknown embeds carry no media.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "$type")]
+enum EmbedView {
+    #[serde(rename = "app.bsky.embed.images#view")]
+    Images { images: Vec<ImageView> },
+    #[serde(rename = "app.bsky.embed.video#view")]
+    Video { alt: Option<String> },
+    #[serde(rename = "app.bsky.embed.external#view")]
+    External { external: ExternalView },
+    #[serde(rename = "app.bsky.embed.recordWithMedia#view")]
+    RecordWithMedia { media: Box<EmbedView> },
+    #[serde(other)]
+    Other,
+}
+
+impl MediaInfo {
+    fn images<'a>(alts: impl ExactSizeIterator<Item = &'a str>) -> Self {
+        let count = alts.len();
+        let alt_texts: Vec<String> = alts
+            .filter(|alt| !alt.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self {
+            image_count: count.min(u8::MAX as usize) as u8,
+            has_alt_text: !alt_texts.is_empty(),
+            alt_texts,
+            ..Self::default()
+        }
+    }
+
+    fn video(alt: Option<&str>) -> Self {
+        Self {
+            has_video: true,
+            alt_texts: alt
+                .filter(|alt| !alt.is_empty())
+                .map(str::to_string)
+                .into_iter()
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    fn external(uri: String, title: &str, description: &str) -> Self {
+        let text = [title.trim(), description.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self {
+            external_uri: Some(uri),
+            external_text: (!text.is_empty()).then_some(text),
+            ..Self::default()
+        }
+    }
+
+    fn from_external(external: &ExternalEmbed) -> Self {
+        Self::external(external.uri.clone(), &external.title, &external.description)
+    }
+
+    /// Media from a decoded record embed. Quoted posts only count for the media attached
+    /// alongside them.
+    pub fn from_embed(embed: Option<&Embed>) -> Self {
+        match embed {
+            Some(Embed::Images(images)) => Self::from_images(images),
+            Some(Embed::Video(video)) => Self::video(video.alt_text.as_deref()),
+            Some(Embed::External(external)) => Self::from_external(external),
+            Some(Embed::QuoteWithMedia(_, media)) => match media {
+                MediaEmbed::Images(images) => Self::from_images(images),
+                MediaEmbed::Video(video) => Self::video(video.alt_text.as_deref()),
+                MediaEmbed::External(external) => Self::from_external(external),
+            },
+            Some(Embed::Quote(_)) | None => Self::default(),
+        }
+    }
+
+    fn from_images(images: &[ImageEmbed]) -> Self {
+        Self::images(images.iter().map(|img| img.alt_text.as_str()))
+    }
+
+    /// Media from an AppView embed view, as returned by `getPostThread` and `searchPosts`.
+    pub fn from_embed_view(embed: Option<&serde_json::Value>) -> Self {
+        embed
+            .and_then(|value| EmbedView::deserialize(value).ok())
+            .map(|view| Self::from_view(&view))
+            .unwrap_or_default()
+    }
+
+    fn from_view(view: &EmbedView) -> Self {
+        match view {
+            EmbedView::Images { images } => Self::images(images.iter().map(|img| img.alt.as_str())),
+            EmbedView::Video { alt } => Self::video(alt.as_deref()),
+            EmbedView::External { external } => {
+                Self::external(external.uri.clone(), &external.title, &external.description)
+            }
+            EmbedView::RecordWithMedia { media } => Self::from_view(media),
+            EmbedView::Other => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn blob(link: &str) -> serde_json::Value {
+        json!({"$type": "blob", "ref": {"$link": link}, "mimeType": "image/png", "size": 1024})
+    }
+
+    #[test]
+    fn test_record_images() {
+        let record = json!({
+            "$type": "app.bsky.embed.images",
+            "images": [{"alt": "", "image": blob("bafkreia")}, {"alt": "boss arena", "image": blob("bafkreib")}],
+        });
+        let Some(Embed::Images(images)) = from_record(record) else {
+            panic!("expected images");
+        };
+        assert_eq!(images[1].cid.0, "bafkreib");
+        assert_eq!(images[1].mime_type, "image/png");
+
+        let media = MediaInfo::from_embed(Some(&Embed::Images(images)));
+        assert_eq!(media.image_count, 2);
+        assert!(media.has_alt_text);
+        assert_eq!(media.alt_texts, vec!["boss arena"]);
+    }
+
+    #[test]
+    fn test_record_video() {
+        let record = json!({"$type": "app.bsky.embed.video", "video": {"cid": "bafkreiv", "mimeType": "video/mp4"}});
+        let Some(Embed::Video(video)) = from_record(record) else {
+            panic!("expected a video");
+        };
+        assert_eq!(video.cid.0, "bafkreiv");
+        assert!(MediaInfo::from_embed(Some(&Embed::Video(video))).has_video);
+    }
+
+    #[test]
+    fn test_record_external() {
+        let record = json!({
+            "$type": "app.bsky.embed.external",
+            "external": {"uri": "https://itch.io/game", "title": "Game", "thumb": blob("bafkreit")},
+        });
+        let Some(Embed::External(external)) = from_record(record) else {
+            panic!("expected an external link");
+        };
+        assert_eq!(external.thumbnail.as_ref().unwrap().0, "bafkreit");
+        assert_eq!(external.description, "");
+
+        let media = MediaInfo::from_embed(Some(&Embed::External(external)));
+        assert_eq!(media.external_uri.as_deref(), Some("https://itch.io/game"));
+        assert_eq!(media.external_text.as_deref(), Some("Game"));
+    }
+
+    #[test]
+    fn test_record_quotes() {
+        let quote = json!({
+            "$type": "app.bsky.embed.record",
+            "record": {"cid": "bafyreib", "uri": "at://did:plc:other/app.bsky.feed.post/3kq"},
+        });
+        let embed = from_record(quote);
+        assert!(matches!(&embed, Some(Embed::Quote(uri)) if uri.0.ends_with("/3kq")));
+        assert_eq!(MediaInfo::from_embed(embed.as_ref()).image_count, 0);
+
+        let with_media = json!({
+            "$type": "app.bsky.embed.recordWithMedia",
+            "record": {"record": {"cid": "bafyreib", "uri": "at://did:plc:other/app.bsky.feed.post/3kq"}},
+            "media": {"$type": "app.bsky.embed.video", "video": {"cid": "bafkreiv"}},
+        });
+        let embed = from_record(with_media);
+        assert!(matches!(
+            &embed,
+            Some(Embed::QuoteWithMedia(_, MediaEmbed::Video(_)))
+        ));
+        assert!(MediaInfo::from_embed(embed.as_ref()).has_video);
+    }
+
+    #[test]
+    fn test_record_unknown_or_malformed() {
+        assert!(from_record(json!({"$type": "app.bsky.embed.gallery"})).is_none());
+        assert!(from_record(json!({"$type": "app.bsky.embed.images"})).is_none());
+        assert!(MediaInfo::from_embed(None).facet_tags.is_none());
+    }
+
+    #[test]
+    fn test_view_images() {
+        let view = json!({
+            "$type": "app.bsky.embed.images#view",
+            "images": [{"alt": "", "thumb": "https://cdn/1"}, {"alt": "a knight mid-swing"}],
+        });
+        let media = MediaInfo::from_embed_view(Some(&view));
+        assert_eq!(media.image_count, 2);
+        assert!(media.has_alt_text);
+    }
+
+    #[test]
+    fn test_view_video_and_external() {
+        let video = json!({"$type": "app.bsky.embed.video#view", "cid": "bafkreiv", "playlist": "https://video/1"});
+        assert!(MediaInfo::from_embed_view(Some(&video)).has_video);
+
+        let external = json!({
+            "$type": "app.bsky.embed.external#view",
+            "external": {"uri": "https://store.steampowered.com/app/1", "title": "", "description": ""},
+        });
+        let media = MediaInfo::from_embed_view(Some(&external));
+        assert_eq!(
+            media.external_uri.as_deref(),
+            Some("https://store.steampowered.com/app/1")
+        );
+        assert!(media.external_text.is_none());
+
+        let card = json!({
+            "$type": "app.bsky.embed.external#view",
+            "external": {"uri": "https://example.dev/devlog-14", "title": "Devlog #14", "description": "Rewriting the enemy AI"},
+        });
+        let media = MediaInfo {
+            alt_texts: vec!["patrol routes".to_string()],
+            ..MediaInfo::from_embed_view(Some(&card))
+        };
+        assert_eq!(
+            media.attached_text(),
+            "patrol routes\nDevlog #14\nRewriting the enemy AI"
+        );
+    }
+
+    #[test]
+    fn test_view_quotes() {
+        let quote = json!({"$type": "app.bsky.embed.record#view", "record": {}});
+        assert_eq!(MediaInfo::from_embed_view(Some(&quote)).image_count, 0);
+
+        let with_media = json!({
+            "$type": "app.bsky.embed.recordWithMedia#view",
+            "record": {"record": {}},
+            "media": {"$type": "app.bsky.embed.images#view", "images": [{"alt": ""}]},
+        });
+        let media = MediaInfo::from_embed_view(Some(&with_media));
+        assert_eq!(media.image_count, 1);
+        assert!(!media.has_alt_text);
+        assert!(media.alt_texts.is_empty());
+        assert!(!MediaInfo::from_embed_view(None).has_video);
+    }
+}
@@ -1,9 +1,13 @@
-use crate::db::DbPool;
-use crate::schema::{engagement_cache, replies, reposts, spammers};
-use crate::settings::settings;
-use chrono::Utc;
+use crate::db::{count_author_history, get_post_author, DbPool};
+use crate::schema::{
+    author_history, engagement_cache, likes, posts, replies, reposts, spammers, user_interactions,
+};
+use crate::settings::{settings, LikeMultipliers};
+use crate::utils::time::{system_clock, SharedClock};
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -78,14 +82,25 @@ pub struct EngagementCache {
 #[derive(Clone)]
 pub struct EngagementTracker {
     pool: DbPool,
+    clock: SharedClock,
+    /// The brigading guard's last look at each post, reused for
+    /// `engagement.brigading.recheck_secs` so a burst of likes doesn't run it on every one.
+    burst_checks: Arc<Mutex<HashMap<String, BurstSignals>>>,
 }
 
 impl EngagementTracker {
     pub fn new(pool: DbPool) -> Self {
-        Self { pool }
+        Self::with_clock(pool, system_clock())
+    }
+
+    pub fn with_clock(pool: DbPool, clock: SharedClock) -> Self {
+        Self {
+            pool,
+            clock,
+            burst_checks: Arc::default(),
+        }
     }
 
-    #[allow(dead_code)]
     pub fn record_repost(
         &self,
         post_uri: &str,
@@ -108,7 +123,7 @@ impl EngagementTracker {
             post_uri: post_uri.to_string(),
             repost_uri: repost_uri.to_string(),
             reposter_did: reposter_did.to_string(),
-            timestamp: Utc::now().timestamp(),
+            timestamp: self.clock.timestamp(),
         };
 
         diesel::insert_or_ignore_into(reposts::table)
@@ -121,6 +136,47 @@ impl EngagementTracker {
         Ok(())
     }
 
+    /// Takes back a repost and updates the reposted post's engagement.
+    pub fn remove_repost(&self, repost_uri: &str) -> Result<(), DieselError> {
+        let mut conn = self
+            .pool
+            .get()
+            .map_err(|_| DieselError::BrokenTransactionManager)?;
+        let post_uris: Vec<String> =
+            diesel::delete(reposts::table.filter(reposts::repost_uri.eq(repost_uri)))
+                .returning(reposts::post_uri)
+                .get_results(&mut conn)?;
+        for post_uri in post_uris {
+            self.update_engagement_cache(&mut conn, &post_uri)?;
+        }
+        Ok(())
+    }
+
+    /// Counts a reply towards a post's engagement.
+    pub fn record_reply(
+        &self,
+        post_uri: &str,
+        reply_uri: &str,
+        author_did: &str,
+    ) -> Result<(), DieselError> {
+        let mut conn = self
+            .pool
+            .get()
+            .map_err(|_| DieselError::BrokenTransactionManager)?;
+
+        let new_reply = NewReply {
+            post_uri: post_uri.to_string(),
+            reply_uri: reply_uri.to_string(),
+            author_did: author_did.to_string(),
+            timestamp: self.clock.timestamp(),
+        };
+        diesel::insert_or_ignore_into(replies::table)
+            .values(&new_reply)
+            .execute(&mut conn)?;
+
+        self.update_engagement_cache(&mut conn, post_uri)
+    }
+
     pub fn record_like(&self, post_uri: &str) -> Result<(), DieselError> {
         let mut conn = self
             .pool
@@ -135,7 +191,7 @@ impl EngagementTracker {
         reposter_did: &str,
     ) -> Option<SpamDetected> {
         let s = settings();
-        let now = Utc::now().timestamp();
+        let now = self.clock.timestamp();
         let window_start = now - (s.spam.velocity_window_hours * 3600);
 
         let recent_count: i64 = reposts::table
@@ -158,13 +214,42 @@ impl EngagementTracker {
         None
     }
 
+    /// Detects an author whose accepted posts over the velocity window, plus `pending` ones not
+    /// yet written, reach `spam.max_posts_per_hour`, flagging them when `spam.flag_post_flood`.
+    pub fn check_post_flood(&self, author_did: &str, pending: usize) -> Option<SpamDetected> {
+        let s = settings();
+        if s.spam.max_posts_per_hour <= 0.0 {
+            return None;
+        }
+        let mut conn = self.pool.get().ok()?;
+        let window_start = self.clock.timestamp() - (s.spam.velocity_window_hours * 3600);
+
+        let recent_count =
+            count_author_history(&mut conn, author_did, window_start).unwrap_or(0) + pending as i64;
+        let frequency = recent_count as f32 / s.spam.velocity_window_hours as f32;
+        if frequency < s.spam.max_posts_per_hour {
+            return None;
+        }
+
+        let spam = SpamDetected {
+            did: author_did.to_string(),
+            reason: format!("high post frequency: {:.1}/hr", frequency),
+            frequency: Some(frequency),
+        };
+        if s.spam.flag_post_flood {
+            self.flag_spammer_internal(&mut conn, author_did, &spam.reason, spam.frequency)
+                .ok();
+        }
+        Some(spam)
+    }
+
     fn update_engagement_cache(
         &self,
         conn: &mut diesel::SqliteConnection,
         post_uri: &str,
     ) -> Result<(), DieselError> {
         let s = settings();
-        let now = Utc::now().timestamp();
+        let now = self.clock.timestamp();
         let window_start = now - (s.spam.velocity_window_hours * 3600);
 
         let reply_count: i64 = replies::table
@@ -193,15 +278,30 @@ impl EngagementTracker {
             .get_result(conn)
             .unwrap_or(0);
 
-        let like_count: i64 = crate::schema::likes::table
-            .filter(crate::schema::likes::post_uri.eq(post_uri))
+        // An author reposting their own post isn't another dev vouching for it.
+        let author = get_post_author(conn, post_uri).unwrap_or_default();
+        let recent_dev_reposts: i64 = reposts::table
+            .filter(reposts::post_uri.eq(post_uri))
+            .filter(reposts::timestamp.gt(window_start))
+            .filter(
+                reposts::reposter_did
+                    .eq_any(author_history::table.select(author_history::author_did)),
+            )
+            .filter(reposts::reposter_did.ne(&author))
             .count()
             .get_result(conn)
             .unwrap_or(0);
 
-        let velocity = recent_replies as f32 * s.engagement.weights.reply
-            + recent_reposts as f32 * s.engagement.weights.repost
-            + like_count as f32 * s.engagement.weights.like * 0.1;
+        let like_sources = self.like_sources(conn, post_uri, i64::MIN);
+        let like_count = like_sources.total;
+
+        let raw_velocity = recent_replies as f32 * s.engagement.weights.reply
+            + (recent_reposts - recent_dev_reposts) as f32 * s.engagement.weights.repost
+            + recent_dev_reposts as f32 * s.engagement.weights.dev_repost
+            + like_sources.weighted(&s.engagement.like_multipliers)
+                * s.engagement.weights.like
+                * 0.1;
+        let velocity = self.guard_velocity(conn, post_uri, raw_velocity, window_start, now);
 
         let entry = EngagementCacheEntry {
             post_uri: post_uri.to_string(),
@@ -222,6 +322,132 @@ impl EngagementTracker {
         Ok(())
     }
 
+    /// Who liked `post_uri` since `since`, by how well the feed knows them.
+    fn like_sources(
+        &self,
+        conn: &mut diesel::SqliteConnection,
+        post_uri: &str,
+        since: i64,
+    ) -> LikeSources {
+        let dev_authors = author_history::table.select(author_history::author_did);
+        // An author liking their own post counts as neither another dev nor a subscriber.
+        let author = get_post_author(conn, post_uri).unwrap_or_default();
+
+        let like_count: i64 = likes::table
+            .filter(likes::post_uri.eq(post_uri))
+            .filter(likes::liked_at.ge(since))
+            .count()
+            .get_result(conn)
+            .unwrap_or(0);
+
+        let dev_likes: i64 = likes::table
+            .filter(likes::post_uri.eq(post_uri))
+            .filter(likes::liked_at.ge(since))
+            .filter(likes::liker_did.eq_any(dev_authors.nullable()))
+            .filter(likes::liker_did.ne(&author))
+            .count()
+            .get_result(conn)
+            .unwrap_or(0);
+
+        let subscriber_likes: i64 =
+            likes::table
+                .filter(likes::post_uri.eq(post_uri))
+                .filter(likes::liked_at.ge(since))
+                .filter(likes::liker_did.ne_all(dev_authors.nullable()))
+                .filter(likes::liker_did.ne(&author))
+                .filter(likes::liker_did.eq_any(
+                    user_interactions::table.select(user_interactions::user_did.nullable()),
+                ))
+                .count()
+                .get_result(conn)
+                .unwrap_or(0);
+
+        LikeSources {
+            total: like_count,
+            dev_authors: dev_likes,
+            subscribers: subscriber_likes,
+        }
+    }
+
+    fn guard_velocity(
+        &self,
+        conn: &mut diesel::SqliteConnection,
+        post_uri: &str,
+        velocity: f32,
+        window_start: i64,
+        now: i64,
+    ) -> f32 {
+        let recheck_secs = settings().engagement.brigading.recheck_secs;
+        let cached = self.burst_checks.lock().ok().and_then(|checks| {
+            checks
+                .get(post_uri)
+                .filter(|signals| now - signals.checked_at < recheck_secs)
+                .copied()
+        });
+        let signals = match cached {
+            Some(signals) => signals,
+            None => {
+                let signals = self.burst_signals(conn, post_uri, window_start, now);
+                if let Ok(mut checks) = self.burst_checks.lock() {
+                    checks.retain(|_, signals| now - signals.checked_at < recheck_secs);
+                    checks.insert(post_uri.to_string(), signals);
+                }
+                signals
+            }
+        };
+
+        guard_burst(
+            velocity,
+            &signals.cohort,
+            signals.liker_entropy,
+            signals.unknown_share,
+        )
+    }
+
+    /// Looks at the likes inside the burst window only: how concentrated the likers of the
+    /// author's posts are, and how many of this post's likers the feed has never seen.
+    fn burst_signals(
+        &self,
+        conn: &mut diesel::SqliteConnection,
+        post_uri: &str,
+        window_start: i64,
+        now: i64,
+    ) -> BurstSignals {
+        let cohort: Vec<f32> = engagement_cache::table
+            .filter(engagement_cache::post_uri.ne(post_uri))
+            .filter(engagement_cache::last_updated.gt(window_start))
+            .select(engagement_cache::velocity_score)
+            .load(conn)
+            .unwrap_or_default();
+
+        let author_likers: Vec<Option<String>> = get_post_author(conn, post_uri)
+            .and_then(|author| {
+                posts::table
+                    .inner_join(likes::table)
+                    .filter(posts::author_did.eq(author))
+                    .filter(likes::liked_at.gt(window_start))
+                    .select(likes::liker_did)
+                    .load(conn)
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        let mut per_liker: HashMap<String, i64> = HashMap::new();
+        for liker in author_likers.into_iter().flatten() {
+            *per_liker.entry(liker).or_default() += 1;
+        }
+        let counts: Vec<i64> = per_liker.into_values().collect();
+
+        BurstSignals {
+            checked_at: now,
+            cohort: CohortStats::new(&cohort),
+            liker_entropy: normalized_entropy(&counts),
+            unknown_share: self
+                .like_sources(conn, post_uri, window_start + 1)
+                .unknown_share(),
+        }
+    }
+
     #[allow(dead_code)]
     pub fn get_engagement(&self, post_uri: &str) -> Option<EngagementCache> {
         let mut conn = self.pool.get().ok()?;
@@ -250,6 +476,19 @@ impl EngagementTracker {
             > 0
     }
 
+    /// Flagged spammers, most recently flagged first.
+    #[allow(dead_code)]
+    pub fn get_spammers(&self) -> Result<Vec<Spammer>, DieselError> {
+        let mut conn = self
+            .pool
+            .get()
+            .map_err(|_| DieselError::BrokenTransactionManager)?;
+        spammers::table
+            .order(spammers::flagged_at.desc())
+            .select(Spammer::as_select())
+            .load(&mut conn)
+    }
+
     #[allow(dead_code)]
     pub fn flag_spammer(&self, did: &str, reason: &str) -> Result<(), DieselError> {
         let mut conn = self
@@ -259,6 +498,14 @@ impl EngagementTracker {
         self.flag_spammer_internal(&mut conn, did, reason, None)
     }
 
+    pub fn unflag_spammer(&self, did: &str) -> Result<usize, DieselError> {
+        let mut conn = self
+            .pool
+            .get()
+            .map_err(|_| DieselError::BrokenTransactionManager)?;
+        diesel::delete(spammers::table.filter(spammers::did.eq(did))).execute(&mut conn)
+    }
+
     fn flag_spammer_internal(
         &self,
         conn: &mut diesel::SqliteConnection,
@@ -270,7 +517,7 @@ impl EngagementTracker {
             did: did.to_string(),
             reason: reason.to_string(),
             repost_frequency: frequency,
-            flagged_at: Utc::now().timestamp(),
+            flagged_at: self.clock.timestamp(),
             auto_detected: if frequency.is_some() { 1 } else { 0 },
         };
 
@@ -299,6 +546,113 @@ impl EngagementTracker {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+struct LikeSources {
+    total: i64,
+    dev_authors: i64,
+    subscribers: i64,
+}
+
+impl LikeSources {
+    fn unknown(&self) -> i64 {
+        (self.total - self.dev_authors - self.subscribers).max(0)
+    }
+
+    fn unknown_share(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.unknown() as f32 / self.total as f32
+        }
+    }
+
+    fn weighted(&self, multipliers: &LikeMultipliers) -> f32 {
+        self.dev_authors as f32 * multipliers.dev_author
+            + self.subscribers as f32 * multipliers.subscriber
+            + self.unknown() as f32 * multipliers.unknown
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BurstSignals {
+    checked_at: i64,
+    cohort: CohortStats,
+    liker_entropy: f32,
+    unknown_share: f32,
+}
+
+/// Velocities of the other posts engaged with during the burst window.
+#[derive(Debug, Clone, Copy)]
+struct CohortStats {
+    size: usize,
+    mean: f32,
+    std_dev: f32,
+}
+
+impl CohortStats {
+    fn new(velocities: &[f32]) -> Self {
+        let size = velocities.len();
+        if size == 0 {
+            return Self {
+                size,
+                mean: 0.0,
+                std_dev: f32::EPSILON,
+            };
+        }
+        let mean = velocities.iter().sum::<f32>() / size as f32;
+        let variance = velocities.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / size as f32;
+        Self {
+            size,
+            mean,
+            std_dev: variance.sqrt().max(f32::EPSILON),
+        }
+    }
+}
+
+/// How evenly likes spread over likers, from 0 when one account gave them all to 1. A single
+/// like says nothing either way and counts as even.
+fn normalized_entropy(counts: &[i64]) -> f32 {
+    let total: i64 = counts.iter().sum();
+    if total < 2 {
+        return 1.0;
+    }
+    if counts.iter().filter(|&&c| c > 0).count() < 2 {
+        return 0.0;
+    }
+
+    let entropy: f32 = counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f32 / total as f32;
+            -p * p.log2()
+        })
+        .sum();
+    entropy / (counts.len() as f32).log2()
+}
+
+fn guard_burst(velocity: f32, cohort: &CohortStats, liker_entropy: f32, unknown_share: f32) -> f32 {
+    let guard = &settings().engagement.brigading;
+    if cohort.size < guard.min_cohort_size {
+        return velocity;
+    }
+
+    let CohortStats { mean, std_dev, .. } = *cohort;
+    let z_score = (velocity - mean) / std_dev;
+
+    let suspicious =
+        liker_entropy < guard.min_liker_entropy || unknown_share > guard.max_unknown_liker_share;
+    if z_score < guard.z_score_threshold || !suspicious {
+        return velocity;
+    }
+
+    if z_score >= guard.zero_z_score {
+        0.0
+    } else {
+        velocity.min(mean + guard.z_score_threshold * std_dev)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,4 +665,47 @@ mod tests {
             + 10.0 * s.engagement.weights.like * 0.1;
         assert!((velocity - 22.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_weighted_likes() {
+        let multipliers = LikeMultipliers {
+            dev_author: 3.0,
+            subscriber: 2.0,
+            unknown: 1.0,
+        };
+        let drive_by = LikeSources {
+            total: 10,
+            ..Default::default()
+        };
+        assert_eq!(drive_by.weighted(&multipliers), 10.0);
+
+        let mixed = LikeSources {
+            total: 10,
+            dev_authors: 2,
+            subscribers: 3,
+        };
+        assert_eq!(mixed.weighted(&multipliers), 6.0 + 6.0 + 5.0);
+        assert_eq!(mixed.unknown_share(), 0.5);
+    }
+
+    #[test]
+    fn test_normalized_entropy() {
+        assert_eq!(normalized_entropy(&[]), 1.0);
+        assert_eq!(normalized_entropy(&[1]), 1.0);
+        assert_eq!(normalized_entropy(&[12]), 0.0);
+        assert!((normalized_entropy(&[3, 3, 3, 3]) - 1.0).abs() < 0.001);
+        assert!(normalized_entropy(&[40, 1, 1, 1]) < 0.5);
+    }
+
+    #[test]
+    fn test_guard_burst() {
+        let velocities: Vec<f32> = (0..20).map(|i| 1.0 + (i % 3) as f32).collect();
+        let cohort = CohortStats::new(&velocities);
+        let small_cohort = CohortStats::new(&velocities[..3]);
+
+        assert_eq!(guard_burst(50.0, &cohort, 1.0, 0.0), 50.0);
+        assert_eq!(guard_burst(2.5, &cohort, 0.1, 1.0), 2.5);
+        assert_eq!(guard_burst(50.0, &cohort, 0.1, 1.0), 0.0);
+        assert_eq!(guard_burst(50.0, &small_cohort, 0.1, 1.0), 50.0);
+    }
 }
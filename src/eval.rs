@@ -0,0 +1,222 @@
+use crate::scoring::{
+    apply_filters, calculate_priority, extract_content_signals, has_hashtags_for,
+    keyword_matches_for, weighted_keyword_score, Filter, FilterResult, MLHandle, MediaInfo,
+    PrioritySignals,
+};
+use crate::settings::settings;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Outcome reason for posts that pass the filters but match no gamedev keyword or hashtag.
+pub const NO_RELEVANCE: &str = "no-relevance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Label {
+    Accept,
+    Reject,
+}
+
+/// One line of a golden dataset. Only `text` and `label` are required.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabeledPost {
+    pub text: String,
+    pub label: Label,
+    #[serde(default)]
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub image_count: u8,
+    #[serde(default)]
+    pub has_video: bool,
+    #[serde(default)]
+    pub has_alt_text: bool,
+    #[serde(default)]
+    pub external_uri: Option<String>,
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// Tag facets; leave out to parse hashtags from the text.
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+impl LabeledPost {
+    pub fn media(&self) -> MediaInfo {
+        MediaInfo {
+            image_count: self.image_count,
+            has_video: self.has_video,
+            has_alt_text: self.has_alt_text,
+            external_uri: self.external_uri.clone(),
+            facet_links: self.links.clone(),
+            facet_tags: self.tags.clone(),
+            facet_mentions: Vec::new(),
+            alt_texts: Vec::new(),
+            external_text: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Accepted {
+        priority: f32,
+    },
+    /// Rejected by a `Filter`, named as it is in logs, or for no relevance.
+    Rejected(String),
+}
+
+impl Outcome {
+    pub fn reason(&self) -> &str {
+        match self {
+            Self::Accepted { .. } => "accepted",
+            Self::Rejected(reason) => reason,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accepted { priority } => write!(f, "accepted ({priority:.2})"),
+            Self::Rejected(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Runs a post through filtering, relevance, the models and priority scoring as the firehose
+/// does. Author checks, translation and near-duplicate detection depend on live state and are
+/// skipped.
+pub async fn evaluate(post: &LabeledPost, ml_handle: &MLHandle) -> Outcome {
+    let media = post.media();
+    let lang = post.lang.as_deref().or(Some("en"));
+
+    if let FilterResult::Reject(filter) =
+        apply_filters(&post.text, lang, None, &media, |_| false, |_| false)
+    {
+        return Outcome::Rejected(filter.to_string());
+    }
+
+    let keyword_matches = keyword_matches_for(&post.text, lang);
+    let (found_hashtags, _) = has_hashtags_for(&post.text, media.facet_tags.as_deref(), lang);
+    if keyword_matches.is_empty() && !found_hashtags {
+        return Outcome::Rejected(NO_RELEVANCE.to_string());
+    }
+
+    let quality = ml_handle.score(post.text.clone()).await;
+    let content = extract_content_signals(&post.text, &media);
+    let mut signals = PrioritySignals::new(&quality, &content);
+    signals.keyword_score = weighted_keyword_score(&keyword_matches);
+    let priority = calculate_priority(&signals).priority;
+
+    if priority < settings().scoring.rejection.min_priority {
+        return Outcome::Rejected(Filter::LowPriority.to_string());
+    }
+    Outcome::Accepted { priority }
+}
+
+/// Posts per label that ended with one outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LabelCounts {
+    pub labeled_accept: usize,
+    pub labeled_reject: usize,
+}
+
+/// Accuracy over a dataset, with acceptance as the positive class.
+#[derive(Debug, Clone, Default)]
+pub struct EvalReport {
+    pub true_accepts: usize,
+    pub false_accepts: usize,
+    pub true_rejects: usize,
+    pub false_rejects: usize,
+    /// Outcomes ("accepted", a filter name or `no-relevance`) by label.
+    pub by_reason: BTreeMap<String, LabelCounts>,
+}
+
+impl EvalReport {
+    pub fn record(&mut self, label: Label, outcome: &Outcome) {
+        let accepted = matches!(outcome, Outcome::Accepted { .. });
+        let counts = self
+            .by_reason
+            .entry(outcome.reason().to_string())
+            .or_default();
+        match (label, accepted) {
+            (Label::Accept, true) => self.true_accepts += 1,
+            (Label::Accept, false) => self.false_rejects += 1,
+            (Label::Reject, true) => self.false_accepts += 1,
+            (Label::Reject, false) => self.true_rejects += 1,
+        }
+        match label {
+            Label::Accept => counts.labeled_accept += 1,
+            Label::Reject => counts.labeled_reject += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.true_accepts + self.false_accepts + self.true_rejects + self.false_rejects
+    }
+
+    pub fn precision(&self) -> f32 {
+        ratio(self.true_accepts, self.true_accepts + self.false_accepts)
+    }
+
+    pub fn recall(&self) -> f32 {
+        ratio(self.true_accepts, self.true_accepts + self.false_rejects)
+    }
+
+    pub fn f1(&self) -> f32 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            return 0.0;
+        }
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 {
+        return 0.0;
+    }
+    numerator as f32 / denominator as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_metrics() {
+        let mut report = EvalReport::default();
+        let accepted = Outcome::Accepted { priority: 1.0 };
+        let promo = Outcome::Rejected("promo-link".to_string());
+
+        for _ in 0..3 {
+            report.record(Label::Accept, &accepted);
+        }
+        report.record(Label::Reject, &accepted);
+        report.record(Label::Accept, &promo);
+        report.record(Label::Reject, &promo);
+        report.record(Label::Reject, &promo);
+
+        assert_eq!(report.total(), 7);
+        assert_eq!(report.precision(), 0.75);
+        assert_eq!(report.recall(), 0.75);
+        assert_eq!(report.f1(), 0.75);
+        assert_eq!(
+            report.by_reason["promo-link"],
+            LabelCounts {
+                labeled_accept: 1,
+                labeled_reject: 2
+            }
+        );
+        assert_eq!(EvalReport::default().f1(), 0.0);
+    }
+
+    #[test]
+    fn test_labeled_post_defaults() {
+        let post: LabeledPost =
+            serde_json::from_str(r#"{"text": "made a boss fight", "label": "accept"}"#).unwrap();
+        assert_eq!(post.label, Label::Accept);
+        assert!(post.media().facet_tags.is_none());
+        assert!(serde_json::from_str::<LabeledPost>(r#"{"text": "x", "label": "maybe"}"#).is_err());
+    }
+}
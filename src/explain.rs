@@ -0,0 +1,103 @@
+use crate::db::NewPostScore;
+use crate::scoring::{Filter, PriorityBreakdown, PrioritySignals, QualityAssessment, TermMatch};
+use serde::{Deserialize, Serialize};
+
+/// Why a scored post was accepted or rejected, kept in `post_scores` so operators can look it
+/// up after the fact. Posts rejected by a filter before scoring aren't recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreExplanation {
+    /// The filter that rejected the post after scoring, such as `low-priority`, or `None` when
+    /// it was accepted.
+    pub rejected: Option<String>,
+    pub quality: QualityAssessment,
+    pub signals: PrioritySignals,
+    pub breakdown: PriorityBreakdown,
+    /// The gamedev keywords and hashtags that made the post relevant, and where they matched.
+    #[serde(default)]
+    pub matches: Vec<TermMatch>,
+}
+
+impl ScoreExplanation {
+    pub fn new(
+        quality: &QualityAssessment,
+        signals: &PrioritySignals,
+        breakdown: &PriorityBreakdown,
+    ) -> Self {
+        let mut breakdown = breakdown.clone();
+        breakdown.boost_reasons = breakdown.boost_reasons.iter().map(|r| plain(r)).collect();
+        breakdown.penalty_reasons = breakdown.penalty_reasons.iter().map(|r| plain(r)).collect();
+        Self {
+            rejected: None,
+            quality: quality.clone(),
+            signals: signals.clone(),
+            breakdown,
+            matches: Vec::new(),
+        }
+    }
+
+    pub fn with_matches(mut self, matches: Vec<TermMatch>) -> Self {
+        self.matches = matches;
+        self
+    }
+
+    pub fn reject(&mut self, filter: Filter) {
+        self.rejected = Some(filter.to_string());
+    }
+
+    pub fn to_row(
+        &self,
+        uri: &str,
+        scored_at: i64,
+        config_id: Option<i32>,
+    ) -> Option<NewPostScore> {
+        Some(NewPostScore {
+            uri: uri.to_string(),
+            explanation: serde_json::to_string(self).ok()?,
+            scored_at,
+            config_id,
+        })
+    }
+}
+
+/// A log line's reason without its terminal styling and column padding.
+pub(crate) fn plain(reason: &str) -> String {
+    console::strip_ansi_codes(reason)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::calculate_priority;
+
+    #[test]
+    fn test_explanation_round_trips_without_styling() {
+        let signals = PrioritySignals {
+            is_first_person: true,
+            ..Default::default()
+        };
+        let breakdown = calculate_priority(&signals);
+        let mut explanation =
+            ScoreExplanation::new(&QualityAssessment::default(), &signals, &breakdown);
+        explanation.reject(Filter::LowPriority);
+
+        let row = explanation
+            .to_row("at://did:plc:abc/app.bsky.feed.post/1", 100, Some(1))
+            .unwrap();
+        let stored: ScoreExplanation = serde_json::from_str(&row.explanation).unwrap();
+        assert_eq!(stored.rejected.as_deref(), Some("low-priority"));
+        assert_eq!(stored.breakdown.priority, breakdown.priority);
+        assert!(stored
+            .breakdown
+            .boost_reasons
+            .iter()
+            .any(|r| r.starts_with("first-person: +")));
+        assert!(!stored
+            .breakdown
+            .boost_reasons
+            .iter()
+            .any(|r| r.contains('\x1b')));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::gateway::requester_did;
+use crate::settings::settings;
+use crate::utils::logs;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use skyfeed::{Did, FeedHandler, FeedRequest, Interaction, InteractionEvent, Uri};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+const MAX_FEED_LIMIT: u8 = 100;
+
+/// Serves the feed generator endpoints from `handler`, separately from whatever ingests posts,
+/// so the feed stays up while the firehose or Jetstream reconnects.
+pub fn router<H: FeedHandler + Send + Sync + 'static>(handler: Arc<Mutex<H>>) -> Router {
+    Router::new()
+        .route("/.well-known/did.json", get(did_document))
+        .route(
+            "/xrpc/app.bsky.feed.describeFeedGenerator",
+            get(describe_feed_generator::<H>),
+        )
+        .route(
+            "/xrpc/app.bsky.feed.getFeedSkeleton",
+            get(get_feed_skeleton::<H>),
+        )
+        .route(
+            "/xrpc/app.bsky.feed.sendInteractions",
+            post(send_interactions::<H>),
+        )
+        .with_state(handler)
+}
+
+pub async fn serve<H: FeedHandler + Send + Sync + 'static>(
+    handler: Arc<Mutex<H>>,
+    addr: SocketAddr,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    logs::log_feed_server_listening(&addr);
+    axum::serve(listener, router(handler)).await
+}
+
+fn service_did() -> String {
+    format!("did:web:{}", settings().server.feed_hostname)
+}
+
+async fn did_document() -> Json<serde_json::Value> {
+    let hostname = settings().server.feed_hostname.clone();
+    Json(json!({
+        "@context": ["https://www.w3.org/ns/did/v1"],
+        "id": service_did(),
+        "service": [{
+            "id": "#bsky_fg",
+            "type": "BskyFeedGenerator",
+            "serviceEndpoint": format!("https://{hostname}"),
+        }],
+    }))
+}
+
+async fn describe_feed_generator<H: FeedHandler + Send + Sync + 'static>(
+    State(handler): State<Arc<Mutex<H>>>,
+) -> Json<serde_json::Value> {
+    let publisher_did = settings().server.publisher_did.clone();
+    let feeds: Vec<_> = handler
+        .lock()
+        .await
+        .available_feeds()
+        .await
+        .into_iter()
+        .map(
+            |name| json!({ "uri": format!("at://{publisher_did}/app.bsky.feed.generator/{name}") }),
+        )
+        .collect();
+    Json(json!({ "did": service_did(), "feeds": feeds }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SkeletonParams {
+    feed: String,
+    cursor: Option<String>,
+    limit: Option<String>,
+}
+
+fn parse_limit(limit: Option<&str>) -> Result<Option<u8>, StatusCode> {
+    match limit {
+        None => Ok(None),
+        Some(value) => match value.parse::<u8>() {
+            Ok(limit) if (1..=MAX_FEED_LIMIT).contains(&limit) => Ok(Some(limit)),
+            _ => Err(StatusCode::BAD_REQUEST),
+        },
+    }
+}
+
+fn user_did(headers: &HeaderMap) -> Option<Did> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(requester_did)
+        .map(Did)
+}
+
+async fn get_feed_skeleton<H: FeedHandler + Send + Sync + 'static>(
+    State(handler): State<Arc<Mutex<H>>>,
+    headers: HeaderMap,
+    Query(params): Query<SkeletonParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let request = FeedRequest {
+        cursor: params.cursor,
+        feed: params.feed,
+        limit: parse_limit(params.limit.as_deref())?,
+        user_did: user_did(&headers),
+    };
+    let result = handler.lock().await.serve_feed(request).await;
+    let feed: Vec<_> = result
+        .feed
+        .into_iter()
+        .map(|uri| json!({ "post": uri.0 }))
+        .collect();
+    Ok(Json(json!({ "cursor": result.cursor, "feed": feed })))
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionsBody {
+    interactions: Vec<InteractionItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InteractionItem {
+    item: Option<String>,
+    event: Option<String>,
+    feed_context: Option<String>,
+}
+
+fn parse_event(event: &str) -> Option<InteractionEvent> {
+    let event = match event.strip_prefix("app.bsky.feed.defs#")? {
+        "requestLess" => InteractionEvent::RequestLess,
+        "requestMore" => InteractionEvent::RequestMore,
+        "clickthroughItem" => InteractionEvent::ClickthroughItem,
+        "clickthroughAuthor" => InteractionEvent::ClickthroughAuthor,
+        "clickthroughReposter" => InteractionEvent::ClickthroughReposter,
+        "clickthroughEmbed" => InteractionEvent::ClickthroughEmbed,
+        "interactionSeen" => InteractionEvent::InteractionSeen,
+        "interactionLike" => InteractionEvent::InteractionLike,
+        "interactionRepost" => InteractionEvent::InteractionRepost,
+        "interactionReply" => InteractionEvent::InteractionReply,
+        "interactionQuote" => InteractionEvent::InteractionQuote,
+        "interactionShare" => InteractionEvent::InteractionShare,
+        _ => return None,
+    };
+    Some(event)
+}
+
+async fn send_interactions<H: FeedHandler + Send + Sync + 'static>(
+    State(handler): State<Arc<Mutex<H>>>,
+    headers: HeaderMap,
+    Json(body): Json<InteractionsBody>,
+) -> Response {
+    let Some(user_did) = user_did(&headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    // Unknown events are skipped rather than failing the batch, as newer clients may send them.
+    let interactions: Vec<_> = body
+        .interactions
+        .into_iter()
+        .filter_map(|item| {
+            Some(Interaction {
+                item: Uri(item.item?),
+                event: parse_event(item.event.as_deref()?)?,
+                feed_context: item.feed_context,
+            })
+        })
+        .collect();
+    handler
+        .lock()
+        .await
+        .handle_interactions(user_did, interactions)
+        .await;
+    Json(json!({})).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit(None), Ok(None));
+        assert_eq!(parse_limit(Some("30")), Ok(Some(30)));
+        assert_eq!(parse_limit(Some("0")), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(parse_limit(Some("101")), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(parse_limit(Some("lots")), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_parse_event() {
+        assert!(matches!(
+            parse_event("app.bsky.feed.defs#requestLess"),
+            Some(InteractionEvent::RequestLess)
+        ));
+        assert!(matches!(
+            parse_event("app.bsky.feed.defs#interactionSeen"),
+            Some(InteractionEvent::InteractionSeen)
+        ));
+        assert!(parse_event("app.bsky.feed.defs#somethingNew").is_none());
+        assert!(parse_event("requestLess").is_none());
+    }
+}
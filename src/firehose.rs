@@ -0,0 +1,250 @@
+use crate::settings::settings;
+use crate::utils::{logs, systemd};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use strum::Display;
+
+const SILENCE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Backoff,
+}
+
+impl ConnectionState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Connected,
+            2 => Self::Backoff,
+            _ => Self::Connecting,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FirehoseStatus {
+    pub state: ConnectionState,
+    pub last_event_at: Option<i64>,
+    pub seconds_since_event: Option<i64>,
+    pub reconnects: u64,
+    pub consecutive_failures: u32,
+    pub replayed_events: u64,
+    /// Posts rejected for being little more than a link and hashtags.
+    pub link_only_posts: u64,
+    /// Posts skipped because processing them panicked.
+    pub panicked_posts: u64,
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    state: AtomicU8,
+    last_event_at: AtomicI64,
+    connected_at: AtomicI64,
+    reconnects: AtomicU64,
+    consecutive_failures: AtomicU32,
+    replayed_events: AtomicU64,
+    link_only_posts: AtomicU64,
+    panicked_posts: AtomicU64,
+}
+
+/// Connection state of the firehose, updated by the handler on every event and by the
+/// supervisor around each (re)connect.
+#[derive(Debug, Clone, Default)]
+pub struct FirehoseMonitor {
+    inner: Arc<MonitorState>,
+}
+
+impl FirehoseMonitor {
+    pub fn record_event(&self, now: i64) {
+        self.inner.last_event_at.store(now, Ordering::Relaxed);
+        if self.state() != ConnectionState::Connected {
+            self.set_state(ConnectionState::Connected);
+            self.inner.consecutive_failures.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_replay(&self) {
+        self.inner.replayed_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_link_only(&self) {
+        self.inner.link_only_posts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_panic(&self) {
+        self.inner.panicked_posts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        ConnectionState::from_u8(self.inner.state.load(Ordering::Relaxed))
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.inner.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    fn mark_connecting(&self, now: i64) {
+        self.set_state(ConnectionState::Connecting);
+        self.inner.connected_at.store(now, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) -> u32 {
+        self.set_state(ConnectionState::Backoff);
+        self.inner.reconnects.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed)
+            + 1
+    }
+
+    /// Seconds without events, counted from the last event or the current connect attempt.
+    fn silence(&self, now: i64) -> i64 {
+        let last_event_at = self.inner.last_event_at.load(Ordering::Relaxed);
+        let connected_at = self.inner.connected_at.load(Ordering::Relaxed);
+        now - last_event_at.max(connected_at)
+    }
+
+    pub fn status(&self) -> FirehoseStatus {
+        let last_event_at =
+            Some(self.inner.last_event_at.load(Ordering::Relaxed)).filter(|t| *t > 0);
+        FirehoseStatus {
+            state: self.state(),
+            last_event_at,
+            seconds_since_event: last_event_at.map(|t| Utc::now().timestamp() - t),
+            reconnects: self.inner.reconnects.load(Ordering::Relaxed),
+            consecutive_failures: self.inner.consecutive_failures.load(Ordering::Relaxed),
+            replayed_events: self.inner.replayed_events.load(Ordering::Relaxed),
+            link_only_posts: self.inner.link_only_posts.load(Ordering::Relaxed),
+            panicked_posts: self.inner.panicked_posts.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The most recent event URIs, oldest evicted first, used to skip events the relay delivers
+/// again after a reconnect before they reach filtering and the models.
+#[derive(Debug, Clone, Default)]
+pub struct RecentUris {
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl RecentUris {
+    /// Remembers `uri` and returns whether it was new. `capacity` comes from settings, so it
+    /// is passed on every call to follow reloads.
+    pub fn insert(&mut self, uri: &str, capacity: usize) -> bool {
+        if self.seen.contains(uri) {
+            return false;
+        }
+        self.order.push_back(uri.to_string());
+        self.seen.insert(uri.to_string());
+        while self.order.len() > capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+pub fn backoff_delay(failures: u32, base: Duration, max: Duration) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    base.saturating_mul(1 << exponent).min(max)
+}
+
+async fn wait_for_silence(monitor: &FirehoseMonitor, timeout: i64) {
+    loop {
+        tokio::time::sleep(SILENCE_CHECK_INTERVAL).await;
+        if monitor.silence(Utc::now().timestamp()) >= timeout {
+            return;
+        }
+    }
+}
+
+/// Keeps the firehose running: restarts `connect` whenever it returns or no events arrive
+/// for `firehose.silence_timeout_secs`, backing off exponentially between attempts.
+pub async fn supervise<F, Fut>(monitor: FirehoseMonitor, mut connect: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    loop {
+        let s = settings();
+        monitor.mark_connecting(Utc::now().timestamp());
+
+        let reason = tokio::select! {
+            _ = connect() => "disconnected",
+            _ = wait_for_silence(&monitor, s.firehose.silence_timeout_secs as i64) => "silent",
+        };
+
+        let failures = monitor.record_failure();
+        let delay = backoff_delay(
+            failures,
+            Duration::from_secs(s.firehose.backoff_base_secs),
+            Duration::from_secs(s.firehose.backoff_max_secs),
+        );
+        logs::log_firehose_reconnect(reason, failures, delay);
+        if failures >= s.firehose.alert_after_failures {
+            logs::log_firehose_alert(failures);
+            systemd::notify_status(&format!("firehose down: {failures} failed reconnects"));
+        }
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let base = Duration::from_secs(1);
+        let max = Duration::from_secs(60);
+
+        assert_eq!(backoff_delay(1, base, max), Duration::from_secs(1));
+        assert_eq!(backoff_delay(2, base, max), Duration::from_secs(2));
+        assert_eq!(backoff_delay(4, base, max), Duration::from_secs(8));
+        assert_eq!(backoff_delay(10, base, max), max);
+        assert_eq!(backoff_delay(u32::MAX, base, max), max);
+    }
+
+    #[test]
+    fn test_recent_uris_window() {
+        let mut recent = RecentUris::default();
+        assert!(recent.insert("at://a", 2));
+        assert!(!recent.insert("at://a", 2));
+        assert!(recent.insert("at://b", 2));
+        assert!(recent.insert("at://c", 2));
+        assert!(recent.insert("at://a", 2));
+        assert!(!recent.insert("at://c", 2));
+        assert!(recent.insert("at://d", 0));
+        assert!(recent.insert("at://d", 0));
+    }
+
+    #[test]
+    fn test_events_reset_failures() {
+        let monitor = FirehoseMonitor::default();
+        monitor.mark_connecting(100);
+        assert_eq!(monitor.silence(130), 30);
+
+        assert_eq!(monitor.record_failure(), 1);
+        assert_eq!(monitor.record_failure(), 2);
+        assert_eq!(monitor.state(), ConnectionState::Backoff);
+
+        monitor.record_event(140);
+        let status = monitor.status();
+        assert_eq!(status.state, ConnectionState::Connected);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(status.reconnects, 2);
+        assert_eq!(monitor.silence(150), 10);
+    }
+}
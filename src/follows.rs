@@ -0,0 +1,105 @@
+use crate::settings::settings;
+use crate::utils::bluesky::fetch_follows;
+use crate::utils::time::SharedClock;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+struct Follows {
+    fetched_at: i64,
+    dids: Arc<HashSet<String>>,
+}
+
+/// Who each reader follows, kept in memory. Lookups never wait on the network: a missing or
+/// stale list is refetched in the background and the previous one, if any, is used meanwhile.
+#[derive(Clone)]
+pub struct FollowCache {
+    client: reqwest::Client,
+    clock: SharedClock,
+    entries: Arc<Mutex<HashMap<String, Follows>>>,
+}
+
+impl FollowCache {
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            clock,
+            entries: Arc::default(),
+        }
+    }
+
+    pub fn get(&self, did: &str) -> Arc<HashSet<String>> {
+        let s = settings();
+        if !s.feed.follows.enabled {
+            return Arc::default();
+        }
+        let now = self.clock.timestamp();
+        let ttl = s.feed.follows.cache_ttl_mins * 60;
+
+        let Ok(mut entries) = self.entries.lock() else {
+            return Arc::default();
+        };
+        if let Some(entry) = entries.get(did).filter(|e| now - e.fetched_at < ttl) {
+            return entry.dids.clone();
+        }
+
+        let current = entries.get(did).map(|e| e.dids.clone()).unwrap_or_default();
+        // Readers that haven't been back for a while don't need their follows kept around.
+        entries.retain(|reader, e| reader == did || now - e.fetched_at < ttl * 2);
+        // Marked fresh before the fetch so concurrent requests don't start their own.
+        entries.insert(
+            did.to_string(),
+            Follows {
+                fetched_at: now,
+                dids: current.clone(),
+            },
+        );
+        drop(entries);
+
+        let cache = self.clone();
+        let did = did.to_string();
+        let max_follows = s.feed.follows.max_follows;
+        tokio::spawn(async move {
+            let Ok(follows) = fetch_follows(&cache.client, &did, max_follows).await else {
+                return;
+            };
+            cache.insert(&did, follows.into_iter().collect());
+        });
+
+        current
+    }
+
+    fn insert(&self, did: &str, follows: HashSet<String>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(
+                did.to_string(),
+                Follows {
+                    fetched_at: self.clock.timestamp(),
+                    dids: Arc::new(follows),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::time::ManualClock;
+
+    #[tokio::test]
+    async fn test_follows_served_until_stale() {
+        let clock = ManualClock::new(1_000_000);
+        let cache = FollowCache::new(Arc::new(clock.clone()));
+        let follows = HashSet::from(["did:plc:friend".to_string()]);
+        cache.insert("did:plc:reader", follows.clone());
+
+        assert_eq!(*cache.get("did:plc:reader"), follows);
+
+        // Once stale, the old list is served while the refetch runs, and other readers' lists
+        // that nobody asked for in two TTLs are dropped.
+        cache.insert("did:plc:gone", HashSet::new());
+        clock.advance(settings().feed.follows.cache_ttl_mins * 60 * 2);
+        assert_eq!(*cache.get("did:plc:reader"), follows);
+        assert!(!cache.entries.lock().unwrap().contains_key("did:plc:gone"));
+    }
+}
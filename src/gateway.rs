@@ -0,0 +1,794 @@
+use crate::db::{self, DbPool};
+use crate::scoring::{feed_context, ConfidenceTier};
+use crate::settings::{settings, Tls};
+use crate::utils::logs;
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::connect_info::Connected;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::serve::{IncomingStream, Listener};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const FEED_SKELETON_PATH: &str = "/xrpc/app.bsky.feed.getFeedSkeleton";
+const PROXY_HEADER_MAX_LEN: usize = 107;
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections whose PROXY header has been read, waiting to be served.
+const PROXY_READY_BACKLOG: usize = 128;
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+static X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+static X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeerAddr(pub SocketAddr);
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, ProxyProtocolListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, ProxyProtocolListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+impl Connected<SocketAddr> for PeerAddr {
+    fn connect_info(remote_addr: SocketAddr) -> Self {
+        Self(remote_addr)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SkeletonKey {
+    feed: Option<String>,
+    cursor: Option<String>,
+    limit: Option<String>,
+}
+
+impl SkeletonKey {
+    fn from_query(query: Option<&str>) -> Self {
+        let query = query.unwrap_or_default();
+        Self {
+            feed: query_param(query, "feed"),
+            cursor: query_param(query, "cursor"),
+            limit: query_param(query, "limit"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedSkeleton {
+    stored_at: Instant,
+    etag: String,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+impl CachedSkeleton {
+    fn new(content_type: Option<HeaderValue>, body: Bytes) -> Self {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        Self {
+            stored_at: Instant::now(),
+            etag: format!("\"{:016x}\"", hasher.finish()),
+            content_type,
+            body,
+        }
+    }
+
+    fn respond(&self, if_none_match: Option<&str>, ttl: Duration) -> Response {
+        let remaining = ttl.saturating_sub(self.stored_at.elapsed()).as_secs();
+        let mut response = if if_none_match.is_some_and(|tags| etag_matches(tags, &self.etag)) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let mut response = Response::new(Body::from(self.body.clone()));
+            if let Some(content_type) = &self.content_type {
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_TYPE, content_type.clone());
+            }
+            response
+        };
+
+        let headers = response.headers_mut();
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Ok(cache_control) = HeaderValue::from_str(&format!("public, max-age={remaining}")) {
+            headers.insert(header::CACHE_CONTROL, cache_control);
+        }
+        response
+    }
+}
+
+/// Anonymous skeleton responses are identical for everyone, so they are shared for a short TTL.
+#[derive(Clone, Default)]
+struct SkeletonCache {
+    entries: Arc<Mutex<HashMap<SkeletonKey, CachedSkeleton>>>,
+}
+
+impl SkeletonCache {
+    fn get(&self, key: &SkeletonKey, ttl: Duration) -> Option<CachedSkeleton> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|entry| entry.stored_at.elapsed() < ttl)
+            .cloned()
+    }
+
+    fn insert(&self, key: SkeletonKey, entry: CachedSkeleton, ttl: Duration, max_entries: usize) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if max_entries == 0 {
+            return;
+        }
+        if entries.len() >= max_entries {
+            entries.retain(|_, cached| cached.stored_at.elapsed() < ttl);
+        }
+        // Still full of fresh pages: the oldest makes way, so new cursors can be cached.
+        if entries.len() >= max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, entry);
+    }
+}
+
+#[derive(Clone)]
+pub struct GatewayState {
+    upstream: String,
+    client: reqwest::Client,
+    skeletons: SkeletonCache,
+    /// Each post's feed context and when it was looked up, kept for `feed_cache_ttl_secs` so
+    /// personalised pages, which aren't cached, don't query for every post they serve.
+    contexts: Arc<Mutex<HashMap<String, (Instant, String)>>>,
+    pool: DbPool,
+}
+
+impl GatewayState {
+    pub fn new(upstream: SocketAddr, pool: DbPool) -> Self {
+        Self {
+            upstream: format!("http://{upstream}"),
+            client: reqwest::Client::new(),
+            skeletons: SkeletonCache::default(),
+            contexts: Arc::default(),
+            pool,
+        }
+    }
+
+    /// Looks up the "TIER · topic" context for each post in a skeleton page.
+    fn feed_contexts(&self, uris: &[String]) -> HashMap<String, String> {
+        let ttl = Duration::from_secs(settings().server.feed_cache_ttl_secs);
+        let mut contexts = HashMap::new();
+        let mut missing = Vec::new();
+        if let Ok(cached) = self.contexts.lock() {
+            for uri in uris {
+                match cached.get(uri) {
+                    Some((stored_at, context)) if stored_at.elapsed() < ttl => {
+                        contexts.insert(uri.clone(), context.clone());
+                    }
+                    _ => missing.push(uri.clone()),
+                }
+            }
+        }
+        if missing.is_empty() {
+            return contexts;
+        }
+
+        let Ok(mut conn) = self.pool.get() else {
+            return contexts;
+        };
+        let looked_up: Vec<(String, String)> = db::get_posts_by_uris(&mut conn, &missing)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|post| {
+                let tier = ConfidenceTier::from_priority(post.priority);
+                let topic = post.topic();
+                (post.uri, feed_context(tier, topic))
+            })
+            .collect();
+        if let Ok(mut cached) = self.contexts.lock() {
+            let now = Instant::now();
+            cached.retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+            if !ttl.is_zero() {
+                for (uri, context) in &looked_up {
+                    cached.insert(uri.clone(), (now, context.clone()));
+                }
+            }
+        }
+        contexts.extend(looked_up);
+        contexts
+    }
+
+    /// Adds each item's `feedContext` to a skeleton page. skyfeed has no notion of it.
+    fn with_feed_context(&self, body: Bytes) -> Bytes {
+        let Ok(mut skeleton) = serde_json::from_slice::<serde_json::Value>(&body) else {
+            return body;
+        };
+        let uris = skeleton_uris(&skeleton);
+        let contexts = self.feed_contexts(&uris);
+        annotate_skeleton(&mut skeleton, &contexts);
+        serde_json::to_vec(&skeleton).map_or(body, Bytes::from)
+    }
+}
+
+pub fn router(state: GatewayState) -> Router {
+    Router::new()
+        .route(FEED_SKELETON_PATH, get(feed_skeleton))
+        .fallback(forward)
+        .layer(middleware::from_fn(access_log))
+        .with_state(state)
+}
+
+pub async fn serve(state: GatewayState, addr: SocketAddr) -> io::Result<()> {
+    let s = settings();
+    let app = router(state).into_make_service_with_connect_info::<PeerAddr>();
+
+    match (&s.server.tls, s.server.proxy_protocol) {
+        (Some(_), true) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "proxy_protocol cannot be combined with built-in tls",
+        )),
+        (
+            Some(Tls {
+                cert_path,
+                key_path,
+            }),
+            false,
+        ) => {
+            let config = RustlsConfig::from_pem_file(cert_path, key_path).await?;
+            logs::log_gateway_listening(&addr, true);
+            axum_server::bind_rustls(addr, config).serve(app).await
+        }
+        (None, true) => {
+            let listener = ProxyProtocolListener::new(TcpListener::bind(addr).await?)?;
+            logs::log_gateway_listening(&addr, false);
+            axum::serve(listener, app).await
+        }
+        (None, false) => {
+            let listener = TcpListener::bind(addr).await?;
+            logs::log_gateway_listening(&addr, false);
+            axum::serve(listener, app).await
+        }
+    }
+}
+
+async fn forward(
+    State(state): State<GatewayState>,
+    ConnectInfo(PeerAddr(peer)): ConnectInfo<PeerAddr>,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    proxy(&state, peer, request).await
+}
+
+async fn feed_skeleton(
+    State(state): State<GatewayState>,
+    ConnectInfo(PeerAddr(peer)): ConnectInfo<PeerAddr>,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    let s = settings();
+    let ttl = Duration::from_secs(s.server.feed_cache_ttl_secs);
+    let is_anonymous = !request.headers().contains_key(header::AUTHORIZATION);
+
+    if !is_anonymous || ttl.is_zero() {
+        let response = proxy(&state, peer, request).await?;
+        let mut response = with_feed_context(&state, response).await?;
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("private, no-store"),
+        );
+        return Ok(response);
+    }
+
+    let key = SkeletonKey::from_query(request.uri().query());
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    if let Some(cached) = state.skeletons.get(&key, ttl) {
+        return Ok(cached.respond(if_none_match.as_deref(), ttl));
+    }
+
+    let response = proxy(&state, peer, request).await?;
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    // Cached with its feed context, so hits are served as they are.
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+    let body = state.with_feed_context(body);
+    let cached = CachedSkeleton::new(parts.headers.get(header::CONTENT_TYPE).cloned(), body);
+    let response = cached.respond(if_none_match.as_deref(), ttl);
+    state
+        .skeletons
+        .insert(key, cached, ttl, s.server.feed_cache_max_entries);
+
+    Ok(response)
+}
+
+async fn with_feed_context(
+    state: &GatewayState,
+    response: Response,
+) -> Result<Response, StatusCode> {
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+    let body = state.with_feed_context(body);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+fn skeleton_uris(skeleton: &serde_json::Value) -> Vec<String> {
+    skeleton["feed"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item["post"].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+fn annotate_skeleton(skeleton: &mut serde_json::Value, contexts: &HashMap<String, String>) {
+    let Some(items) = skeleton["feed"].as_array_mut() else {
+        return;
+    };
+    for item in items {
+        let context = item["post"].as_str().and_then(|uri| contexts.get(uri));
+        if let (Some(context), Some(fields)) = (context.cloned(), item.as_object_mut()) {
+            fields.insert(
+                "feedContext".to_string(),
+                serde_json::Value::String(context),
+            );
+        }
+    }
+}
+
+async fn proxy(
+    state: &GatewayState,
+    peer: SocketAddr,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    let trusted = trusted_proxies();
+    let client_ip = client_ip(peer.ip(), request.headers(), &trusted);
+
+    let (parts, body) = request.into_parts();
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+
+    let mut headers = strip_hop_by_hop(&parts.headers);
+    headers.remove(&X_FORWARDED_FOR);
+    if let Ok(value) = HeaderValue::from_str(&client_ip.to_string()) {
+        headers.insert(X_FORWARDED_FOR.clone(), value.clone());
+        headers.insert(X_REAL_IP.clone(), value);
+    }
+
+    let upstream = state
+        .client
+        .request(parts.method, format!("{}{}", state.upstream, path))
+        .headers(headers)
+        .body(body)
+        .send()
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+    let status = upstream.status();
+    let headers = strip_hop_by_hop(upstream.headers());
+    let bytes = upstream
+        .bytes()
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+    let mut response = Response::new(Body::from(bytes));
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Ok(response)
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccessEntry {
+    pub method: String,
+    pub path: String,
+    pub feed: Option<String>,
+    pub requester_did: Option<String>,
+    pub client_ip: String,
+    pub status: u16,
+    pub latency_ms: u64,
+}
+
+async fn access_log(
+    ConnectInfo(PeerAddr(peer)): ConnectInfo<PeerAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let s = settings();
+    if !s.server.access_log.enabled {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let feed = request
+        .uri()
+        .query()
+        .and_then(|query| query_param(query, "feed"));
+    let requester_did = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(requester_did);
+    let client_ip = client_ip(peer.ip(), request.headers(), &trusted_proxies());
+
+    let response = next.run(request).await;
+
+    let latency = started.elapsed();
+    let status = response.status();
+    let is_notable = !status.is_success()
+        || latency >= Duration::from_millis(s.server.access_log.slow_request_ms);
+    if is_notable || rand::rng().random::<f32>() < s.server.access_log.sample_rate {
+        logs::log_access(&AccessEntry {
+            method,
+            path,
+            feed,
+            requester_did,
+            client_ip: client_ip.to_string(),
+            status: status.as_u16(),
+            latency_ms: latency.as_millis() as u64,
+        });
+    }
+
+    response
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+}
+
+/// Reads the `iss` claim from the service-auth JWT without verifying it, so it only picks
+/// whose feed to personalise and is never trusted for anything privileged.
+pub(crate) fn requester_did(authorization: &str) -> Option<String> {
+    let token = authorization.strip_prefix("Bearer ")?;
+    let payload = token.split('.').nth(1)?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    claims
+        .get("iss")?
+        .as_str()
+        .map(|iss| iss.split('#').next().unwrap_or(iss).to_string())
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+fn strip_hop_by_hop(headers: &HeaderMap) -> HeaderMap {
+    headers
+        .iter()
+        .filter(|(name, _)| !HOP_BY_HOP_HEADERS.contains(&name.as_str()))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+fn trusted_proxies() -> Vec<IpAddr> {
+    settings()
+        .server
+        .trusted_proxies
+        .iter()
+        .filter_map(|ip| ip.parse().ok())
+        .collect()
+}
+
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted: &[IpAddr]) -> IpAddr {
+    if !trusted.contains(&peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all(&X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+
+    forwarded
+        .into_iter()
+        .rev()
+        .find(|ip| !trusted.contains(ip))
+        .unwrap_or(peer)
+}
+
+/// Accepts connections prefixed with a PROXY protocol v1 header and reports the
+/// original client address instead of the load balancer's. Each header is read in its own task,
+/// so a client that is slow to send one doesn't hold up the connections behind it.
+pub struct ProxyProtocolListener {
+    local_addr: SocketAddr,
+    ready: mpsc::Receiver<(TcpStream, SocketAddr)>,
+}
+
+impl ProxyProtocolListener {
+    pub fn new(inner: TcpListener) -> io::Result<Self> {
+        let local_addr = inner.local_addr()?;
+        let (sender, ready) = mpsc::channel(PROXY_READY_BACKLOG);
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                let (mut stream, peer) = match inner.accept().await {
+                    Ok(accepted) => accepted,
+                    // Usually out of file descriptors; give connections time to close.
+                    Err(_) => {
+                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                        continue;
+                    }
+                };
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let header =
+                        tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_proxy_header(&mut stream));
+                    if let Ok(Ok(source)) = header.await {
+                        let _ = sender.send((stream, source.unwrap_or(peer))).await;
+                    }
+                });
+            }
+        });
+        Ok(Self { local_addr, ready })
+    }
+}
+
+impl Listener for ProxyProtocolListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.ready.recv().await {
+            Some(connection) => connection,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn read_proxy_header(stream: &mut TcpStream) -> io::Result<Option<SocketAddr>> {
+    let mut prefix = [0u8; 6];
+    let peeked = stream.peek(&mut prefix).await?;
+    if &prefix[..peeked] != b"PROXY " {
+        return Ok(None);
+    }
+
+    let mut line = Vec::with_capacity(PROXY_HEADER_MAX_LEN);
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= PROXY_HEADER_MAX_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy header too long",
+            ));
+        }
+        line.push(stream.read_u8().await?);
+    }
+
+    Ok(parse_proxy_header(&String::from_utf8_lossy(&line)))
+}
+
+pub fn parse_proxy_header(line: &str) -> Option<SocketAddr> {
+    let mut parts = line.trim_end().split(' ');
+    if parts.next()? != "PROXY" {
+        return None;
+    }
+    match parts.next()? {
+        "TCP4" | "TCP6" => {}
+        _ => return None,
+    }
+    let source_ip: IpAddr = parts.next()?.parse().ok()?;
+    let _destination_ip = parts.next()?;
+    let source_port: u16 = parts.next()?.parse().ok()?;
+    Some(SocketAddr::new(source_ip, source_port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_ignores_untrusted_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        headers.insert(&X_FORWARDED_FOR, HeaderValue::from_static("1.2.3.4"));
+        assert_eq!(
+            client_ip(ip("9.9.9.9"), &headers, &[ip("10.0.0.1")]),
+            ip("9.9.9.9")
+        );
+    }
+
+    #[test]
+    fn test_client_ip_skips_trusted_hops() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            &X_FORWARDED_FOR,
+            HeaderValue::from_static("6.6.6.6, 1.2.3.4, 10.0.0.2"),
+        );
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        assert_eq!(client_ip(ip("10.0.0.1"), &headers, &trusted), ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn test_requester_did_from_jwt() {
+        let payload =
+            URL_SAFE_NO_PAD.encode(r#"{"iss":"did:plc:abc123#atproto_labeler","aud":"x"}"#);
+        let header = format!("Bearer eyJhbGciOiJFUzI1NksifQ.{payload}.sig");
+        assert_eq!(requester_did(&header), Some("did:plc:abc123".to_string()));
+        assert_eq!(requester_did("Bearer garbage"), None);
+        assert_eq!(requester_did("Basic abc"), None);
+    }
+
+    #[test]
+    fn test_query_param() {
+        let query = "feed=at%3A%2F%2Fdid%3Aweb%3Aexample.com%2Ffeed&limit=30";
+        assert_eq!(
+            query_param(query, "feed"),
+            Some("at://did:web:example.com/feed".to_string())
+        );
+        assert_eq!(query_param(query, "limit"), Some("30".to_string()));
+        assert_eq!(query_param(query, "cursor"), None);
+    }
+
+    #[test]
+    fn test_annotate_skeleton() {
+        let mut skeleton = serde_json::json!({
+            "cursor": "30",
+            "feed": [{ "post": "at://a/post/1" }, { "post": "at://a/post/2" }]
+        });
+        assert_eq!(
+            skeleton_uris(&skeleton),
+            vec!["at://a/post/1".to_string(), "at://a/post/2".to_string()]
+        );
+
+        let contexts = HashMap::from([(
+            "at://a/post/1".to_string(),
+            "STRONG · sharing work".to_string(),
+        )]);
+        annotate_skeleton(&mut skeleton, &contexts);
+
+        assert_eq!(skeleton["feed"][0]["feedContext"], "STRONG · sharing work");
+        assert!(skeleton["feed"][1].get("feedContext").is_none());
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"x\", \"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"abd\"", "\"abc\""));
+    }
+
+    #[test]
+    fn test_skeleton_cache_expires() {
+        let cache = SkeletonCache::default();
+        let key = SkeletonKey::from_query(Some("feed=a&limit=10"));
+        let entry = CachedSkeleton::new(None, Bytes::from_static(b"{}"));
+        cache.insert(key.clone(), entry, Duration::from_secs(60), 10);
+
+        assert!(cache.get(&key, Duration::from_secs(60)).is_some());
+        assert!(cache.get(&key, Duration::ZERO).is_none());
+        assert!(cache
+            .get(
+                &SkeletonKey::from_query(Some("feed=a&limit=20")),
+                Duration::from_secs(60)
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_skeleton_cache_evicts_when_full() {
+        let cache = SkeletonCache::default();
+        let ttl = Duration::from_secs(60);
+        let key = |limit: usize| SkeletonKey::from_query(Some(&format!("feed=a&limit={limit}")));
+        for limit in 0..3 {
+            let entry = CachedSkeleton {
+                stored_at: Instant::now() - Duration::from_secs(10 - limit as u64),
+                ..CachedSkeleton::new(None, Bytes::from_static(b"{}"))
+            };
+            cache.insert(key(limit), entry, ttl, 2);
+        }
+
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+        assert!(cache.get(&key(0), ttl).is_none());
+        assert!(cache.get(&key(2), ttl).is_some());
+
+        let entry = CachedSkeleton::new(None, Bytes::from_static(b"{}"));
+        cache.insert(key(3), entry, Duration::ZERO, 2);
+        assert_eq!(cache.entries.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cached_skeleton_not_modified() {
+        let entry = CachedSkeleton::new(None, Bytes::from_static(b"{\"feed\":[]}"));
+        let ttl = Duration::from_secs(30);
+
+        let fresh = entry.respond(None, ttl);
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert!(fresh.headers().contains_key(header::ETAG));
+
+        let revalidated = entry.respond(Some(&entry.etag), ttl);
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn test_parse_proxy_header() {
+        assert_eq!(
+            parse_proxy_header("PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n"),
+            Some("192.168.0.1:56324".parse().unwrap())
+        );
+        assert_eq!(parse_proxy_header("PROXY UNKNOWN\r\n"), None);
+        assert_eq!(parse_proxy_header("GET / HTTP/1.1\r\n"), None);
+    }
+}
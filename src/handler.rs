@@ -1,100 +1,253 @@
+use crate::buffers::{drop_lowest, drop_target, BufferMonitor, PendingInteractions};
+use crate::cleanup::{self, CleanupReport};
+use crate::config_history;
+use crate::cursor::FeedCursor;
 use crate::db::{
-    self, block_author, delete_posts_by_author, get_post_author, get_user_preferences,
-    get_user_seen_posts, insert_interactions, DbPool, NewBlockedAuthor, NewInteraction, NewLike,
-    NewPost, INTERACTION_REQUEST_LESS, INTERACTION_REQUEST_MORE, INTERACTION_SEEN,
+    self, block_author, delete_posts_by_author, get_post_author, insert_interactions, DbPool,
+    NewAuthorHistory, NewAuthorStat, NewBlockedAuthor, NewCoverageGap, NewInteraction,
+    NewLanguageStat, NewLike, NewOptedOutAuthor, NewPost, NewPostScore, NewShadowScore,
+    INTERACTION_CLICKTHROUGH, INTERACTION_REQUEST_LESS, INTERACTION_REQUEST_MORE, INTERACTION_SEEN,
+    INTERACTION_SHARE,
 };
+use crate::embeddings::EmbeddingStore;
 use crate::engagement::EngagementTracker;
+use crate::explain::ScoreExplanation;
+use crate::firehose::{FirehoseMonitor, RecentUris};
+use crate::follows::FollowCache;
+use crate::handles::HandleCache;
+use crate::jetstream::RepostHandler;
+use crate::negatives::NegativeSet;
+#[cfg(feature = "ocr")]
+use crate::ocr;
+use crate::ranking::{self, cap_feed, rank_key, rank_uncapped, RankContext, Reader};
 use crate::scoring::{
-    apply_filters, calculate_priority, extract_content_signals, has_hashtags, has_keywords,
-    FilterResult, MLHandle, MediaInfo, PrioritySignals,
+    alt_text_relevance, apply_filters, author_reputation, calculate_priority, check_post_age,
+    cosine_similarity, extract_content_signals, has_hashtags, has_hashtags_for, has_keywords,
+    has_keywords_for, hashtag_matches_for, keyword_matches_for, weighted_keyword_score,
+    AuthorRecord, Embedding, Filter, FilterResult, MLHandle, MediaInfo, PrioritySignals,
+    QualityAssessment, SharedVectorIndex, TermMatch,
 };
-use crate::settings::settings;
+use crate::settings::{settings, BufferOverflow};
+use crate::shadow::ShadowScorer;
+use crate::translation::Translator;
+use crate::utils::ids::{self, AtUri};
 use crate::utils::logs::{self, PostAssessment};
-use chrono::Utc;
+use crate::utils::time::SharedClock;
+use crate::webhooks::{AcceptedPostEvent, WebhookDispatcher};
+use futures_util::FutureExt;
 use rand::Rng;
 use skyfeed::{
-    Did, Embed, FeedHandler, FeedRequest, FeedResult, Interaction, InteractionEvent, Post, Uri,
+    Did, FeedHandler, FeedRequest, FeedResult, Interaction, InteractionEvent, Post, Uri,
 };
-use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct GameDevFeedHandler {
     pool: DbPool,
+    /// Serves the feed, so readers don't wait on ingestion's connections.
+    read_pool: DbPool,
     ml_handle: MLHandle,
+    vector_index: SharedVectorIndex,
     engagement: EngagementTracker,
+    embeddings: EmbeddingStore,
+    handles: HandleCache,
+    follows: FollowCache,
+    negatives: NegativeSet,
+    translator: Translator,
+    webhooks: WebhookDispatcher,
+    firehose: FirehoseMonitor,
+    buffers: BufferMonitor,
+    clock: SharedClock,
+    recent_uris: RecentUris,
     pending_posts: Vec<NewPost>,
+    /// URIs of the stored posts, so replies and reposts can be matched to the feed without a
+    /// query each. Pending posts are checked separately.
+    feed_uris: Arc<Mutex<HashSet<String>>>,
+    /// Webhook events for the pending posts, delivered once they're stored.
+    pending_webhooks: Vec<AcceptedPostEvent>,
+    /// Embeddings of the pending posts, added to the vector index once they're stored.
+    pending_embeddings: HashMap<String, Embedding>,
     pending_likes: Vec<NewLike>,
     pending_deletes: Vec<String>,
     pending_like_deletes: Vec<String>,
+    /// Posts rejected for their language since the last flush, as (rejected, relevant) per language.
+    pending_languages: HashMap<String, (i32, i32)>,
+    /// What authors' posts and readers' feedback added to their reputation since the last flush.
+    pending_author_stats: HashMap<String, AuthorRecord>,
+    /// Replies authors made to their own threads since the last flush, per root post.
+    pending_thread_replies: HashMap<String, i32>,
+    /// Wall-clock time of the last firehose event, seeded from the newest stored post so the
+    /// downtime before a restart is recorded as a coverage gap too.
+    last_event_at: i64,
+    received_event: bool,
+    pending_gaps: Vec<NewCoverageGap>,
+    /// Score explanations for posts scored since the last flush.
+    pending_scores: Vec<NewPostScore>,
+    shadow: ShadowScorer,
+    /// Shadow profile verdicts for posts scored since the last flush.
+    pending_shadow_scores: Vec<NewShadowScore>,
+    /// Feed interactions received since the last flush. Shared because they arrive through
+    /// `&self`.
+    pending_interactions: Arc<Mutex<PendingInteractions>>,
+    /// Posts translated or read by OCR off the handler lock, waiting to be assessed.
+    prepared_posts: Arc<Mutex<Vec<PreparedPost>>>,
+    /// Posts scored by the ML worker off the handler lock, waiting to be ranked.
+    scored_posts: Arc<Mutex<Vec<ScoredPost>>>,
+    /// Translation, OCR and scoring tasks still running.
+    preparing: Arc<AtomicUsize>,
+    alt_text_reference: Arc<Mutex<Option<ReferenceEmbedding>>>,
+}
+
+/// Counts a task towards `preparing` until it's dropped.
+struct Preparing(Arc<AtomicUsize>);
+
+impl Preparing {
+    fn start(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Self(count.clone())
+    }
+}
+
+impl Drop for Preparing {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The embedding of `scoring.alt_text.reference`, with the text it was computed for.
+struct ReferenceEmbedding {
+    text: String,
+    embedding: Vec<f32>,
+}
+
+/// A post whose translation or image text has come back, with the text to score it on.
+struct PreparedPost {
+    post: Post,
+    media_info: MediaInfo,
+    scoring_text: String,
+    translated_from: Option<String>,
+}
+
+/// A post that passed the filters and matched, with what ranking it needs from before scoring.
+struct ScoredPost {
+    uri: AtUri,
+    author: ids::Did,
+    text: String,
+    lang: Option<String>,
+    created_at: i64,
+    media_info: MediaInfo,
+    scoring_text: String,
+    /// Alt text or image text to score, when `scoring.alt_text` is enabled and there is some.
+    attached_text: Option<String>,
+    keyword_score: f32,
+    matches: Vec<TermMatch>,
+    assessment: PostAssessment,
+    quality: QualityAssessment,
 }
 
 impl GameDevFeedHandler {
-    pub fn new(pool: DbPool, ml_handle: MLHandle) -> Self {
-        let engagement = EngagementTracker::new(pool.clone());
+    pub fn new(
+        pool: DbPool,
+        ml_handle: MLHandle,
+        vector_index: SharedVectorIndex,
+        firehose: FirehoseMonitor,
+        buffers: BufferMonitor,
+        clock: SharedClock,
+    ) -> Self {
+        let engagement = EngagementTracker::with_clock(pool.clone(), clock.clone());
+        let embeddings = EmbeddingStore::new(pool.clone());
+        let handles = HandleCache::new(pool.clone(), ml_handle.clone());
+        let last_event_at = pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::latest_post_timestamp(&mut conn))
+            .unwrap_or(0);
+        // Stored posts seed the dedup window so a replay right after a restart is skipped too.
+        let mut recent_uris = RecentUris::default();
+        let stored_uris = pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::get_post_uris(&mut conn).ok())
+            .unwrap_or_default();
+        for uri in &stored_uris {
+            recent_uris.insert(uri, settings().firehose.dedup_window);
+        }
+        let feed_uris = Arc::new(Mutex::new(stored_uris.into_iter().collect()));
+        let negatives = NegativeSet::default();
+        if let Ok(mut conn) = pool.get() {
+            negatives.reload(&mut conn).ok();
+        }
         Self {
+            read_pool: pool.clone(),
             pool,
             ml_handle,
+            vector_index,
             engagement,
+            embeddings,
+            handles,
+            follows: FollowCache::new(clock.clone()),
+            negatives,
+            translator: Translator::new(),
+            webhooks: WebhookDispatcher::new(),
+            firehose,
+            buffers,
+            clock,
+            recent_uris,
             pending_posts: Vec::new(),
+            feed_uris,
+            pending_webhooks: Vec::new(),
+            pending_embeddings: HashMap::new(),
             pending_likes: Vec::new(),
             pending_deletes: Vec::new(),
             pending_like_deletes: Vec::new(),
+            pending_languages: HashMap::new(),
+            pending_author_stats: HashMap::new(),
+            pending_thread_replies: HashMap::new(),
+            last_event_at,
+            received_event: false,
+            pending_gaps: Vec::new(),
+            pending_scores: Vec::new(),
+            shadow: ShadowScorer::default(),
+            pending_shadow_scores: Vec::new(),
+            pending_interactions: Arc::default(),
+            prepared_posts: Arc::default(),
+            scored_posts: Arc::default(),
+            preparing: Arc::default(),
+            alt_text_reference: Arc::default(),
         }
     }
 
-    fn extract_media_info(post: &Post) -> MediaInfo {
-        match &post.embed {
-            Some(Embed::Images(images)) => MediaInfo {
-                image_count: images.len().min(255) as u8,
-                has_video: false,
-                has_alt_text: images.iter().any(|img| !img.alt_text.is_empty()),
-                external_uri: None,
-                facet_links: Vec::new(),
-            },
-            Some(Embed::Video(_)) => MediaInfo {
-                image_count: 0,
-                has_video: true,
-                has_alt_text: false,
-                external_uri: None,
-                facet_links: Vec::new(),
-            },
-            Some(Embed::External(external)) => MediaInfo {
-                image_count: 0,
-                has_video: false,
-                has_alt_text: false,
-                external_uri: Some(external.uri.clone()),
-                facet_links: Vec::new(),
-            },
-            Some(Embed::QuoteWithMedia(_, skyfeed::MediaEmbed::Images(images))) => MediaInfo {
-                image_count: images.len().min(255) as u8,
-                has_video: false,
-                has_alt_text: images.iter().any(|img| !img.alt_text.is_empty()),
-                external_uri: None,
-                facet_links: Vec::new(),
-            },
-            Some(Embed::QuoteWithMedia(_, skyfeed::MediaEmbed::Video(_))) => MediaInfo {
-                image_count: 0,
-                has_video: true,
-                has_alt_text: false,
-                external_uri: None,
-                facet_links: Vec::new(),
-            },
-            Some(Embed::QuoteWithMedia(_, skyfeed::MediaEmbed::External(external))) => MediaInfo {
-                image_count: 0,
-                has_video: false,
-                has_alt_text: false,
-                external_uri: Some(external.uri.clone()),
-                facet_links: Vec::new(),
-            },
-            _ => MediaInfo::default(),
-        }
+    /// Serves the feed from `read_pool` instead of the pool ingestion writes through.
+    pub fn with_read_pool(mut self, read_pool: DbPool) -> Self {
+        self.read_pool = read_pool;
+        self
     }
 
     fn is_spammer(&self, did: &str) -> bool {
         self.engagement.is_spammer(did)
     }
 
+    /// Whether a stored or pending post is nearly the same as `embedding`.
+    fn is_near_duplicate(&self, embedding: &[f32]) -> bool {
+        let s = settings();
+        let min_similarity = s.semantic.dedup_similarity;
+        self.pending_embeddings
+            .values()
+            .any(|pending| cosine_similarity(embedding, pending) >= min_similarity)
+            || self
+                .vector_index
+                .read()
+                .map(|index| {
+                    !index
+                        .neighbors_within(embedding, min_similarity, 1)
+                        .is_empty()
+                })
+                .unwrap_or(false)
+    }
+
     fn is_blocked_author(&self, did: &str) -> bool {
         let mut conn = match self.pool.get() {
             Ok(c) => c,
@@ -103,16 +256,77 @@ impl GameDevFeedHandler {
         db::is_blocked_author(&mut conn, did)
     }
 
-    pub fn flush_pending(&mut self) -> Result<(), diesel::result::Error> {
-        if self.pending_posts.is_empty()
-            && self.pending_likes.is_empty()
-            && self.pending_deletes.is_empty()
-            && self.pending_like_deletes.is_empty()
-        {
-            return Ok(());
+    fn has_opted_out(&self, did: &str) -> bool {
+        let Ok(mut conn) = self.pool.get() else {
+            return false;
+        };
+        db::is_opted_out_author(&mut conn, did)
+    }
+
+    /// Remembers an author who tagged a post to opt out, and removes their posts from the
+    /// database, the archive, the vector index and the buffer.
+    fn record_opt_out(&mut self, did: &str, post_uri: &str) {
+        let Ok(mut conn) = self.pool.get() else {
+            return;
+        };
+        let entry = NewOptedOutAuthor {
+            did: did.to_string(),
+            post_uri: post_uri.to_string(),
+            opted_out_at: self.clock.timestamp(),
+        };
+        let Ok(Some(deleted)) = db::opt_out_author(&mut conn, entry) else {
+            return;
+        };
+        self.forget_posts(&deleted);
+        let pending = self.pending_posts.len();
+        self.pending_posts.retain(|post| {
+            post.author_did
+                .as_ref()
+                .is_none_or(|author| author.as_str() != did)
+        });
+        let kept: HashSet<&str> = self
+            .pending_posts
+            .iter()
+            .map(|post| post.uri.as_str())
+            .collect();
+        self.pending_embeddings
+            .retain(|uri, _| kept.contains(uri.as_str()));
+        let removed = deleted.len() + pending - self.pending_posts.len();
+        logs::log_author_opted_out(did, self.handles.get(did).as_deref(), removed);
+    }
+
+    /// Writes the buffered events and returns how many posts, likes and deletions were written.
+    /// Without a free database connection the flush is skipped and everything stays buffered.
+    pub fn flush_pending(&mut self) -> Result<usize, diesel::result::Error> {
+        let has_interactions = self
+            .pending_interactions
+            .lock()
+            .is_ok_and(|pending| !pending.is_empty());
+        if !has_interactions && self.nothing_pending() {
+            return Ok(0);
+        }
+        let Some(mut conn) = db::try_connection(&self.pool) else {
+            self.buffers.record_skipped_flush();
+            logs::log_pool_exhausted("flush");
+            return Ok(0);
+        };
+
+        let interactions = match self.pending_interactions.lock() {
+            Ok(mut pending) if !pending.is_empty() => pending.drain(),
+            _ => Vec::new(),
+        };
+        let interaction_count = interactions.len();
+        if !interactions.is_empty() {
+            self.record_author_feedback(&mut conn, &interactions);
+            if insert_interactions(&mut conn, interactions).is_err() {
+                self.buffers.record_failed_interactions(interaction_count);
+            }
+            self.buffers.observe_interactions(0);
         }
 
-        let mut conn = self.pool.get().expect("Failed to get connection");
+        if self.nothing_pending() {
+            return Ok(interaction_count);
+        }
 
         let deletes: Vec<_> = self.pending_deletes.drain(..).collect();
         let like_deletes: Vec<_> = self.pending_like_deletes.drain(..).collect();
@@ -120,152 +334,1086 @@ impl GameDevFeedHandler {
         for uri in &deletes {
             db::delete_post(&mut conn, uri)?;
         }
+        self.forget_posts(&deletes);
         for uri in &like_deletes {
             db::delete_like(&mut conn, uri)?;
         }
 
-        let posts_to_insert: Vec<_> = self.pending_posts.drain(..).collect();
+        let mut posts_to_insert: Vec<_> = self
+            .pending_posts
+            .drain(..)
+            .filter(|post| !deletes.iter().any(|uri| post.uri.as_str() == uri))
+            .collect();
+        let mut embeddings = std::mem::take(&mut self.pending_embeddings);
+        // Authors blocked since their posts were accepted, e.g. from the admin API or
+        // `moderate`, which can't reach this buffer.
+        let authors: Vec<String> = posts_to_insert
+            .iter()
+            .filter_map(|post| post.author_did.as_ref().map(|did| did.to_string()))
+            .collect();
+        let blocked = db::get_blocked_among(&mut conn, &authors)?;
+        if !blocked.is_empty() {
+            posts_to_insert.retain(|post| {
+                post.author_did
+                    .as_ref()
+                    .is_none_or(|did| !blocked.contains(did.as_str()))
+            });
+        }
+        let webhook_events = std::mem::take(&mut self.pending_webhooks);
         let likes_to_insert: Vec<_> = self
             .pending_likes
             .drain(..)
-            .filter(|like| !deletes.contains(&like.post_uri))
+            .filter(|like| !deletes.iter().any(|uri| like.post_uri == *uri))
+            .collect();
+
+        let now = self.clock.timestamp();
+        let history: Vec<NewAuthorHistory> = posts_to_insert
+            .iter()
+            .filter_map(|post| {
+                post.author_did.as_ref().map(|did| NewAuthorHistory {
+                    post_uri: post.uri.to_string(),
+                    author_did: did.to_string(),
+                    accepted_at: now,
+                })
+            })
             .collect();
 
         let post_count = posts_to_insert.len();
         let like_count = likes_to_insert.len();
 
         if !posts_to_insert.is_empty() {
+            let inserted: HashSet<String> = posts_to_insert
+                .iter()
+                .map(|post| post.uri.to_string())
+                .collect();
             db::insert_posts(&mut conn, posts_to_insert)?;
+            if let Ok(mut feed_uris) = self.feed_uris.lock() {
+                feed_uris.extend(inserted.iter().cloned());
+            }
+            // Indexed only once stored, so related posts never point at a post that isn't.
+            embeddings.retain(|uri, _| inserted.contains(uri));
+            if let Ok(mut index) = self.vector_index.write() {
+                for (uri, embedding) in embeddings {
+                    index.insert(uri, embedding);
+                }
+            }
+            db::insert_author_history(&mut conn, history)?;
+            // Only posts still buffered, not dropped or opted out since, are announced.
+            for event in &webhook_events {
+                if inserted.contains(event.uri.as_str()) {
+                    self.webhooks.post_accepted(event);
+                }
+            }
         }
         if !likes_to_insert.is_empty() {
+            let affinity = &settings().feed.like_affinity;
+            let affinity_likes = affinity.enabled.then(|| likes_to_insert.clone());
             db::insert_likes(&mut conn, likes_to_insert)?;
+            // Affinities only tune ranking, so failing to record them doesn't fail the flush.
+            if let Some(likes) = affinity_likes {
+                if let Err(e) =
+                    db::record_like_affinities(&mut conn, &likes, now, affinity.half_life_hours)
+                {
+                    logs::log_like_affinity_failed(&e.to_string());
+                }
+            }
+        }
+
+        let thread_replies = std::mem::take(&mut self.pending_thread_replies);
+        if !thread_replies.is_empty() {
+            db::record_thread_replies(&mut conn, thread_replies)?;
+        }
+
+        let day = now - now.rem_euclid(86400);
+        let config_id = self.config_id().unwrap_or(0);
+        let language_stats: Vec<NewLanguageStat> = self
+            .pending_languages
+            .drain()
+            .map(|(lang, (rejected, relevant))| NewLanguageStat {
+                lang,
+                day,
+                config_id,
+                rejected,
+                relevant,
+            })
+            .collect();
+        if !language_stats.is_empty() {
+            db::record_language_stats(&mut conn, language_stats)?;
+        }
+
+        let author_stats: Vec<NewAuthorStat> = self
+            .pending_author_stats
+            .drain()
+            .map(|(did, record)| NewAuthorStat::new(did, day, config_id, &record))
+            .collect();
+        if !author_stats.is_empty() {
+            db::record_author_stats(&mut conn, author_stats)?;
+        }
+
+        let gaps: Vec<_> = self.pending_gaps.drain(..).collect();
+        if !gaps.is_empty() {
+            db::insert_coverage_gaps(&mut conn, gaps)?;
+        }
+
+        let scores: Vec<_> = self.pending_scores.drain(..).collect();
+        if !scores.is_empty() {
+            db::upsert_post_scores(&mut conn, scores)?;
+        }
+        let shadow_scores: Vec<_> = self.pending_shadow_scores.drain(..).collect();
+        if !shadow_scores.is_empty() {
+            db::upsert_shadow_scores(&mut conn, shadow_scores)?;
         }
 
         logs::log_flush(post_count, like_count);
+        self.buffers
+            .observe(self.pending_posts.len(), self.pending_likes.len());
+
+        Ok(post_count + like_count + deletes.len() + like_deletes.len() + interaction_count)
+    }
+
+    fn nothing_pending(&self) -> bool {
+        self.pending_posts.is_empty()
+            && self.pending_likes.is_empty()
+            && self.pending_deletes.is_empty()
+            && self.pending_like_deletes.is_empty()
+            && self.pending_languages.is_empty()
+            && self.pending_author_stats.is_empty()
+            && self.pending_thread_replies.is_empty()
+            && self.pending_gaps.is_empty()
+            && self.pending_scores.is_empty()
+            && self.pending_shadow_scores.is_empty()
+    }
+
+    /// Flushes right away once `flush.immediate_threshold` posts and likes are waiting, rather
+    /// than on the next tick of the flush loop.
+    fn flush_if_busy(&mut self) {
+        if self.pending_posts.len() + self.pending_likes.len()
+            >= settings().flush.immediate_threshold
+        {
+            if let Err(e) = self.flush_pending() {
+                logs::log_flush_failed(&e.to_string());
+            }
+        }
+    }
+
+    /// Applies `flush.posts_overflow` once the pending posts reach `flush.max_pending_posts`.
+    /// A forced flush that leaves them over the limit, as when no connection is free, falls
+    /// back to dropping the lowest.
+    fn enforce_post_limit(&mut self) -> Result<(), diesel::result::Error> {
+        let s = settings();
+        let max = s.flush.max_pending_posts;
+        if self.pending_posts.len() < max {
+            return Ok(());
+        }
+
+        let flushed = match s.flush.posts_overflow {
+            BufferOverflow::Flush => self.force_flush().map(|_| ()),
+            BufferOverflow::DropLowest => Ok(()),
+        };
+        if self.pending_posts.len() >= max {
+            self.drop_lowest_posts(max);
+        }
+        flushed
+    }
 
-        Ok(())
+    /// Drops the lowest-priority pending posts down to the drop target. Dropped posts are never
+    /// announced, as webhooks only fire once a post is stored.
+    fn drop_lowest_posts(&mut self, max: usize) {
+        let dropped = drop_lowest(&mut self.pending_posts, drop_target(max), |post| {
+            post.priority
+        });
+        let dropped_uris: HashSet<&str> = dropped.iter().map(|post| post.uri.as_str()).collect();
+        self.pending_webhooks
+            .retain(|event| !dropped_uris.contains(event.uri.as_str()));
+        self.pending_embeddings
+            .retain(|uri, _| !dropped_uris.contains(uri.as_str()));
+        self.buffers.record_dropped_posts(dropped.len());
+        logs::log_buffer_dropped("posts", dropped.len());
     }
 
-    pub fn cleanup_old_posts(&self) -> Result<usize, diesel::result::Error> {
+    /// Applies `flush.likes_overflow` once the pending likes reach `flush.max_pending_likes`,
+    /// falling back to dropping the lowest like `enforce_post_limit`.
+    fn enforce_like_limit(&mut self) -> Result<(), diesel::result::Error> {
         let s = settings();
-        let mut conn = self.pool.get().expect("Failed to get connection");
-        let now = Utc::now().timestamp();
-        let cutoff = now - (s.feed.cutoff_hours * 3600);
+        let max = s.flush.max_pending_likes;
+        if self.pending_likes.len() < max {
+            return Ok(());
+        }
 
-        let engagement_deleted = self.engagement.cleanup_old_engagement(cutoff).unwrap_or(0);
-        let posts_deleted = db::cleanup_old_posts(&mut conn, cutoff, s.feed.max_stored_posts)?;
+        let flushed = match s.flush.likes_overflow {
+            BufferOverflow::Flush => self.force_flush().map(|_| ()),
+            BufferOverflow::DropLowest => Ok(()),
+        };
+        if self.pending_likes.len() >= max {
+            self.drop_lowest_likes(max);
+        }
+        flushed
+    }
 
-        let total_deleted = engagement_deleted + posts_deleted;
-        logs::log_cleanup(total_deleted);
+    /// Drops pending likes down to the drop target, ranked by the priority of the liked post.
+    /// Likes on posts outside the feed, which the flush would discard anyway, go first. With no
+    /// connection free, only the priorities of pending posts are known.
+    fn drop_lowest_likes(&mut self, max: usize) {
+        let uris: Vec<&str> = self
+            .pending_likes
+            .iter()
+            .map(|like| like.post_uri.as_str())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mut priorities = db::try_connection(&self.pool)
+            .and_then(|mut conn| db::get_post_priorities(&mut conn, &uris).ok())
+            .unwrap_or_default();
+        for post in &self.pending_posts {
+            priorities.insert(post.uri.to_string(), post.priority);
+        }
 
-        Ok(total_deleted)
+        let dropped = drop_lowest(&mut self.pending_likes, drop_target(max), |like| {
+            priorities
+                .get(like.post_uri.as_str())
+                .copied()
+                .unwrap_or(f32::NEG_INFINITY)
+        });
+        self.buffers.record_dropped_likes(dropped.len());
+        logs::log_buffer_dropped("likes", dropped.len());
     }
 
-    #[allow(dead_code)]
-    pub fn engagement_tracker(&self) -> &EngagementTracker {
-        &self.engagement
+    fn force_flush(&mut self) -> Result<usize, diesel::result::Error> {
+        self.buffers.record_forced_flush();
+        self.flush_pending()
     }
-}
 
-impl FeedHandler for GameDevFeedHandler {
-    async fn available_feeds(&mut self) -> Vec<String> {
-        vec!["Game Dev Progress".to_string()]
+    /// Stores posts enough readers asked to see less of as negative examples, so new posts like
+    /// them are penalized.
+    pub fn harvest_negative_examples(&self) -> Result<usize, diesel::result::Error> {
+        let Some(mut conn) = db::try_connection(&self.pool) else {
+            logs::log_pool_exhausted("negative example harvest");
+            return Ok(0);
+        };
+        let added = self.negatives.harvest(&mut conn, self.clock.timestamp())?;
+        logs::log_negative_examples_harvested(added);
+        Ok(added)
     }
 
-    async fn insert_post(&mut self, post: Post) {
+    /// Deletes expired posts and their engagement, or returns `None` when no database connection
+    /// was free and the run was skipped.
+    pub fn cleanup_old_posts(&self) -> Result<Option<CleanupReport>, diesel::result::Error> {
+        let Some(mut conn) = db::try_connection(&self.pool) else {
+            logs::log_pool_exhausted("cleanup");
+            return Ok(None);
+        };
+        let report = cleanup::cleanup_expired(&mut conn, &self.engagement, self.clock.timestamp())?;
+
+        let live: HashSet<String> = db::get_post_uris(&mut conn)?.into_iter().collect();
+        if let Ok(mut index) = self.vector_index.write() {
+            index.retain(|uri| live.contains(uri));
+        }
+        if let Ok(mut feed_uris) = self.feed_uris.lock() {
+            *feed_uris = live;
+        }
+
+        logs::log_cleanup(report.total());
+
+        Ok(Some(report))
+    }
+
+    /// The `config_history` row for the settings in effect, recorded on first use after a reload.
+    fn config_id(&self) -> Option<i32> {
+        config_history::active_config_id(&self.pool, self.clock.timestamp())
+    }
+
+    fn record_score(&mut self, uri: &str, explanation: &ScoreExplanation) {
+        let config_id = self.config_id();
+        if let Some(row) = explanation.to_row(uri, self.clock.timestamp(), config_id) {
+            self.pending_scores.push(row);
+        }
+    }
+
+    fn author_stats(&mut self, author_did: &str) -> &mut AuthorRecord {
+        self.pending_author_stats
+            .entry(author_did.to_string())
+            .or_default()
+    }
+
+    /// Counts more/less requests towards the reputation of the authors of the posts.
+    fn record_author_feedback(
+        &mut self,
+        conn: &mut diesel::SqliteConnection,
+        interactions: &[NewInteraction],
+    ) {
+        for interaction in interactions {
+            let is_more = interaction.interaction_type == INTERACTION_REQUEST_MORE;
+            if !is_more && interaction.interaction_type != INTERACTION_REQUEST_LESS {
+                continue;
+            }
+            let Some(author) = get_post_author(conn, &interaction.post_uri) else {
+                continue;
+            };
+            let stats = self.author_stats(&author);
+            if is_more {
+                stats.request_more += 1;
+            } else {
+                stats.request_less += 1;
+            }
+        }
+    }
+
+    fn author_reputation(&self, author_did: &str) -> f32 {
+        let s = settings();
+        let now = self.clock.timestamp();
+        let since_day = now - now.rem_euclid(86400) - s.authors.reputation.window_days * 86400;
+        self.pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::get_author_record(&mut conn, author_did, since_day).ok())
+            .map(|record| author_reputation(&record))
+            .unwrap_or(0.0)
+    }
+
+    fn prior_accepted_posts(&self, author_did: &str) -> u32 {
+        let s = settings();
+        let since = self.clock.timestamp() - (s.authors.history_window_days * 86400);
+        self.pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::count_author_history(&mut conn, author_did, since).ok())
+            .unwrap_or(0) as u32
+    }
+
+    /// False for a post or like URI already seen within `firehose.dedup_window`, which the relay
+    /// redelivers after a reconnect.
+    fn is_new_event(&mut self, uri: &str) -> bool {
+        let is_new = self
+            .recent_uris
+            .insert(uri, settings().firehose.dedup_window);
+        if !is_new {
+            self.firehose.record_replay();
+        }
+        is_new
+    }
+
+    /// Records a coverage gap when the firehose has been silent for longer than
+    /// `backfill.gap_min_secs`, so the gap backfill can search that range.
+    fn note_event(&mut self) {
+        let now = self.clock.timestamp();
+        self.firehose.record_event(now);
+        let silence = now - self.last_event_at;
+        if self.last_event_at > 0 && silence >= settings().backfill.gap_min_secs {
+            let reason = if self.received_event {
+                "silence"
+            } else {
+                "restart"
+            };
+            logs::log_coverage_gap(self.last_event_at, now, reason);
+            self.pending_gaps.push(NewCoverageGap {
+                started_at: self.last_event_at,
+                ended_at: now,
+                reason: reason.to_string(),
+            });
+        }
+        self.last_event_at = now;
+        self.received_event = true;
+    }
+
+    /// Counts a reply towards its root's thread length when the author is continuing their own
+    /// thread, replying to the root or to one of their own replies.
+    fn record_thread_reply(&mut self, post: &Post) {
+        let Some(reply) = &post.reply else {
+            return;
+        };
+        let by_author = |uri: &Uri| {
+            uri.0
+                .parse::<AtUri>()
+                .ok()
+                .and_then(|uri| uri.did())
+                .is_some_and(|did| did == post.author_did.0)
+        };
+        let own_thread = by_author(&reply.root) && by_author(&reply.parent);
+        if own_thread {
+            *self
+                .pending_thread_replies
+                .entry(reply.root.0.clone())
+                .or_default() += 1;
+        }
+    }
+
+    /// What a post with no text of its own is scored on: its images' alt text.
+    fn describe_images(&self, post: &Post, media: &MediaInfo) -> Option<String> {
+        let s = settings();
+        if !s.scoring.alt_text.enabled || !post.text.trim().is_empty() {
+            return None;
+        }
+        (!media.alt_texts.is_empty()).then(|| media.alt_texts.join("\n"))
+    }
+
+    /// Reads the text in the images of a post with no text or alt text on a separate task,
+    /// and queues the post to be assessed on it. Returns false when OCR doesn't apply or is
+    /// already running on as many posts as it may.
+    #[cfg(feature = "ocr")]
+    fn read_images_later(&self, post: &Post, media_info: &MediaInfo) -> bool {
+        let s = settings();
+        if !s.scoring.alt_text.enabled
+            || !post.text.trim().is_empty()
+            || !media_info.alt_texts.is_empty()
+        {
+            return false;
+        }
+        let Some(permit) = ocr::try_reserve() else {
+            return false;
+        };
+        let max_images = s.scoring.alt_text.ocr_max_images;
+        let prepared_posts = self.prepared_posts.clone();
+        let preparing = Preparing::start(&self.preparing);
+        let (post, mut media_info) = (post.clone(), media_info.clone());
+        tokio::spawn(async move {
+            let _preparing = preparing;
+            media_info.alt_texts =
+                ocr::read_images(&post.author_did.0, post.embed.as_ref(), max_images).await;
+            drop(permit);
+            let scoring_text = if media_info.alt_texts.is_empty() {
+                post.text.clone()
+            } else {
+                media_info.alt_texts.join("\n")
+            };
+            if let Ok(mut prepared) = prepared_posts.lock() {
+                prepared.push(PreparedPost {
+                    post,
+                    media_info,
+                    scoring_text,
+                    translated_from: None,
+                });
+            }
+        });
+        true
+    }
+
+    async fn alt_text_relevance(&self, alt_text: &str, lang: Option<&str>) -> f32 {
+        let alt_embedding = self.ml_handle.embed(alt_text.to_string()).await;
+        let reference_embedding = self.alt_text_reference().await;
+        alt_text_relevance(
+            alt_text,
+            lang,
+            cosine_similarity(&alt_embedding, &reference_embedding),
+        )
+    }
+
+    /// Embeds `scoring.alt_text.reference` once, and again only when a reload changes it.
+    async fn alt_text_reference(&self) -> Vec<f32> {
+        let reference = settings().scoring.alt_text.reference.clone();
+        let cached = self.alt_text_reference.lock().ok().and_then(|cached| {
+            cached
+                .as_ref()
+                .filter(|cached| cached.text == reference)
+                .map(|cached| cached.embedding.clone())
+        });
+        if let Some(embedding) = cached {
+            return embedding;
+        }
+        let embedding = self.ml_handle.embed(reference.clone()).await;
+        if !embedding.is_empty() {
+            if let Ok(mut cached) = self.alt_text_reference.lock() {
+                *cached = Some(ReferenceEmbedding {
+                    text: reference,
+                    embedding: embedding.clone(),
+                });
+            }
+        }
+        embedding
+    }
+
+    /// Whether `uri` is a stored post or one waiting to be flushed.
+    fn is_in_feed(&self, uri: &str) -> bool {
+        self.feed_uris
+            .lock()
+            .is_ok_and(|feed_uris| feed_uris.contains(uri))
+            || self
+                .pending_posts
+                .iter()
+                .any(|post| post.uri.as_str() == uri)
+    }
+
+    /// Takes deleted posts out of the in-memory feed set and the vector index, so duplicate
+    /// checks and interest matching stop seeing them before the next cleanup.
+    fn forget_posts(&self, uris: &[String]) {
+        if let Ok(mut index) = self.vector_index.write() {
+            for uri in uris {
+                index.remove(uri);
+            }
+        }
+        if let Ok(mut feed_uris) = self.feed_uris.lock() {
+            for uri in uris {
+                feed_uris.remove(uri);
+            }
+        }
+    }
+
+    /// Counts a reply towards its parent's engagement when the parent is in the feed and the
+    /// reply is someone else's.
+    fn record_reply_engagement(&self, post: &Post) {
+        let Some(reply) = &post.reply else {
+            return;
+        };
+        let parent_author = reply
+            .parent
+            .0
+            .parse::<AtUri>()
+            .ok()
+            .and_then(|uri| uri.did());
+        if parent_author.is_none_or(|did| did == post.author_did.0) {
+            return;
+        }
+        if self.is_in_feed(&reply.parent.0) {
+            self.engagement
+                .record_reply(&reply.parent.0, &post.uri.0, &post.author_did.0)
+                .ok();
+        }
+    }
+
+    /// Counts a post rejected for its language and returns whether it looked like gamedev.
+    fn record_rejected_language(&mut self, lang: &str, text: &str) -> bool {
+        let relevant = has_keywords(text).0 || has_hashtags(text).0;
+        let lang: String = lang.trim().to_lowercase().chars().take(16).collect();
+        let entry = self.pending_languages.entry(lang).or_default();
+        entry.0 += 1;
+        if relevant {
+            entry.1 += 1;
+        }
+        relevant
+    }
+
+    /// Serves the trending feed: posts by engagement velocity alone, highest first. The cursor
+    /// carries the last post's velocity, so posts that change velocity between pages may repeat
+    /// or be skipped.
+    fn serve_trending(&self, request: &FeedRequest) -> FeedResult {
+        let s = settings();
+        let now = self.clock.timestamp();
+        let cutoff = now - (s.feed.trending.cutoff_hours * 3600);
+        // Velocity is only recomputed on new engagement, so a burst that has gone quiet would
+        // otherwise keep its rank. Like rescoring, trust it only within the velocity window.
+        let fresh_since = now - (s.spam.velocity_window_hours * 3600);
+
+        let Ok(mut conn) = self.read_pool.get() else {
+            return FeedResult {
+                cursor: None,
+                feed: vec![],
+            };
+        };
+        let trending = db::get_trending_feed(&mut conn, cutoff, fresh_since).unwrap_or_default();
+
+        let cursor: Option<FeedCursor> = request.cursor.as_deref().and_then(|c| c.parse().ok());
+        let limit = request
+            .limit
+            .map(|l| (l as usize).min(s.feed.max_limit))
+            .unwrap_or(s.feed.default_limit);
+
+        let start_index = cursor.as_ref().map_or(0, |c| {
+            c.resume_index(&trending, ranking::bucket_seconds(), ranking::trending_key)
+        });
+        let page = &trending[start_index..(start_index + limit).min(trending.len())];
+
+        let next_cursor = match page.last() {
+            Some(last) if start_index + page.len() < trending.len() => {
+                Some(FeedCursor::after(0, ranking::trending_key(last)).to_string())
+            }
+            _ => None,
+        };
+        let feed: Vec<Uri> = page.iter().map(|(uri, _)| Uri(uri.clone())).collect();
+
+        logs::log_feed_served(feed.len(), request.cursor.as_ref());
+
+        FeedResult {
+            cursor: next_cursor,
+            feed,
+        }
+    }
+
+    /// Translates a relevant post rejected for its language on a separate task, so the
+    /// request doesn't hold up ingestion, and queues it to be assessed again.
+    fn translate_later(&self, post: &Post, media_info: &MediaInfo, text: &str, lang: &str) {
+        if !settings().ml.translation.enabled {
+            return;
+        }
+        let translator = self.translator.clone();
+        let prepared_posts = self.prepared_posts.clone();
+        let preparing = Preparing::start(&self.preparing);
+        let (post, media_info, text, lang) = (
+            post.clone(),
+            media_info.clone(),
+            text.to_string(),
+            lang.to_string(),
+        );
+        tokio::spawn(async move {
+            let _preparing = preparing;
+            let Some(translated) = translator.translate(&text, &lang).await else {
+                return;
+            };
+            if let Ok(mut prepared) = prepared_posts.lock() {
+                prepared.push(PreparedPost {
+                    post,
+                    media_info,
+                    scoring_text: translated,
+                    translated_from: Some(lang),
+                });
+            }
+        });
+    }
+
+    /// Assesses the posts whose translation or image text came back since the last call, and
+    /// ranks the ones scored since.
+    pub async fn process_prepared_posts(&mut self) {
+        let scored = match self.scored_posts.lock() {
+            Ok(mut scored) => std::mem::take(&mut *scored),
+            Err(_) => Vec::new(),
+        };
+        for scored in scored {
+            let uri = scored.uri.to_string();
+            if let Err(panic) = AssertUnwindSafe(self.rank_scored_post(scored))
+                .catch_unwind()
+                .await
+            {
+                self.firehose.record_panic();
+                logs::log_post_panicked(&uri, panic.as_ref());
+            }
+        }
+
+        let prepared = match self.prepared_posts.lock() {
+            Ok(mut prepared) => std::mem::take(&mut *prepared),
+            Err(_) => return,
+        };
+        for prepared in prepared {
+            let uri = prepared.post.uri.0.clone();
+            let assessed = self.assess_post(
+                prepared.post,
+                prepared.media_info,
+                prepared.scoring_text,
+                prepared.translated_from,
+            );
+            if let Err(panic) = AssertUnwindSafe(assessed).catch_unwind().await {
+                self.firehose.record_panic();
+                logs::log_post_panicked(&uri, panic.as_ref());
+            }
+        }
+    }
+
+    /// Waits for every translation, OCR and scoring task to finish and processes its post, for
+    /// when nothing more is coming in.
+    pub async fn finish_prepared_posts(&mut self) {
+        loop {
+            self.process_prepared_posts().await;
+            let idle = self.preparing.load(Ordering::SeqCst) == 0;
+            let queued = self.prepared_posts.lock().map_or(0, |p| p.len())
+                + self.scored_posts.lock().map_or(0, |p| p.len());
+            if idle && queued == 0 {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Scores a post on a separate task, so the handler lock isn't held while it waits in the
+    /// ML queue, and queues it to be ranked. Influencers' posts are never shed; others are shed
+    /// when the queue is full of posts with more keyword and hashtag matches.
+    fn score_later(&self, post: ScoredPost, signal: Option<usize>) {
+        let ml_handle = self.ml_handle.clone();
+        let scored_posts = self.scored_posts.clone();
+        let preparing = Preparing::start(&self.preparing);
+        tokio::spawn(async move {
+            let _preparing = preparing;
+            let Some(quality) = ml_handle.try_score(post.scoring_text.clone(), signal).await else {
+                return;
+            };
+            if let Ok(mut scored) = scored_posts.lock() {
+                scored.push(ScoredPost { quality, ..post });
+            }
+        });
+    }
+
+    async fn process_post(&mut self, post: Post) {
+        self.note_event();
+        if !self.is_new_event(&post.uri.0) {
+            return;
+        }
         if post.reply.is_some() {
+            self.record_thread_reply(&post);
+            self.record_reply_engagement(&post);
+            return;
+        }
+
+        let media_info = MediaInfo::from_embed(post.embed.as_ref());
+        #[cfg(feature = "ocr")]
+        if self.read_images_later(&post, &media_info) {
             return;
         }
+        let scoring_text = match self.describe_images(&post, &media_info) {
+            Some(description) => description,
+            None => post.text.clone(),
+        };
+        self.assess_post(post, media_info, scoring_text, None).await;
+    }
+
+    /// Filters and scores a post on `scoring_text`, which is its text, its images' description
+    /// or, when `translated_from` is set, its translation from that language.
+    async fn assess_post(
+        &mut self,
+        post: Post,
+        media_info: MediaInfo,
+        scoring_text: String,
+        translated_from: Option<String>,
+    ) {
+        let (uri, author) = match (
+            post.uri.0.parse::<AtUri>(),
+            post.author_did.0.parse::<ids::Did>(),
+        ) {
+            (Ok(uri), Ok(author)) => (uri, author),
+            (Err(e), _) | (_, Err(e)) => {
+                logs::log_malformed_record(&e.to_string());
+                return;
+            }
+        };
 
         let text = &post.text;
         let lang = post.langs.first().map(|s| s.as_str());
-        let author_did = post.author_did.0.as_str();
+        let author_did = author.as_str();
 
         let mut assessment = PostAssessment::new(text);
+        if let Some(source_lang) = &translated_from {
+            assessment.set_translation(source_lang);
+        }
 
-        let media_info = Self::extract_media_info(&post);
-
-        let filter_result = apply_filters(
-            text,
-            lang,
+        let mut filter_result = apply_filters(
+            &scoring_text,
+            // A translation is English whatever the post says it's in.
+            lang.filter(|_| translated_from.is_none()),
             Some(author_did),
             &media_info,
             |did| self.is_spammer(did),
             |did| self.is_blocked_author(did),
         );
+        // Looked up only for posts that would otherwise be accepted, since it takes a query.
+        if filter_result == FilterResult::Pass && self.has_opted_out(author_did) {
+            filter_result = FilterResult::Reject(Filter::AuthorOptOut);
+        }
+
+        if let (FilterResult::Reject(Filter::EnglishOnly), Some(lang)) = (&filter_result, lang) {
+            if self.record_rejected_language(lang, &scoring_text) {
+                self.translate_later(&post, &media_info, &scoring_text, lang);
+            }
+        }
+        let created_at = check_post_age(
+            post.timestamp.timestamp(),
+            self.clock.timestamp(),
+            &settings().filters.post_age,
+        );
+        if let (FilterResult::Pass, Err(filter)) = (&filter_result, &created_at) {
+            filter_result = FilterResult::Reject(filter.clone());
+        }
         assessment.set_filter_result(filter_result.clone());
 
-        if let FilterResult::Reject(_) = filter_result {
+        if filter_result == FilterResult::Reject(Filter::LinkOnly) {
+            self.firehose.record_link_only();
+        }
+        if filter_result == FilterResult::Reject(Filter::AuthorOptOut) {
+            self.record_opt_out(author_did, uri.as_str());
+        }
+        if let FilterResult::Reject(filter) = &filter_result {
+            let relevant = || {
+                has_keywords_for(&scoring_text, lang).0
+                    || has_hashtags_for(&scoring_text, media_info.facet_tags.as_deref(), lang).0
+            };
+            if filter.is_spam() && relevant() {
+                self.author_stats(author_did).spam_flags += 1;
+            }
+            assessment.log();
             return;
         }
+        let Ok(created_at) = created_at else {
+            return;
+        };
 
         let s = settings();
         let is_influencer = s.filters.influencer_dids.contains(&author_did.to_string());
 
-        let (found_keywords, _keyword_count) = has_keywords(text);
-        let (found_hashtags, _hashtag_count) = has_hashtags(text);
-        assessment.set_relevance(found_keywords, found_hashtags);
+        let mut keyword_matches = keyword_matches_for(text, lang);
+        let hashtag_matches = hashtag_matches_for(text, media_info.facet_tags.as_deref(), lang);
+        let attached_text = media_info.attached_text();
+        let scores_attached_text = s.scoring.alt_text.enabled && !attached_text.is_empty();
+        if scores_attached_text {
+            keyword_matches.extend(keyword_matches_for(&attached_text, lang).into_iter().map(
+                |m| TermMatch {
+                    in_attached_text: true,
+                    ..m
+                },
+            ));
+        }
+        let relevant = !keyword_matches.is_empty() || !hashtag_matches.is_empty();
+        let keyword_score = weighted_keyword_score(&keyword_matches);
+        let matches: Vec<TermMatch> = keyword_matches
+            .iter()
+            .chain(&hashtag_matches)
+            .cloned()
+            .collect();
+        assessment.set_relevance(keyword_matches, hashtag_matches);
 
-        if !found_keywords && !found_hashtags && !is_influencer {
+        if !relevant && !is_influencer {
+            assessment.log();
             return;
         }
 
-        if is_influencer && !found_keywords && !found_hashtags {
-            logs::log_influencer_accepted(author_did);
+        let pending = self
+            .pending_posts
+            .iter()
+            .filter(|p| {
+                p.author_did
+                    .as_ref()
+                    .is_some_and(|did| did.as_str() == author_did)
+            })
+            .count();
+        if self
+            .engagement
+            .check_post_flood(author_did, pending)
+            .is_some()
+        {
+            assessment.set_filter_result(FilterResult::Reject(Filter::PostFlood));
+            self.author_stats(author_did).spam_flags += 1;
+            assessment.log();
+            return;
+        }
+
+        self.handles.refresh_lazily(author_did);
+
+        if is_influencer && !relevant {
+            let handle = self.handles.get(author_did);
+            logs::log_influencer_accepted(author_did, handle.as_deref());
         }
 
-        let quality = self.ml_handle.score(text.clone()).await;
+        let signal = (!is_influencer).then_some(matches.len());
+        self.score_later(
+            ScoredPost {
+                uri,
+                author: author.clone(),
+                text: text.clone(),
+                lang: lang.map(str::to_string),
+                created_at,
+                media_info,
+                scoring_text,
+                attached_text: scores_attached_text.then_some(attached_text),
+                keyword_score,
+                matches,
+                assessment,
+                quality: QualityAssessment::default(),
+            },
+            signal,
+        );
+    }
+
+    /// Ranks a scored post and buffers it if it's accepted.
+    async fn rank_scored_post(&mut self, scored: ScoredPost) {
+        let ScoredPost {
+            uri,
+            author,
+            text,
+            lang,
+            created_at,
+            media_info,
+            scoring_text,
+            attached_text,
+            keyword_score,
+            matches,
+            mut assessment,
+            quality,
+        } = scored;
+        let s = settings();
+        let lang = lang.as_deref();
+        let author_did = author.as_str();
+        self.author_stats(author_did).assessed += 1;
 
-        let content = extract_content_signals(text, &media_info);
+        let content = extract_content_signals(&scoring_text, &media_info);
         assessment.set_content(content.clone(), media_info.clone());
 
-        let signals = PrioritySignals::new(&quality, &content);
+        let mut signals = PrioritySignals::new(&quality, &content);
+        signals.keyword_score = keyword_score;
+        signals.bio_relevance = self.handles.bio_relevance(author_did);
+        signals.prior_accepted = self.prior_accepted_posts(author_did);
+        signals.author_reputation = self.author_reputation(author_did);
+        if let Some(attached_text) = &attached_text {
+            signals.alt_text_relevance = self.alt_text_relevance(attached_text, lang).await;
+        }
+        // Embedded up front only when there's something to compare against; otherwise posts
+        // rejected for low priority are never embedded.
+        let mut embedding = None;
+        if s.scoring.negative_examples.enabled && !self.negatives.is_empty() {
+            let e = self
+                .embeddings
+                .get_or_embed(&self.ml_handle, uri.as_str(), &scoring_text)
+                .await;
+            signals.negative_similarity = self.negatives.closest(&e);
+            embedding = Some(e);
+        }
         let priority = calculate_priority(&signals);
+        let now = self.clock.timestamp();
+        if let Some(shadow) = self.shadow.score(uri.as_str(), &signals, &priority, now) {
+            self.pending_shadow_scores.push(shadow);
+        }
+        let mut explanation =
+            ScoreExplanation::new(&quality, &signals, &priority).with_matches(matches);
         assessment.set_priority(quality, signals, priority.clone());
 
         if priority.priority < settings().scoring.rejection.min_priority {
             assessment.reject_low_priority();
-            assessment.print();
+            assessment.log();
+            explanation.reject(Filter::LowPriority);
+            self.record_score(uri.as_str(), &explanation);
             return;
         }
 
-        assessment.print();
+        let embedding = match embedding {
+            Some(e) => e,
+            None => {
+                self.embeddings
+                    .get_or_embed(&self.ml_handle, uri.as_str(), &scoring_text)
+                    .await
+            }
+        };
+        if !embedding.is_empty() {
+            if self.is_near_duplicate(&embedding) {
+                assessment.reject_near_duplicate();
+                assessment.log();
+                explanation.reject(Filter::NearDuplicate);
+                self.record_score(uri.as_str(), &explanation);
+                return;
+            }
+
+            self.pending_embeddings.insert(uri.to_string(), embedding);
+        }
+
+        assessment.log();
+        self.record_score(uri.as_str(), &explanation);
+        let stats = self.author_stats(author_did);
+        stats.accepted += 1;
+        // Reputation is fed back into the author boost, so it's built from what posts score
+        // without it.
+        stats.score_sum += priority.base_priority();
 
         let new_post = NewPost::new(
-            post.uri.0.clone(),
-            text.clone(),
-            post.timestamp.timestamp(),
+            uri,
+            text,
+            created_at,
             priority.priority,
             &media_info,
             &content,
-            Some(author_did.to_string()),
-        );
-
+            Some(author.clone()),
+        )
+        .with_lang(s.filters.feed_language(lang))
+        .with_config_id(self.config_id());
+
+        if self.webhooks.is_enabled() {
+            self.pending_webhooks.push(AcceptedPostEvent::from_post(
+                &new_post,
+                &explanation.breakdown,
+            ));
+        }
         self.pending_posts.push(new_post);
+        if let Err(e) = self.enforce_post_limit() {
+            logs::log_flush_failed(&e.to_string());
+        }
+        self.buffers
+            .observe(self.pending_posts.len(), self.pending_likes.len());
+        self.flush_if_busy();
+    }
+
+    #[allow(dead_code)]
+    pub fn engagement_tracker(&self) -> &EngagementTracker {
+        &self.engagement
+    }
+}
+
+impl FeedHandler for GameDevFeedHandler {
+    async fn available_feeds(&mut self) -> Vec<String> {
+        let s = settings();
+        std::iter::once("Game Dev Progress".to_string())
+            .chain(
+                s.filters
+                    .languages
+                    .iter()
+                    .filter(|l| l.enabled)
+                    .map(|l| l.feed_name.clone()),
+            )
+            .chain(
+                s.feed
+                    .trending
+                    .enabled
+                    .then(|| s.feed.trending.feed_name.clone()),
+            )
+            .collect()
+    }
+
+    /// A panic while processing one post skips that post instead of ending the handler task
+    /// and with it ingestion.
+    async fn insert_post(&mut self, post: Post) {
+        self.process_prepared_posts().await;
+        let uri = post.uri.0.clone();
+        if let Err(panic) = AssertUnwindSafe(self.process_post(post))
+            .catch_unwind()
+            .await
+        {
+            self.firehose.record_panic();
+            logs::log_post_panicked(&uri, panic.as_ref());
+        }
     }
 
     async fn delete_post(&mut self, uri: Uri) {
+        self.note_event();
+        if let Ok(mut index) = self.vector_index.write() {
+            index.remove(&uri.0);
+        }
+        self.pending_embeddings.remove(&uri.0);
         self.pending_deletes.push(uri.0.clone());
     }
 
     async fn insert_like(&mut self, like_uri: Uri, liked_post_uri: Uri) {
-        self.engagement.record_like(&liked_post_uri.0).ok();
+        self.note_event();
+        if !self.is_new_event(&like_uri.0) {
+            return;
+        }
+        let (like_uri, post_uri) = match (
+            like_uri.0.parse::<AtUri>(),
+            liked_post_uri.0.parse::<AtUri>(),
+        ) {
+            (Ok(like_uri), Ok(post_uri)) => (like_uri, post_uri),
+            (Err(e), _) | (_, Err(e)) => {
+                logs::log_malformed_record(&e.to_string());
+                return;
+            }
+        };
+        self.engagement.record_like(post_uri.as_str()).ok();
         self.pending_likes.push(NewLike {
-            post_uri: liked_post_uri.0.clone(),
-            like_uri: like_uri.0.clone(),
+            liker_did: like_uri.did(),
+            post_uri,
+            like_uri,
+            liked_at: self.clock.timestamp(),
         });
+        if let Err(e) = self.enforce_like_limit() {
+            logs::log_flush_failed(&e.to_string());
+        }
+        self.buffers
+            .observe(self.pending_posts.len(), self.pending_likes.len());
+        self.flush_if_busy();
     }
 
     async fn delete_like(&mut self, like_uri: Uri) {
+        self.note_event();
         self.pending_like_deletes.push(like_uri.0.clone());
     }
 
     async fn serve_feed(&self, request: FeedRequest) -> FeedResult {
         let s = settings();
-        let now = Utc::now();
+        if s.feed.trending.serves(&request.feed) {
+            return self.serve_trending(&request);
+        }
+        let now = self.clock.now();
         let cutoff = now.timestamp() - (s.feed.cutoff_hours * 3600);
 
-        let mut conn = match self.pool.get() {
+        let mut conn = match self.read_pool.get() {
             Ok(c) => c,
             Err(_) => {
                 return FeedResult {
@@ -275,7 +1423,8 @@ impl FeedHandler for GameDevFeedHandler {
             }
         };
 
-        let posts = match db::get_feed(&mut conn, cutoff) {
+        let feed_lang = s.filters.language_for_feed(&request.feed);
+        let posts = match db::get_language_feed(&mut conn, cutoff, &feed_lang) {
             Ok(p) => p,
             Err(_) => {
                 return FeedResult {
@@ -285,90 +1434,62 @@ impl FeedHandler for GameDevFeedHandler {
             }
         };
 
-        let seen_posts: HashSet<String> = request
-            .user_did
-            .as_ref()
-            .and_then(|did| get_user_seen_posts(&mut conn, &did.0, cutoff).ok())
-            .map(|posts| posts.into_iter().collect())
-            .unwrap_or_default();
-
-        let (boosted_authors, penalized_authors): (HashSet<String>, HashSet<String>) = request
+        let mut reader = request
             .user_did
             .as_ref()
-            .and_then(|did| get_user_preferences(&mut conn, &did.0).ok())
-            .map(|prefs| {
-                let mut boosted = HashSet::new();
-                let mut penalized = HashSet::new();
-                for pref in prefs {
-                    if let Some(author) = get_post_author(&mut conn, &pref.post_uri) {
-                        if pref.is_request_more {
-                            boosted.insert(author);
-                        } else {
-                            penalized.insert(author);
-                        }
-                    }
-                }
-                (boosted, penalized)
+            .map(|did| Reader {
+                follows: self.follows.get(&did.0),
+                ..Reader::load(&mut conn, &did.0, cutoff, now.timestamp())
             })
             .unwrap_or_default();
+        if reader.interests.is_some() {
+            if let Ok(index) = self.vector_index.read() {
+                reader.match_interests(&posts, |uri| index.embedding(uri).map(Vec::as_slice));
+            }
+        }
 
-        let start_index = request
-            .cursor
-            .as_ref()
-            .and_then(|c| c.parse::<usize>().ok())
-            .unwrap_or(0);
+        // Cursors from before a redeploy or otherwise unreadable start over from the top.
+        let cursor: Option<FeedCursor> = request.cursor.as_deref().and_then(|c| c.parse().ok());
 
         let limit = request
             .limit
             .map(|l| (l as usize).min(s.feed.max_limit))
             .unwrap_or(s.feed.default_limit);
 
-        let mut rng = rand::rng();
-
-        let mut scored_posts: Vec<_> = posts
-            .iter()
-            .filter(|p| !seen_posts.contains(&p.uri))
-            .map(|p| {
-                let preference_modifier = p
-                    .author_did
-                    .as_ref()
-                    .map(|author| {
-                        if boosted_authors.contains(author) {
-                            s.feed.preference_boost
-                        } else if penalized_authors.contains(author) {
-                            s.feed.preference_penalty
-                        } else {
-                            1.0
-                        }
-                    })
-                    .unwrap_or(1.0);
-
-                let variance = rng.random_range(-s.feed.shuffle_variance..s.feed.shuffle_variance);
-                let adjusted_priority = p.priority * preference_modifier * (1.0 + variance);
-
-                (p, adjusted_priority)
-            })
-            .collect();
-
-        let bucket_seconds = s.feed.priority_bucket_hours * 3600;
-        scored_posts.sort_by(|a, b| {
-            let bucket_a = a.0.timestamp / bucket_seconds;
-            let bucket_b = b.0.timestamp / bucket_seconds;
-            match bucket_b.cmp(&bucket_a) {
-                Ordering::Equal => b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal),
-                other => other,
+        let seed = cursor
+            .as_ref()
+            .map_or_else(|| rand::rng().random(), |c| c.seed);
+
+        let ctx = RankContext::new(&posts, now.timestamp(), seed);
+        let ranked = rank_uncapped(&posts, &reader, &ctx);
+
+        // The author cap takes the feed out of rank order. A page picks up just past the last
+        // post served while it's still in the feed. Otherwise it resumes in rank order after
+        // the posts already served, with the cap applied from there.
+        let mut capped_from = 0;
+        let mut scored_posts = cap_feed(ranked.clone());
+        let mut start_index = 0;
+        if let Some(cursor) = &cursor {
+            match cursor.resume_after(&scored_posts, |(post, _)| post.uri.as_str()) {
+                Some(index) => start_index = index,
+                None => {
+                    capped_from = cursor.resume_index(&ranked, ranking::bucket_seconds(), rank_key);
+                    scored_posts = cap_feed(ranked[capped_from..].to_vec());
+                }
             }
-        });
-
-        let page_posts: Vec<_> = scored_posts
-            .into_iter()
-            .skip(start_index)
-            .take(limit)
-            .collect();
-
-        let filtered_count = posts.len() - seen_posts.len().min(posts.len());
-        let next_cursor = if start_index + limit < filtered_count {
-            Some((start_index + limit).to_string())
+        }
+        let filtered_count = scored_posts.len();
+        let end_index = (start_index + limit).min(filtered_count);
+        let page_posts = &scored_posts[start_index..end_index];
+
+        let next_cursor = if end_index < filtered_count {
+            FeedCursor::after_capped(
+                seed,
+                &ranked[capped_from..],
+                &scored_posts[..end_index],
+                rank_key,
+            )
+            .map(|cursor| cursor.to_string())
         } else {
             None
         };
@@ -388,34 +1509,44 @@ impl FeedHandler for GameDevFeedHandler {
 
         let s = settings();
         let is_moderator = s.filters.moderator_dids.contains(&user_did.0);
-        let now = Utc::now().timestamp();
-        let mut db_interactions = Vec::new();
+        let now = self.clock.timestamp();
 
-        for interaction in &interactions {
+        let max_per_request = s.flush.max_interactions_per_request;
+        let over_limit = interactions.len().saturating_sub(max_per_request);
+        let interactions = &interactions[..interactions.len().min(max_per_request)];
+        let db_interactions = interactions.iter().filter_map(|interaction| {
             let interaction_type = match interaction.event {
-                InteractionEvent::RequestLess => Some(INTERACTION_REQUEST_LESS),
-                InteractionEvent::RequestMore => Some(INTERACTION_REQUEST_MORE),
-                InteractionEvent::InteractionSeen => Some(INTERACTION_SEEN),
-                _ => None,
+                InteractionEvent::RequestLess => INTERACTION_REQUEST_LESS,
+                InteractionEvent::RequestMore => INTERACTION_REQUEST_MORE,
+                InteractionEvent::InteractionSeen => INTERACTION_SEEN,
+                InteractionEvent::ClickthroughItem
+                | InteractionEvent::ClickthroughAuthor
+                | InteractionEvent::ClickthroughReposter
+                | InteractionEvent::ClickthroughEmbed => INTERACTION_CLICKTHROUGH,
+                InteractionEvent::InteractionShare => INTERACTION_SHARE,
+                // Likes, reposts, replies and quotes arrive through the firehose.
+                _ => return None,
             };
+            Some(NewInteraction {
+                user_did: user_did.0.clone(),
+                post_uri: interaction.item.0.clone(),
+                interaction_type: interaction_type.to_string(),
+                created_at: now,
+            })
+        });
 
-            if let Some(itype) = interaction_type {
-                db_interactions.push(NewInteraction {
-                    user_did: user_did.0.clone(),
-                    post_uri: interaction.item.0.clone(),
-                    interaction_type: itype.to_string(),
-                    created_at: now,
-                });
-            }
+        if let Ok(mut pending) = self.pending_interactions.lock() {
+            let queued = pending.extend(db_interactions, s.flush.max_pending_interactions);
+            self.buffers
+                .record_dropped_interactions(queued.dropped + over_limit);
+            self.buffers
+                .record_duplicate_interactions(queued.duplicates);
+            self.buffers.observe_interactions(pending.len());
         }
 
-        if let Ok(mut conn) = self.pool.get() {
-            if !db_interactions.is_empty() {
-                let _ = insert_interactions(&mut conn, db_interactions);
-            }
-
-            if is_moderator {
-                for interaction in &interactions {
+        if is_moderator {
+            if let Ok(mut conn) = self.pool.get() {
+                for interaction in interactions {
                     if !matches!(interaction.event, InteractionEvent::RequestLess) {
                         continue;
                     }
@@ -429,11 +1560,58 @@ impl FeedHandler for GameDevFeedHandler {
                                 blocked_at: now,
                             },
                         );
-                        let deleted = delete_posts_by_author(&mut conn, &author).unwrap_or(0);
-                        logs::log_author_blocked(&user_did.0, &author, deleted);
+                        let deleted =
+                            delete_posts_by_author(&mut conn, &author).unwrap_or_default();
+                        self.forget_posts(&deleted);
+                        let deleted = deleted.len();
+                        let moderator_handle = self.handles.get(&user_did.0);
+                        let author_handle = self.handles.get(&author);
+                        logs::log_author_blocked(
+                            (&user_did.0, moderator_handle.as_deref()),
+                            (&author, author_handle.as_deref()),
+                            deleted,
+                        );
                     }
                 }
             }
         }
     }
 }
+
+impl RepostHandler for GameDevFeedHandler {
+    async fn insert_repost(&mut self, repost_uri: Uri, reposted_post_uri: Uri) {
+        self.note_event();
+        if !self.is_new_event(&repost_uri.0) {
+            return;
+        }
+        let reposter = match repost_uri.0.parse::<AtUri>() {
+            Ok(uri) => uri.did(),
+            Err(e) => {
+                logs::log_malformed_record(&e.to_string());
+                return;
+            }
+        };
+        let Some(reposter) = reposter else {
+            return;
+        };
+        // Reposting your own post doesn't count towards its engagement.
+        let author = reposted_post_uri
+            .0
+            .parse::<AtUri>()
+            .ok()
+            .and_then(|uri| uri.did());
+        if author.is_none_or(|author| author == reposter) {
+            return;
+        }
+        if self.is_in_feed(&reposted_post_uri.0) {
+            self.engagement
+                .record_repost(&reposted_post_uri.0, &repost_uri.0, reposter.as_str())
+                .ok();
+        }
+    }
+
+    async fn delete_repost(&mut self, repost_uri: Uri) {
+        self.note_event();
+        self.engagement.remove_repost(&repost_uri.0).ok();
+    }
+}
@@ -0,0 +1,127 @@
+use crate::db::{self, DbPool, NewHandle};
+use crate::scoring::{bio_relevance, cosine_similarity, MLHandle};
+use crate::settings::settings;
+use crate::utils::bluesky::{fetch_profile, Profile};
+use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct HandleCache {
+    pool: DbPool,
+    ml_handle: MLHandle,
+    client: reqwest::Client,
+    /// DIDs whose profile is being fetched, so a burst of posts by one author fetches it once.
+    refreshing: Arc<Mutex<HashSet<String>>>,
+}
+
+/// Takes a DID off the refreshing set when its refresh ends, however it ends.
+struct Refreshing {
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    did: String,
+}
+
+impl Drop for Refreshing {
+    fn drop(&mut self) {
+        if let Ok(mut refreshing) = self.refreshing.lock() {
+            refreshing.remove(&self.did);
+        }
+    }
+}
+
+impl HandleCache {
+    pub fn new(pool: DbPool, ml_handle: MLHandle) -> Self {
+        Self {
+            pool,
+            ml_handle,
+            client: reqwest::Client::new(),
+            refreshing: Arc::default(),
+        }
+    }
+
+    pub fn get(&self, did: &str) -> Option<String> {
+        let mut conn = self.pool.get().ok()?;
+        db::get_handle(&mut conn, did).map(|(handle, _)| handle)
+    }
+
+    pub fn bio_relevance(&self, did: &str) -> f32 {
+        self.pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::get_bio_score(&mut conn, did))
+            .unwrap_or(0.0)
+    }
+
+    pub fn refresh_lazily(&self, did: &str) {
+        let s = settings();
+        let stale_before = Utc::now().timestamp() - (s.authors.handle_ttl_hours * 3600);
+
+        let is_fresh = self
+            .pool
+            .get()
+            .ok()
+            .and_then(|mut conn| db::get_handle(&mut conn, did))
+            .is_some_and(|(_, updated_at)| updated_at > stale_before);
+        if is_fresh {
+            return;
+        }
+        let is_new = self
+            .refreshing
+            .lock()
+            .is_ok_and(|mut refreshing| refreshing.insert(did.to_string()));
+        if !is_new {
+            return;
+        }
+        let guard = Refreshing {
+            refreshing: self.refreshing.clone(),
+            did: did.to_string(),
+        };
+
+        let cache = self.clone();
+        let did = did.to_string();
+        tokio::spawn(async move {
+            let _guard = guard;
+            let Ok(profile) = fetch_profile(&cache.client, &did).await else {
+                return;
+            };
+            // An unchanged bio keeps its score rather than being embedded again.
+            let stored = cache
+                .pool
+                .get()
+                .ok()
+                .and_then(|mut conn| db::get_bio(&mut conn, &did));
+            let bio_score = match stored {
+                Some((bio, score)) if bio == profile.description => score,
+                _ => cache.score_bio(&profile).await,
+            };
+            if let Ok(mut conn) = cache.pool.get() {
+                let _ = db::upsert_handle(
+                    &mut conn,
+                    NewHandle {
+                        did,
+                        handle: profile.handle,
+                        updated_at: Utc::now().timestamp(),
+                        description: profile.description,
+                        bio_score,
+                    },
+                );
+            }
+        });
+    }
+
+    async fn score_bio(&self, profile: &Profile) -> f32 {
+        let Some(bio) = profile
+            .description
+            .as_deref()
+            .filter(|b| !b.trim().is_empty())
+        else {
+            return 0.0;
+        };
+
+        let reference = settings().authors.bio_reference.clone();
+        let bio_embedding = self.ml_handle.embed(bio.to_string()).await;
+        let reference_embedding = self.ml_handle.embed(reference).await;
+
+        bio_relevance(bio, cosine_similarity(&bio_embedding, &reference_embedding))
+    }
+}
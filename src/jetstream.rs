@@ -0,0 +1,483 @@
+use crate::embeds;
+use crate::settings::settings;
+use crate::utils::logs;
+use crate::utils::time::{self, ManualClock};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use skyfeed::{Cid, Did, FeedHandler, Post, Reply, Uri};
+use std::io;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+const LIKE_COLLECTION: &str = "app.bsky.feed.like";
+const REPOST_COLLECTION: &str = "app.bsky.feed.repost";
+
+/// How far before the last seen event a reconnect resumes. The overlap is dropped by the
+/// handler's dedup window.
+const RESUME_OVERLAP_US: i64 = 5_000_000;
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    did: String,
+    time_us: i64,
+    kind: String,
+    commit: Option<Commit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Commit {
+    operation: String,
+    collection: String,
+    rkey: String,
+    cid: Option<String>,
+    record: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostRecord {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    langs: Vec<String>,
+    created_at: Option<String>,
+    reply: Option<ReplyRecord>,
+    embed: Option<serde_json::Value>,
+    labels: Option<SelfLabels>,
+}
+
+/// A like or repost, both of which point at the post they're about.
+#[derive(Debug, Deserialize)]
+struct SubjectRecord {
+    subject: StrongRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct StrongRef {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplyRecord {
+    root: StrongRef,
+    parent: StrongRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct SelfLabels {
+    #[serde(default)]
+    values: Vec<SelfLabel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SelfLabel {
+    val: String,
+}
+
+#[derive(Debug)]
+pub enum JetstreamEvent {
+    Post(Box<Post>),
+    DeletePost(Uri),
+    Like { like_uri: Uri, post_uri: Uri },
+    DeleteLike(Uri),
+    Repost { repost_uri: Uri, post_uri: Uri },
+    DeleteRepost(Uri),
+}
+
+/// Reposts, which skyfeed's `FeedHandler` has no hook for. Only Jetstream ingestion delivers
+/// them.
+pub trait RepostHandler {
+    fn insert_repost(
+        &mut self,
+        repost_uri: Uri,
+        reposted_post_uri: Uri,
+    ) -> impl std::future::Future<Output = ()> + Send;
+
+    fn delete_repost(&mut self, repost_uri: Uri) -> impl std::future::Future<Output = ()> + Send;
+}
+
+/// Parses one Jetstream message into the event skyfeed would have produced for it, along with
+/// its `time_us` cursor. Identity and account events, and other collections, yield `None`.
+pub fn parse_event(message: &str) -> Option<(i64, JetstreamEvent)> {
+    let event: Event = serde_json::from_str(message).ok()?;
+    if event.kind != "commit" {
+        return None;
+    }
+    let commit = event.commit?;
+    let uri = Uri(format!(
+        "at://{}/{}/{}",
+        event.did, commit.collection, commit.rkey
+    ));
+
+    let parsed = match (commit.collection.as_str(), commit.operation.as_str()) {
+        (POST_COLLECTION, "create") => {
+            let record: PostRecord = serde_json::from_value(commit.record?).ok()?;
+            let timestamp = record
+                .created_at
+                .as_deref()
+                .map(time::parse_rfc3339)
+                .and_then(Result::ok)
+                .or_else(|| time::from_timestamp_micros(event.time_us).ok())?;
+            JetstreamEvent::Post(Box::new(Post {
+                author_did: Did(event.did),
+                cid: Cid(commit.cid.unwrap_or_default()),
+                uri,
+                text: record.text,
+                labels: record
+                    .labels
+                    .map(|labels| labels.values.into_iter().map(|l| l.val).collect())
+                    .unwrap_or_default(),
+                langs: record.langs,
+                reply: record.reply.map(|reply| Reply {
+                    parent: Uri(reply.parent.uri),
+                    root: Uri(reply.root.uri),
+                }),
+                embed: record.embed.and_then(embeds::from_record),
+                timestamp,
+            }))
+        }
+        (POST_COLLECTION, "delete") => JetstreamEvent::DeletePost(uri),
+        (LIKE_COLLECTION, "create") => {
+            let record: SubjectRecord = serde_json::from_value(commit.record?).ok()?;
+            JetstreamEvent::Like {
+                like_uri: uri,
+                post_uri: Uri(record.subject.uri),
+            }
+        }
+        (LIKE_COLLECTION, "delete") => JetstreamEvent::DeleteLike(uri),
+        (REPOST_COLLECTION, "create") => {
+            let record: SubjectRecord = serde_json::from_value(commit.record?).ok()?;
+            JetstreamEvent::Repost {
+                repost_uri: uri,
+                post_uri: Uri(record.subject.uri),
+            }
+        }
+        (REPOST_COLLECTION, "delete") => JetstreamEvent::DeleteRepost(uri),
+        _ => return None,
+    };
+    Some((event.time_us, parsed))
+}
+
+/// `time_us` of the last event handled, so a reconnect resumes where the stream left off
+/// instead of at the live tail.
+#[derive(Debug, Clone, Default)]
+pub struct JetstreamCursor {
+    time_us: Arc<AtomicI64>,
+}
+
+impl JetstreamCursor {
+    fn advance(&self, time_us: i64) {
+        self.time_us.fetch_max(time_us, Ordering::Relaxed);
+    }
+
+    fn resume_at(&self) -> Option<i64> {
+        let time_us = self.time_us.load(Ordering::Relaxed);
+        (time_us > 0).then(|| time_us - RESUME_OVERLAP_US)
+    }
+}
+
+pub fn subscribe_url(endpoint: &str, cursor: Option<i64>) -> String {
+    let mut url = format!(
+        "{endpoint}?wantedCollections={POST_COLLECTION}&wantedCollections={LIKE_COLLECTION}\
+         &wantedCollections={REPOST_COLLECTION}"
+    );
+    if let Some(cursor) = cursor {
+        url.push_str(&format!("&cursor={cursor}"));
+    }
+    url
+}
+
+/// Streams posts, likes and reposts from Jetstream into `handler` until the connection ends.
+/// Meant to run under `firehose::supervise`, which reconnects it.
+pub async fn connect<H: FeedHandler + RepostHandler>(
+    endpoint: String,
+    cursor: JetstreamCursor,
+    handler: Arc<Mutex<H>>,
+) {
+    let url = subscribe_url(&endpoint, cursor.resume_at());
+    let mut recording = open_recording().await;
+    let mut stream = match connect_async(url.as_str()).await {
+        Ok((stream, _)) => stream,
+        Err(e) => {
+            logs::log_jetstream_error(&e.to_string());
+            return;
+        }
+    };
+
+    while let Some(message) = stream.next().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(e) => {
+                logs::log_jetstream_error(&e.to_string());
+                break;
+            }
+        };
+        if let Some(recording) = recording.as_mut() {
+            if let Err(e) = record(recording, text.as_str()).await {
+                logs::log_jetstream_error(&format!("failed to record event: {e}"));
+            }
+        }
+        let Some((time_us, event)) = parse_event(text.as_str()) else {
+            continue;
+        };
+        dispatch(&mut *handler.lock().await, event).await;
+        cursor.advance(time_us);
+    }
+
+    if let Some(recording) = recording.as_mut() {
+        if let Err(e) = recording.flush().await {
+            logs::log_jetstream_error(&format!("failed to flush the recording: {e}"));
+        }
+    }
+}
+
+async fn record(recording: &mut BufWriter<File>, message: &str) -> io::Result<()> {
+    recording.write_all(message.as_bytes()).await?;
+    recording.write_all(b"\n").await
+}
+
+async fn dispatch<H: FeedHandler + RepostHandler>(handler: &mut H, event: JetstreamEvent) {
+    match event {
+        JetstreamEvent::Post(post) => handler.insert_post(*post).await,
+        JetstreamEvent::DeletePost(uri) => handler.delete_post(uri).await,
+        JetstreamEvent::Like { like_uri, post_uri } => {
+            handler.insert_like(like_uri, post_uri).await
+        }
+        JetstreamEvent::DeleteLike(uri) => handler.delete_like(uri).await,
+        JetstreamEvent::Repost {
+            repost_uri,
+            post_uri,
+        } => handler.insert_repost(repost_uri, post_uri).await,
+        JetstreamEvent::DeleteRepost(uri) => handler.delete_repost(uri).await,
+    }
+}
+
+/// Appends to `server.record_path`, if set, so the session can be replayed later. Writes are
+/// buffered and flushed when the connection ends.
+async fn open_recording() -> Option<BufWriter<File>> {
+    let path = settings().server.record_path.clone()?;
+    match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+    {
+        Ok(file) => Some(BufWriter::new(file)),
+        Err(e) => {
+            logs::log_jetstream_error(&format!("failed to open {path}: {e}"));
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    pub events: usize,
+    /// Lines that aren't a post, like or repost event, including identity and account events.
+    pub skipped: usize,
+}
+
+/// Feeds a recorded Jetstream session, one message per line as written by `server.record_path`,
+/// through `handler` as fast as it takes them, via the same calls as the live stream. `clock` is
+/// set to each event's time before it's handled, so the handler sees the recording's time.
+pub async fn replay<H: FeedHandler + RepostHandler, R: AsyncBufRead + Unpin>(
+    dump: R,
+    handler: Arc<Mutex<H>>,
+    clock: &ManualClock,
+) -> io::Result<ReplayStats> {
+    let mut stats = ReplayStats::default();
+    let mut lines = dump.lines();
+    while let Some(line) = lines.next_line().await? {
+        let Some((time_us, event)) = parse_event(&line) else {
+            stats.skipped += 1;
+            continue;
+        };
+        clock.set(time_us / 1_000_000);
+        dispatch(&mut *handler.lock().await, event).await;
+        stats.events += 1;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::time::Clock;
+    use skyfeed::{Embed, FeedRequest, FeedResult, Interaction, MediaEmbed};
+
+    #[test]
+    fn test_parse_post_with_quote_and_images() {
+        let message = r#"{"did":"did:plc:dev","time_us":1725911162329308,"kind":"commit","commit":{"rev":"3l3qo2vuowo2b","operation":"create","collection":"app.bsky.feed.post","rkey":"3l3qo2vutsw2b","cid":"bafyreia","record":{"$type":"app.bsky.feed.post","createdAt":"2024-09-09T19:46:02.102Z","langs":["en"],"text":"New boss fight!","labels":{"$type":"com.atproto.label.defs#selfLabels","values":[{"val":"graphic-media"}]},"embed":{"$type":"app.bsky.embed.recordWithMedia","record":{"$type":"app.bsky.embed.record","record":{"cid":"bafyreib","uri":"at://did:plc:other/app.bsky.feed.post/3kq"}},"media":{"$type":"app.bsky.embed.images","images":[{"alt":"boss arena","image":{"$type":"blob","ref":{"$link":"bafkreic"},"mimeType":"image/png","size":1024}}]}}}}}"#;
+
+        let (time_us, event) = parse_event(message).unwrap();
+        assert_eq!(time_us, 1725911162329308);
+        let JetstreamEvent::Post(post) = event else {
+            panic!("expected a post");
+        };
+        assert_eq!(
+            post.uri.0,
+            "at://did:plc:dev/app.bsky.feed.post/3l3qo2vutsw2b"
+        );
+        assert_eq!(post.author_did.0, "did:plc:dev");
+        assert_eq!(post.langs, vec!["en"]);
+        assert_eq!(post.labels, vec!["graphic-media"]);
+        assert_eq!(post.timestamp.timestamp(), 1725911162);
+        let Some(Embed::QuoteWithMedia(quoted, MediaEmbed::Images(images))) = &post.embed else {
+            panic!("expected a quote with images");
+        };
+        assert_eq!(quoted.0, "at://did:plc:other/app.bsky.feed.post/3kq");
+        assert_eq!(images[0].alt_text, "boss arena");
+        assert_eq!(images[0].cid.0, "bafkreic");
+    }
+
+    #[test]
+    fn test_parse_likes_and_deletes() {
+        let like = r#"{"did":"did:plc:fan","time_us":2,"kind":"commit","commit":{"operation":"create","collection":"app.bsky.feed.like","rkey":"3kl","record":{"$type":"app.bsky.feed.like","createdAt":"2024-09-09T19:46:02.102Z","subject":{"cid":"bafyreia","uri":"at://did:plc:dev/app.bsky.feed.post/3kp"}}}}"#;
+        let Some((2, JetstreamEvent::Like { like_uri, post_uri })) = parse_event(like) else {
+            panic!("expected a like");
+        };
+        assert_eq!(like_uri.0, "at://did:plc:fan/app.bsky.feed.like/3kl");
+        assert_eq!(post_uri.0, "at://did:plc:dev/app.bsky.feed.post/3kp");
+
+        let delete = r#"{"did":"did:plc:dev","time_us":3,"kind":"commit","commit":{"operation":"delete","collection":"app.bsky.feed.post","rkey":"3kp"}}"#;
+        let repost = r#"{"did":"did:plc:fan","time_us":5,"kind":"commit","commit":{"operation":"create","collection":"app.bsky.feed.repost","rkey":"3kr","record":{"$type":"app.bsky.feed.repost","createdAt":"2024-09-09T19:46:02.102Z","subject":{"cid":"bafyreia","uri":"at://did:plc:dev/app.bsky.feed.post/3kp"}}}}"#;
+        let Some((
+            5,
+            JetstreamEvent::Repost {
+                repost_uri,
+                post_uri,
+            },
+        )) = parse_event(repost)
+        else {
+            panic!("expected a repost");
+        };
+        assert_eq!(repost_uri.0, "at://did:plc:fan/app.bsky.feed.repost/3kr");
+        assert_eq!(post_uri.0, "at://did:plc:dev/app.bsky.feed.post/3kp");
+
+        assert!(matches!(
+            parse_event(delete),
+            Some((3, JetstreamEvent::DeletePost(uri))) if uri.0 == "at://did:plc:dev/app.bsky.feed.post/3kp"
+        ));
+        let unrepost = r#"{"did":"did:plc:fan","time_us":6,"kind":"commit","commit":{"operation":"delete","collection":"app.bsky.feed.repost","rkey":"3kr"}}"#;
+        assert!(matches!(
+            parse_event(unrepost),
+            Some((6, JetstreamEvent::DeleteRepost(uri))) if uri.0 == "at://did:plc:fan/app.bsky.feed.repost/3kr"
+        ));
+
+        let identity = r#"{"did":"did:plc:dev","time_us":4,"kind":"identity","identity":{}}"#;
+        assert!(parse_event(identity).is_none());
+        assert!(parse_event("not json").is_none());
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        posts: Vec<String>,
+        likes: Vec<String>,
+        reposts: Vec<String>,
+        deletes: usize,
+    }
+
+    impl RepostHandler for Recorder {
+        async fn insert_repost(&mut self, repost_uri: Uri, _reposted_post_uri: Uri) {
+            self.reposts.push(repost_uri.0);
+        }
+
+        async fn delete_repost(&mut self, _repost_uri: Uri) {
+            self.deletes += 1;
+        }
+    }
+
+    impl FeedHandler for Recorder {
+        async fn available_feeds(&mut self) -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn insert_post(&mut self, post: Post) {
+            self.posts.push(post.uri.0);
+        }
+
+        async fn delete_post(&mut self, _uri: Uri) {
+            self.deletes += 1;
+        }
+
+        async fn insert_like(&mut self, like_uri: Uri, _liked_post_uri: Uri) {
+            self.likes.push(like_uri.0);
+        }
+
+        async fn delete_like(&mut self, _like_uri: Uri) {
+            self.deletes += 1;
+        }
+
+        async fn serve_feed(&self, _request: FeedRequest) -> FeedResult {
+            FeedResult {
+                cursor: None,
+                feed: vec![],
+            }
+        }
+
+        async fn handle_interactions(&self, _user_did: Did, _interactions: Vec<Interaction>) {}
+    }
+
+    #[tokio::test]
+    async fn test_replay_drives_handler() {
+        let dump = [
+            r#"{"did":"did:plc:dev","time_us":1,"kind":"commit","commit":{"operation":"create","collection":"app.bsky.feed.post","rkey":"3kp","cid":"bafyreia","record":{"text":"Shipped the level editor","createdAt":"2024-09-09T19:46:02.102Z"}}}"#,
+            r#"{"did":"did:plc:dev","time_us":2,"kind":"identity","identity":{}}"#,
+            r#"{"did":"did:plc:fan","time_us":3,"kind":"commit","commit":{"operation":"create","collection":"app.bsky.feed.like","rkey":"3kl","record":{"subject":{"cid":"bafyreia","uri":"at://did:plc:dev/app.bsky.feed.post/3kp"}}}}"#,
+            r#"{"did":"did:plc:fan","time_us":4,"kind":"commit","commit":{"operation":"create","collection":"app.bsky.feed.repost","rkey":"3kr","record":{"subject":{"cid":"bafyreia","uri":"at://did:plc:dev/app.bsky.feed.post/3kp"}}}}"#,
+            r#"{"did":"did:plc:dev","time_us":1725911162329308,"kind":"commit","commit":{"operation":"delete","collection":"app.bsky.feed.post","rkey":"3kp"}}"#,
+        ]
+        .join("\n");
+        let handler = Arc::new(Mutex::new(Recorder::default()));
+        let clock = ManualClock::new(0);
+
+        let stats = replay(dump.as_bytes(), handler.clone(), &clock)
+            .await
+            .unwrap();
+        assert_eq!(clock.timestamp(), 1725911162);
+        assert_eq!(
+            stats,
+            ReplayStats {
+                events: 4,
+                skipped: 1
+            }
+        );
+
+        let recorder = handler.lock().await;
+        assert_eq!(
+            recorder.posts,
+            vec!["at://did:plc:dev/app.bsky.feed.post/3kp"]
+        );
+        assert_eq!(
+            recorder.likes,
+            vec!["at://did:plc:fan/app.bsky.feed.like/3kl"]
+        );
+        assert_eq!(
+            recorder.reposts,
+            vec!["at://did:plc:fan/app.bsky.feed.repost/3kr"]
+        );
+        assert_eq!(recorder.deletes, 1);
+    }
+
+    #[test]
+    fn test_cursor_resumes_with_overlap() {
+        let cursor = JetstreamCursor::default();
+        assert_eq!(cursor.resume_at(), None);
+        assert_eq!(
+            subscribe_url("wss://jetstream.example/subscribe", None),
+            "wss://jetstream.example/subscribe?wantedCollections=app.bsky.feed.post&wantedCollections=app.bsky.feed.like&wantedCollections=app.bsky.feed.repost"
+        );
+
+        cursor.advance(10_000_000);
+        cursor.advance(9_000_000);
+        assert_eq!(cursor.resume_at(), Some(10_000_000 - RESUME_OVERLAP_US));
+    }
+}
@@ -1,7 +1,31 @@
+pub mod analytics;
+pub mod archive;
 pub mod backfill;
+pub mod buffers;
+pub mod cleanup;
+pub mod config_history;
+pub mod cursor;
 pub mod db;
+pub mod diversity;
+pub mod embeddings;
+pub mod embeds;
 pub mod engagement;
+pub mod eval;
+pub mod explain;
+pub mod feed_server;
+pub mod firehose;
+pub mod follows;
+pub mod gateway;
+pub mod handles;
+pub mod lint;
+pub mod maintenance;
+pub mod negatives;
+pub mod ranking;
+pub mod rescore;
 pub mod schema;
 pub mod scoring;
 pub mod settings;
+pub mod snapshot;
+pub mod translation;
 pub mod utils;
+pub mod webhooks;
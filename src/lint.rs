@@ -0,0 +1,333 @@
+use crate::settings::Filters;
+use std::collections::HashSet;
+
+/// Blocked keywords are matched as substrings, so short ones also hit unrelated words.
+const AMBIGUOUS_MAX_LEN: usize = 4;
+const VOWELS: &[char] = &['a', 'e', 'i', 'o', 'u'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    pub list: String,
+    pub entry: String,
+    pub message: String,
+}
+
+impl LintWarning {
+    fn new(list: &str, entry: &str, message: impl Into<String>) -> Self {
+        Self {
+            list: list.to_string(),
+            entry: entry.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+struct Lists<'a> {
+    prefix: String,
+    gamedev_keywords: &'a [String],
+    gamedev_hashtags: &'a [String],
+    blocked_keywords: &'a [String],
+    promo_domains: &'a [String],
+}
+
+pub fn lint_filters(filters: &Filters) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+
+    let mut lists = vec![Lists {
+        prefix: "filters".to_string(),
+        gamedev_keywords: &filters.gamedev_keywords,
+        gamedev_hashtags: &filters.gamedev_hashtags,
+        blocked_keywords: &filters.blocked_keywords,
+        promo_domains: &filters.promo_domains,
+    }];
+    lists.extend(filters.languages.iter().map(|l| Lists {
+        prefix: format!("filters.languages[{}]", l.code),
+        gamedev_keywords: &l.gamedev_keywords,
+        gamedev_hashtags: &l.gamedev_hashtags,
+        blocked_keywords: &l.blocked_keywords,
+        promo_domains: &l.promo_domains,
+    }));
+
+    for lists in &lists {
+        let name = |list: &str| format!("{}.{}", lists.prefix, list);
+
+        check_duplicates(
+            &name("gamedev_keywords"),
+            lists.gamedev_keywords,
+            &mut warnings,
+        );
+        check_duplicates(
+            &name("gamedev_hashtags"),
+            lists.gamedev_hashtags,
+            &mut warnings,
+        );
+        check_duplicates(
+            &name("blocked_keywords"),
+            lists.blocked_keywords,
+            &mut warnings,
+        );
+        check_duplicates(&name("promo_domains"), lists.promo_domains, &mut warnings);
+
+        check_word_shadowing(
+            &name("gamedev_keywords"),
+            lists.gamedev_keywords,
+            &mut warnings,
+        );
+        check_substring_shadowing(
+            &name("blocked_keywords"),
+            lists.blocked_keywords,
+            &mut warnings,
+        );
+        check_substring_shadowing(&name("promo_domains"), lists.promo_domains, &mut warnings);
+
+        check_hashtag_prefix(
+            &name("gamedev_hashtags"),
+            lists.gamedev_hashtags,
+            &mut warnings,
+        );
+        check_ambiguous(
+            &name("blocked_keywords"),
+            lists.blocked_keywords,
+            &mut warnings,
+        );
+        check_blocked_overlap(
+            &name("blocked_keywords"),
+            lists.blocked_keywords,
+            lists.gamedev_keywords.iter().chain(lists.gamedev_hashtags),
+            &mut warnings,
+        );
+        check_promo_in_neutral(
+            &name("promo_domains"),
+            lists.promo_domains,
+            lists.gamedev_keywords.iter().chain(lists.gamedev_hashtags),
+            &mut warnings,
+        );
+    }
+
+    check_duplicates(
+        "filters.blocked_hashtags",
+        &filters.blocked_hashtags,
+        &mut warnings,
+    );
+    check_substring_shadowing(
+        "filters.blocked_hashtags",
+        &filters.blocked_hashtags,
+        &mut warnings,
+    );
+    check_hashtag_prefix(
+        "filters.blocked_hashtags",
+        &filters.blocked_hashtags,
+        &mut warnings,
+    );
+    check_blocked_overlap(
+        "filters.blocked_hashtags",
+        &filters.blocked_hashtags,
+        filters.gamedev_hashtags.iter(),
+        &mut warnings,
+    );
+    check_unused_weights(filters, &mut warnings);
+
+    warnings
+}
+
+/// Weights are looked up by keyword, so one for a keyword no list has never applies.
+fn check_unused_weights(filters: &Filters, warnings: &mut Vec<LintWarning>) {
+    let keywords: HashSet<String> = filters
+        .gamedev_keywords
+        .iter()
+        .chain(filters.languages.iter().flat_map(|l| &l.gamedev_keywords))
+        .map(|kw| kw.to_lowercase())
+        .collect();
+    let mut weighted: Vec<&String> = filters.keyword_weights.keys().collect();
+    weighted.sort();
+    for keyword in weighted {
+        if !keywords.contains(&keyword.to_lowercase()) {
+            warnings.push(LintWarning::new(
+                "filters.keyword_weights",
+                keyword,
+                "not in any gamedev_keywords list, so it never applies",
+            ));
+        }
+    }
+}
+
+fn check_duplicates(list: &str, entries: &[String], warnings: &mut Vec<LintWarning>) {
+    let mut seen = HashSet::new();
+    for entry in entries {
+        if !seen.insert(entry.trim().to_lowercase()) {
+            warnings.push(LintWarning::new(list, entry, "duplicate entry, remove it"));
+        }
+    }
+}
+
+/// Keywords match whole words, so a phrase containing another keyword's words never adds a match.
+fn check_word_shadowing(list: &str, entries: &[String], warnings: &mut Vec<LintWarning>) {
+    let words = |entry: &str| -> Vec<String> {
+        entry
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+
+    for entry in entries {
+        let entry_words = words(entry);
+        let shadow = entries.iter().find(|other| {
+            let other_words = words(other);
+            other_words.len() < entry_words.len()
+                && entry_words
+                    .windows(other_words.len().max(1))
+                    .any(|window| window == other_words.as_slice())
+        });
+        if let Some(shadow) = shadow {
+            warnings.push(LintWarning::new(
+                list,
+                entry,
+                format!("already matched by \"{shadow}\", remove it"),
+            ));
+        }
+    }
+}
+
+/// Blocklists and promo domains match substrings, so an entry containing another is redundant.
+fn check_substring_shadowing(list: &str, entries: &[String], warnings: &mut Vec<LintWarning>) {
+    for entry in entries {
+        let lower = entry.to_lowercase();
+        let shadow = entries.iter().find(|other| {
+            let other = other.to_lowercase();
+            other.len() < lower.len() && lower.contains(&other)
+        });
+        if let Some(shadow) = shadow {
+            warnings.push(LintWarning::new(
+                list,
+                entry,
+                format!("already matched by \"{shadow}\", remove it"),
+            ));
+        }
+    }
+}
+
+fn check_hashtag_prefix(list: &str, entries: &[String], warnings: &mut Vec<LintWarning>) {
+    for entry in entries.iter().filter(|e| !e.starts_with('#')) {
+        warnings.push(LintWarning::new(
+            list,
+            entry,
+            format!("hashtag never matches without '#', use \"#{entry}\""),
+        ));
+    }
+}
+
+/// Short words such as "mint" or "ico" also match inside "minted" or "icon". Acronyms and
+/// tokens without vowels or with digits, like "nft" or "web3", rarely turn up inside words.
+fn check_ambiguous(list: &str, entries: &[String], warnings: &mut Vec<LintWarning>) {
+    for entry in entries {
+        let trimmed = entry.trim().to_lowercase();
+        let word_like = trimmed.contains(VOWELS) && !trimmed.contains(|c: char| c.is_ascii_digit());
+        if trimmed.chars().count() <= AMBIGUOUS_MAX_LEN && !trimmed.contains(' ') && word_like {
+            warnings.push(LintWarning::new(
+                list,
+                entry,
+                "short keyword also matches inside longer words, consider a more specific phrase",
+            ));
+        }
+    }
+}
+
+fn check_blocked_overlap<'a>(
+    list: &str,
+    blocked: &[String],
+    relevant: impl Iterator<Item = &'a String> + Clone,
+    warnings: &mut Vec<LintWarning>,
+) {
+    for entry in blocked {
+        let lower = entry.to_lowercase();
+        if let Some(relevant) = relevant.clone().find(|r| r.to_lowercase().contains(&lower)) {
+            warnings.push(LintWarning::new(
+                list,
+                entry,
+                format!("rejects every post matching \"{relevant}\""),
+            ));
+        }
+    }
+}
+
+fn check_promo_in_neutral<'a>(
+    list: &str,
+    promo_domains: &[String],
+    neutral: impl Iterator<Item = &'a String> + Clone,
+    warnings: &mut Vec<LintWarning>,
+) {
+    for domain in promo_domains {
+        let name = domain
+            .to_lowercase()
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if name.len() < 3 {
+            continue;
+        }
+        if let Some(neutral) = neutral.clone().find(|n| {
+            let n = n.trim_start_matches('#').to_lowercase();
+            n == name || n == domain.to_lowercase()
+        }) {
+            warnings.push(LintWarning::new(
+                list,
+                domain,
+                format!("also listed as gamedev signal \"{neutral}\""),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use std::collections::HashMap;
+
+    fn filters() -> Filters {
+        let mut filters = Settings::default().filters;
+        filters.gamedev_keywords = vec!["godot".into(), "godot engine".into(), "Godot".into()];
+        filters.gamedev_hashtags = vec!["#gamedev".into(), "indiedev".into(), "#itch".into()];
+        filters.blocked_keywords = vec![
+            "crypto".into(),
+            "cryptocurrency".into(),
+            "nft".into(),
+            "mint".into(),
+        ];
+        filters.blocked_hashtags = vec!["#nft".into()];
+        filters.promo_domains = vec!["itch.io".into()];
+        filters.keyword_weights = HashMap::from([("godot".into(), 0.8), ("sdl".into(), 0.4)]);
+        filters.languages.clear();
+        filters
+    }
+
+    #[test]
+    fn test_lint_filters() {
+        let warnings = lint_filters(&filters());
+        let has = |list: &str, entry: &str| {
+            warnings
+                .iter()
+                .any(|w| w.list == format!("filters.{list}") && w.entry == entry)
+        };
+
+        assert!(has("gamedev_keywords", "Godot"));
+        assert!(has("gamedev_keywords", "godot engine"));
+        assert!(has("blocked_keywords", "cryptocurrency"));
+        assert!(has("blocked_keywords", "mint"));
+        assert!(!has("blocked_keywords", "nft"));
+        assert!(has("gamedev_hashtags", "indiedev"));
+        assert!(has("promo_domains", "itch.io"));
+        assert!(!has("gamedev_keywords", "godot"));
+        assert!(!has("blocked_keywords", "crypto"));
+        assert!(has("keyword_weights", "sdl"));
+        assert!(!has("keyword_weights", "godot"));
+    }
+
+    #[test]
+    fn test_default_filters_lint_clean() {
+        assert_eq!(lint_filters(&Settings::default().filters), Vec::new());
+    }
+}
@@ -1,69 +1,281 @@
+mod admin;
+mod analytics;
+mod api;
+mod archive;
 mod backfill;
+mod buffers;
+mod cleanup;
+mod config_history;
+mod cursor;
 mod db;
+mod diversity;
+mod embeddings;
+mod embeds;
 mod engagement;
+mod explain;
+mod feed_server;
+mod firehose;
+mod follows;
+mod gateway;
 mod handler;
+mod handles;
+mod jetstream;
+mod maintenance;
+mod negatives;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod ranking;
+mod rescore;
 mod schema;
 pub mod scoring;
 pub mod settings;
+mod shadow;
+mod translation;
 pub mod utils;
+mod webhooks;
 
-use anyhow::Result;
-use db::{configure_connection, establish_pool};
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use anyhow::{Context, Result};
+use db::{establish_pool, establish_read_pool, DbPool};
 use handler::GameDevFeedHandler;
-use scoring::MLHandle;
-use settings::settings;
+use scoring::{MLHandle, VectorIndex};
+use settings::{settings, IngestMode};
 use skyfeed::{start, Config};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
-use utils::logs;
-
-const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+use utils::time::{job_period_mins, job_period_secs, ManualClock, SharedClock};
+use utils::{logs, systemd};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenvy::dotenv().ok();
 
     let s = settings();
-    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "feed.db".to_string());
+    let database_url =
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| s.server.database_url.clone());
     let port: u16 = std::env::var("PORT")
         .ok()
         .and_then(|p| p.parse().ok())
         .unwrap_or(3030);
+    let api_port: u16 = std::env::var("API_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(3031);
+    let admin_port: u16 = std::env::var("ADMIN_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(3032);
+
+    // Brings the database up to date and exits, for deploy pipelines that migrate before
+    // swapping the binary in.
+    if std::env::args().skip(1).any(|arg| arg == "--migrate-only") {
+        establish_pool(&database_url);
+        logs::log_migrations_done(&database_url);
+        return Ok(());
+    }
+    // Rebuilds the database so maintenance can return free pages. Run with the feed stopped.
+    if std::env::args()
+        .skip(1)
+        .any(|arg| arg == "--enable-incremental-vacuum")
+    {
+        let pool = establish_pool(&database_url);
+        let mut conn = pool.get().context("failed to open the database")?;
+        let rebuilt = maintenance::enable_incremental_vacuum(&mut conn)
+            .context("failed to enable incremental vacuum")?;
+        logs::log_incremental_vacuum_enabled(&database_url, rebuilt);
+        return Ok(());
+    }
 
     logs::log_init(&s.server.feed_hostname, port, s.server.enable_backfill);
+    if let Some(profile) = settings::active_profile() {
+        logs::log_settings_profile(&profile);
+    }
 
     if let Err(e) = settings::spawn_settings_watcher() {
         eprintln!("warning: failed to start settings watcher: {e}");
     }
 
-    let pool = establish_pool(&database_url);
+    let bind_ip: IpAddr = s
+        .server
+        .bind_address
+        .parse()
+        .with_context(|| format!("invalid server.bind_address {:?}", s.server.bind_address))?;
+    let admin_bind_ip: IpAddr = s.server.admin_bind_address.parse().with_context(|| {
+        format!(
+            "invalid server.admin_bind_address {:?}",
+            s.server.admin_bind_address
+        )
+    })?;
 
+    systemd::notify_status("running migrations");
+    let pool = establish_pool(&database_url);
+    let read_pool = establish_read_pool(&database_url);
     {
         let mut conn = pool.get().expect("Failed to get initial connection");
-        configure_connection(&mut conn).expect("Failed to configure SQLite connection");
-        conn.run_pending_migrations(MIGRATIONS)
-            .expect("Failed to run database migrations");
+        let now = chrono::Utc::now().timestamp();
+        if let Err(e) = config_history::record_active_config(&mut conn, now) {
+            eprintln!("warning: failed to record the active config: {e}");
+        }
     }
 
+    systemd::notify_status("loading models");
     logs::log_ml_loading();
-    let ml_handle = MLHandle::spawn()?;
+    let embedding_store = embeddings::EmbeddingStore::new(pool.clone());
+    let ml_handle = MLHandle::spawn_with_sink(Some(embedding_store.spawn_writer()))?;
     logs::log_ml_ready();
 
     if s.server.enable_backfill {
+        systemd::notify_status("backfilling");
         backfill::run_backfill(pool.clone(), &ml_handle).await;
     }
 
-    let handler = Arc::new(Mutex::new(GameDevFeedHandler::new(pool, ml_handle)));
+    let vector_index = load_vector_index(&pool).shared();
+
+    let firehose_monitor = firehose::FirehoseMonitor::default();
+    let buffer_monitor = buffers::BufferMonitor::default();
+    let cleanup_monitor = cleanup::CleanupMonitor::default();
+    let api_state = api::ApiState::new(
+        read_pool.clone(),
+        vector_index.clone(),
+        firehose_monitor.clone(),
+        buffer_monitor.clone(),
+        cleanup_monitor.clone(),
+        ml_handle.clone(),
+    );
+    tokio::spawn(async move {
+        if let Err(e) = api::serve(api_state, SocketAddr::new(bind_ip, api_port)).await {
+            eprintln!("warning: api server stopped: {e}");
+        }
+    });
+    let admin_state = admin::AdminState::new(pool.clone(), ml_handle.clone(), vector_index.clone());
+    tokio::spawn(async move {
+        let addr = SocketAddr::new(admin_bind_ip, admin_port);
+        if let Err(e) = admin::serve(admin_state, addr).await {
+            eprintln!("warning: admin api server stopped: {e}");
+        }
+    });
+
+    if s.server.enable_backfill {
+        let gap_pool = pool.clone();
+        let gap_ml_handle = ml_handle.clone();
+        tokio::spawn(async move {
+            let period = job_period_secs(settings().backfill.gap_poll_interval_secs);
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                backfill::run_gap_backfill(gap_pool.clone(), &gap_ml_handle).await;
+            }
+        });
+    }
+
+    let gateway_pool = read_pool.clone();
+    let rescore_pool = pool.clone();
+    // A replay runs on the recording's time, so post ages and the feed window follow it.
+    let replay_clock = ManualClock::new(chrono::Utc::now().timestamp());
+    let clock: SharedClock = match s.server.ingest_mode {
+        IngestMode::Replay => Arc::new(replay_clock.clone()),
+        _ => utils::time::system_clock(),
+    };
+    let rescore_clock = clock.clone();
+    let cleanup_clock = clock.clone();
+    let maintenance_pool = pool.clone();
+    let flush_monitor = buffer_monitor.clone();
+    let maintenance_monitor = buffer_monitor.clone();
+    let handler = Arc::new(Mutex::new(
+        GameDevFeedHandler::new(
+            pool,
+            ml_handle,
+            vector_index,
+            firehose_monitor.clone(),
+            buffer_monitor,
+            clock,
+        )
+        .with_read_pool(read_pool),
+    ));
 
     let handler_flush = handler.clone();
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(10));
+        let mut delay = Duration::from_millis(settings().flush.min_interval_ms);
         loop {
-            interval.tick().await;
-            let mut h = handler_flush.lock().await;
-            let _ = h.flush_pending();
+            tokio::time::sleep(delay).await;
+            let flushed = {
+                let mut handler = handler_flush.lock().await;
+                handler.process_prepared_posts().await;
+                handler.flush_pending().unwrap_or_else(|e| {
+                    logs::log_flush_failed(&e.to_string());
+                    0
+                })
+            };
+            flush_monitor.record_flush(flushed);
+            delay = buffers::next_flush_interval(delay, flushed, &settings().flush);
+        }
+    });
+
+    let handler_maintenance = handler.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(job_period_mins(settings().maintenance.interval_mins)).await;
+            if !settings().maintenance.enabled {
+                continue;
+            }
+            // Holding the handler keeps flushes from writing while the WAL is truncated. The
+            // buffers are checked once it's held, since they fill while waiting for it.
+            let _handler = handler_maintenance.lock().await;
+            let max_vacuum_pages = {
+                let s = settings();
+                let status = maintenance_monitor.status();
+                if let Some(reason) =
+                    maintenance::busy_reason(&status, &s.maintenance, s.flush.idle_max_entries)
+                {
+                    logs::log_maintenance_skipped(&reason);
+                    continue;
+                }
+                s.maintenance.max_vacuum_pages
+            };
+            let Some(mut conn) = db::get_connection(&maintenance_pool).await else {
+                logs::log_pool_exhausted("maintenance");
+                continue;
+            };
+            match maintenance::run_maintenance(&mut conn, max_vacuum_pages) {
+                Ok(report) => logs::log_maintenance(&report),
+                Err(e) => logs::log_maintenance_failed(&e.to_string()),
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(job_period_mins(settings().rescore.interval_mins)).await;
+            let s = settings();
+            if !s.rescore.enabled {
+                continue;
+            }
+            let Some(mut conn) = db::get_connection(&rescore_pool).await else {
+                logs::log_pool_exhausted("rescore");
+                continue;
+            };
+            let now = rescore_clock.timestamp();
+            let since = now - (s.feed.cutoff_hours * 3600);
+            if let Ok(updated) = rescore::rescore_recent_posts(&mut conn, since, now) {
+                logs::log_rescore(updated);
+            }
+        }
+    });
+
+    let handler_negatives = handler.clone();
+    tokio::spawn(async move {
+        loop {
+            let period = settings().scoring.negative_examples.harvest_interval_mins;
+            tokio::time::sleep(job_period_mins(period)).await;
+            if settings().scoring.negative_examples.enabled {
+                handler_negatives
+                    .lock()
+                    .await
+                    .harvest_negative_examples()
+                    .ok();
+            }
         }
     });
 
@@ -73,22 +285,135 @@ async fn main() -> Result<()> {
         loop {
             interval.tick().await;
             let h = handler_cleanup.lock().await;
-            let _ = h.cleanup_old_posts();
+            match h.cleanup_old_posts() {
+                Ok(Some(report)) => cleanup_monitor.record(&report, cleanup_clock.timestamp()),
+                Ok(None) => cleanup_monitor.record_skipped(),
+                Err(_) => {}
+            }
         }
     });
 
-    let config = Config {
+    if let Some(interval) = systemd::watchdog_interval() {
+        let handler_watchdog = handler.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let _h = handler_watchdog.lock().await;
+                systemd::ping_watchdog();
+            }
+        });
+    }
+
+    systemd::notify_ready();
+
+    let upstream = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), s.server.internal_port);
+    let gateway_state = gateway::GatewayState::new(upstream, gateway_pool);
+    tokio::spawn(async move {
+        if let Err(e) = gateway::serve(gateway_state, SocketAddr::new(bind_ip, port)).await {
+            eprintln!("error: feed gateway stopped: {e}");
+            std::process::exit(1);
+        }
+    });
+
+    match s.server.ingest_mode {
+        IngestMode::Firehose => {
+            serve_feed(handler.clone(), upstream);
+
+            // skyfeed always serves alongside its subscription, so it gets a throwaway loopback
+            // port and the feed stays up while the subscription backs off.
+            let skyfeed_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+            firehose::supervise(firehose_monitor, || {
+                start(
+                    skyfeed_config(),
+                    s.server.firehose_limit,
+                    handler.clone(),
+                    skyfeed_addr,
+                )
+            })
+            .await
+        }
+        IngestMode::Jetstream => {
+            serve_feed(handler.clone(), upstream);
+
+            let cursor = jetstream::JetstreamCursor::default();
+            firehose::supervise(firehose_monitor, || {
+                jetstream::connect(
+                    s.server.jetstream_url.clone(),
+                    cursor.clone(),
+                    handler.clone(),
+                )
+            })
+            .await
+        }
+        IngestMode::Replay => {
+            serve_feed(handler.clone(), upstream);
+
+            let path = s.server.replay_path.clone();
+            let dump = tokio::fs::File::open(&path)
+                .await
+                .with_context(|| format!("failed to open {path}"))?;
+            let stats = jetstream::replay(
+                tokio::io::BufReader::new(dump),
+                handler.clone(),
+                &replay_clock,
+            )
+            .await
+            .with_context(|| format!("failed to read {path}"))?;
+            {
+                let mut replayed = handler.lock().await;
+                replayed.finish_prepared_posts().await;
+                replayed
+                    .flush_pending()
+                    .context("failed to flush the replayed posts")?;
+            }
+            logs::log_replay_finished(stats.events, stats.skipped, &path);
+
+            // Keep serving the replayed feed until stopped.
+            tokio::signal::ctrl_c().await.ok();
+        }
+    }
+
+    systemd::notify_stopping();
+    Ok(())
+}
+
+/// Serves the feed endpoints behind the gateway, independently of whichever source is
+/// supplying posts, so reconnecting to it never takes the feed down.
+fn serve_feed(handler: Arc<Mutex<GameDevFeedHandler>>, upstream: SocketAddr) {
+    tokio::spawn(async move {
+        if let Err(e) = feed_server::serve(handler, upstream).await {
+            eprintln!("error: feed server stopped: {e}");
+            std::process::exit(1);
+        }
+    });
+}
+
+fn skyfeed_config() -> Config {
+    let s = settings();
+    Config {
         publisher_did: s.server.publisher_did.clone(),
         feed_generator_hostname: s.server.feed_hostname.clone(),
-    };
+    }
+}
 
-    start(
-        config,
-        s.server.firehose_limit,
-        handler,
-        ([0, 0, 0, 0], port),
-    )
-    .await;
+fn load_vector_index(pool: &DbPool) -> VectorIndex {
+    let s = settings();
+    let mut index = VectorIndex::new(
+        s.semantic.index_connections,
+        s.semantic.index_ef_construction,
+        s.semantic.index_ef_search,
+    );
+    let cutoff = chrono::Utc::now().timestamp() - (s.feed.cutoff_hours * 3600);
 
-    Ok(())
+    if let Ok(mut conn) = pool.get() {
+        if let Ok(embeddings) = db::get_feed_embeddings(&mut conn, cutoff) {
+            for (uri, embedding) in embeddings {
+                index.insert(uri, embedding);
+            }
+        }
+    }
+
+    logs::log_index_loaded(index.len());
+    index
 }
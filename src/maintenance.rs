@@ -0,0 +1,132 @@
+use crate::buffers::BufferStatus;
+use crate::settings::Maintenance;
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use diesel::sqlite::SqliteConnection;
+
+/// `PRAGMA auto_vacuum` mode that lets `incremental_vacuum` return free pages.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+#[derive(QueryableByName)]
+struct PragmaValue {
+    #[diesel(sql_type = BigInt)]
+    value: i64,
+}
+
+#[derive(QueryableByName)]
+struct Checkpoint {
+    #[diesel(sql_type = BigInt)]
+    busy: i64,
+    #[diesel(sql_type = BigInt)]
+    checkpointed: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Incremental vacuuming is on. Without it, no pages are reclaimed.
+    pub incremental_vacuum: bool,
+    /// Free pages returned to the filesystem.
+    pub reclaimed_pages: i64,
+    /// WAL pages copied into the database before it was truncated.
+    pub checkpointed_pages: i64,
+    /// A reader kept the checkpoint from finishing, so the WAL wasn't truncated.
+    pub checkpoint_busy: bool,
+    pub page_size: i64,
+}
+
+fn pragma_value(conn: &mut SqliteConnection, name: &str) -> QueryResult<i64> {
+    diesel::sql_query(format!("SELECT {name} AS value FROM pragma_{name}"))
+        .get_result::<PragmaValue>(conn)
+        .map(|row| row.value)
+}
+
+/// Deletes only add pages to the freelist unless incremental vacuuming is on, and turning it on
+/// for an existing database takes a full `VACUUM`. That rewrites the whole file, needs about as
+/// much free disk again and blocks writers until it's done, so it's only run on request, with
+/// the feed stopped. Returns whether the rebuild was needed.
+pub fn enable_incremental_vacuum(conn: &mut SqliteConnection) -> QueryResult<bool> {
+    if pragma_value(conn, "auto_vacuum")? == AUTO_VACUUM_INCREMENTAL {
+        return Ok(false);
+    }
+    conn.batch_execute("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+    // VACUUM may renumber posts, and the search index refers to them by rowid.
+    conn.batch_execute("INSERT INTO posts_fts (posts_fts) VALUES ('rebuild');")?;
+    Ok(true)
+}
+
+/// Frees up to `max_vacuum_pages` pages if incremental vacuuming is on, then checkpoints the WAL
+/// and truncates it.
+pub fn run_maintenance(
+    conn: &mut SqliteConnection,
+    max_vacuum_pages: i64,
+) -> QueryResult<MaintenanceReport> {
+    let incremental_vacuum = pragma_value(conn, "auto_vacuum")? == AUTO_VACUUM_INCREMENTAL;
+
+    let mut reclaimed_pages = 0;
+    if incremental_vacuum {
+        // `incremental_vacuum` frees one page per step, so it has to run through `batch_execute`
+        // rather than a prepared statement stepped once.
+        let free_before = pragma_value(conn, "freelist_count")?;
+        conn.batch_execute(&format!(
+            "PRAGMA incremental_vacuum({});",
+            max_vacuum_pages.max(1)
+        ))?;
+        reclaimed_pages = free_before - pragma_value(conn, "freelist_count")?;
+    }
+
+    let checkpoint =
+        diesel::sql_query("PRAGMA wal_checkpoint(TRUNCATE)").get_result::<Checkpoint>(conn)?;
+
+    Ok(MaintenanceReport {
+        incremental_vacuum,
+        reclaimed_pages,
+        checkpointed_pages: checkpoint.checkpointed.max(0),
+        checkpoint_busy: checkpoint.busy != 0,
+        page_size: pragma_value(conn, "page_size")?,
+    })
+}
+
+/// Why maintenance should wait for a quieter moment, if the buffers say ingest is busy.
+pub fn busy_reason(
+    buffers: &BufferStatus,
+    maintenance: &Maintenance,
+    idle_max_entries: usize,
+) -> Option<String> {
+    if buffers.occupancy > maintenance.max_buffer_occupancy {
+        return Some(format!("buffers {:.0}% full", buffers.occupancy * 100.0));
+    }
+    if buffers.last_flush_entries > idle_max_entries {
+        return Some(format!(
+            "last flush wrote {} entries",
+            buffers.last_flush_entries
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffers::BufferMonitor;
+    use crate::settings::settings;
+
+    #[test]
+    fn test_busy_reason() {
+        let s = settings();
+        let idle_max = s.flush.idle_max_entries;
+        let monitor = BufferMonitor::default();
+        assert_eq!(
+            busy_reason(&monitor.status(), &s.maintenance, idle_max),
+            None
+        );
+
+        monitor.record_flush(idle_max + 1);
+        assert!(busy_reason(&monitor.status(), &s.maintenance, idle_max).is_some());
+
+        monitor.record_flush(0);
+        monitor.observe(s.flush.max_pending_posts, 0);
+        let reason = busy_reason(&monitor.status(), &s.maintenance, idle_max);
+        assert_eq!(reason.as_deref(), Some("buffers 100% full"));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::db;
+use crate::scoring::{cosine_similarity, Embedding};
+use crate::settings::settings;
+use diesel::sqlite::SqliteConnection;
+use diesel::QueryResult;
+use std::sync::{Arc, RwLock};
+
+/// Embeddings of posts readers repeatedly asked to see less of, kept in memory so new posts can
+/// be compared against them while scoring.
+#[derive(Clone, Default)]
+pub struct NegativeSet {
+    embeddings: Arc<RwLock<Vec<Embedding>>>,
+}
+
+impl NegativeSet {
+    /// Adds newly disliked posts to the stored examples and reloads the newest ones.
+    pub fn harvest(&self, conn: &mut SqliteConnection, now: i64) -> QueryResult<usize> {
+        let min_count = settings().scoring.negative_examples.min_request_less as i64;
+        let added = db::harvest_negative_examples(conn, min_count, now)?;
+        self.reload(conn)?;
+        Ok(added)
+    }
+
+    pub fn reload(&self, conn: &mut SqliteConnection) -> QueryResult<()> {
+        let limit = settings().scoring.negative_examples.max_examples;
+        let embeddings = db::get_negative_embeddings(conn, limit)?;
+        if let Ok(mut current) = self.embeddings.write() {
+            *current = embeddings;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.embeddings.read().map_or(true, |e| e.is_empty())
+    }
+
+    /// Similarity of `embedding` to the closest negative example, or 0 with none.
+    pub fn closest(&self, embedding: &[f32]) -> f32 {
+        let Ok(embeddings) = self.embeddings.read() else {
+            return 0.0;
+        };
+        closest_similarity(&embeddings, embedding)
+    }
+}
+
+fn closest_similarity(examples: &[Embedding], embedding: &[f32]) -> f32 {
+    examples
+        .iter()
+        .map(|example| cosine_similarity(example, embedding))
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_similarity() {
+        let examples = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert!((closest_similarity(&examples, &[1.0, 0.1]) - 0.995).abs() < 1e-3);
+        assert_eq!(closest_similarity(&examples, &[-1.0, -1.0]), 0.0);
+        assert_eq!(closest_similarity(&[], &[1.0, 0.0]), 0.0);
+    }
+}
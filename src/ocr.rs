@@ -0,0 +1,78 @@
+use skyfeed::{Embed, ImageEmbed, MediaEmbed};
+use std::process::Stdio;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+const IMAGE_CDN: &str = "https://cdn.bsky.app/img/feed_fullsize/plain";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Posts read at once; image-only posts arriving while all are busy go without OCR.
+const MAX_CONCURRENT_POSTS: usize = 4;
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+static PERMITS: LazyLock<Arc<Semaphore>> =
+    LazyLock::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_POSTS)));
+
+/// A slot to read one post's images in, held until it's done.
+pub fn try_reserve() -> Option<OwnedSemaphorePermit> {
+    PERMITS.clone().try_acquire_owned().ok()
+}
+
+fn images(embed: Option<&Embed>) -> &[ImageEmbed] {
+    match embed {
+        Some(Embed::Images(images))
+        | Some(Embed::QuoteWithMedia(_, MediaEmbed::Images(images))) => images,
+        _ => &[],
+    }
+}
+
+/// Text read from the first `max_images` images of a post, one entry per image with any.
+pub async fn read_images(
+    author_did: &str,
+    embed: Option<&Embed>,
+    max_images: usize,
+) -> Vec<String> {
+    let mut texts = Vec::new();
+    for image in images(embed).iter().take(max_images) {
+        let url = format!("{IMAGE_CDN}/{author_did}/{}@jpeg", image.cid.0);
+        if let Some(text) = read_image(&url).await {
+            texts.push(text);
+        }
+    }
+    texts
+}
+
+async fn read_image(url: &str) -> Option<String> {
+    let bytes = CLIENT
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .bytes()
+        .await
+        .ok()?;
+
+    let mut tesseract = Command::new("tesseract")
+        .args(["stdin", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .ok()?;
+    let mut stdin = tesseract.stdin.take()?;
+    stdin.write_all(&bytes).await.ok()?;
+    drop(stdin);
+
+    let output = tesseract.wait_with_output().await.ok()?;
+    let text = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
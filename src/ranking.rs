@@ -0,0 +1,658 @@
+use crate::analytics::{hour_balance_multipliers, local_slot};
+use crate::cursor::{rank_order, RankKey};
+use crate::db::{self, Post};
+use crate::diversity::cap_authors;
+use crate::scoring::{
+    bound_priority, cosine_similarity, exposure_probability, thread_boost, time_decay,
+    ConfidenceTier, Embedding, TopicLabel,
+};
+use crate::settings::settings;
+use diesel::sqlite::SqliteConnection;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// What ranking needs to know about the user a feed is served to.
+#[derive(Debug, Clone, Default)]
+pub struct Reader {
+    pub seen: HashSet<String>,
+    /// Authors of posts the user asked to see more of.
+    pub boosted_authors: HashSet<String>,
+    /// Authors of posts the user asked to see less of.
+    pub penalized_authors: HashSet<String>,
+    /// Authors of posts the user clicked through or shared.
+    pub engaged_authors: HashSet<String>,
+    /// Accounts the user follows on Bluesky.
+    pub follows: Arc<HashSet<String>>,
+    /// Average embedding of the posts the user liked or asked to see more of.
+    pub interests: Option<Embedding>,
+    /// Similarity of each candidate post to `interests`, filled by `match_interests`.
+    pub interest_similarity: HashMap<String, f32>,
+    /// Share of the user's decayed likes that went to each topic.
+    pub topic_affinity: HashMap<TopicLabel, f32>,
+    /// Decayed likes of each author's posts.
+    pub author_affinity: HashMap<String, f32>,
+}
+
+impl Reader {
+    /// Loads what `did` has seen since `cutoff`, which authors they asked for more or less of or
+    /// engaged with, their interest profile and what they like as of `now`. Lookups that fail
+    /// count as no history.
+    pub fn load(conn: &mut SqliteConnection, did: &str, cutoff: i64, now: i64) -> Self {
+        let seen = db::get_user_seen_posts(conn, did, cutoff)
+            .map(|posts| posts.into_iter().collect())
+            .unwrap_or_default();
+
+        let mut boosted_authors = HashSet::new();
+        let mut penalized_authors = HashSet::new();
+        for pref in db::get_user_preferences(conn, did).unwrap_or_default() {
+            if let Some(author) = db::get_post_author(conn, &pref.post_uri) {
+                if pref.is_request_more {
+                    boosted_authors.insert(author);
+                } else {
+                    penalized_authors.insert(author);
+                }
+            }
+        }
+
+        let engaged_authors = db::get_user_engaged_posts(conn, did)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|uri| db::get_post_author(conn, uri))
+            .collect();
+
+        let profile = &settings().feed.interest_profile;
+        let interests = if profile.enabled {
+            db::get_user_liked_embeddings(conn, did, profile.max_posts)
+                .ok()
+                .and_then(|liked| average_embedding(&liked, profile.min_posts))
+        } else {
+            None
+        };
+
+        let affinity = &settings().feed.like_affinity;
+        let (topic_affinity, author_affinity) = if affinity.enabled {
+            let liked = db::get_like_affinities(conn, did, now, affinity.half_life_hours)
+                .unwrap_or_default();
+            split_affinities(liked, affinity.min_likes)
+        } else {
+            Default::default()
+        };
+
+        Self {
+            seen,
+            boosted_authors,
+            penalized_authors,
+            engaged_authors,
+            follows: Arc::default(),
+            interests,
+            interest_similarity: HashMap::new(),
+            topic_affinity,
+            author_affinity,
+        }
+    }
+
+    /// Compares each post with the reader's interest profile. Posts `embedding` has nothing for
+    /// are left out and get no interest boost.
+    pub fn match_interests<'e>(
+        &mut self,
+        posts: &[Post],
+        embedding: impl Fn(&str) -> Option<&'e [f32]>,
+    ) {
+        let Some(interests) = &self.interests else {
+            return;
+        };
+        self.interest_similarity = posts
+            .iter()
+            .filter_map(|p| {
+                let similarity = cosine_similarity(interests, embedding(&p.uri)?);
+                Some((p.uri.clone(), similarity))
+            })
+            .collect();
+    }
+
+    pub fn preference_modifier(&self, author: Option<&str>) -> f32 {
+        let s = settings();
+        match author {
+            Some(author) if self.boosted_authors.contains(author) => s.feed.preference_boost,
+            Some(author) if self.penalized_authors.contains(author) => s.feed.preference_penalty,
+            Some(author) if self.engaged_authors.contains(author) => s.feed.engaged_author_boost,
+            _ => 1.0,
+        }
+    }
+
+    pub fn follow_modifier(&self, author: Option<&str>) -> f32 {
+        let s = settings();
+        match author {
+            Some(author) if s.feed.follows.enabled && self.follows.contains(author) => {
+                s.feed.follows.boost
+            }
+            _ => 1.0,
+        }
+    }
+
+    pub fn interest_modifier(&self, uri: &str) -> f32 {
+        let profile = &settings().feed.interest_profile;
+        match self.interest_similarity.get(uri) {
+            Some(similarity) if profile.enabled => {
+                1.0 + profile.weight * (similarity - profile.min_similarity).max(0.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    pub fn affinity_modifier(&self, topic: TopicLabel, author: Option<&str>) -> f32 {
+        let affinity = &settings().feed.like_affinity;
+        if !affinity.enabled {
+            return 1.0;
+        }
+        let topic_share = self.topic_affinity.get(&topic).copied().unwrap_or(0.0);
+        let author_likes = author
+            .and_then(|author| self.author_affinity.get(author))
+            .copied()
+            .unwrap_or(0.0);
+        (1.0 + affinity.topic_weight * topic_share + affinity.author_weight * author_likes)
+            .min(affinity.max_boost.max(1.0))
+    }
+}
+
+/// Splits liked topics and authors apart, turning topic likes into shares of the total. Below
+/// `min_likes` in total, topics are left out.
+fn split_affinities(
+    liked: Vec<db::LikeAffinity>,
+    min_likes: f32,
+) -> (HashMap<TopicLabel, f32>, HashMap<String, f32>) {
+    let mut topics = HashMap::new();
+    let mut authors = HashMap::new();
+    for affinity in liked {
+        match affinity.kind.as_str() {
+            db::AFFINITY_TOPIC => {
+                if let Ok(topic) = affinity.value.parse::<TopicLabel>() {
+                    *topics.entry(topic).or_default() += affinity.score;
+                }
+            }
+            db::AFFINITY_AUTHOR => {
+                authors.insert(affinity.value, affinity.score);
+            }
+            _ => {}
+        }
+    }
+    let total: f32 = topics.values().sum();
+    if total < min_likes.max(f32::EPSILON) {
+        topics.clear();
+    }
+    for share in topics.values_mut() {
+        *share /= total;
+    }
+    (topics, authors)
+}
+
+/// Element-wise mean of `embeddings`, or `None` if there are fewer than `min_count` usable ones.
+fn average_embedding(embeddings: &[Embedding], min_count: usize) -> Option<Embedding> {
+    let dims = embeddings.iter().map(Vec::len).find(|&len| len > 0)?;
+    let usable: Vec<&Embedding> = embeddings.iter().filter(|e| e.len() == dims).collect();
+    if usable.len() < min_count.max(1) {
+        return None;
+    }
+    let mut sum = vec![0.0; dims];
+    for embedding in &usable {
+        for (total, value) in sum.iter_mut().zip(embedding.iter()) {
+            *total += value;
+        }
+    }
+    Some(sum.into_iter().map(|v| v / usable.len() as f32).collect())
+}
+
+/// Serve-time inputs shared by every post in one feed request.
+#[derive(Debug, Clone)]
+pub struct RankContext {
+    pub now: i64,
+    /// The session's shuffle seed, carried from page to page in the cursor.
+    pub seed: u64,
+    hour_multipliers: [f32; 24],
+}
+
+impl RankContext {
+    /// `posts` is the whole candidate feed; hour balancing depends on how it's spread over the
+    /// day.
+    pub fn new(posts: &[Post], now: i64, seed: u64) -> Self {
+        let timestamps: Vec<i64> = posts.iter().map(|p| p.timestamp).collect();
+        Self {
+            now,
+            seed,
+            hour_multipliers: hour_balance_multipliers(
+                &timestamps,
+                settings().feed.hour_balance_boost,
+            ),
+        }
+    }
+}
+
+/// How a post's stored priority became its serve-time priority for one reader.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankBreakdown {
+    pub stored_priority: f32,
+    pub thread_boost: f32,
+    /// Stored priority plus thread boost, after the configured priority bounds.
+    pub bounded_priority: f32,
+    pub preference: f32,
+    pub follow: f32,
+    pub interest: f32,
+    /// Boost from the topics and authors the reader likes.
+    pub affinity: f32,
+    pub hour_balance: f32,
+    pub decay: f32,
+    /// Shuffle jitter, applied as `1 + jitter`.
+    pub jitter: f32,
+    /// False when moderate sampling leaves the post out of this session's feed.
+    pub sampled: bool,
+    pub adjusted_priority: f32,
+}
+
+/// `priority` scaled by `modifier`, dividing instead of multiplying when the priority is
+/// negative, so a modifier above 1 raises a post and one below 1, like decay, lowers it whatever
+/// its sign.
+fn scale_priority(priority: f32, modifier: f32) -> f32 {
+    if priority < 0.0 {
+        priority / modifier.max(f32::EPSILON)
+    } else {
+        priority * modifier
+    }
+}
+
+pub fn rank_post(post: &Post, reader: &Reader, ctx: &RankContext) -> RankBreakdown {
+    let s = settings();
+    let preference = reader.preference_modifier(post.author_did.as_deref());
+    let follow = reader.follow_modifier(post.author_did.as_deref());
+    let interest = reader.interest_modifier(&post.uri);
+    let topic = post.topic();
+    let affinity = reader.affinity_modifier(topic, post.author_did.as_deref());
+    let boost = thread_boost(post.thread_length);
+    let priority = post.priority + boost;
+
+    let sampled = !s.feed.moderate_sampling.enabled
+        || sample_draw(ctx.seed, &post.uri) < exposure_probability(priority, preference * follow);
+
+    let bounded_priority = bound_priority(priority, &s.feed.priority_bounds);
+    let (_, hour) = local_slot(post.timestamp, 0);
+    let hour_balance = ctx.hour_multipliers[hour as usize];
+    let decay = time_decay(ctx.now - post.timestamp, topic, &s.feed.decay);
+    let jitter = shuffle_jitter(ctx.seed, &post.uri, s.feed.shuffle_variance);
+
+    RankBreakdown {
+        stored_priority: post.priority,
+        thread_boost: boost,
+        bounded_priority,
+        preference,
+        follow,
+        interest,
+        affinity,
+        hour_balance,
+        decay,
+        jitter,
+        sampled,
+        adjusted_priority: scale_priority(
+            bounded_priority,
+            preference * follow * interest * affinity * hour_balance * decay * (1.0 + jitter),
+        ),
+    }
+}
+
+/// Whether `post` is still young enough to be served for its confidence tier.
+pub fn within_tier_ttl(post: &Post, now: i64) -> bool {
+    let ttls = &settings().feed.tier_ttls;
+    let hours = match ConfidenceTier::from_priority(post.priority) {
+        ConfidenceTier::Strong => ttls.strong_hours,
+        ConfidenceTier::Moderate => ttls.moderate_hours,
+        ConfidenceTier::Weak => ttls.weak_hours,
+    };
+    hours <= 0 || now - post.timestamp < hours * 3600
+}
+
+pub fn bucket_seconds() -> i64 {
+    settings().feed.priority_bucket_hours * 3600
+}
+
+pub fn rank_key<'a>((post, priority): &'a (&Post, f32)) -> RankKey<'a> {
+    RankKey {
+        timestamp: post.timestamp,
+        priority: *priority,
+        uri: &post.uri,
+    }
+}
+
+/// Rank key for a trending post and its velocity. Every trending post shares one time bucket,
+/// so the order is by velocity alone.
+pub fn trending_key((uri, velocity): &(String, f32)) -> RankKey<'_> {
+    RankKey {
+        timestamp: 0,
+        priority: *velocity,
+        uri,
+    }
+}
+
+/// The reader's feed in serving order: unseen posts within their tier's TTL that survive
+/// sampling, ranked by time bucket and adjusted priority, then spread out by the author cap.
+pub fn rank_feed<'a>(
+    posts: &'a [Post],
+    reader: &Reader,
+    ctx: &RankContext,
+) -> Vec<(&'a Post, f32)> {
+    cap_feed(rank_uncapped(posts, reader, ctx))
+}
+
+/// The reader's feed in rank order, before the author cap.
+pub fn rank_uncapped<'a>(
+    posts: &'a [Post],
+    reader: &Reader,
+    ctx: &RankContext,
+) -> Vec<(&'a Post, f32)> {
+    let mut ranked: Vec<_> = posts
+        .iter()
+        .filter(|p| !reader.seen.contains(&p.uri) && within_tier_ttl(p, ctx.now))
+        .filter_map(|p| {
+            let breakdown = rank_post(p, reader, ctx);
+            breakdown
+                .sampled
+                .then_some((p, breakdown.adjusted_priority))
+        })
+        .collect();
+
+    let bucket_seconds = bucket_seconds();
+    ranked.sort_by(|a, b| rank_order(rank_key(a), rank_key(b), bucket_seconds));
+    ranked
+}
+
+/// `ranked` spread out by the author cap, if it's enabled.
+pub fn cap_feed(ranked: Vec<(&Post, f32)>) -> Vec<(&Post, f32)> {
+    let cap = &settings().feed.author_cap;
+    if !cap.enabled {
+        return ranked;
+    }
+    cap_authors(ranked, cap.window, cap.max_posts_per_author, |(p, _)| {
+        p.author_did.as_deref()
+    })
+}
+
+/// Which part of the feed order puts one post above another.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderReason {
+    NewerBucket,
+    HigherPriority,
+    UriTiebreak,
+}
+
+/// Why `first` sorts above `second`, or `None` if it doesn't.
+#[allow(dead_code)]
+pub fn order_reason(first: RankKey, second: RankKey, bucket_seconds: i64) -> Option<OrderReason> {
+    if rank_order(first, second, bucket_seconds) != Ordering::Less {
+        return None;
+    }
+    let bucket_seconds = bucket_seconds.max(1);
+    Some(
+        if first.timestamp / bucket_seconds != second.timestamp / bucket_seconds {
+            OrderReason::NewerBucket
+        } else if first.priority != second.priority {
+            OrderReason::HigherPriority
+        } else {
+            OrderReason::UriTiebreak
+        },
+    )
+}
+
+/// Deterministic draw in [0, 1) for sampling a post, independent of its shuffle jitter.
+fn sample_draw(seed: u64, uri: &str) -> f32 {
+    let mut hasher = DefaultHasher::new();
+    (seed, "sample").hash(&mut hasher);
+    uri.hash(&mut hasher);
+    (hasher.finish() >> 11) as f32 / (1u64 << 53) as f32
+}
+
+fn shuffle_jitter(seed: u64, uri: &str, variance: f32) -> f32 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    uri.hash(&mut hasher);
+    let unit = (hasher.finish() >> 11) as f32 / (1u64 << 53) as f32;
+    variance * (unit * 2.0 - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::FeedCursor;
+
+    fn post(uri: &str, author: &str, timestamp: i64, priority: f32) -> Post {
+        Post {
+            uri: uri.to_string(),
+            text: "finally got the lighting pass working in my engine".to_string(),
+            timestamp,
+            priority,
+            has_media: 0,
+            is_first_person: 1,
+            author_did: Some(author.to_string()),
+            image_count: 0,
+            has_alt_text: 0,
+            link_count: 0,
+            promo_link_count: 0,
+            lang: "en".to_string(),
+            mention_count: 0,
+            thread_length: 1,
+            config_id: None,
+            source: "firehose".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rank_post_multiplies_components() {
+        let posts = vec![post(
+            "at://did:plc:a/app.bsky.feed.post/1",
+            "did:plc:a",
+            7200,
+            1.0,
+        )];
+        let reader = Reader {
+            boosted_authors: HashSet::from(["did:plc:a".to_string()]),
+            follows: Arc::new(HashSet::from(["did:plc:a".to_string()])),
+            ..Reader::default()
+        };
+        let ctx = RankContext::new(&posts, 10_800, 42);
+
+        let breakdown = rank_post(&posts[0], &reader, &ctx);
+        assert_eq!(breakdown.preference, settings().feed.preference_boost);
+        assert_eq!(breakdown.follow, settings().feed.follows.boost);
+        assert_eq!(breakdown, rank_post(&posts[0], &reader, &ctx));
+        let expected = breakdown.bounded_priority
+            * breakdown.preference
+            * breakdown.follow
+            * breakdown.interest
+            * breakdown.affinity
+            * breakdown.hour_balance
+            * breakdown.decay
+            * (1.0 + breakdown.jitter);
+        assert_eq!(breakdown.adjusted_priority, expected);
+    }
+
+    #[test]
+    fn test_engaged_authors_rank_below_explicit_feedback() {
+        let s = settings();
+        let reader = Reader {
+            boosted_authors: HashSet::from(["did:plc:a".to_string()]),
+            engaged_authors: HashSet::from(["did:plc:a".to_string(), "did:plc:b".to_string()]),
+            ..Reader::default()
+        };
+        assert_eq!(
+            reader.preference_modifier(Some("did:plc:a")),
+            s.feed.preference_boost
+        );
+        assert_eq!(
+            reader.preference_modifier(Some("did:plc:b")),
+            s.feed.engaged_author_boost
+        );
+        assert_eq!(reader.preference_modifier(Some("did:plc:c")), 1.0);
+    }
+
+    #[test]
+    fn test_interest_profile() {
+        let liked = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0], vec![]];
+        assert_eq!(
+            average_embedding(&liked, 3),
+            Some(vec![2.0 / 3.0, 2.0 / 3.0])
+        );
+        assert_eq!(average_embedding(&liked, 4), None);
+
+        let posts = vec![
+            post("at://did:plc:a/app.bsky.feed.post/1", "did:plc:a", 0, 1.0),
+            post("at://did:plc:b/app.bsky.feed.post/2", "did:plc:b", 0, 1.0),
+            post("at://did:plc:c/app.bsky.feed.post/3", "did:plc:c", 0, 1.0),
+        ];
+        let embeddings = HashMap::from([
+            (posts[0].uri.clone(), vec![1.0, 1.0]),
+            (posts[1].uri.clone(), vec![-1.0, -1.0]),
+        ]);
+        let mut reader = Reader {
+            interests: Some(vec![0.5, 0.5]),
+            ..Reader::default()
+        };
+        reader.match_interests(&posts, |uri| embeddings.get(uri).map(Vec::as_slice));
+
+        let profile = &settings().feed.interest_profile;
+        let expected = 1.0 + profile.weight * (1.0 - profile.min_similarity);
+        assert!((reader.interest_modifier(&posts[0].uri) - expected).abs() < 1e-5);
+        assert_eq!(reader.interest_modifier(&posts[1].uri), 1.0);
+        assert_eq!(reader.interest_modifier(&posts[2].uri), 1.0);
+    }
+
+    #[test]
+    fn test_like_affinity() {
+        let affinity = |kind: &str, value: &str, score: f32| db::LikeAffinity {
+            user_did: "did:plc:reader".to_string(),
+            kind: kind.to_string(),
+            value: value.to_string(),
+            score,
+            updated_at: 0,
+        };
+        let liked = vec![
+            affinity(db::AFFINITY_TOPIC, "sharing work", 3.0),
+            affinity(db::AFFINITY_TOPIC, "discussion", 1.0),
+            affinity(db::AFFINITY_AUTHOR, "did:plc:a", 2.0),
+        ];
+        let (topic_affinity, author_affinity) = split_affinities(liked.clone(), 4.0);
+        assert_eq!(topic_affinity.get(&TopicLabel::SharingWork), Some(&0.75));
+        assert!(split_affinities(liked, 5.0).0.is_empty());
+
+        let reader = Reader {
+            topic_affinity,
+            author_affinity,
+            ..Reader::default()
+        };
+        let s = &settings().feed.like_affinity;
+        let expected = (1.0 + s.topic_weight * 0.75 + s.author_weight * 2.0).min(s.max_boost);
+        let boost = reader.affinity_modifier(TopicLabel::SharingWork, Some("did:plc:a"));
+        assert!((boost - expected).abs() < 1e-5);
+        assert!(boost > reader.affinity_modifier(TopicLabel::Discussion, Some("did:plc:b")));
+        assert_eq!(reader.affinity_modifier(TopicLabel::Question, None), 1.0);
+
+        assert!(scale_priority(1.0, boost) > 1.0);
+        assert!(scale_priority(-1.0, boost) > -1.0);
+    }
+
+    #[test]
+    fn test_decay_sinks_negative_priorities() {
+        let decay = crate::settings::Decay {
+            enabled: true,
+            half_life_hours: 24.0,
+            topic_rates: HashMap::new(),
+        };
+        let old = time_decay(48 * 3600, TopicLabel::SharingWork, &decay);
+        assert!((old - 0.25).abs() < 1e-5);
+        assert!(scale_priority(2.0, old) < 2.0);
+        assert!(scale_priority(-1.0, old) < -1.0);
+        assert!(scale_priority(-1.0, old) < scale_priority(-1.0, 1.0));
+    }
+
+    #[test]
+    fn test_trending_pages_by_velocity() {
+        let trending = vec![
+            ("at://a".to_string(), 9.0),
+            ("at://b".to_string(), 4.0),
+            ("at://c".to_string(), 4.0),
+            ("at://d".to_string(), 1.0),
+        ];
+        let cursor = FeedCursor::after(0, trending_key(&trending[1]));
+        assert_eq!(
+            cursor.resume_index(&trending, bucket_seconds(), trending_key),
+            2
+        );
+
+        // "b" dropped out of the window: resume at the next post with the same velocity.
+        let remaining: Vec<_> = trending
+            .iter()
+            .filter(|(uri, _)| uri != "at://b")
+            .cloned()
+            .collect();
+        let index = cursor.resume_index(&remaining, bucket_seconds(), trending_key);
+        assert_eq!(remaining[index].0, "at://c");
+    }
+
+    #[test]
+    fn test_tier_ttls() {
+        let s = settings();
+        let confidence = &s.scoring.confidence;
+        let ttl = s.feed.tier_ttls.moderate_hours * 3600;
+
+        let moderate = post(
+            "at://did:plc:a/app.bsky.feed.post/1",
+            "did:plc:a",
+            0,
+            confidence.moderate_min_priority,
+        );
+        assert!(within_tier_ttl(&moderate, ttl - 1));
+        assert!(!within_tier_ttl(&moderate, ttl));
+
+        let strong = post(
+            "at://did:plc:a/app.bsky.feed.post/2",
+            "did:plc:a",
+            0,
+            confidence.strong_min_priority,
+        );
+        assert!(within_tier_ttl(&strong, s.feed.cutoff_hours * 3600));
+    }
+
+    #[test]
+    fn test_rank_feed_skips_seen_and_explains_order() {
+        let posts = vec![
+            post("at://did:plc:a/app.bsky.feed.post/old", "did:plc:a", 0, 5.0),
+            post(
+                "at://did:plc:b/app.bsky.feed.post/new",
+                "did:plc:b",
+                90_000,
+                0.5,
+            ),
+            post(
+                "at://did:plc:c/app.bsky.feed.post/seen",
+                "did:plc:c",
+                90_000,
+                5.0,
+            ),
+        ];
+        let reader = Reader {
+            seen: HashSet::from([posts[2].uri.clone()]),
+            ..Reader::default()
+        };
+        let ranked = rank_feed(&posts, &reader, &RankContext::new(&posts, 90_000, 1));
+
+        let uris: Vec<&str> = ranked.iter().map(|(p, _)| p.uri.as_str()).collect();
+        assert_eq!(uris, vec![posts[1].uri.as_str(), posts[0].uri.as_str()]);
+        assert_eq!(
+            order_reason(rank_key(&ranked[0]), rank_key(&ranked[1]), bucket_seconds()),
+            Some(OrderReason::NewerBucket)
+        );
+        assert_eq!(
+            order_reason(rank_key(&ranked[1]), rank_key(&ranked[0]), bucket_seconds()),
+            None
+        );
+    }
+}
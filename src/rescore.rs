@@ -0,0 +1,83 @@
+use crate::db::{self, RescoreCandidate};
+use crate::scoring::{calculate_engagement_boost, PrioritySignals};
+use crate::settings::settings;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+
+/// Boost changes smaller than this aren't written back.
+const MIN_BOOST_CHANGE: f32 = 0.01;
+
+/// Engagement boost for a post's current counts. The cached velocity is only trusted while it
+/// is within the velocity window; after that the boost falls back to the totals.
+fn engagement_boost(candidate: &RescoreCandidate, now: i64) -> f32 {
+    let window = settings().spam.velocity_window_hours * 3600;
+    let velocity_is_fresh = now - candidate.engagement_updated_at <= window;
+    let signals = PrioritySignals {
+        engagement_velocity: if velocity_is_fresh {
+            candidate.velocity_score
+        } else {
+            0.0
+        },
+        reply_count: candidate.reply_count,
+        repost_count: candidate.repost_count,
+        like_count: candidate.like_count,
+        ..PrioritySignals::default()
+    };
+    calculate_engagement_boost(&signals)
+}
+
+/// Replaces the engagement boost folded into each post newer than `since` with one computed
+/// from its current engagement, so posts that take off after ingest rise in the feed.
+/// Returns the number of posts updated.
+pub fn rescore_recent_posts(
+    conn: &mut SqliteConnection,
+    since: i64,
+    now: i64,
+) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let mut updated = 0;
+        for candidate in db::get_rescore_candidates(conn, since)? {
+            let boost = engagement_boost(&candidate, now);
+            if (boost - candidate.engagement_boost).abs() < MIN_BOOST_CHANGE {
+                continue;
+            }
+            let priority = candidate.priority - candidate.engagement_boost + boost;
+            updated += db::update_engagement_boost(conn, &candidate.uri, priority, boost)?;
+        }
+        Ok(updated)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(velocity_score: f32, engagement_updated_at: i64) -> RescoreCandidate {
+        RescoreCandidate {
+            uri: "at://did:plc:dev/app.bsky.feed.post/1".to_string(),
+            priority: 1.0,
+            engagement_boost: 0.0,
+            like_count: 30,
+            reply_count: 4,
+            repost_count: 2,
+            velocity_score,
+            engagement_updated_at,
+        }
+    }
+
+    #[test]
+    fn test_stale_velocity_falls_back_to_totals() {
+        let now = 100_000;
+        let fresh = engagement_boost(&candidate(50.0, now - 60), now);
+        let stale = engagement_boost(&candidate(50.0, 0), now);
+        let totals = engagement_boost(&candidate(0.0, now), now);
+
+        assert!(fresh > 0.0);
+        assert_eq!(stale, totals);
+        assert_ne!(fresh, stale);
+
+        let mut quiet = candidate(0.0, now);
+        (quiet.like_count, quiet.reply_count, quiet.repost_count) = (0, 0, 0);
+        assert_eq!(engagement_boost(&quiet, now), 0.0);
+    }
+}
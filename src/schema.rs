@@ -1,5 +1,75 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    admin_audit (id) {
+        id -> Integer,
+        actor -> Text,
+        role -> Text,
+        action -> Text,
+        target -> Text,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    archive_posts (uri) {
+        uri -> Text,
+        text_hash -> Text,
+        author_did -> Nullable<Text>,
+        lang -> Text,
+        timestamp -> BigInt,
+        priority -> Float,
+        confidence -> Text,
+        topic -> Text,
+        like_count -> Integer,
+        reply_count -> Integer,
+        repost_count -> Integer,
+        archived_at -> BigInt,
+        config_id -> Nullable<Integer>,
+        source -> Text,
+    }
+}
+
+diesel::table! {
+    author_history (post_uri) {
+        post_uri -> Text,
+        author_did -> Text,
+        accepted_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    author_stats (author_did, day, config_id) {
+        author_did -> Text,
+        day -> BigInt,
+        config_id -> Integer,
+        assessed -> Integer,
+        accepted -> Integer,
+        score_sum -> Float,
+        request_more -> Integer,
+        request_less -> Integer,
+        spam_flags -> Integer,
+    }
+}
+
+diesel::table! {
+    backfill_query_stats (query, day) {
+        query -> Text,
+        day -> BigInt,
+        searched -> Integer,
+        accepted -> Integer,
+    }
+}
+
+diesel::table! {
+    backfill_state (query) {
+        query -> Text,
+        oldest_at -> BigInt,
+        newest_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
+
 diesel::table! {
     blocked_authors (did) {
         did -> Text,
@@ -8,6 +78,26 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    config_history (id) {
+        id -> Integer,
+        hash -> Text,
+        settings -> Text,
+        profile -> Nullable<Text>,
+        activated_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    coverage_gaps (id) {
+        id -> Integer,
+        started_at -> BigInt,
+        ended_at -> BigInt,
+        reason -> Text,
+        backfilled_at -> Nullable<BigInt>,
+    }
+}
+
 diesel::table! {
     engagement_cache (post_uri) {
         post_uri -> Text,
@@ -19,10 +109,77 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    handles (did) {
+        did -> Text,
+        handle -> Text,
+        updated_at -> BigInt,
+        description -> Nullable<Text>,
+        bio_score -> Float,
+    }
+}
+
+diesel::table! {
+    language_stats (lang, day, config_id) {
+        lang -> Text,
+        day -> BigInt,
+        config_id -> Integer,
+        rejected -> Integer,
+        relevant -> Integer,
+    }
+}
+
+diesel::table! {
+    like_affinities (user_did, kind, value) {
+        user_did -> Text,
+        kind -> Text,
+        value -> Text,
+        score -> Float,
+        updated_at -> BigInt,
+    }
+}
+
 diesel::table! {
     likes (post_uri, like_uri) {
         post_uri -> Text,
         like_uri -> Text,
+        liker_did -> Nullable<Text>,
+        liked_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    negative_examples (post_uri) {
+        post_uri -> Text,
+        text -> Text,
+        embedding -> Binary,
+        request_less_count -> Integer,
+        harvested_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    opted_out_authors (did) {
+        did -> Text,
+        post_uri -> Text,
+        opted_out_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    post_embeddings (post_uri) {
+        post_uri -> Text,
+        embedding -> Binary,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    post_scores (uri) {
+        uri -> Text,
+        explanation -> Text,
+        scored_at -> BigInt,
+        config_id -> Nullable<Integer>,
     }
 }
 
@@ -39,6 +196,12 @@ diesel::table! {
         has_alt_text -> Integer,
         link_count -> Integer,
         promo_link_count -> Integer,
+        lang -> Text,
+        mention_count -> Integer,
+        thread_length -> Integer,
+        engagement_boost -> Float,
+        config_id -> Nullable<Integer>,
+        source -> Text,
     }
 }
 
@@ -60,6 +223,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    shadow_scores (uri) {
+        uri -> Text,
+        profile -> Text,
+        live_priority -> Float,
+        shadow_priority -> Float,
+        live_accepted -> Integer,
+        shadow_accepted -> Integer,
+        scored_at -> BigInt,
+    }
+}
+
 diesel::table! {
     spammers (did) {
         did -> Text,
@@ -85,12 +260,28 @@ diesel::joinable!(replies -> posts (post_uri));
 diesel::joinable!(reposts -> posts (post_uri));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    admin_audit,
+    archive_posts,
+    author_history,
+    author_stats,
+    backfill_query_stats,
+    backfill_state,
     blocked_authors,
+    config_history,
+    coverage_gaps,
     engagement_cache,
+    handles,
+    language_stats,
+    like_affinities,
     likes,
+    negative_examples,
+    opted_out_authors,
+    post_embeddings,
+    post_scores,
     posts,
     replies,
     reposts,
+    shadow_scores,
     spammers,
     user_interactions,
 );
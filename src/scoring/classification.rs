@@ -1,11 +1,18 @@
+#[cfg(feature = "onnx")]
+use super::onnx::OnnxClassifier;
 use anyhow::Result;
+#[cfg(feature = "rust-bert")]
 use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
-use std::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
 use strum::{Display, EnumIter, IntoEnumIterator, IntoStaticStr};
 
-use crate::settings::settings;
+use super::queue::{MlQueue, QueueStatus};
+use super::semantic::{embed_batch, load_embedding_model, Embedding};
+use crate::settings::{settings, MlBackend};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, IntoStaticStr)]
 pub enum QualityLabel {
@@ -23,7 +30,7 @@ impl QualityLabel {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QualityAssessment {
     pub engagement_bait_score: f32,
     pub synthetic_score: f32,
@@ -35,102 +42,226 @@ pub enum MLRequest {
         text: String,
         response_tx: tokio::sync::oneshot::Sender<QualityAssessment>,
     },
+    Embed {
+        uri: Option<String>,
+        text: String,
+        response_tx: tokio::sync::oneshot::Sender<Embedding>,
+    },
 }
 
+pub type EmbeddingSink = mpsc::Sender<(String, Embedding)>;
+
 #[derive(Clone)]
 pub struct MLHandle {
-    request_tx: mpsc::Sender<MLRequest>,
+    queue: Arc<MlQueue<MLRequest>>,
+    _closer: Arc<QueueCloser>,
+}
+
+/// Closes the queue once the last handle is dropped, so the worker can finish and stop.
+struct QueueCloser(Arc<MlQueue<MLRequest>>);
+
+impl Drop for QueueCloser {
+    fn drop(&mut self) {
+        self.0.close();
+    }
 }
 
 impl MLHandle {
     pub fn spawn() -> Result<Self> {
-        let (request_tx, request_rx) = mpsc::channel::<MLRequest>();
+        Self::spawn_with_sink(None)
+    }
+
+    pub fn spawn_with_sink(embedding_sink: Option<EmbeddingSink>) -> Result<Self> {
+        let queue = Arc::new(MlQueue::new(settings().ml.queue_capacity));
 
+        let worker_queue = queue.clone();
         thread::spawn(move || {
-            let _ = run_ml_worker(request_rx);
+            if run_ml_worker(&worker_queue, embedding_sink).is_err() {
+                worker_queue.abandon();
+            }
         });
 
-        Ok(Self { request_tx })
+        Ok(Self {
+            _closer: Arc::new(QueueCloser(queue.clone())),
+            queue,
+        })
     }
 
     pub async fn score(&self, text: String) -> QualityAssessment {
         let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        self.queue
+            .push(MLRequest::Score { text, response_tx }, None);
 
-        if self
-            .request_tx
-            .send(MLRequest::Score { text, response_tx })
-            .is_err()
-        {
-            return QualityAssessment::default();
-        }
+        response_rx.await.unwrap_or_default()
+    }
+
+    /// Scores a firehose post, unless the queue is full of posts with a stronger `signal` (its
+    /// keyword and hashtag matches). A post without a signal is never shed. Returns `None` when
+    /// the post was shed.
+    pub async fn try_score(
+        &self,
+        text: String,
+        signal: Option<usize>,
+    ) -> Option<QualityAssessment> {
+        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        self.queue
+            .push(MLRequest::Score { text, response_tx }, signal);
+
+        response_rx.await.ok()
+    }
+
+    pub async fn embed(&self, text: String) -> Embedding {
+        self.request_embedding(None, text).await
+    }
+
+    pub async fn embed_post(&self, uri: String, text: String) -> Embedding {
+        self.request_embedding(Some(uri), text).await
+    }
+
+    async fn request_embedding(&self, uri: Option<String>, text: String) -> Embedding {
+        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        self.queue.push(
+            MLRequest::Embed {
+                uri,
+                text,
+                response_tx,
+            },
+            None,
+        );
 
         response_rx.await.unwrap_or_default()
     }
+
+    pub fn queue_status(&self) -> QueueStatus {
+        self.queue.status()
+    }
 }
 
-fn run_ml_worker(request_rx: mpsc::Receiver<MLRequest>) -> Result<()> {
-    let classifier = ZeroShotClassificationModel::new(Default::default())?;
+fn run_ml_worker(queue: &MlQueue<MLRequest>, embedding_sink: Option<EmbeddingSink>) -> Result<()> {
+    let mut classifier = load_classifier()?;
+    let mut embedder = load_embedding_model()?;
     let s = settings();
     let batch_timeout = Duration::from_millis(s.ml.batch_timeout_ms);
 
-    loop {
-        let mut batch: Vec<(String, tokio::sync::oneshot::Sender<QualityAssessment>)> = Vec::new();
+    while let Some(batch) = queue.next_batch(s.ml.batch_size, batch_timeout) {
+        let mut score_requests: Vec<(String, tokio::sync::oneshot::Sender<QualityAssessment>)> =
+            Vec::new();
+        let mut embed_requests: Vec<(
+            Option<String>,
+            String,
+            tokio::sync::oneshot::Sender<Embedding>,
+        )> = Vec::new();
 
-        match request_rx.recv() {
-            Ok(MLRequest::Score { text, response_tx }) => {
-                batch.push((text, response_tx));
+        for request in batch {
+            match request {
+                MLRequest::Score { text, response_tx } => score_requests.push((text, response_tx)),
+                MLRequest::Embed {
+                    uri,
+                    text,
+                    response_tx,
+                } => embed_requests.push((uri, text, response_tx)),
             }
-            Err(_) => break,
         }
 
-        while batch.len() < s.ml.batch_size {
-            match request_rx.recv_timeout(batch_timeout) {
-                Ok(MLRequest::Score { text, response_tx }) => {
-                    batch.push((text, response_tx));
-                }
-                Err(mpsc::RecvTimeoutError::Timeout) => break,
-                Err(mpsc::RecvTimeoutError::Disconnected) => break,
-            }
-        }
+        if !score_requests.is_empty() {
+            let texts: Vec<&str> = score_requests.iter().map(|(t, _)| t.as_str()).collect();
+            let qualities = assess_quality_batch(&mut classifier, &texts);
 
-        if batch.is_empty() {
-            continue;
+            for (i, (_, response_tx)) in score_requests.into_iter().enumerate() {
+                let quality = qualities.get(i).cloned().unwrap_or_default();
+                let _ = response_tx.send(quality);
+            }
         }
 
-        let texts: Vec<&str> = batch.iter().map(|(t, _)| t.as_str()).collect();
-        let qualities = assess_quality_batch(&classifier, &texts);
+        if !embed_requests.is_empty() {
+            let texts: Vec<&str> = embed_requests.iter().map(|(_, t, _)| t.as_str()).collect();
+            let embeddings = embed_batch(&mut embedder, &texts);
 
-        for (i, (_, response_tx)) in batch.into_iter().enumerate() {
-            let quality = qualities.get(i).cloned().unwrap_or_default();
-            let _ = response_tx.send(quality);
+            for (i, (uri, _, response_tx)) in embed_requests.into_iter().enumerate() {
+                let embedding = embeddings.get(i).cloned().unwrap_or_default();
+                if let (Some(uri), Some(sink)) = (uri, &embedding_sink) {
+                    if !embedding.is_empty() {
+                        let _ = sink.send((uri, embedding.clone()));
+                    }
+                }
+                let _ = response_tx.send(embedding);
+            }
         }
     }
 
     Ok(())
 }
 
-fn assess_quality_batch(
-    classifier: &ZeroShotClassificationModel,
+enum Classifier {
+    #[cfg(feature = "rust-bert")]
+    RustBert(ZeroShotClassificationModel),
+    #[cfg(feature = "onnx")]
+    Onnx(OnnxClassifier),
+}
+
+fn load_classifier() -> Result<Classifier> {
+    let s = settings();
+    match s.ml.backend {
+        #[cfg(feature = "rust-bert")]
+        MlBackend::RustBert => Ok(Classifier::RustBert(ZeroShotClassificationModel::new(
+            Default::default(),
+        )?)),
+        #[cfg(feature = "onnx")]
+        MlBackend::Onnx => Ok(Classifier::Onnx(OnnxClassifier::load(&s.ml.onnx)?)),
+        #[allow(unreachable_patterns)]
+        backend => anyhow::bail!("ml.backend {backend:?} is not compiled in"),
+    }
+}
+
+fn hypothesis(label: &str) -> String {
+    format!("This tweet sounds {}.", label)
+}
+
+/// Per text, the multi-label score of every quality label, keyed by label text.
+fn predict_labels(
+    classifier: &mut Classifier,
     texts: &[&str],
-) -> Vec<QualityAssessment> {
+) -> Result<Vec<HashMap<String, f32>>> {
     let all_labels = QualityLabel::all_labels();
 
-    let result = classifier.predict_multilabel(
-        texts,
-        &all_labels,
-        Some(Box::new(|label| format!("This tweet sounds {}.", label))),
-        128,
-    );
+    match classifier {
+        #[cfg(feature = "rust-bert")]
+        Classifier::RustBert(model) => {
+            let predictions =
+                model.predict_multilabel(texts, &all_labels, Some(Box::new(hypothesis)), 128)?;
+            Ok(predictions
+                .iter()
+                .map(|labels| {
+                    labels
+                        .iter()
+                        .map(|l| (l.text.clone(), l.score as f32))
+                        .collect()
+                })
+                .collect())
+        }
+        #[cfg(feature = "onnx")]
+        Classifier::Onnx(model) => {
+            let hypotheses: Vec<String> = all_labels.iter().map(|l| hypothesis(l)).collect();
+            Ok(model
+                .predict(texts, &hypotheses)?
+                .into_iter()
+                .map(|scores| {
+                    all_labels
+                        .iter()
+                        .map(|l| l.to_string())
+                        .zip(scores)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+}
 
-    match result {
+fn assess_quality_batch(classifier: &mut Classifier, texts: &[&str]) -> Vec<QualityAssessment> {
+    match predict_labels(classifier, texts) {
         Ok(predictions) => predictions
             .iter()
-            .map(|labels| {
-                let scores: std::collections::HashMap<String, f32> = labels
-                    .iter()
-                    .map(|l| (l.text.clone(), l.score as f32))
-                    .collect();
-
+            .map(|scores| {
                 let engagement_bait_score = scores
                     .get(QualityLabel::EngagementBait.to_string().as_str())
                     .copied()
@@ -0,0 +1,132 @@
+use crate::settings::settings;
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum ConfidenceTier {
+    #[strum(to_string = "STRONG")]
+    Strong,
+    #[strum(to_string = "MODERATE")]
+    Moderate,
+    #[strum(to_string = "WEAK")]
+    Weak,
+}
+
+impl ConfidenceTier {
+    pub fn from_priority(priority: f32) -> Self {
+        let s = settings();
+        if priority >= s.scoring.confidence.strong_min_priority {
+            Self::Strong
+        } else if priority >= s.scoring.confidence.moderate_min_priority {
+            Self::Moderate
+        } else {
+            Self::Weak
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+pub enum TopicLabel {
+    #[strum(to_string = "sharing work")]
+    SharingWork,
+    #[strum(to_string = "asking a question")]
+    Question,
+    #[strum(to_string = "sharing a link")]
+    Link,
+    #[strum(to_string = "dev update")]
+    Update,
+    #[strum(to_string = "discussion")]
+    Discussion,
+}
+
+impl TopicLabel {
+    pub fn classify(text: &str, has_media: bool, is_first_person: bool, link_count: u8) -> Self {
+        if has_media && is_first_person {
+            Self::SharingWork
+        } else if text.contains('?') {
+            Self::Question
+        } else if link_count > 0 {
+            Self::Link
+        } else if is_first_person {
+            Self::Update
+        } else {
+            Self::Discussion
+        }
+    }
+}
+
+/// Chance of serving a post when MODERATE sampling is on: STRONG posts always, MODERATE ones
+/// in proportion to where they sit in the band, scaled by the user's affinity for the author.
+pub fn exposure_probability(priority: f32, affinity: f32) -> f32 {
+    let s = settings();
+    let tiers = &s.scoring.confidence;
+    let min_probability = s.feed.moderate_sampling.min_probability;
+
+    let base = match ConfidenceTier::from_priority(priority) {
+        ConfidenceTier::Strong => return 1.0,
+        ConfidenceTier::Moderate => {
+            let band = (tiers.strong_min_priority - tiers.moderate_min_priority).max(f32::EPSILON);
+            let position = ((priority - tiers.moderate_min_priority) / band).clamp(0.0, 1.0);
+            min_probability + (1.0 - min_probability) * position
+        }
+        ConfidenceTier::Weak => 0.0,
+    };
+    (base * affinity).clamp(0.0, 1.0)
+}
+
+pub fn feed_context(tier: ConfidenceTier, topic: TopicLabel) -> String {
+    format!("{tier} · {topic}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiers_follow_thresholds() {
+        let s = settings();
+        let strong = s.scoring.confidence.strong_min_priority;
+        let moderate = s.scoring.confidence.moderate_min_priority;
+
+        assert_eq!(
+            ConfidenceTier::from_priority(strong),
+            ConfidenceTier::Strong
+        );
+        assert_eq!(
+            ConfidenceTier::from_priority(moderate),
+            ConfidenceTier::Moderate
+        );
+        assert_eq!(
+            ConfidenceTier::from_priority(moderate - 0.01),
+            ConfidenceTier::Weak
+        );
+    }
+
+    #[test]
+    fn test_exposure_probability() {
+        let s = settings();
+        let strong = s.scoring.confidence.strong_min_priority;
+        let moderate = s.scoring.confidence.moderate_min_priority;
+
+        assert_eq!(exposure_probability(strong, 0.3), 1.0);
+        assert_eq!(exposure_probability(moderate - 0.01, 1.5), 0.0);
+
+        let low = exposure_probability(moderate, 1.0);
+        let high = exposure_probability(strong - 0.01, 1.0);
+        assert!((low - s.feed.moderate_sampling.min_probability).abs() < 1e-6);
+        assert!(high > low);
+        assert!(exposure_probability(moderate, 1.5) > low);
+    }
+
+    #[test]
+    fn test_feed_context_label() {
+        let topic = TopicLabel::classify("i finally got the boss fight working", true, true, 0);
+        assert_eq!(
+            feed_context(ConfidenceTier::Strong, topic),
+            "STRONG · sharing work"
+        );
+        assert_eq!(
+            TopicLabel::classify("how do you handle save files?", false, false, 0),
+            TopicLabel::Question
+        );
+    }
+}
@@ -1,10 +1,15 @@
 use crate::settings::settings;
+use crate::utils::bluesky::{
+    extract_facet_links, extract_facet_mentions, extract_facet_tags, Facet,
+};
 use regex::Regex;
 use std::sync::LazyLock;
 
 const FIRST_PERSON: &[&str] = &["i ", "i'", "we ", "we'", "my ", "our "];
 
 static URL_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"https?://[^\s]+").unwrap());
+static MENTION_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\s)@[a-zA-Z0-9-]+(?:\.[a-zA-Z0-9-]+)+").unwrap());
 
 #[derive(Debug, Clone, Default)]
 pub struct ContentSignals {
@@ -14,6 +19,8 @@ pub struct ContentSignals {
     pub has_alt_text: bool,
     pub link_count: u8,
     pub promo_link_count: u8,
+    pub mention_count: u8,
+    pub is_reply_bait: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -23,6 +30,43 @@ pub struct MediaInfo {
     pub has_alt_text: bool,
     pub external_uri: Option<String>,
     pub facet_links: Vec<String>,
+    /// Tags from `app.bsky.richtext.facet#tag` facets, `None` when the record has no facets.
+    pub facet_tags: Option<Vec<String>>,
+    pub facet_mentions: Vec<String>,
+    /// Non-empty image and video descriptions, in embed order.
+    pub alt_texts: Vec<String>,
+    /// Title and description of the link card, when it has either.
+    pub external_text: Option<String>,
+}
+
+impl MediaInfo {
+    /// Text that comes with the media rather than the post: alt text, then the link card.
+    pub fn attached_text(&self) -> String {
+        self.alt_texts
+            .iter()
+            .chain(&self.external_text)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Media described by `score-post`'s `--media`, `--video` and `--alt` flags.
+    pub fn from_flags(has_image: bool, has_video: bool, has_alt_text: bool) -> Self {
+        Self {
+            image_count: has_image as u8,
+            has_video,
+            has_alt_text,
+            ..Self::default()
+        }
+    }
+
+    /// Adds the links, tags and mentions from a record's facets.
+    pub fn with_facets(mut self, facets: &Option<Vec<Facet>>) -> Self {
+        self.facet_links = extract_facet_links(facets);
+        self.facet_tags = extract_facet_tags(facets);
+        self.facet_mentions = extract_facet_mentions(facets);
+        self
+    }
 }
 
 pub fn extract_content_signals(text: &str, media: &MediaInfo) -> ContentSignals {
@@ -50,9 +94,30 @@ pub fn extract_content_signals(text: &str, media: &MediaInfo) -> ContentSignals
         has_alt_text: media.has_alt_text,
         link_count,
         promo_link_count,
+        mention_count: count_mentions(text, media),
+        is_reply_bait: detect_reply_bait(text),
     }
 }
 
+/// Mention facets when the record has them, otherwise `@handle.domain`s in the text.
+pub fn count_mentions(text: &str, media: &MediaInfo) -> u8 {
+    let count = if media.facet_mentions.is_empty() {
+        MENTION_PATTERN.find_iter(text).count()
+    } else {
+        media.facet_mentions.len()
+    };
+    count.min(255) as u8
+}
+
+pub fn detect_reply_bait(text: &str) -> bool {
+    let s = settings();
+    let text_lower = text.to_lowercase();
+    s.filters
+        .reply_bait_phrases
+        .iter()
+        .any(|phrase| text_lower.contains(phrase.as_str()))
+}
+
 pub fn detect_first_person(text: &str) -> bool {
     let text_lower = text.to_lowercase();
     FIRST_PERSON.iter().any(|fp| text_lower.contains(fp))
@@ -62,6 +127,40 @@ pub fn is_first_person(text: &str) -> bool {
     detect_first_person(text)
 }
 
+/// The text without its links: full URLs, and the shortened `example.com/pa...` form clients
+/// show for the post's link facets and external embed.
+pub fn strip_links(text: &str, media: &MediaInfo) -> String {
+    let shown_links: Vec<String> = media
+        .facet_links
+        .iter()
+        .chain(&media.external_uri)
+        .map(|uri| {
+            let uri = uri.to_lowercase();
+            let without_scheme = uri.split_once("://").map_or(uri.as_str(), |(_, rest)| rest);
+            without_scheme.trim_start_matches("www.").to_string()
+        })
+        .collect();
+
+    let without_urls = URL_PATTERN.replace_all(text, "");
+    without_urls
+        .split_whitespace()
+        .filter(|word| {
+            let shown = word
+                .to_lowercase()
+                .trim_end_matches(['.', '…'])
+                .trim_start_matches("www.")
+                .to_string();
+            !(shown.contains('.') && shown_links.iter().any(|link| link.starts_with(&shown)))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the post has a link in its text, facets or embed.
+pub fn has_links(text: &str, media: &MediaInfo) -> bool {
+    URL_PATTERN.is_match(text) || !media.facet_links.is_empty() || media.external_uri.is_some()
+}
+
 pub fn count_links(text: &str) -> (u8, u8) {
     let s = settings();
     let promo_domains = &s.filters.promo_domains;
@@ -118,6 +217,22 @@ mod tests {
         assert!(!detect_first_person("They built a great game"));
     }
 
+    #[test]
+    fn test_mentions_and_reply_bait() {
+        let text = "Tag a dev who needs to see this! cc @alice.bsky.social @bob.dev, not me@mail";
+        let signals = extract_content_signals(text, &MediaInfo::default());
+        assert_eq!(signals.mention_count, 2);
+        assert!(signals.is_reply_bait);
+
+        let media = MediaInfo {
+            facet_mentions: vec!["did:plc:alice".to_string()],
+            ..Default::default()
+        };
+        let signals = extract_content_signals("Shipped the new dash with @alice", &media);
+        assert_eq!(signals.mention_count, 1);
+        assert!(!signals.is_reply_bait);
+    }
+
     #[test]
     fn test_count_links() {
         let (total, promo) = count_links("Check out https://example.com");
@@ -155,6 +270,10 @@ mod tests {
             has_alt_text: true,
             external_uri: None,
             facet_links: vec!["https://itch.io/game".to_string()],
+            facet_tags: None,
+            facet_mentions: Vec::new(),
+            alt_texts: vec!["the jump arc".to_string()],
+            external_text: None,
         };
         let signals = extract_content_signals("I'm working on my game", &media);
 
@@ -1,6 +1,7 @@
-use super::content::{is_promo_domain, MediaInfo};
-use super::relevance::{count_all_hashtags, strip_hashtags};
-use crate::settings::settings;
+use super::content::{has_links, is_promo_domain, strip_links, MediaInfo};
+use super::relevance::{extract_hashtags, find_ignore_case, strip_hashtags, TermMatch};
+use crate::settings::{settings, PostAge};
+use std::collections::{HashMap, HashSet};
 use strum::Display;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -13,6 +14,8 @@ pub enum FilterResult {
 pub enum Filter {
     #[strum(serialize = "min-length")]
     MinLength,
+    #[strum(serialize = "low-diversity")]
+    LowDiversity,
     #[strum(serialize = "english-only")]
     EnglishOnly,
     #[strum(serialize = "blocked-keyword")]
@@ -23,12 +26,49 @@ pub enum Filter {
     Spammer,
     #[strum(serialize = "blocked-author")]
     BlockedAuthor,
+    /// Tagged with an opt-out hashtag, or by an author who has opted out before.
+    #[strum(serialize = "author-opt-out")]
+    AuthorOptOut,
     #[strum(serialize = "promo-link")]
     PromoLink,
+    #[strum(serialize = "link-only")]
+    LinkOnly,
     #[strum(serialize = "too-many-hashtags")]
     TooManyHashtags(usize),
     #[strum(serialize = "low-priority")]
     LowPriority,
+    #[strum(serialize = "near-duplicate")]
+    NearDuplicate,
+    /// Claims to have been created too long before it arrived, or too far after.
+    #[strum(serialize = "implausible-timestamp")]
+    ImplausibleTimestamp,
+    /// The author already had `spam.max_posts_per_hour` posts accepted recently.
+    #[strum(serialize = "post-flood")]
+    PostFlood,
+}
+
+impl Filter {
+    /// Rejections that count against an author's reputation.
+    pub fn is_spam(&self) -> bool {
+        matches!(
+            self,
+            Self::Spammer
+                | Self::PromoLink
+                | Self::LowDiversity
+                | Self::TooManyHashtags(_)
+                | Self::PostFlood
+        )
+    }
+
+    /// Where in `text` the blocked keyword or hashtag behind this rejection matched.
+    pub fn term_match(&self, text: &str) -> Option<TermMatch> {
+        match self {
+            Self::BlockedKeyword(term) | Self::BlockedHashtag(term) => {
+                Some(TermMatch::new(term, text, find_ignore_case(text, term)))
+            }
+            _ => None,
+        }
+    }
 }
 
 pub fn apply_filters(
@@ -40,31 +80,51 @@ pub fn apply_filters(
     mut blocked_author_check: impl FnMut(&str) -> bool,
 ) -> FilterResult {
     let s = settings();
+    // Checked first so the opt-out is recorded whatever else is wrong with the post.
+    let hashtags = extract_hashtags(text, media.facet_tags.as_deref());
+    if hashtags
+        .iter()
+        .any(|tag| s.filters.opt_out_hashtags.contains(tag))
+    {
+        return FilterResult::Reject(Filter::AuthorOptOut);
+    }
+
     let stripped = strip_hashtags(text);
     if stripped.len() < s.scoring.thresholds.min_text_length {
         return FilterResult::Reject(Filter::MinLength);
     }
 
+    let lexicon = lang.and_then(|lang| s.filters.language(lang));
     if let Some(lang) = lang {
-        if !lang.starts_with("en") {
+        if !lang.starts_with("en") && lexicon.is_none() {
             return FilterResult::Reject(Filter::EnglishOnly);
         }
     }
 
     let text_lower = text.to_lowercase();
+    let extra_blocked = lexicon
+        .map(|l| l.blocked_keywords.as_slice())
+        .unwrap_or_default();
+    let extra_promo = lexicon
+        .map(|l| l.promo_domains.as_slice())
+        .unwrap_or_default();
 
-    for keyword in &s.filters.blocked_keywords {
+    for keyword in s.filters.blocked_keywords.iter().chain(extra_blocked) {
         if text_lower.contains(keyword) {
             return FilterResult::Reject(Filter::BlockedKeyword(keyword.to_string()));
         }
     }
 
     for hashtag in &s.filters.blocked_hashtags {
-        if text_lower.contains(hashtag) {
+        if hashtags.iter().any(|tag| tag.contains(hashtag.as_str())) {
             return FilterResult::Reject(Filter::BlockedHashtag(hashtag.to_string()));
         }
     }
 
+    if is_low_diversity(&text_lower) {
+        return FilterResult::Reject(Filter::LowDiversity);
+    }
+
     if let Some(did) = author_did {
         if blocked_author_check(did) {
             return FilterResult::Reject(Filter::BlockedAuthor);
@@ -74,16 +134,29 @@ pub fn apply_filters(
         }
     }
 
-    let has_promo = media.facet_links.iter().any(|uri| is_promo_domain(uri))
-        || media
-            .external_uri
-            .as_ref()
-            .is_some_and(|uri| is_promo_domain(uri));
+    let is_promo = |uri: &str| {
+        is_promo_domain(uri)
+            || (!extra_promo.is_empty() && {
+                let uri = uri.to_lowercase();
+                extra_promo
+                    .iter()
+                    .any(|domain| uri.contains(domain.as_str()))
+            })
+    };
+    let has_promo = media.facet_links.iter().any(|uri| is_promo(uri))
+        || media.external_uri.as_deref().is_some_and(is_promo);
     if has_promo {
         return FilterResult::Reject(Filter::PromoLink);
     }
 
-    let hashtag_count = count_all_hashtags(text);
+    if has_links(text, media)
+        && strip_hashtags(&strip_links(text, media)).len()
+            < s.scoring.thresholds.min_text_without_links
+    {
+        return FilterResult::Reject(Filter::LinkOnly);
+    }
+
+    let hashtag_count = hashtags.len();
     if hashtag_count > s.scoring.rejection.max_hashtags as usize {
         return FilterResult::Reject(Filter::TooManyHashtags(hashtag_count));
     }
@@ -91,6 +164,64 @@ pub fn apply_filters(
     FilterResult::Pass
 }
 
+/// The creation time to store for a post claiming `created_at` that arrived at `now`, with
+/// slightly future times clamped to `now` if `limits` allow it.
+pub fn check_post_age(created_at: i64, now: i64, limits: &PostAge) -> Result<i64, Filter> {
+    if created_at < now - limits.max_age_hours * 3600 {
+        return Err(Filter::ImplausibleTimestamp);
+    }
+    if created_at > now + limits.max_future_secs {
+        return if limits.clamp_future {
+            Ok(now)
+        } else {
+            Err(Filter::ImplausibleTimestamp)
+        };
+    }
+    Ok(created_at)
+}
+
+/// Left out when counting distinct words, since ordinary sentences repeat them.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have", "i",
+    "in", "is", "it", "its", "me", "my", "not", "of", "on", "or", "so", "that", "the", "this",
+    "to", "was", "we", "with", "you", "your",
+];
+
+/// Too few distinct words, or characters too repetitive, for a post written by hand. Hashtags
+/// count as the words they tag. Takes the lowercased text.
+fn is_low_diversity(lower: &str) -> bool {
+    let thresholds = &settings().scoring.thresholds;
+
+    let words: Vec<&str> = lower
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty() && !STOPWORDS.contains(word))
+        .collect();
+    if words.len() >= thresholds.diversity_min_words.max(1) {
+        let unique: HashSet<&str> = words.iter().copied().collect();
+        if (unique.len() as f32 / words.len() as f32) < thresholds.min_unique_word_ratio {
+            return true;
+        }
+    }
+
+    char_entropy(lower) < thresholds.min_char_entropy
+}
+
+fn char_entropy(text: &str) -> f32 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        *counts.entry(c).or_default() += 1;
+    }
+    let total = counts.values().sum::<usize>() as f32;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f32 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,6 +244,24 @@ mod tests {
         assert_eq!(result, FilterResult::Reject(Filter::MinLength));
     }
 
+    #[test]
+    fn test_filter_low_diversity() {
+        for text in [
+            "gamedev gamedev gamedev #gamedev check my game",
+            "lol lol lol lol lol lol lol lol",
+            "hahahahahahahahahahahahahaha so good",
+        ] {
+            let result = apply_filters(text, Some("en"), None, &no_media(), no_spammer, no_blocked);
+            assert_eq!(result, FilterResult::Reject(Filter::LowDiversity), "{text}");
+        }
+        assert_eq!(char_entropy(""), 0.0);
+
+        // Repeated stopwords alone don't make a post low-diversity.
+        let text = "the art of the game is the art of the player";
+        let result = apply_filters(text, Some("en"), None, &no_media(), no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Pass);
+    }
+
     #[test]
     fn test_filter_english_only() {
         let text = "This is a long enough text for testing purposes";
@@ -127,10 +276,15 @@ mod tests {
     fn test_filter_blocked_keyword() {
         let text = "Check out my new NFT game collection";
         let result = apply_filters(text, Some("en"), None, &no_media(), no_spammer, no_blocked);
-        assert!(matches!(
-            result,
-            FilterResult::Reject(Filter::BlockedKeyword(_))
-        ));
+        let FilterResult::Reject(filter @ Filter::BlockedKeyword(_)) = result else {
+            panic!("expected a blocked keyword, got {result:?}");
+        };
+        let matched = filter.term_match(text).unwrap();
+        assert_eq!(matched.term, "nft");
+        assert_eq!(
+            matched.snippet.as_deref(),
+            Some("Check out my new **NFT** game collection")
+        );
     }
 
     #[test]
@@ -144,6 +298,21 @@ mod tests {
             .any(|h| text_lower.contains(h)));
     }
 
+    #[test]
+    fn test_filter_opt_out_hashtag() {
+        let text = "Please don't share this one around #NoFeed";
+        let result = apply_filters(text, Some("en"), None, &no_media(), no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Reject(Filter::AuthorOptOut));
+
+        // Checked before anything else, so even a post too short to pass is an opt-out.
+        let media = MediaInfo {
+            facet_tags: Some(vec!["nofeed".to_string()]),
+            ..MediaInfo::default()
+        };
+        let result = apply_filters("hi", Some("en"), None, &media, no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Reject(Filter::AuthorOptOut));
+    }
+
     #[test]
     fn test_filter_spammer() {
         let text = "This is a valid gamedev post about my project";
@@ -196,6 +365,26 @@ mod tests {
         assert_eq!(result, FilterResult::Reject(Filter::PromoLink));
     }
 
+    #[test]
+    fn test_filter_link_only() {
+        let text = "https://youtu.be/dQw4w9WgXcQ new trailer #gamedev #indiedev";
+        let result = apply_filters(text, Some("en"), None, &no_media(), no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Reject(Filter::LinkOnly));
+
+        // Clients shorten links in the text; the facet has the full URL.
+        let media = MediaInfo {
+            facet_links: vec!["https://www.example.com/blog/devlog-12-procedural-caves".to_string()],
+            ..Default::default()
+        };
+        let text = "example.com/blog/devlog-1... #gamedev #devlog #screenshotsaturday";
+        let result = apply_filters(text, Some("en"), None, &media, no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Reject(Filter::LinkOnly));
+
+        let text = "Wrote up how the cave generator follows ore veins: example.com/blog/devlog-1... #gamedev";
+        let result = apply_filters(text, Some("en"), None, &media, no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Pass);
+    }
+
     #[test]
     fn test_filter_too_many_hashtags() {
         let text = "My game #one #two #three #four #five #six #seven is great";
@@ -213,6 +402,51 @@ mod tests {
         assert_eq!(result, FilterResult::Pass);
     }
 
+    #[test]
+    fn test_filter_hashtags_from_facets() {
+        let text = "My game #one #two #three #four #five #six #seven is great";
+        let media = MediaInfo {
+            facet_tags: Some(vec!["one".to_string()]),
+            ..Default::default()
+        };
+        let result = apply_filters(text, Some("en"), None, &media, no_spammer, no_blocked);
+        assert_eq!(result, FilterResult::Pass);
+
+        let media = MediaInfo {
+            facet_tags: Some(vec!["NFTart".to_string()]),
+            ..Default::default()
+        };
+        let text = "Working on my game project today";
+        let result = apply_filters(text, Some("en"), None, &media, no_spammer, no_blocked);
+        assert!(matches!(
+            result,
+            FilterResult::Reject(Filter::BlockedHashtag(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_post_age() {
+        let mut limits = PostAge {
+            max_future_secs: 300,
+            clamp_future: true,
+            max_age_hours: 48,
+        };
+        let now = 1_000_000;
+        assert_eq!(check_post_age(now - 60, now, &limits), Ok(now - 60));
+        assert_eq!(check_post_age(now + 60, now, &limits), Ok(now + 60));
+        assert_eq!(check_post_age(now + 86400, now, &limits), Ok(now));
+        assert_eq!(
+            check_post_age(now - 49 * 3600, now, &limits),
+            Err(Filter::ImplausibleTimestamp)
+        );
+
+        limits.clamp_future = false;
+        assert_eq!(
+            check_post_age(now + 86400, now, &limits),
+            Err(Filter::ImplausibleTimestamp)
+        );
+    }
+
     #[test]
     fn test_filter_pass() {
         let text = "Just implemented a new combat system in my game #gamedev";
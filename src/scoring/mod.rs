@@ -1,14 +1,35 @@
+#[cfg(not(any(feature = "rust-bert", feature = "onnx")))]
+compile_error!("enable the `rust-bert` or `onnx` feature for an ML backend");
+
 mod classification;
+pub mod confidence;
 pub mod content;
 pub mod filters;
+#[cfg(feature = "onnx")]
+mod onnx;
 pub mod priority;
+pub mod queue;
 mod relevance;
+pub mod reputation;
+pub mod semantic;
+pub mod vector_index;
 
-pub use classification::{MLHandle, QualityAssessment};
+pub use classification::{EmbeddingSink, MLHandle, QualityAssessment};
+pub use confidence::{exposure_probability, feed_context, ConfidenceTier, TopicLabel};
 pub use content::{extract_content_signals, is_promo_domain, ContentSignals, MediaInfo};
-pub use filters::{apply_filters, Filter, FilterResult};
-pub use priority::{calculate_priority, PriorityBreakdown, PrioritySignals};
-pub use relevance::{count_all_hashtags, has_hashtags, has_keywords};
+pub use filters::{apply_filters, check_post_age, Filter, FilterResult};
+pub use priority::{
+    bound_priority, calculate_engagement_boost, calculate_priority, calculate_priority_with,
+    thread_boost, time_decay, PriorityBreakdown, PrioritySignals,
+};
+pub use queue::QueueStatus;
+pub use relevance::{
+    alt_text_relevance, bio_relevance, has_hashtags, has_hashtags_for, has_keywords,
+    has_keywords_for, hashtag_matches_for, keyword_matches_for, weighted_keyword_score, TermMatch,
+};
+pub use reputation::{author_reputation, AuthorRecord};
+pub use semantic::{cosine_similarity, decode_embedding, encode_embedding, Embedding};
+pub use vector_index::{SharedVectorIndex, VectorIndex};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,201 @@
+use super::semantic::Embedding;
+use crate::settings::OnnxModels;
+use anyhow::{anyhow, Result};
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::Session;
+use ort::value::Tensor;
+use tokenizers::{Encoding, PaddingParams, Tokenizer, TruncationParams};
+
+struct Batch {
+    shape: [usize; 2],
+    input_ids: Vec<i64>,
+    attention_mask: Vec<i64>,
+    token_type_ids: Vec<i64>,
+}
+
+impl Batch {
+    fn new(encodings: &[Encoding]) -> Self {
+        let seq_len = encodings.first().map(Encoding::len).unwrap_or(0);
+        let flatten = |field: fn(&Encoding) -> &[u32]| -> Vec<i64> {
+            encodings
+                .iter()
+                .flat_map(|e| field(e).iter().map(|v| i64::from(*v)))
+                .collect()
+        };
+        Self {
+            shape: [encodings.len(), seq_len],
+            input_ids: flatten(Encoding::get_ids),
+            attention_mask: flatten(Encoding::get_attention_mask),
+            token_type_ids: flatten(Encoding::get_type_ids),
+        }
+    }
+}
+
+fn load_session(path: &str, intra_threads: usize) -> Result<Session> {
+    Ok(Session::builder()?
+        .with_optimization_level(GraphOptimizationLevel::Level3)?
+        .with_intra_threads(intra_threads)?
+        .commit_from_file(path)?)
+}
+
+fn load_tokenizer(path: &str, max_length: usize) -> Result<Tokenizer> {
+    let mut tokenizer = Tokenizer::from_file(path).map_err(|e| anyhow!("{path}: {e}"))?;
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length,
+            ..Default::default()
+        }))
+        .map_err(|e| anyhow!("{path}: {e}"))?;
+    tokenizer.with_padding(Some(PaddingParams::default()));
+    Ok(tokenizer)
+}
+
+/// Runs the model on a padded batch and returns its first output as (shape, values).
+fn run(session: &mut Session, batch: Batch) -> Result<(Vec<usize>, Vec<f32>)> {
+    let wants_type_ids = session.inputs.iter().any(|i| i.name == "token_type_ids");
+
+    let mut inputs = ort::inputs![
+        "input_ids" => Tensor::from_array((batch.shape, batch.input_ids))?,
+        "attention_mask" => Tensor::from_array((batch.shape, batch.attention_mask))?,
+    ];
+    if wants_type_ids {
+        inputs.push((
+            "token_type_ids".into(),
+            Tensor::from_array((batch.shape, batch.token_type_ids))?.into(),
+        ));
+    }
+
+    let outputs = session.run(inputs)?;
+    let (shape, values) = outputs[0].try_extract_tensor::<f32>()?;
+    Ok((shape.iter().map(|d| *d as usize).collect(), values.to_vec()))
+}
+
+/// Averages token states over the attention mask and L2-normalizes, as sentence-transformers do.
+fn mean_pool(
+    hidden: &[f32],
+    mask: &[i64],
+    batch: usize,
+    seq_len: usize,
+    dim: usize,
+) -> Vec<Embedding> {
+    (0..batch)
+        .map(|b| {
+            let mut pooled = vec![0.0f32; dim];
+            let mut tokens = 0.0f32;
+            for t in 0..seq_len {
+                if mask[b * seq_len + t] == 0 {
+                    continue;
+                }
+                tokens += 1.0;
+                let offset = (b * seq_len + t) * dim;
+                for (p, h) in pooled.iter_mut().zip(&hidden[offset..offset + dim]) {
+                    *p += h;
+                }
+            }
+            let norm = pooled
+                .iter()
+                .map(|v| (v / tokens.max(1.0)).powi(2))
+                .sum::<f32>()
+                .sqrt()
+                .max(f32::EPSILON);
+            pooled.iter().map(|v| v / tokens.max(1.0) / norm).collect()
+        })
+        .collect()
+}
+
+/// Softmax over just the entailment and contradiction logits, like multi-label zero-shot.
+fn entailment_probability(logits: &[f32], entailment: usize, contradiction: usize) -> f32 {
+    let (e, c) = (logits[entailment], logits[contradiction]);
+    1.0 / (1.0 + (c - e).exp())
+}
+
+pub struct OnnxEmbedder {
+    session: Session,
+    tokenizer: Tokenizer,
+}
+
+impl OnnxEmbedder {
+    pub fn load(config: &OnnxModels) -> Result<Self> {
+        Ok(Self {
+            session: load_session(&config.embedding_model, config.intra_threads)?,
+            tokenizer: load_tokenizer(&config.embedding_tokenizer, config.max_length)?,
+        })
+    }
+
+    pub fn embed(&mut self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!(e))?;
+        let batch = Batch::new(&encodings);
+        let mask = batch.attention_mask.clone();
+        let [batch_size, seq_len] = batch.shape;
+
+        let (shape, hidden) = run(&mut self.session, batch)?;
+        let dim = *shape.last().ok_or_else(|| anyhow!("empty model output"))?;
+        Ok(mean_pool(&hidden, &mask, batch_size, seq_len, dim))
+    }
+}
+
+pub struct OnnxClassifier {
+    session: Session,
+    tokenizer: Tokenizer,
+    entailment_index: usize,
+    contradiction_index: usize,
+}
+
+impl OnnxClassifier {
+    pub fn load(config: &OnnxModels) -> Result<Self> {
+        Ok(Self {
+            session: load_session(&config.classifier_model, config.intra_threads)?,
+            tokenizer: load_tokenizer(&config.classifier_tokenizer, config.max_length)?,
+            entailment_index: config.entailment_index,
+            contradiction_index: config.contradiction_index,
+        })
+    }
+
+    /// Entailment probability of every hypothesis for every premise, premise-major.
+    pub fn predict(&mut self, premises: &[&str], hypotheses: &[String]) -> Result<Vec<Vec<f32>>> {
+        let pairs: Vec<(&str, &str)> = premises
+            .iter()
+            .flat_map(|p| hypotheses.iter().map(move |h| (*p, h.as_str())))
+            .collect();
+        if pairs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| anyhow!(e))?;
+        let (shape, logits) = run(&mut self.session, Batch::new(&encodings))?;
+        let classes = *shape.last().ok_or_else(|| anyhow!("empty model output"))?;
+
+        let scores: Vec<f32> = logits
+            .chunks_exact(classes)
+            .map(|l| entailment_probability(l, self.entailment_index, self.contradiction_index))
+            .collect();
+        Ok(scores
+            .chunks(hypotheses.len())
+            .map(<[f32]>::to_vec)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_pool_skips_padding() {
+        let hidden = [1.0, 0.0, 3.0, 0.0, 100.0, 100.0];
+        let pooled = mean_pool(&hidden, &[1, 1, 0], 1, 3, 2);
+        assert_eq!(pooled, vec![vec![1.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_entailment_probability() {
+        assert!((entailment_probability(&[0.0, 0.0, 0.0], 1, 0) - 0.5).abs() < 1e-6);
+        assert!(entailment_probability(&[-2.0, 3.0, 0.0], 1, 0) > 0.99);
+    }
+}
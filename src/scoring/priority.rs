@@ -1,9 +1,11 @@
 use super::classification::QualityAssessment;
+use super::confidence::TopicLabel;
 use super::content::ContentSignals;
-use crate::settings::settings;
+use crate::settings::{settings, Decay, PriorityBounds, PrioritySquash, Settings};
 use crate::utils::logs::{dim, format_signed, pad_label};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PrioritySignals {
     pub engagement_bait_score: f32,
     pub synthetic_score: f32,
@@ -14,6 +16,20 @@ pub struct PrioritySignals {
     pub has_video: bool,
     pub has_alt_text: bool,
     pub link_count: u8,
+    pub mention_count: u8,
+    pub is_reply_bait: bool,
+    /// Summed weights of the gamedev keywords in the post, from `weighted_keyword_score`.
+    #[serde(default)]
+    pub keyword_score: f32,
+    /// How gamedev the alt text and link card look, from `alt_text_relevance`.
+    pub alt_text_relevance: f32,
+
+    pub bio_relevance: f32,
+    pub prior_accepted: u32,
+    /// From `author_reputation`, in [-1, 1].
+    pub author_reputation: f32,
+    /// Similarity to the closest post readers asked to see less of.
+    pub negative_similarity: f32,
 
     pub engagement_velocity: f32,
     pub reply_count: i32,
@@ -32,24 +48,39 @@ impl PrioritySignals {
             has_video: content.has_video,
             has_alt_text: content.has_alt_text,
             link_count: content.link_count,
+            mention_count: content.mention_count,
+            is_reply_bait: content.is_reply_bait,
             ..Default::default()
         }
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PriorityBreakdown {
     pub quality_penalty: f32,
     pub content_modifier: f32,
     pub engagement_boost: f32,
     pub authenticity_boost: f32,
+    pub author_boost: f32,
     pub priority: f32,
     pub boost_reasons: Vec<String>,
     pub penalty_reasons: Vec<String>,
 }
 
+impl PriorityBreakdown {
+    /// The priority the post earned on its own, before the boosts and penalties from its
+    /// author's bio, history and reputation.
+    pub fn base_priority(&self) -> f32 {
+        self.priority - self.author_boost
+    }
+}
+
 pub fn calculate_priority(signals: &PrioritySignals) -> PriorityBreakdown {
-    let s = settings();
+    calculate_priority_with(signals, &settings())
+}
+
+/// `calculate_priority` under `s` instead of the live settings, as shadow scoring does.
+pub fn calculate_priority_with(signals: &PrioritySignals, s: &Settings) -> PriorityBreakdown {
     let mut boosts = Vec::new();
     let mut penalties = Vec::new();
 
@@ -60,7 +91,7 @@ pub fn calculate_priority(signals: &PrioritySignals) -> PriorityBreakdown {
         penalties.push(format!(
             "{}{}",
             pad_label("engagement-bait:", 2),
-            format_signed(signals.engagement_bait_score * -1.0)
+            format_signed(-signals.engagement_bait_score)
         ));
     }
 
@@ -69,7 +100,37 @@ pub fn calculate_priority(signals: &PrioritySignals) -> PriorityBreakdown {
         penalties.push(format!(
             "{}{}",
             pad_label("synthetic:", 2),
-            format_signed(signals.synthetic_score * -1.0)
+            format_signed(-signals.synthetic_score)
+        ));
+    }
+
+    if signals.is_reply_bait {
+        quality_penalty += s.scoring.penalties.reply_bait;
+        penalties.push(format!(
+            "{}{}",
+            pad_label("reply-bait:", 2),
+            format_signed(-s.scoring.penalties.reply_bait)
+        ));
+    }
+
+    if signals.mention_count >= s.scoring.penalties.many_mentions_threshold {
+        quality_penalty += s.scoring.penalties.many_mentions;
+        penalties.push(format!(
+            "{}{} {}",
+            pad_label("mentions:", 2),
+            format_signed(-s.scoring.penalties.many_mentions),
+            dim().apply_to(format!("({})", signals.mention_count))
+        ));
+    }
+
+    let negative = &s.scoring.negative_examples;
+    if negative.enabled && signals.negative_similarity >= negative.min_similarity {
+        quality_penalty += negative.penalty;
+        penalties.push(format!(
+            "{}{} {}",
+            pad_label("disliked:", 2),
+            format_signed(-negative.penalty),
+            dim().apply_to(format!("({:.2})", signals.negative_similarity))
         ));
     }
 
@@ -102,6 +163,30 @@ pub fn calculate_priority(signals: &PrioritySignals) -> PriorityBreakdown {
         ));
     }
 
+    if signals.keyword_score > 0.0 {
+        let keyword_boost = (signals.keyword_score * s.scoring.bonuses.keyword)
+            .min(s.scoring.bonuses.keyword_max_boost);
+        content_modifier += keyword_boost;
+        boosts.push(format!(
+            "{}{} {}",
+            pad_label("keywords:", 2),
+            format_signed(keyword_boost),
+            dim().apply_to(format!("({:.1})", signals.keyword_score))
+        ));
+    }
+
+    let alt = &s.scoring.alt_text;
+    if alt.enabled && signals.alt_text_relevance >= alt.min_relevance {
+        let alt_boost = signals.alt_text_relevance * alt.bonus;
+        content_modifier += alt_boost;
+        boosts.push(format!(
+            "{}{} {}",
+            pad_label("alt-relevance:", 2),
+            format_signed(alt_boost),
+            dim().apply_to(format!("({:.2})", signals.alt_text_relevance))
+        ));
+    }
+
     if signals.images >= s.scoring.penalties.many_images_threshold {
         content_modifier -= s.scoring.penalties.many_images;
         penalties.push(format!(
@@ -127,7 +212,7 @@ pub fn calculate_priority(signals: &PrioritySignals) -> PriorityBreakdown {
         ));
     }
 
-    let engagement_boost = calculate_engagement_boost(signals);
+    let engagement_boost = engagement_boost_with(signals, s);
     if engagement_boost >= s.scoring.quality.engagement_boost_min {
         boosts.push(format!(
             "{}{}",
@@ -145,21 +230,115 @@ pub fn calculate_priority(signals: &PrioritySignals) -> PriorityBreakdown {
         ));
     }
 
-    let priority = content_modifier + engagement_boost + authenticity_boost - quality_penalty;
+    let base_priority = content_modifier + engagement_boost + authenticity_boost - quality_penalty;
+
+    let mut author_boost = 0.0;
+    if base_priority < s.scoring.author.borderline_max_priority {
+        if signals.bio_relevance > 0.0 {
+            let bio_boost = signals.bio_relevance * s.scoring.author.bio_relevance;
+            author_boost += bio_boost;
+            boosts.push(format!(
+                "{}{}",
+                pad_label("dev-bio:", 2),
+                format_signed(bio_boost),
+            ));
+        }
+
+        if signals.prior_accepted > 0 {
+            let history_boost = (signals.prior_accepted as f32 * s.scoring.author.history_per_post)
+                .min(s.scoring.author.history_max_boost);
+            author_boost += history_boost;
+            boosts.push(format!(
+                "{}{} {}",
+                pad_label("history:", 2),
+                format_signed(history_boost),
+                dim().apply_to(format!("({})", signals.prior_accepted))
+            ));
+        }
+    }
+
+    let reputation = &s.authors.reputation;
+    if reputation.enabled && signals.author_reputation > 0.0 {
+        let reputation_boost = signals.author_reputation * reputation.boost;
+        author_boost += reputation_boost;
+        boosts.push(format!(
+            "{}{} {}",
+            pad_label("reputation:", 2),
+            format_signed(reputation_boost),
+            dim().apply_to(format!("({:.2})", signals.author_reputation))
+        ));
+    } else if reputation.enabled && signals.author_reputation < 0.0 {
+        let reputation_penalty = -signals.author_reputation * reputation.penalty;
+        author_boost -= reputation_penalty;
+        penalties.push(format!(
+            "{}{} {}",
+            pad_label("reputation:", 2),
+            format_signed(-reputation_penalty),
+            dim().apply_to(format!("({:.2})", signals.author_reputation))
+        ));
+    }
+
+    let priority = base_priority + author_boost;
 
     PriorityBreakdown {
         quality_penalty,
         content_modifier,
         engagement_boost,
         authenticity_boost,
+        author_boost,
         priority,
         boost_reasons: boosts,
         penalty_reasons: penalties,
     }
 }
 
-fn calculate_engagement_boost(signals: &PrioritySignals) -> f32 {
+/// Serve-time boost for a root post the author kept adding to, as devlog threads tend to be.
+pub fn thread_boost(thread_length: i32) -> f32 {
     let s = settings();
+    let bonuses = &s.scoring.bonuses;
+    if thread_length < bonuses.thread_min_length {
+        return 0.0;
+    }
+    ((thread_length - 1) as f32 * bonuses.thread_per_post).min(bonuses.thread_max_boost)
+}
+
+/// Serve-time multiplier that halves a post's priority every `half_life_hours`, sped up or
+/// slowed down by its topic's rate. Negative priorities are divided by it instead, so they sink
+/// as they age rather than rising towards zero.
+pub fn time_decay(age_secs: i64, topic: TopicLabel, decay: &Decay) -> f32 {
+    if !decay.enabled || decay.half_life_hours <= 0.0 {
+        return 1.0;
+    }
+    let rate = decay
+        .topic_rates
+        .get(&topic.to_string())
+        .copied()
+        .unwrap_or(1.0)
+        .max(0.0);
+    let age_hours = age_secs.max(0) as f32 / 3600.0;
+    0.5f32.powf(age_hours * rate / decay.half_life_hours)
+}
+
+/// Brings a serve-time priority within `bounds` the way its `squash` mode says.
+pub fn bound_priority(priority: f32, bounds: &PriorityBounds) -> f32 {
+    let (floor, ceiling) = (bounds.floor, bounds.ceiling.max(bounds.floor));
+    match bounds.squash {
+        PrioritySquash::Off => priority,
+        PrioritySquash::Clamp => priority.clamp(floor, ceiling),
+        PrioritySquash::Logistic if ceiling > floor => {
+            let range = ceiling - floor;
+            let midpoint = floor + range / 2.0;
+            floor + range / (1.0 + (-4.0 * (priority - midpoint) / range).exp())
+        }
+        PrioritySquash::Logistic => floor,
+    }
+}
+
+pub fn calculate_engagement_boost(signals: &PrioritySignals) -> f32 {
+    engagement_boost_with(signals, &settings())
+}
+
+fn engagement_boost_with(signals: &PrioritySignals, s: &Settings) -> f32 {
     if signals.engagement_velocity > 0.0 {
         (signals.engagement_velocity.ln_1p() * s.engagement.velocity_scale)
             .min(s.engagement.max_boost)
@@ -198,6 +377,201 @@ mod tests {
             .any(|r| r.contains("first-person")));
     }
 
+    #[test]
+    fn test_reply_bait_and_mentions_penalized() {
+        let s = settings();
+        let signals = PrioritySignals {
+            is_reply_bait: true,
+            mention_count: s.scoring.penalties.many_mentions_threshold,
+            ..Default::default()
+        };
+        let breakdown = calculate_priority(&signals);
+        assert_eq!(
+            breakdown.quality_penalty,
+            s.scoring.penalties.reply_bait + s.scoring.penalties.many_mentions
+        );
+        assert!(breakdown
+            .penalty_reasons
+            .iter()
+            .any(|r| r.contains("reply-bait")));
+    }
+
+    #[test]
+    fn test_negative_example_penalty() {
+        let negative = &settings().scoring.negative_examples;
+        let mut signals = PrioritySignals {
+            negative_similarity: negative.min_similarity - 0.01,
+            ..Default::default()
+        };
+        assert_eq!(calculate_priority(&signals).quality_penalty, 0.0);
+
+        signals.negative_similarity = negative.min_similarity;
+        let breakdown = calculate_priority(&signals);
+        assert_eq!(breakdown.quality_penalty, negative.penalty);
+        assert!(breakdown
+            .penalty_reasons
+            .iter()
+            .any(|r| r.contains("disliked")));
+    }
+
+    #[test]
+    fn test_alt_text_relevance_boost() {
+        let alt = &settings().scoring.alt_text;
+        let mut signals = PrioritySignals {
+            alt_text_relevance: alt.min_relevance - 0.01,
+            ..Default::default()
+        };
+        let below = calculate_priority(&signals).priority;
+
+        signals.alt_text_relevance = 1.0;
+        let breakdown = calculate_priority(&signals);
+        assert!((breakdown.priority - below - alt.bonus).abs() < 1e-6);
+        assert!(breakdown
+            .boost_reasons
+            .iter()
+            .any(|r| r.contains("alt-relevance")));
+    }
+
+    #[test]
+    fn test_priority_with_other_settings() {
+        let signals = PrioritySignals {
+            is_first_person: true,
+            ..Default::default()
+        };
+        let mut shadow = (**settings()).clone();
+        shadow.scoring.bonuses.first_person += 0.5;
+
+        let live = calculate_priority(&signals).priority;
+        let shadowed = calculate_priority_with(&signals, &shadow).priority;
+        assert!((shadowed - live - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_keyword_score_boost() {
+        let bonuses = &settings().scoring.bonuses;
+        let mut signals = PrioritySignals::default();
+        let base = calculate_priority(&signals).priority;
+
+        signals.keyword_score = 0.4;
+        let weak = calculate_priority(&signals).priority - base;
+        signals.keyword_score = 1.0;
+        let strong = calculate_priority(&signals);
+        assert!(weak > 0.0 && weak < strong.priority - base);
+        assert!(strong.boost_reasons.iter().any(|r| r.contains("keywords")));
+
+        signals.keyword_score = 100.0;
+        let capped = calculate_priority(&signals).priority - base;
+        assert!((capped - bonuses.keyword_max_boost).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_thread_boost() {
+        let s = settings();
+        let min_length = s.scoring.bonuses.thread_min_length;
+
+        assert_eq!(thread_boost(1), 0.0);
+        assert_eq!(thread_boost(min_length - 1), 0.0);
+        assert!(thread_boost(min_length) > 0.0);
+        assert!(thread_boost(min_length + 1) > thread_boost(min_length));
+        assert_eq!(thread_boost(1000), s.scoring.bonuses.thread_max_boost);
+    }
+
+    #[test]
+    fn test_time_decay_by_topic() {
+        let mut decay = crate::settings::Settings::default().feed.decay;
+        decay.enabled = true;
+        decay.half_life_hours = 10.0;
+        decay.topic_rates = [("discussion".to_string(), 2.0)].into();
+        let hours = |h: i64| h * 3600;
+
+        assert_eq!(time_decay(0, TopicLabel::Update, &decay), 1.0);
+        assert_eq!(time_decay(hours(10), TopicLabel::Update, &decay), 0.5);
+        assert_eq!(time_decay(hours(10), TopicLabel::Discussion, &decay), 0.25);
+        assert_eq!(time_decay(hours(-5), TopicLabel::Update, &decay), 1.0);
+
+        decay.enabled = false;
+        assert_eq!(time_decay(hours(10), TopicLabel::Discussion, &decay), 1.0);
+    }
+
+    #[test]
+    fn test_bound_priority() {
+        let mut bounds = crate::settings::Settings::default().feed.priority_bounds;
+        bounds.floor = -1.0;
+        bounds.ceiling = 3.0;
+
+        bounds.squash = PrioritySquash::Off;
+        assert_eq!(bound_priority(10.0, &bounds), 10.0);
+
+        bounds.squash = PrioritySquash::Clamp;
+        assert_eq!(bound_priority(10.0, &bounds), 3.0);
+        assert_eq!(bound_priority(-4.0, &bounds), -1.0);
+
+        bounds.squash = PrioritySquash::Logistic;
+        assert_eq!(bound_priority(1.0, &bounds), 1.0);
+        let (high, higher) = (bound_priority(4.0, &bounds), bound_priority(40.0, &bounds));
+        assert!(high < higher && higher <= 3.0);
+        assert!((bound_priority(1.01, &bounds) - 1.01).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_bio_boost_only_for_borderline() {
+        let mut signals = PrioritySignals {
+            link_count: 1,
+            bio_relevance: 1.0,
+            ..Default::default()
+        };
+        let borderline = calculate_priority(&signals);
+        assert!(borderline.author_boost > 0.0);
+        assert!(borderline
+            .boost_reasons
+            .iter()
+            .any(|r| r.contains("dev-bio")));
+
+        signals.link_count = 0;
+        signals.has_video = true;
+        signals.is_first_person = true;
+        let strong = calculate_priority(&signals);
+        assert_eq!(strong.author_boost, 0.0);
+    }
+
+    #[test]
+    fn test_author_reputation_boost_and_penalty() {
+        let reputation = &settings().authors.reputation;
+        let mut signals = PrioritySignals {
+            has_video: true,
+            is_first_person: true,
+            author_reputation: 1.0,
+            ..Default::default()
+        };
+        let trusted = calculate_priority(&signals);
+        assert!((trusted.author_boost - reputation.boost).abs() < 1e-6);
+
+        signals.author_reputation = -1.0;
+        let demoted = calculate_priority(&signals);
+        assert!((demoted.author_boost + reputation.penalty).abs() < 1e-6);
+        assert!((demoted.base_priority() - trusted.base_priority()).abs() < 1e-6);
+        assert!(demoted
+            .penalty_reasons
+            .iter()
+            .any(|r| r.contains("reputation")));
+    }
+
+    #[test]
+    fn test_history_boost_is_bounded() {
+        let s = settings();
+        let signals = PrioritySignals {
+            link_count: 1,
+            prior_accepted: 1000,
+            ..Default::default()
+        };
+        let breakdown = calculate_priority(&signals);
+        assert_eq!(breakdown.author_boost, s.scoring.author.history_max_boost);
+        assert!(breakdown
+            .boost_reasons
+            .iter()
+            .any(|r| r.contains("history")));
+    }
+
     #[test]
     fn test_video_boost() {
         let mut signals = PrioritySignals::default();
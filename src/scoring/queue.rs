@@ -0,0 +1,185 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueStatus {
+    pub depth: usize,
+    pub peak_depth: usize,
+    pub capacity: usize,
+    /// Firehose posts dropped unscored because the queue was full.
+    pub shed: u64,
+}
+
+struct Queued<T> {
+    request: T,
+    /// How strongly the post looked like gamedev before scoring. `None` is never shed.
+    signal: Option<usize>,
+}
+
+struct QueueState<T> {
+    pending: VecDeque<Queued<T>>,
+    closed: bool,
+    peak_depth: usize,
+    shed: u64,
+}
+
+/// Requests waiting for the ML worker. Once `capacity` are waiting, a new sheddable request
+/// either replaces the queued one with the lowest signal, or is dropped itself if its signal is
+/// no higher. Requests without a signal are always queued.
+pub struct MlQueue<T> {
+    state: Mutex<QueueState<T>>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+impl<T> MlQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                pending: VecDeque::new(),
+                closed: false,
+                peak_depth: 0,
+                shed: 0,
+            }),
+            ready: Condvar::new(),
+            capacity,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queues `request`, returning the request that was shed to make room, if any. That may be
+    /// `request` itself. Dropping a shed request tells its caller it won't be answered.
+    pub fn push(&self, request: T, signal: Option<usize>) -> Option<T> {
+        let mut state = self.lock();
+        if state.closed {
+            return Some(request);
+        }
+        let mut shed = None;
+        if let Some(signal) = signal {
+            if state.pending.len() >= self.capacity {
+                let weakest = state
+                    .pending
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, queued)| Some((i, queued.signal?)))
+                    .min_by_key(|&(_, signal)| signal);
+                match weakest {
+                    Some((i, weakest)) if weakest < signal => {
+                        shed = state.pending.remove(i).map(|queued| queued.request);
+                    }
+                    _ => {
+                        state.shed += 1;
+                        return Some(request);
+                    }
+                }
+                state.shed += 1;
+            }
+        }
+        state.pending.push_back(Queued { request, signal });
+        state.peak_depth = state.peak_depth.max(state.pending.len());
+        drop(state);
+        self.ready.notify_one();
+        shed
+    }
+
+    /// Waits for a request, then for up to `batch_size`, as long as each next one arrives within
+    /// `timeout`. Returns `None` once the queue is closed and drained.
+    pub fn next_batch(&self, batch_size: usize, timeout: Duration) -> Option<Vec<T>> {
+        let mut state = self
+            .ready
+            .wait_while(self.lock(), |state| {
+                state.pending.is_empty() && !state.closed
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        if state.pending.is_empty() {
+            return None;
+        }
+
+        while state.pending.len() < batch_size && !state.closed {
+            let waiting = state.pending.len();
+            let (next, result) = self
+                .ready
+                .wait_timeout_while(state, timeout, |state| {
+                    state.pending.len() <= waiting && !state.closed
+                })
+                .unwrap_or_else(PoisonError::into_inner);
+            state = next;
+            if result.timed_out() {
+                break;
+            }
+        }
+
+        let count = state.pending.len().min(batch_size.max(1));
+        Some(
+            state
+                .pending
+                .drain(..count)
+                .map(|queued| queued.request)
+                .collect(),
+        )
+    }
+
+    /// Lets the worker finish what's queued and stop.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_all();
+    }
+
+    /// Closes the queue and drops what's waiting, for when the worker can't run.
+    pub fn abandon(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        state.pending.clear();
+        drop(state);
+        self.ready.notify_all();
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let state = self.lock();
+        QueueStatus {
+            depth: state.pending.len(),
+            peak_depth: state.peak_depth,
+            capacity: self.capacity,
+            shed: state.shed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sheds_lowest_signal_first() {
+        let queue = MlQueue::new(2);
+        assert_eq!(queue.push("a", Some(1)), None);
+        assert_eq!(queue.push("b", Some(3)), None);
+
+        // Full: a stronger post replaces the weakest, a weaker one is dropped itself.
+        assert_eq!(queue.push("c", Some(2)), Some("a"));
+        assert_eq!(queue.push("d", Some(1)), Some("d"));
+        // Requests without a signal, like embeddings, always get in.
+        assert_eq!(queue.push("e", None), None);
+
+        let status = queue.status();
+        assert_eq!((status.depth, status.peak_depth, status.shed), (3, 3, 2));
+
+        let batch = queue.next_batch(8, Duration::from_millis(1)).unwrap();
+        assert_eq!(batch, vec!["b", "c", "e"]);
+        assert_eq!(queue.status().depth, 0);
+
+        queue.push("f", None);
+        queue.close();
+        assert_eq!(queue.push("g", None), Some("g"));
+        assert_eq!(
+            queue.next_batch(8, Duration::from_millis(1)),
+            Some(vec!["f"])
+        );
+        assert_eq!(queue.next_batch(8, Duration::from_millis(1)), None);
+    }
+}
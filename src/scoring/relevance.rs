@@ -1,63 +1,324 @@
-use crate::settings::settings;
-use regex::Regex;
-use std::sync::LazyLock;
+use crate::settings::{settings, settings_generation};
+use regex::{Match, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
 
 static WORD_SPLIT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[^a-zA-Z0-9]+").unwrap());
-static HASHTAG_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#\w+").unwrap());
+static WORD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[a-zA-Z0-9]+").unwrap());
+/// Mirrors how Bluesky clients detect tags: `#` after whitespace, up to the next whitespace.
+static HASHTAG_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(^|\s)[#＃]([^\s#＃\u{00AD}\u{2060}\u{200A}\u{200B}\u{200C}\u{200D}\u{20E2}]+)")
+        .unwrap()
+});
+static TRAILING_PUNCTUATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\p{P}+$").unwrap());
+static TAG_CHARACTER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[^\d\p{P}]").unwrap());
 
 pub fn strip_hashtags(text: &str) -> String {
-    HASHTAG_PATTERN.replace_all(text, "").trim().to_string()
+    HASHTAG_PATTERN.replace_all(text, "$1").trim().to_string()
 }
 
-pub fn count_all_hashtags(text: &str) -> usize {
-    HASHTAG_PATTERN.find_iter(text).count()
+/// The post's hashtags as lowercase `#tag`s. Tag facets are authoritative when the record has
+/// facets; otherwise tags are parsed from the text, skipping purely numeric ones like `#1`.
+pub fn extract_hashtags(text: &str, facet_tags: Option<&[String]>) -> Vec<String> {
+    if let Some(tags) = facet_tags {
+        return tags
+            .iter()
+            .map(|tag| format!("#{}", tag.trim_start_matches(['#', '＃']).to_lowercase()))
+            .collect();
+    }
+
+    HASHTAG_PATTERN
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let tag = TRAILING_PUNCTUATION.replace(caps.get(2)?.as_str(), "");
+            TAG_CHARACTER
+                .is_match(&tag)
+                .then(|| format!("#{}", tag.to_lowercase()))
+        })
+        .collect()
 }
 
-fn contains_keyword(text: &str, keyword: &str) -> bool {
-    let keyword_parts: Vec<&str> = WORD_SPLIT
-        .split(keyword)
-        .filter(|s| !s.is_empty())
-        .collect();
-    let words: Vec<&str> = WORD_SPLIT.split(text).filter(|s| !s.is_empty()).collect();
+/// Folds common English plurals ("shaders", "gamejams", "assets", "bodies") to their singular.
+fn fold_plural(word: &str) -> String {
+    let mut word = word.to_ascii_lowercase();
+    if word.len() > 4 && word.ends_with("ies") {
+        word.truncate(word.len() - 3);
+        word.push('y');
+    } else if word.len() > 4
+        && ["ches", "shes", "sses", "xes", "zes"]
+            .iter()
+            .any(|suffix| word.ends_with(suffix))
+    {
+        word.truncate(word.len() - 2);
+    } else if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+        word.pop();
+    }
+    word
+}
 
-    if keyword_parts.len() == 1 {
-        words.iter().any(|w| w.eq_ignore_ascii_case(keyword))
+/// A word as keywords are compared: lowercased, and folded to singular with `fold_plurals`.
+fn normalize_word(word: &str, fold_plurals: bool) -> String {
+    if fold_plurals {
+        fold_plural(word)
     } else {
-        words.windows(keyword_parts.len()).any(|window| {
+        word.to_ascii_lowercase()
+    }
+}
+
+/// A configured keyword with its words already normalized.
+struct Keyword {
+    term: String,
+    parts: Vec<String>,
+}
+
+impl Keyword {
+    fn new(term: &str, fold_plurals: bool) -> Self {
+        Self {
+            term: term.to_string(),
+            parts: WORD_SPLIT
+                .split(term)
+                .filter(|s| !s.is_empty())
+                .map(|part| normalize_word(part, fold_plurals))
+                .collect(),
+        }
+    }
+}
+
+/// The configured keywords, normalized once per settings generation instead of per post.
+struct Keywords {
+    fold_plurals: bool,
+    base: Vec<Keyword>,
+    /// Keywords of each enabled lexicon, by lexicon code.
+    languages: HashMap<String, Vec<Keyword>>,
+}
+
+static KEYWORDS: RwLock<Option<(u64, Arc<Keywords>)>> = RwLock::new(None);
+
+fn keywords() -> Arc<Keywords> {
+    let generation = settings_generation();
+    if let Some((loaded_at, keywords)) = KEYWORDS.read().unwrap().as_ref() {
+        if *loaded_at == generation {
+            return keywords.clone();
+        }
+    }
+
+    let s = settings();
+    let fold_plurals = s.filters.fold_plurals;
+    let normalize = |terms: &[String]| -> Vec<Keyword> {
+        terms
+            .iter()
+            .map(|term| Keyword::new(term, fold_plurals))
+            .filter(|kw| !kw.parts.is_empty())
+            .collect()
+    };
+    let keywords = Arc::new(Keywords {
+        fold_plurals,
+        base: normalize(&s.filters.gamedev_keywords),
+        languages: s
+            .filters
+            .languages
+            .iter()
+            .filter(|l| l.enabled)
+            .map(|l| (l.code.clone(), normalize(&l.gamedev_keywords)))
+            .collect(),
+    });
+    *KEYWORDS.write().unwrap() = Some((generation, keywords.clone()));
+    keywords
+}
+
+/// Byte range of the first run of `words` matching `keyword`.
+fn find_keyword(words: &[(Match, String)], keyword: &Keyword) -> Option<(usize, usize)> {
+    words
+        .windows(keyword.parts.len())
+        .find(|window| {
             window
                 .iter()
-                .zip(keyword_parts.iter())
-                .all(|(w, kw)| w.eq_ignore_ascii_case(kw))
+                .zip(&keyword.parts)
+                .all(|((_, word), part)| word == part)
         })
+        .map(|window| (window[0].0.start(), window[window.len() - 1].0.end()))
+}
+
+/// Byte range of `tag` (a lowercase `#tag`) where it's written in `text`.
+fn find_hashtag(text: &str, tag: &str) -> Option<(usize, usize)> {
+    HASHTAG_PATTERN.captures_iter(text).find_map(|caps| {
+        let name = caps.get(2)?;
+        let found = TRAILING_PUNCTUATION.replace(name.as_str(), "");
+        if format!("#{}", found.to_lowercase()) != tag {
+            return None;
+        }
+        let sign = text[..name.start()].chars().next_back()?;
+        Some((name.start() - sign.len_utf8(), name.start() + found.len()))
+    })
+}
+
+/// Byte range of the first case-insensitive occurrence of `needle` in `text`.
+pub fn find_ignore_case(text: &str, needle: &str) -> Option<(usize, usize)> {
+    let needle = needle.to_lowercase();
+    if needle.is_empty() {
+        return None;
     }
+    text.char_indices().find_map(|(start, _)| {
+        let mut lowered = String::new();
+        for (offset, c) in text[start..].char_indices() {
+            lowered.extend(c.to_lowercase());
+            if lowered.len() >= needle.len() {
+                return (lowered == needle).then_some((start, start + offset + c.len_utf8()));
+            }
+        }
+        None
+    })
+}
+
+/// Characters of context kept on each side of a match in its snippet.
+const SNIPPET_CONTEXT_CHARS: usize = 30;
+
+/// A configured keyword or hashtag found in a post, and where.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermMatch {
+    pub term: String,
+    /// Byte range in the text it was found in. `None` for tag facets not written in the text.
+    pub span: Option<(usize, usize)>,
+    /// The text around the match, on one line, with the match in `**`.
+    pub snippet: Option<String>,
+    /// Found in alt text or image text rather than the post body.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_attached_text: bool,
+}
+
+impl TermMatch {
+    pub fn new(term: &str, text: &str, span: Option<(usize, usize)>) -> Self {
+        Self {
+            term: term.to_string(),
+            span,
+            snippet: span.map(|span| snippet(text, span)),
+            in_attached_text: false,
+        }
+    }
+}
+
+fn snippet(text: &str, (start, end): (usize, usize)) -> String {
+    let before: Vec<char> = text[..start]
+        .chars()
+        .rev()
+        .take(SNIPPET_CONTEXT_CHARS)
+        .collect();
+    let before: String = before.into_iter().rev().collect();
+    let after: String = text[end..].chars().take(SNIPPET_CONTEXT_CHARS).collect();
+    let lead = if before.len() < start { "…" } else { "" };
+    let trail = if end + after.len() < text.len() {
+        "…"
+    } else {
+        ""
+    };
+    format!("{lead}{before}**{}**{after}{trail}", &text[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub fn has_keywords(text: &str) -> (bool, usize) {
-    let s = settings();
-    let keywords = &s.filters.gamedev_keywords;
-    let text_lower = text.to_lowercase();
-    let count = keywords
-        .iter()
-        .filter(|kw| contains_keyword(&text_lower, kw))
-        .count();
+    has_keywords_for(text, None)
+}
+
+/// Matches the base keywords plus those of the enabled lexicon for `lang`, if any.
+pub fn has_keywords_for(text: &str, lang: Option<&str>) -> (bool, usize) {
+    let count = keyword_matches_for(text, lang).len();
     (count > 0, count)
 }
 
-pub fn has_hashtags(text: &str) -> (bool, usize) {
+/// Each keyword `has_keywords_for` counts, with where it first matched.
+pub fn keyword_matches_for(text: &str, lang: Option<&str>) -> Vec<TermMatch> {
+    let keywords = keywords();
     let s = settings();
-    let hashtags = &s.filters.gamedev_hashtags;
-    let text_lower = text.to_lowercase();
-    let text_hashtags: Vec<&str> = HASHTAG_PATTERN
-        .find_iter(&text_lower)
-        .map(|m| m.as_str())
+    let extra = lang
+        .and_then(|lang| s.filters.language(lang))
+        .and_then(|l| keywords.languages.get(l.code.as_str()))
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let words: Vec<(Match, String)> = WORD
+        .find_iter(text)
+        .map(|word| (word, normalize_word(word.as_str(), keywords.fold_plurals)))
         .collect();
-    let count = hashtags
+    keywords
+        .base
         .iter()
-        .filter(|tag| text_hashtags.iter().any(|h| *h == tag.as_str()))
-        .count();
+        .chain(extra)
+        .filter_map(|kw| {
+            find_keyword(&words, kw).map(|span| TermMatch::new(&kw.term, text, Some(span)))
+        })
+        .collect()
+}
+
+/// Summed `filters.keyword_weights` of the keyword matches in the post body. Matches in
+/// attached text are left to `alt_text_relevance`.
+pub fn weighted_keyword_score(matches: &[TermMatch]) -> f32 {
+    let s = settings();
+    matches
+        .iter()
+        .filter(|m| !m.in_attached_text)
+        .map(|m| s.filters.keyword_weight(&m.term))
+        .sum()
+}
+
+pub fn has_hashtags(text: &str) -> (bool, usize) {
+    has_hashtags_for(text, None, None)
+}
+
+pub fn has_hashtags_for(
+    text: &str,
+    facet_tags: Option<&[String]>,
+    lang: Option<&str>,
+) -> (bool, usize) {
+    let count = hashtag_matches_for(text, facet_tags, lang).len();
     (count > 0, count)
 }
 
+/// Each hashtag `has_hashtags_for` counts, with where it's written in the text.
+pub fn hashtag_matches_for(
+    text: &str,
+    facet_tags: Option<&[String]>,
+    lang: Option<&str>,
+) -> Vec<TermMatch> {
+    let s = settings();
+    let extra = lang
+        .and_then(|lang| s.filters.language(lang))
+        .map(|l| l.gamedev_hashtags.as_slice())
+        .unwrap_or_default();
+    let text_hashtags = extract_hashtags(text, facet_tags);
+    s.filters
+        .gamedev_hashtags
+        .iter()
+        .chain(extra)
+        .filter(|tag| text_hashtags.contains(tag))
+        .map(|tag| TermMatch::new(tag, text, find_hashtag(text, tag)))
+        .collect()
+}
+
+pub fn bio_relevance(bio: &str, reference_similarity: f32) -> f32 {
+    let s = settings();
+    let keywords = weighted_keyword_score(&keyword_matches_for(bio, None));
+    let (_, hashtag_count) = has_hashtags(bio);
+    let keyword_score = (keywords + hashtag_count as f32).min(3.0) / 3.0;
+
+    (keyword_score * s.authors.bio_keyword_weight
+        + reference_similarity.max(0.0) * s.authors.bio_embedding_weight)
+        .clamp(0.0, 1.0)
+}
+
+/// How much alt text and link cards look like gamedev, scored like `bio_relevance` with the
+/// `lang` lexicon applied.
+pub fn alt_text_relevance(alt_text: &str, lang: Option<&str>, reference_similarity: f32) -> f32 {
+    let alt = &settings().scoring.alt_text;
+    let keywords = weighted_keyword_score(&keyword_matches_for(alt_text, lang));
+    let (_, hashtag_count) = has_hashtags_for(alt_text, None, lang);
+    let keyword_score = (keywords + hashtag_count as f32).min(3.0) / 3.0;
+
+    (keyword_score * alt.keyword_weight + reference_similarity.max(0.0) * alt.embedding_weight)
+        .clamp(0.0, 1.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,6 +337,67 @@ mod tests {
         assert_eq!(count, 2);
     }
 
+    #[test]
+    fn test_keyword_plural_matching() {
+        assert!(has_keywords("new devlogs are up for both games").0);
+        assert!(has_keywords("Joined two gamejams this month").0);
+        assert!(has_keywords("swapping game engines mid-project").0);
+        assert!(!has_keywords("a devil of a day").0);
+
+        assert_eq!(fold_plural("shaders"), "shader");
+        assert_eq!(fold_plural("bodies"), "body");
+        assert_eq!(fold_plural("patches"), "patch");
+        assert_eq!(fold_plural("glass"), "glass");
+        assert_eq!(fold_plural("ues"), "ues");
+    }
+
+    #[test]
+    fn test_weighted_keyword_score() {
+        let devlog = keyword_matches_for("New devlog is up", None);
+        assert_eq!(weighted_keyword_score(&devlog), 1.0);
+
+        let glsl = keyword_matches_for("Ported the water shader to GLSL", None);
+        assert_eq!(glsl.len(), 1);
+        assert!(weighted_keyword_score(&glsl) < 1.0);
+
+        let attached: Vec<TermMatch> = devlog
+            .into_iter()
+            .map(|m| TermMatch {
+                in_attached_text: true,
+                ..m
+            })
+            .collect();
+        assert_eq!(weighted_keyword_score(&attached), 0.0);
+    }
+
+    #[test]
+    fn test_term_match_positions() {
+        let text = "Spent the weekend on my Game Engines talk.\nMore soon #GameDev!";
+        let keywords = keyword_matches_for(text, None);
+        let engine = keywords.iter().find(|m| m.term == "game engine").unwrap();
+        assert_eq!(engine.span, Some((24, 36)));
+        assert_eq!(
+            engine.snippet.as_deref(),
+            Some("Spent the weekend on my **Game Engines** talk. More soon #GameDev!")
+        );
+
+        let hashtags = hashtag_matches_for(text, None, None);
+        assert_eq!(hashtags.len(), 1);
+        assert_eq!(
+            &text[hashtags[0].span.unwrap().0..hashtags[0].span.unwrap().1],
+            "#GameDev"
+        );
+
+        let long = format!("{}gamedev{}", "a ".repeat(40), " b".repeat(40));
+        let snippet = keyword_matches_for(&long, None)[0].snippet.clone().unwrap();
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("**gamedev**"));
+
+        let tagged = hashtag_matches_for("progress", Some(&["gamedev".to_string()]), None);
+        assert_eq!(tagged[0].span, None);
+        assert_eq!(find_ignore_case("Buy NFTs", "nft"), Some((4, 7)));
+    }
+
     #[test]
     fn test_keyword_substring_matching() {
         let (found, _) = has_keywords("Community radio pioneers since 1996");
@@ -88,6 +410,43 @@ mod tests {
         assert!(!found);
     }
 
+    #[test]
+    fn test_bio_relevance() {
+        let dev = bio_relevance("Indie gamedev making a metroidvania #indiedev", 0.0);
+        let other = bio_relevance("Coffee lover and cat parent", 0.0);
+        assert!(dev > other);
+        assert_eq!(other, 0.0);
+        assert!(bio_relevance("Coffee lover", 0.8) > other);
+    }
+
+    #[test]
+    fn test_alt_text_relevance() {
+        let screenshot =
+            alt_text_relevance("Pixel art platformer level in the Godot editor", None, 0.0);
+        assert!(screenshot > 0.0);
+        assert_eq!(
+            alt_text_relevance("A cat asleep on a keyboard", None, 0.0),
+            0.0
+        );
+        assert!(alt_text_relevance("A cat asleep on a keyboard", None, 0.6) > 0.0);
+    }
+
+    #[test]
+    fn test_extract_hashtags() {
+        assert_eq!(
+            extract_hashtags("Level #1 done! #game_dev, #ゲーム開発 and#nope #42", None),
+            vec!["#game_dev", "#ゲーム開発"]
+        );
+
+        let facet_tags = vec!["IndieDev".to_string()];
+        assert_eq!(
+            extract_hashtags("untagged #gamedev text", Some(&facet_tags)),
+            vec!["#indiedev"]
+        );
+        assert!(has_hashtags_for("no tags in text", Some(&facet_tags), None).0);
+        assert!(!has_hashtags_for("#gamedev written as text", Some(&[]), None).0);
+    }
+
     #[test]
     fn test_hashtag_case_insensitivity() {
         let (found, _) = has_hashtags("Working on my project #GAMEDEV");
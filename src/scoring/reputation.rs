@@ -0,0 +1,82 @@
+use crate::settings::settings;
+
+/// What an author's posts and readers' feedback on them added up to over a window.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthorRecord {
+    /// Posts that were relevant and went on to be scored.
+    pub assessed: i64,
+    pub accepted: i64,
+    /// Sum of the priorities of accepted posts.
+    pub score_sum: f32,
+    pub request_more: i64,
+    pub request_less: i64,
+    /// Relevant posts rejected as spam: by a flagged spammer, with a promo link, keyword
+    /// stuffing or too many hashtags.
+    pub spam_flags: i64,
+}
+
+/// The author's reputation in [-1, 1], or 0 until they have `min_posts` posts on record.
+pub fn author_reputation(record: &AuthorRecord) -> f32 {
+    let s = settings();
+    let r = &s.authors.reputation;
+    let posts = record.assessed + record.spam_flags;
+    if !r.enabled || posts == 0 || posts < r.min_posts {
+        return 0.0;
+    }
+
+    let accept_rate = record.accepted as f32 / record.assessed.max(1) as f32;
+    let average_score = if record.accepted > 0 {
+        record.score_sum / record.accepted as f32
+    } else {
+        0.0
+    };
+    let score = (average_score / r.reference_score.max(f32::EPSILON)).clamp(0.0, 1.0);
+    let requests = record.request_more + record.request_less;
+    let feedback = if requests > 0 {
+        (record.request_more - record.request_less) as f32 / requests as f32
+    } else {
+        0.0
+    };
+    let spam_rate = record.spam_flags as f32 / posts as f32;
+
+    (r.accept_weight * (2.0 * accept_rate - 1.0)
+        + r.score_weight * (2.0 * score - 1.0)
+        + r.feedback_weight * feedback
+        - r.spam_weight * spam_rate)
+        .clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_author_reputation() {
+        let min_posts = settings().authors.reputation.min_posts;
+        let regular = AuthorRecord {
+            assessed: min_posts,
+            accepted: min_posts,
+            score_sum: min_posts as f32 * 2.0,
+            request_more: 3,
+            ..Default::default()
+        };
+        assert!(author_reputation(&regular) > 0.5);
+
+        let newcomer = AuthorRecord {
+            assessed: min_posts - 1,
+            ..regular.clone()
+        };
+        assert_eq!(author_reputation(&newcomer), 0.0);
+
+        let borderline = AuthorRecord {
+            assessed: min_posts * 2,
+            accepted: 2,
+            score_sum: 0.2,
+            request_less: 2,
+            spam_flags: 2,
+            ..Default::default()
+        };
+        assert!(author_reputation(&borderline) < -0.5);
+        assert!(author_reputation(&borderline) >= -1.0);
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "onnx")]
+use super::onnx::OnnxEmbedder;
+use crate::settings::{settings, MlBackend};
+use anyhow::Result;
+#[cfg(feature = "rust-bert")]
+use rust_bert::pipelines::sentence_embeddings::{
+    SentenceEmbeddingsBuilder, SentenceEmbeddingsModel, SentenceEmbeddingsModelType,
+};
+use simsimd::SpatialSimilarity;
+
+pub type Embedding = Vec<f32>;
+
+pub enum Embedder {
+    #[cfg(feature = "rust-bert")]
+    RustBert(SentenceEmbeddingsModel),
+    #[cfg(feature = "onnx")]
+    Onnx(OnnxEmbedder),
+}
+
+pub fn load_embedding_model() -> Result<Embedder> {
+    let s = settings();
+    match s.ml.backend {
+        #[cfg(feature = "rust-bert")]
+        MlBackend::RustBert => {
+            let model_type = if s.ml.multilingual_embeddings {
+                SentenceEmbeddingsModelType::DistiluseBaseMultilingualCased
+            } else {
+                SentenceEmbeddingsModelType::AllMiniLmL12V2
+            };
+            let model = SentenceEmbeddingsBuilder::remote(model_type).create_model()?;
+            Ok(Embedder::RustBert(model))
+        }
+        #[cfg(feature = "onnx")]
+        MlBackend::Onnx => Ok(Embedder::Onnx(OnnxEmbedder::load(&s.ml.onnx)?)),
+        #[allow(unreachable_patterns)]
+        backend => anyhow::bail!("ml.backend {backend:?} is not compiled in"),
+    }
+}
+
+pub fn embed_batch(model: &mut Embedder, texts: &[&str]) -> Vec<Embedding> {
+    let embeddings = match model {
+        #[cfg(feature = "rust-bert")]
+        Embedder::RustBert(model) => model.encode(texts).map_err(anyhow::Error::from),
+        #[cfg(feature = "onnx")]
+        Embedder::Onnx(model) => model.embed(texts),
+    };
+    embeddings.unwrap_or_else(|_| vec![Embedding::new(); texts.len()])
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    f32::cosine(a, b)
+        .map(|distance| 1.0 - distance as f32)
+        .unwrap_or(0.0)
+}
+
+pub fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn decode_embedding(bytes: &[u8]) -> Embedding {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_roundtrip() {
+        let embedding = vec![0.5, -1.25, 3.0, 0.0];
+        let bytes = encode_embedding(&embedding);
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(decode_embedding(&bytes), embedding);
+    }
+
+    #[test]
+    fn test_decode_ignores_trailing_bytes() {
+        let mut bytes = encode_embedding(&[1.0, 2.0]);
+        bytes.push(0xff);
+        assert_eq!(decode_embedding(&bytes), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_cosine_similarity_mismatched() {
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0]), 0.0);
+    }
+}
@@ -0,0 +1,417 @@
+use super::semantic::{cosine_similarity, Embedding};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+pub type SharedVectorIndex = Arc<RwLock<VectorIndex>>;
+
+#[derive(Debug, Clone, Copy)]
+struct Scored {
+    similarity: f32,
+    node: usize,
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity
+            .total_cmp(&other.similarity)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    uri: String,
+    embedding: Embedding,
+    neighbors: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// Incrementally maintained HNSW graph over post embeddings, scored by cosine similarity.
+/// Removed posts are tombstoned and the graph is compacted once they outnumber live ones.
+#[derive(Debug, Clone)]
+pub struct VectorIndex {
+    nodes: Vec<Node>,
+    ids: HashMap<String, usize>,
+    entry: Option<usize>,
+    max_level: usize,
+    connections: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    deleted: usize,
+}
+
+impl VectorIndex {
+    pub fn new(connections: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Self {
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry: None,
+            max_level: 0,
+            connections: connections.max(2),
+            ef_construction: ef_construction.max(1),
+            ef_search: ef_search.max(1),
+            deleted: 0,
+        }
+    }
+
+    pub fn shared(self) -> SharedVectorIndex {
+        Arc::new(RwLock::new(self))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.ids.contains_key(uri)
+    }
+
+    pub fn embedding(&self, uri: &str) -> Option<&Embedding> {
+        self.ids.get(uri).map(|&id| &self.nodes[id].embedding)
+    }
+
+    pub fn insert(&mut self, uri: String, embedding: Embedding) {
+        if embedding.is_empty() {
+            return;
+        }
+        if self.contains(&uri) {
+            self.remove(&uri);
+        }
+
+        let id = self.nodes.len();
+        let level = self.random_level();
+        self.nodes.push(Node {
+            uri: uri.clone(),
+            embedding,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.ids.insert(uri, id);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            self.max_level = level;
+            return;
+        };
+
+        let query = self.nodes[id].embedding.clone();
+        let mut entry_points = vec![entry];
+
+        for layer in (level + 1..=self.max_level).rev() {
+            entry_points = self
+                .search_layer(&query, &entry_points, 1, layer)
+                .into_iter()
+                .map(|s| s.node)
+                .collect();
+        }
+
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, self.ef_construction, layer);
+            let max_neighbors = self.max_neighbors(layer);
+            let selected: Vec<usize> = candidates
+                .iter()
+                .filter(|c| c.node != id)
+                .take(max_neighbors)
+                .map(|c| c.node)
+                .collect();
+
+            for &neighbor in &selected {
+                self.connect(neighbor, id, layer);
+            }
+            self.nodes[id].neighbors[layer] = selected;
+            entry_points = candidates.into_iter().map(|c| c.node).collect();
+        }
+
+        if level > self.max_level {
+            self.entry = Some(id);
+            self.max_level = level;
+        }
+    }
+
+    pub fn remove(&mut self, uri: &str) -> bool {
+        let Some(id) = self.ids.remove(uri) else {
+            return false;
+        };
+        self.nodes[id].deleted = true;
+        self.deleted += 1;
+
+        if self.deleted > self.ids.len() {
+            self.compact();
+        }
+        true
+    }
+
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) -> usize {
+        let stale: Vec<String> = self.ids.keys().filter(|uri| !keep(uri)).cloned().collect();
+        for uri in &stale {
+            self.remove(uri);
+        }
+        stale.len()
+    }
+
+    pub fn search(&self, query: &[f32], limit: usize) -> Vec<(String, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if limit == 0 || query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut entry_points = vec![entry];
+        for layer in (1..=self.max_level).rev() {
+            entry_points = self
+                .search_layer(query, &entry_points, 1, layer)
+                .into_iter()
+                .map(|s| s.node)
+                .collect();
+        }
+
+        let ef = self.ef_search.max(limit + self.deleted.min(limit));
+        self.search_layer(query, &entry_points, ef, 0)
+            .into_iter()
+            .filter(|s| !self.nodes[s.node].deleted)
+            .take(limit)
+            .map(|s| (self.nodes[s.node].uri.clone(), s.similarity))
+            .collect()
+    }
+
+    pub fn neighbors_within(
+        &self,
+        query: &[f32],
+        min_similarity: f32,
+        limit: usize,
+    ) -> Vec<(String, f32)> {
+        self.search(query, limit)
+            .into_iter()
+            .filter(|(_, similarity)| *similarity >= min_similarity)
+            .collect()
+    }
+
+    pub fn clusters(&self, min_similarity: f32, min_size: usize) -> Vec<Vec<String>> {
+        let mut assigned: HashSet<&str> = HashSet::new();
+        let mut clusters = Vec::new();
+        let probe = self.ef_search.max(min_size);
+
+        for node in self.nodes.iter().filter(|n| !n.deleted) {
+            if assigned.contains(node.uri.as_str()) {
+                continue;
+            }
+
+            let members: Vec<String> = self
+                .neighbors_within(&node.embedding, min_similarity, probe)
+                .into_iter()
+                .map(|(uri, _)| uri)
+                .filter(|uri| !assigned.contains(uri.as_str()))
+                .collect();
+
+            if members.len() < min_size {
+                continue;
+            }
+
+            for uri in &members {
+                if let Some(&id) = self.ids.get(uri) {
+                    assigned.insert(self.nodes[id].uri.as_str());
+                }
+            }
+            clusters.push(members);
+        }
+
+        clusters
+    }
+
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut candidates: BinaryHeap<Scored> = BinaryHeap::new();
+        let mut results: BinaryHeap<std::cmp::Reverse<Scored>> = BinaryHeap::new();
+
+        for &node in entry_points {
+            let scored = Scored {
+                similarity: cosine_similarity(query, &self.nodes[node].embedding),
+                node,
+            };
+            candidates.push(scored);
+            results.push(std::cmp::Reverse(scored));
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(current) = candidates.pop() {
+            let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+            if current.similarity < worst && results.len() >= ef {
+                break;
+            }
+
+            let Some(neighbors) = self.nodes[current.node].neighbors.get(layer) else {
+                continue;
+            };
+
+            for &neighbor in neighbors {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let scored = Scored {
+                    similarity: cosine_similarity(query, &self.nodes[neighbor].embedding),
+                    node: neighbor,
+                };
+                let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+                if results.len() < ef || scored.similarity > worst {
+                    candidates.push(scored);
+                    results.push(std::cmp::Reverse(scored));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut sorted: Vec<Scored> = results.into_iter().map(|r| r.0).collect();
+        sorted.sort_by(|a, b| b.cmp(a));
+        sorted
+    }
+
+    fn connect(&mut self, from: usize, to: usize, layer: usize) {
+        let max_neighbors = self.max_neighbors(layer);
+        let Some(neighbors) = self.nodes[from].neighbors.get(layer) else {
+            return;
+        };
+        let mut neighbors = neighbors.clone();
+        neighbors.push(to);
+
+        if neighbors.len() > max_neighbors {
+            let origin = &self.nodes[from].embedding;
+            let mut scored: Vec<Scored> = neighbors
+                .iter()
+                .map(|&node| Scored {
+                    similarity: cosine_similarity(origin, &self.nodes[node].embedding),
+                    node,
+                })
+                .collect();
+            scored.sort_by(|a, b| b.cmp(a));
+            neighbors = scored
+                .into_iter()
+                .take(max_neighbors)
+                .map(|s| s.node)
+                .collect();
+        }
+
+        self.nodes[from].neighbors[layer] = neighbors;
+    }
+
+    fn compact(&mut self) {
+        let live: Vec<(String, Embedding)> = self
+            .nodes
+            .drain(..)
+            .filter(|n| !n.deleted)
+            .map(|n| (n.uri, n.embedding))
+            .collect();
+
+        self.ids.clear();
+        self.entry = None;
+        self.max_level = 0;
+        self.deleted = 0;
+
+        for (uri, embedding) in live {
+            self.insert(uri, embedding);
+        }
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.connections * 2
+        } else {
+            self.connections
+        }
+    }
+
+    fn random_level(&self) -> usize {
+        let level_scale = 1.0 / (self.connections as f64).ln();
+        let uniform: f64 = rand::rng().random_range(f64::EPSILON..1.0);
+        ((-uniform.ln() * level_scale) as usize).min(16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(angle: f32) -> Embedding {
+        vec![angle.cos(), angle.sin()]
+    }
+
+    #[test]
+    fn test_empty_index_search() {
+        let index = VectorIndex::new(8, 32, 16);
+        assert!(index.search(&[1.0, 0.0], 5).is_empty());
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut index = VectorIndex::new(8, 32, 16);
+        index.insert("a".into(), unit(0.0));
+        index.insert("b".into(), unit(1.0));
+        assert_eq!(index.len(), 2);
+        assert!(index.remove("a"));
+        assert!(!index.remove("a"));
+        assert_eq!(index.len(), 1);
+        assert!(!index.contains("a"));
+    }
+
+    #[test]
+    fn test_reinsert_replaces_entry() {
+        let mut index = VectorIndex::new(8, 32, 16);
+        index.insert("a".into(), unit(0.0));
+        index.insert("a".into(), unit(1.0));
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.embedding("a"), Some(&unit(1.0)));
+    }
+
+    #[test]
+    fn test_retain_drops_stale() {
+        let mut index = VectorIndex::new(8, 32, 16);
+        for i in 0..10 {
+            index.insert(format!("post-{i}"), unit(i as f32 * 0.1));
+        }
+        let removed = index.retain(|uri| uri.ends_with('1') || uri.ends_with('2'));
+        assert_eq!(removed, 8);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_search_skips_removed() {
+        let mut index = VectorIndex::new(8, 32, 16);
+        for i in 0..20 {
+            index.insert(format!("post-{i}"), unit(i as f32 * 0.05));
+        }
+        index.remove("post-3");
+        let results = index.search(&unit(0.15), 20);
+        assert!(results.iter().all(|(uri, _)| uri != "post-3"));
+        assert_eq!(results.len(), 19);
+    }
+}
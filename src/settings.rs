@@ -1,13 +1,55 @@
 use arc_swap::{ArcSwap, Guard};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use ron::Value;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, OnceLock};
+use strum::{Display, EnumString};
+use tokio::signal::unix::{signal, SignalKind};
 
 use crate::utils::logs;
 
 static SETTINGS: OnceLock<ArcSwap<Settings>> = OnceLock::new();
+/// Bumped whenever the loaded settings are replaced.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+const PROFILES_PATH: &str = "settings.profiles.ron";
+const PROFILE_ENV: &str = "DEVLOGS_PROFILE";
+
+/// Serializes a setting enum as its kebab-case name, with `#[serde(into = "String",
+/// try_from = "String")]`. Profiles and overrides are merged through `ron::Value`, which has no
+/// enum variants, so an enum serialized as a variant wouldn't survive the round trip.
+macro_rules! string_enum {
+    ($($type:ident),+ $(,)?) => {
+        $(
+            impl From<$type> for String {
+                fn from(value: $type) -> Self {
+                    value.to_string()
+                }
+            }
+
+            impl TryFrom<String> for $type {
+                type Error = strum::ParseError;
+
+                fn try_from(value: String) -> Result<Self, Self::Error> {
+                    value.parse()
+                }
+            }
+        )+
+    };
+}
+
+string_enum!(
+    IngestMode,
+    PrioritySquash,
+    MlBackend,
+    AdminRole,
+    BufferOverflow,
+    WebhookFormat,
+);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
@@ -19,17 +61,102 @@ pub struct Settings {
     pub spam: Spam,
     pub backfill: Backfill,
     pub filters: Filters,
+    pub api: Api,
+    pub semantic: Semantic,
+    pub authors: Authors,
+    pub firehose: Firehose,
+    pub webhooks: Webhooks,
+    pub archive: Archive,
+    pub flush: Flush,
+    pub rescore: Rescore,
+    pub maintenance: Maintenance,
+    pub database: Database,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Filters {
     pub gamedev_keywords: Vec<String>,
+    /// How much each gamedev keyword counts towards a post's keyword score, for any language.
+    /// Keywords not listed weigh 1.0.
+    pub keyword_weights: HashMap<String, f32>,
     pub gamedev_hashtags: Vec<String>,
     pub blocked_keywords: Vec<String>,
     pub blocked_hashtags: Vec<String>,
+    /// Hashtags an author can add to a post to keep it, and their later posts, out of the feed.
+    pub opt_out_hashtags: Vec<String>,
     pub promo_domains: Vec<String>,
     pub moderator_dids: Vec<String>,
     pub influencer_dids: Vec<String>,
+    pub languages: Vec<LanguageLexicon>,
+    /// Match "shaders" or "gamejams" against the singular keywords.
+    pub fold_plurals: bool,
+    /// Phrases that ask readers to reply, tag or quote ("tag a dev who..."), matched lowercase.
+    pub reply_bait_phrases: Vec<String>,
+    pub post_age: PostAge,
+}
+
+/// How far a firehose post's claimed creation time may be from when it arrives. Clients with
+/// a wrong clock otherwise pin posts to the top of the feed or past the cutoff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostAge {
+    /// Posts claiming to be from further in the future are stored as created on arrival, or
+    /// rejected when `clamp_future` is off.
+    pub max_future_secs: i64,
+    pub clamp_future: bool,
+    /// Older posts are rejected.
+    pub max_age_hours: i64,
+}
+
+impl Filters {
+    /// Weight of `keyword` in the keyword score.
+    pub fn keyword_weight(&self, keyword: &str) -> f32 {
+        self.keyword_weights
+            .get(&keyword.to_lowercase())
+            .copied()
+            .unwrap_or(1.0)
+            .max(0.0)
+    }
+
+    /// The enabled non-English lexicon serving `lang`, matched on its primary subtag.
+    pub fn language(&self, lang: &str) -> Option<&LanguageLexicon> {
+        let primary = lang.split(['-', '_']).next().unwrap_or(lang);
+        self.languages
+            .iter()
+            .find(|l| l.enabled && l.code.eq_ignore_ascii_case(primary))
+    }
+
+    /// The feed language a post is stored under: an enabled lexicon's code, or "en".
+    pub fn feed_language(&self, lang: Option<&str>) -> String {
+        lang.and_then(|lang| self.language(lang))
+            .map(|l| l.code.clone())
+            .unwrap_or_else(|| "en".to_string())
+    }
+
+    /// The feed language requested by a feed URI, matched on its record key.
+    pub fn language_for_feed(&self, feed_uri: &str) -> String {
+        let rkey = feed_rkey(feed_uri);
+        self.languages
+            .iter()
+            .find(|l| l.enabled && l.feed_name == rkey)
+            .map(|l| l.code.clone())
+            .unwrap_or_else(|| "en".to_string())
+    }
+}
+
+fn feed_rkey(feed_uri: &str) -> &str {
+    feed_uri.rsplit('/').next().unwrap_or(feed_uri)
+}
+
+/// Extra lexicon for a non-English feed; matched on top of the base English lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageLexicon {
+    pub code: String,
+    pub feed_name: String,
+    pub enabled: bool,
+    pub gamedev_keywords: Vec<String>,
+    pub gamedev_hashtags: Vec<String>,
+    pub blocked_keywords: Vec<String>,
+    pub promo_domains: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,7 +164,54 @@ pub struct Server {
     pub publisher_did: String,
     pub feed_hostname: String,
     pub firehose_limit: usize,
+    pub ingest_mode: IngestMode,
+    /// Jetstream `subscribe` endpoint, used when `ingest_mode` is `jetstream`.
+    pub jetstream_url: String,
+    /// Every Jetstream message received is appended here when set, for `replay` mode.
+    pub record_path: Option<String>,
+    /// Recorded Jetstream messages read when `ingest_mode` is `replay`.
+    pub replay_path: String,
     pub enable_backfill: bool,
+    pub database_url: String,
+    pub log_assessments: bool,
+    pub rejection_log_rates: HashMap<String, f32>,
+    pub default_rejection_log_rate: f32,
+    pub bind_address: String,
+    /// Where the admin API listens, on `ADMIN_PORT`. Keep it private: it's loopback by default.
+    pub admin_bind_address: String,
+    pub internal_port: u16,
+    pub tls: Option<Tls>,
+    pub proxy_protocol: bool,
+    pub trusted_proxies: Vec<String>,
+    pub access_log: AccessLog,
+    pub feed_cache_ttl_secs: u64,
+    pub feed_cache_max_entries: usize,
+}
+
+/// Where posts and likes are read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+#[strum(serialize_all = "kebab-case")]
+pub enum IngestMode {
+    /// The full CBOR repo firehose, consumed by skyfeed.
+    Firehose,
+    /// Jetstream's JSON stream, filtered server-side to posts and likes.
+    Jetstream,
+    /// A Jetstream session recorded to `record_path`, read once from `replay_path`.
+    Replay,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessLog {
+    pub enabled: bool,
+    pub sample_rate: f32,
+    pub slow_request_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tls {
+    pub cert_path: String,
+    pub key_path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,11 +221,25 @@ pub struct Scoring {
     pub penalties: ContentPenalties,
     pub quality: QualityThresholds,
     pub rejection: RejectionThresholds,
+    pub author: AuthorBonuses,
+    pub confidence: ConfidenceTiers,
+    pub negative_examples: NegativeExamples,
+    pub alt_text: AltTextScoring,
+    pub shadow: ShadowScoring,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScoringThresholds {
     pub min_text_length: usize,
+    /// Posts with a link need this much text besides their links and hashtags.
+    pub min_text_without_links: usize,
+    /// Posts of at least `diversity_min_words` words need this share of distinct words, so
+    /// keyword-stuffed posts ("gamedev gamedev gamedev") are dropped before scoring. Common
+    /// words such as "the" and "of" aren't counted.
+    pub min_unique_word_ratio: f32,
+    pub diversity_min_words: usize,
+    /// Shannon entropy in bits per character, over the text without whitespace.
+    pub min_char_entropy: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +247,15 @@ pub struct ContentBonuses {
     pub first_person: f32,
     pub video: f32,
     pub image_with_alt: f32,
+    /// Threads the author continued to at least this many posts get `thread_per_post` for
+    /// every post after the root, up to `thread_max_boost`, when served.
+    pub thread_min_length: i32,
+    pub thread_per_post: f32,
+    pub thread_max_boost: f32,
+    /// Priority per unit of keyword score (the summed weights of the keywords in the post),
+    /// up to `keyword_max_boost`.
+    pub keyword: f32,
+    pub keyword_max_boost: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +263,61 @@ pub struct ContentPenalties {
     pub many_images: f32,
     pub many_images_threshold: u8,
     pub link_exponential_base: f32,
+    pub reply_bait: f32,
+    pub many_mentions: f32,
+    pub many_mentions_threshold: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorBonuses {
+    pub bio_relevance: f32,
+    pub history_per_post: f32,
+    pub history_max_boost: f32,
+    pub borderline_max_priority: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceTiers {
+    pub strong_min_priority: f32,
+    pub moderate_min_priority: f32,
+}
+
+/// Posts that at least `min_request_less` readers asked to see less of are kept as negative
+/// examples, checked every `harvest_interval_mins`. New posts at least `min_similarity` to one
+/// of the newest `max_examples` of them lose `penalty` priority.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegativeExamples {
+    pub enabled: bool,
+    pub min_request_less: usize,
+    pub min_similarity: f32,
+    pub penalty: f32,
+    pub max_examples: i64,
+    pub harvest_interval_mins: u64,
+}
+
+/// Alt text and link card titles and descriptions are scored like bios: gamedev keywords
+/// weighted by `keyword_weight` plus similarity to `reference` weighted by `embedding_weight`.
+/// Posts at least `min_relevance` get up to `bonus` priority, and matching keywords in them
+/// make a post relevant.
+/// Posts with no body text are filtered and scored by their descriptions, read with OCR from
+/// up to `ocr_max_images` images when built with the `ocr` feature and there is no alt text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AltTextScoring {
+    pub enabled: bool,
+    pub reference: String,
+    pub keyword_weight: f32,
+    pub embedding_weight: f32,
+    pub min_relevance: f32,
+    pub bonus: f32,
+    pub ocr_max_images: usize,
+}
+
+/// Scores firehose posts a second time under `profile` from `settings.profiles.ron`, merged
+/// over the live settings, and records what it would have accepted without changing the feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowScoring {
+    pub enabled: bool,
+    pub profile: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,14 +336,35 @@ pub struct QualityThresholds {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Engagement {
     pub weights: EngagementWeights,
+    pub like_multipliers: LikeMultipliers,
+    pub brigading: BrigadingGuard,
     pub velocity_scale: f32,
     pub max_boost: f32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrigadingGuard {
+    pub min_cohort_size: usize,
+    pub z_score_threshold: f32,
+    pub zero_z_score: f32,
+    pub min_liker_entropy: f32,
+    pub max_unknown_liker_share: f32,
+    /// Seconds a post's burst signals are reused before the guard queries them again.
+    pub recheck_secs: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeMultipliers {
+    pub dev_author: f32,
+    pub subscriber: f32,
+    pub unknown: f32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EngagementWeights {
     pub reply: f32,
     pub repost: f32,
+    pub dev_repost: f32,
     pub like: f32,
 }
 
@@ -104,19 +377,190 @@ pub struct Feed {
     pub shuffle_variance: f32,
     pub preference_boost: f32,
     pub preference_penalty: f32,
+    /// Boost for authors whose posts the reader clicked through or shared, when they haven't
+    /// asked for more or less of them.
+    pub engaged_author_boost: f32,
     pub priority_bucket_hours: i64,
+    pub hour_balance_boost: f32,
+    pub moderate_sampling: ModerateSampling,
+    pub author_cap: AuthorCap,
+    pub decay: Decay,
+    pub priority_bounds: PriorityBounds,
+    pub follows: FollowBoost,
+    pub interest_profile: InterestProfile,
+    pub like_affinity: LikeAffinities,
+    pub tier_ttls: TierTtls,
+    pub trending: TrendingFeed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerateSampling {
+    pub enabled: bool,
+    pub min_probability: f32,
+}
+
+/// Serve-time bounds on a post's priority, so one large boost can't outrank fresher posts for
+/// days. Acceptance and confidence tiers still use the unbounded priority.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityBounds {
+    pub squash: PrioritySquash,
+    pub floor: f32,
+    pub ceiling: f32,
+}
+
+/// How priorities are brought within `floor` and `ceiling`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+#[strum(serialize_all = "kebab-case")]
+pub enum PrioritySquash {
+    /// Leave priorities unbounded.
+    Off,
+    /// Cut off at the bounds.
+    Clamp,
+    /// A logistic curve through the midpoint of the bounds with slope 1 there, so mid-range
+    /// priorities barely change and outliers level off smoothly.
+    Logistic,
+}
+
+/// Serve-time priority decay, so older posts sink within their time bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decay {
+    pub enabled: bool,
+    /// Hours for a post's serve-time priority to halve.
+    pub half_life_hours: f32,
+    /// How much faster posts with each topic label age, such as `"discussion": 2.0` for twice
+    /// as fast. Labels left out age at 1.0.
+    pub topic_rates: HashMap<String, f32>,
+}
+
+/// How long posts of each confidence tier are served, in hours. Posts still leave the feed
+/// after `cutoff_hours`; `0` leaves a tier to that cutoff alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierTtls {
+    pub strong_hours: i64,
+    pub moderate_hours: i64,
+    pub weak_hours: i64,
+}
+
+/// The "Game Dev Trending" feed: posts of any language from the last `cutoff_hours`, ranked only
+/// by engagement velocity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendingFeed {
+    pub enabled: bool,
+    pub feed_name: String,
+    pub cutoff_hours: i64,
+}
+
+impl TrendingFeed {
+    /// Whether a feed URI asks for the trending feed, matched on its record key.
+    pub fn serves(&self, feed_uri: &str) -> bool {
+        self.enabled && feed_rkey(feed_uri) == self.feed_name
+    }
+}
+
+/// Serve-time boost for posts by authors the reader follows on Bluesky. Follows are fetched in
+/// the background on a reader's first request and refreshed after `cache_ttl_mins`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowBoost {
+    pub enabled: bool,
+    pub boost: f32,
+    pub cache_ttl_mins: i64,
+    /// Follows read per reader; accounts following more than this get a partial list.
+    pub max_follows: usize,
+}
+
+/// Serve-time boost for posts close to what the reader liked or asked to see more of. The
+/// reader's profile is the average embedding of their `max_posts` most recent such posts, and a
+/// post gets `1 + weight * (similarity - min_similarity)` when it's more similar than that.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterestProfile {
+    pub enabled: bool,
+    pub weight: f32,
+    pub min_similarity: f32,
+    pub max_posts: i64,
+    /// Readers with fewer liked posts than this get no profile.
+    pub min_posts: usize,
+}
+
+/// Serve-time boost from what the reader liked on Bluesky. Each like of a feed post counts
+/// towards the reader's affinity for its topic and author, halving every `half_life_hours`.
+/// A post gets `1 + topic_weight * share + author_weight * author_likes`, up to `max_boost`,
+/// where `share` is the part of the reader's topic likes that went to its topic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeAffinities {
+    pub enabled: bool,
+    pub half_life_hours: f32,
+    pub topic_weight: f32,
+    pub author_weight: f32,
+    pub max_boost: f32,
+    /// Readers with fewer decayed topic likes than this get no topic boost.
+    pub min_likes: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorCap {
+    pub enabled: bool,
+    pub max_posts_per_author: usize,
+    /// Feed positions the cap applies to, counted from the top of the feed.
+    pub window: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ml {
     pub batch_size: usize,
     pub batch_timeout_ms: u64,
+    /// Posts waiting to be scored before the ones with the fewest keyword and hashtag matches
+    /// are dropped unscored.
+    pub queue_capacity: usize,
+    pub backend: MlBackend,
+    pub multilingual_embeddings: bool,
+    pub onnx: OnnxModels,
+    pub translation: Translation,
+}
+
+/// Inference backend; each needs its cargo feature (`rust-bert`, the default, or `onnx`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+#[strum(serialize_all = "kebab-case")]
+pub enum MlBackend {
+    RustBert,
+    Onnx,
+}
+
+/// Exported (optionally quantized) ONNX models and their `tokenizer.json` files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnnxModels {
+    /// An NLI model used for zero-shot quality labels.
+    pub classifier_model: String,
+    pub classifier_tokenizer: String,
+    /// Logit positions of the classifier's entailment and contradiction classes.
+    pub entailment_index: usize,
+    pub contradiction_index: usize,
+    /// A sentence-transformers model, mean-pooled over its last hidden state.
+    pub embedding_model: String,
+    pub embedding_tokenizer: String,
+    pub max_length: usize,
+    pub intra_threads: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    pub enabled: bool,
+    pub api_url: String,
+    pub api_key: Option<String>,
+    pub max_per_hour: u32,
+    pub timeout_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Spam {
     pub repost_threshold: f32,
     pub velocity_window_hours: i64,
+    /// Accepted posts per hour over the velocity window past which an author's next posts are
+    /// rejected as a post flood. `0` turns the check off.
+    pub max_posts_per_hour: f32,
+    /// Also flag a flooding author as a spammer, instead of only rejecting the extra posts.
+    pub flag_post_flood: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,6 +568,214 @@ pub struct Backfill {
     pub limit: usize,
     pub hours: i64,
     pub search_limit: u32,
+    /// Pages of `search_limit` posts each query may walk in each direction per run.
+    pub max_pages_per_query: u32,
+    pub gap_min_secs: i64,
+    pub gap_poll_interval_secs: u64,
+    /// Searched in order. Bluesky search syntax works, e.g. `#devlog -giveaway`.
+    pub queries: Vec<String>,
+    /// Left out of every query's results, so posts that would only be filtered aren't fetched
+    /// and scored.
+    pub exclude_terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Api {
+    pub related_default_limit: usize,
+    pub related_max_limit: usize,
+    pub search_default_limit: usize,
+    pub search_max_limit: usize,
+    /// How much priority scales text relevance when ranking search results.
+    pub search_priority_weight: f32,
+    /// Text matches fetched per requested result before re-ranking by priority.
+    pub search_candidates_per_result: usize,
+    /// Page size for `/api/v1/archive` when the request doesn't set `limit`, and its cap.
+    pub archive_default_limit: usize,
+    pub archive_max_limit: usize,
+    /// Posts shown by `/api/v1/preview` when the request doesn't set `limit`, and its cap.
+    pub preview_default_limit: usize,
+    pub preview_max_limit: usize,
+    /// Characters of each post's text shown in the preview.
+    pub preview_excerpt_chars: usize,
+    /// Tokens for the admin API, in addition to `ADMIN_TOKEN`, which acts as an owner.
+    pub admin_tokens: Vec<AdminToken>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminToken {
+    /// Who the token belongs to, recorded with each action in the audit log.
+    pub name: String,
+    pub token: String,
+    pub role: AdminRole,
+}
+
+/// What an admin token may do. Each role can also do everything the roles before it can.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Display, EnumString, Serialize, Deserialize,
+)]
+#[serde(into = "String", try_from = "String")]
+#[strum(serialize_all = "kebab-case")]
+pub enum AdminRole {
+    /// Read-only access, such as the audit log.
+    Analyst,
+    /// Acts on single posts and authors.
+    Moderator,
+    /// Also runs bulk operations.
+    Owner,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Semantic {
+    pub dedup_similarity: f32,
+    pub cluster_similarity: f32,
+    pub cluster_min_size: usize,
+    pub index_connections: usize,
+    pub index_ef_construction: usize,
+    pub index_ef_search: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Authors {
+    pub handle_ttl_hours: i64,
+    pub history_window_days: i64,
+    /// What author bios are compared to. A bio is only scored again when it changes.
+    pub bio_reference: String,
+    pub bio_keyword_weight: f32,
+    pub bio_embedding_weight: f32,
+    pub reputation: AuthorReputation,
+}
+
+/// A rolling score in [-1, 1] from an author's last `window_days` of posts, once they have at
+/// least `min_posts` relevant or spam-flagged ones. Accept rate, average priority of accepted
+/// posts against `reference_score`, reader feedback and spam flags are weighted and summed.
+/// Positive reputation adds up to `boost` priority; negative takes off up to `penalty`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorReputation {
+    pub enabled: bool,
+    pub window_days: i64,
+    pub min_posts: i64,
+    pub accept_weight: f32,
+    pub score_weight: f32,
+    pub feedback_weight: f32,
+    pub spam_weight: f32,
+    pub reference_score: f32,
+    pub boost: f32,
+    pub penalty: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Firehose {
+    pub silence_timeout_secs: u64,
+    pub backoff_base_secs: u64,
+    pub backoff_max_secs: u64,
+    pub alert_after_failures: u32,
+    /// Post and like URIs remembered so events redelivered after a reconnect are skipped.
+    pub dedup_window: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhooks {
+    pub timeout_ms: u64,
+    pub endpoints: Vec<WebhookEndpoint>,
+}
+
+/// How often buffered events are written to the database, and how many may wait.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Flush {
+    pub min_interval_ms: u64,
+    pub max_interval_ms: u64,
+    /// Flushes writing at most this many posts, likes and deletions stretch the interval.
+    pub idle_max_entries: usize,
+    /// Pending posts and likes that trigger a flush without waiting for the interval.
+    pub immediate_threshold: usize,
+    pub max_pending_posts: usize,
+    pub max_pending_likes: usize,
+    /// Feed interactions waiting for a flush; more are dropped until it runs.
+    pub max_pending_interactions: usize,
+    /// Interactions read from one request; the rest are dropped.
+    pub max_interactions_per_request: usize,
+    pub posts_overflow: BufferOverflow,
+    pub likes_overflow: BufferOverflow,
+}
+
+/// What to do when a pending buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+#[strum(serialize_all = "kebab-case")]
+pub enum BufferOverflow {
+    /// Write the buffers to the database right away.
+    Flush,
+    /// Drop the entries for the lowest-priority posts until the buffer is back under its limit.
+    DropLowest,
+}
+
+/// Expired posts are copied here by the cleanup job instead of being lost with the feed window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub enabled: bool,
+    /// Archived posts older than this are pruned; 0 keeps them forever.
+    pub retention_days: i64,
+}
+
+/// Periodically folds the engagement collected since ingest into stored post priorities.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rescore {
+    pub enabled: bool,
+    pub interval_mins: u64,
+}
+
+/// Periodically checkpoints the WAL back into the database and truncates it, then returns up to
+/// `max_vacuum_pages` free pages to the filesystem. Skipped while the buffers are more than
+/// `max_buffer_occupancy` full or the last flush wrote more than `flush.idle_max_entries`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Maintenance {
+    pub enabled: bool,
+    pub interval_mins: u64,
+    pub max_vacuum_pages: i64,
+    pub max_buffer_occupancy: f32,
+}
+
+/// The connection pool, read when it is opened. Flushes and cleanup skip their cycle when no
+/// connection is free, and background jobs after `acquire_retries` more tries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Database {
+    /// Connections for ingestion, background jobs and the admin API.
+    pub write_pool_size: u32,
+    /// Read-only connections for serving the feed and the stats API.
+    pub read_pool_size: u32,
+    /// How long requests and other callers that need a connection wait for a free one.
+    pub connection_timeout_ms: u64,
+    pub acquire_retries: u32,
+    /// Wait before the first retry, growing with each one.
+    pub retry_backoff_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Feed languages (`en`, or a lexicon `code`) sent to this endpoint; empty for every feed.
+    pub langs: Vec<String>,
+    /// Sent as the `X-Webhook-Secret` header so the receiver can reject forged calls.
+    pub secret: Option<String>,
+    /// Only posts accepted in the STRONG confidence tier are sent.
+    #[serde(default)]
+    pub strong_only: bool,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+/// Body of a webhook call.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, Serialize, Deserialize,
+)]
+#[serde(into = "String", try_from = "String")]
+#[strum(serialize_all = "kebab-case")]
+pub enum WebhookFormat {
+    /// The accepted post event as JSON.
+    #[default]
+    Json,
+    /// A Discord webhook message with the post as an embed.
+    Discord,
 }
 
 impl Default for Settings {
@@ -133,21 +785,57 @@ impl Default for Settings {
                 publisher_did: "did:web:example.com".to_string(),
                 feed_hostname: "example.com".to_string(),
                 firehose_limit: 5000,
+                ingest_mode: IngestMode::Firehose,
+                jetstream_url: "wss://jetstream2.us-east.bsky.network/subscribe".to_string(),
+                record_path: None,
+                replay_path: "replay.jsonl".to_string(),
                 enable_backfill: false,
+                database_url: "feed.db".to_string(),
+                log_assessments: true,
+                rejection_log_rates: HashMap::from([
+                    ("low-priority".to_string(), 1.0),
+                    ("near-duplicate".to_string(), 1.0),
+                ]),
+                default_rejection_log_rate: 0.0,
+                bind_address: "0.0.0.0".to_string(),
+                admin_bind_address: "127.0.0.1".to_string(),
+                internal_port: 3040,
+                tls: None,
+                proxy_protocol: false,
+                trusted_proxies: vec![],
+                access_log: AccessLog {
+                    enabled: true,
+                    sample_rate: 0.1,
+                    slow_request_ms: 1000,
+                },
+                feed_cache_ttl_secs: 30,
+                feed_cache_max_entries: 1000,
             },
             scoring: Scoring {
                 thresholds: ScoringThresholds {
                     min_text_length: 20,
+                    min_text_without_links: 20,
+                    min_unique_word_ratio: 0.6,
+                    diversity_min_words: 5,
+                    min_char_entropy: 3.0,
                 },
                 bonuses: ContentBonuses {
                     first_person: 0.2,
                     video: 0.1,
                     image_with_alt: 0.1,
+                    thread_min_length: 3,
+                    thread_per_post: 0.05,
+                    thread_max_boost: 0.3,
+                    keyword: 0.05,
+                    keyword_max_boost: 0.15,
                 },
                 penalties: ContentPenalties {
                     many_images: 0.2,
                     many_images_threshold: 3,
                     link_exponential_base: 3.0,
+                    reply_bait: 0.5,
+                    many_mentions: 0.3,
+                    many_mentions_threshold: 3,
                 },
                 quality: QualityThresholds {
                     poor_quality_penalty_min: 0.5,
@@ -158,13 +846,58 @@ impl Default for Settings {
                     min_priority: -5.0,
                     max_hashtags: 6,
                 },
+                author: AuthorBonuses {
+                    bio_relevance: 0.3,
+                    history_per_post: 0.05,
+                    history_max_boost: 0.3,
+                    borderline_max_priority: 0.0,
+                },
+                confidence: ConfidenceTiers {
+                    strong_min_priority: 0.8,
+                    moderate_min_priority: 0.3,
+                },
+                negative_examples: NegativeExamples {
+                    enabled: true,
+                    min_request_less: 3,
+                    min_similarity: 0.85,
+                    penalty: 1.0,
+                    max_examples: 500,
+                    harvest_interval_mins: 30,
+                },
+                alt_text: AltTextScoring {
+                    enabled: true,
+                    reference: "screenshot of a video game in development".to_string(),
+                    keyword_weight: 0.5,
+                    embedding_weight: 0.5,
+                    min_relevance: 0.3,
+                    bonus: 0.5,
+                    ocr_max_images: 2,
+                },
+                shadow: ShadowScoring {
+                    enabled: false,
+                    profile: "shadow".to_string(),
+                },
             },
             engagement: Engagement {
                 weights: EngagementWeights {
                     reply: 3.0,
                     repost: 2.0,
+                    dev_repost: 4.0,
                     like: 1.0,
                 },
+                like_multipliers: LikeMultipliers {
+                    dev_author: 2.5,
+                    subscriber: 2.0,
+                    unknown: 1.0,
+                },
+                brigading: BrigadingGuard {
+                    min_cohort_size: 10,
+                    z_score_threshold: 3.0,
+                    zero_z_score: 6.0,
+                    min_liker_entropy: 0.6,
+                    max_unknown_liker_share: 0.9,
+                    recheck_secs: 60,
+                },
                 velocity_scale: 0.1,
                 max_boost: 0.5,
             },
@@ -176,20 +909,113 @@ impl Default for Settings {
                 shuffle_variance: 0.05,
                 preference_boost: 1.5,
                 preference_penalty: 0.3,
+                engaged_author_boost: 1.15,
                 priority_bucket_hours: 1,
+                hour_balance_boost: 0.0,
+                moderate_sampling: ModerateSampling {
+                    enabled: false,
+                    min_probability: 0.1,
+                },
+                author_cap: AuthorCap {
+                    enabled: true,
+                    max_posts_per_author: 2,
+                    window: 50,
+                },
+                decay: Decay {
+                    enabled: false,
+                    half_life_hours: 24.0,
+                    topic_rates: HashMap::from([
+                        ("discussion".to_string(), 2.0),
+                        ("asking a question".to_string(), 1.5),
+                        ("sharing work".to_string(), 0.75),
+                    ]),
+                },
+                priority_bounds: PriorityBounds {
+                    squash: PrioritySquash::Off,
+                    floor: -1.0,
+                    ceiling: 2.0,
+                },
+                follows: FollowBoost {
+                    enabled: true,
+                    boost: 1.3,
+                    cache_ttl_mins: 60,
+                    max_follows: 5000,
+                },
+                interest_profile: InterestProfile {
+                    enabled: true,
+                    weight: 0.5,
+                    min_similarity: 0.3,
+                    max_posts: 50,
+                    min_posts: 3,
+                },
+                like_affinity: LikeAffinities {
+                    enabled: true,
+                    half_life_hours: 168.0,
+                    topic_weight: 0.3,
+                    author_weight: 0.05,
+                    max_boost: 1.4,
+                    min_likes: 3.0,
+                },
+                tier_ttls: TierTtls {
+                    strong_hours: 0,
+                    moderate_hours: 48,
+                    weak_hours: 24,
+                },
+                trending: TrendingFeed {
+                    enabled: true,
+                    feed_name: "devlogs-trending".into(),
+                    cutoff_hours: 6,
+                },
             },
             ml: Ml {
                 batch_size: 16,
                 batch_timeout_ms: 10,
+                queue_capacity: 256,
+                backend: MlBackend::RustBert,
+                multilingual_embeddings: false,
+                onnx: OnnxModels {
+                    classifier_model: "models/nli-deberta-v3-xsmall/model_quantized.onnx".into(),
+                    classifier_tokenizer: "models/nli-deberta-v3-xsmall/tokenizer.json".into(),
+                    entailment_index: 1,
+                    contradiction_index: 0,
+                    embedding_model: "models/all-MiniLM-L12-v2/model_quantized.onnx".into(),
+                    embedding_tokenizer: "models/all-MiniLM-L12-v2/tokenizer.json".into(),
+                    max_length: 128,
+                    intra_threads: 2,
+                },
+                translation: Translation {
+                    enabled: false,
+                    api_url: "http://127.0.0.1:5000/translate".into(),
+                    api_key: None,
+                    max_per_hour: 200,
+                    timeout_ms: 3000,
+                },
             },
             spam: Spam {
                 repost_threshold: 10.0,
                 velocity_window_hours: 1,
+                max_posts_per_hour: 6.0,
+                flag_post_flood: false,
             },
             backfill: Backfill {
                 limit: 200,
                 hours: 96,
                 search_limit: 50,
+                max_pages_per_query: 5,
+                gap_min_secs: 120,
+                gap_poll_interval_secs: 900,
+                queries: vec![
+                    "gamedev".to_string(),
+                    "indiedev".to_string(),
+                    "devlog".to_string(),
+                    "game development".to_string(),
+                    "#screenshotsaturday".to_string(),
+                ],
+                exclude_terms: vec![
+                    "giveaway".to_string(),
+                    "nft".to_string(),
+                    "crypto".to_string(),
+                ],
             },
             filters: Filters {
                 gamedev_keywords: vec![
@@ -230,6 +1056,13 @@ impl Default for Settings {
                     "glsl".into(),
                     "wgsl".into(),
                 ],
+                keyword_weights: HashMap::from([
+                    ("devlog".into(), 1.0),
+                    ("glsl".into(), 0.5),
+                    ("wgsl".into(), 0.5),
+                    ("fmod".into(), 0.6),
+                    ("wwise".into(), 0.6),
+                ]),
                 gamedev_hashtags: vec![
                     "#gamedev".into(),
                     "#indiedev".into(),
@@ -252,7 +1085,7 @@ impl Default for Settings {
                     "nft".into(),
                     "web3".into(),
                     "blockchain".into(),
-                    "mint".into(),
+                    "free mint".into(),
                     "airdrop".into(),
                     "whitelist".into(),
                     "rugpull".into(),
@@ -260,18 +1093,12 @@ impl Default for Settings {
                     "ethereum".into(),
                     "bitcoin".into(),
                     "token sale".into(),
-                    "ico".into(),
-                    "hodl".into(),
+                    "initial coin offering".into(),
                 ],
                 blocked_hashtags: vec![
                     "#nft".into(),
-                    "#nfts".into(),
-                    "#nftart".into(),
-                    "#nftgaming".into(),
                     "#crypto".into(),
-                    "#cryptocurrency".into(),
                     "#web3".into(),
-                    "#web3gaming".into(),
                     "#blockchain".into(),
                     "#freemint".into(),
                     "#airdrop".into(),
@@ -279,8 +1106,8 @@ impl Default for Settings {
                     "#ethereum".into(),
                     "#bitcoin".into(),
                 ],
+                opt_out_hashtags: vec!["#nofeed".into()],
                 promo_domains: vec![
-                    "store.steampowered.com".into(),
                     "steampowered.com".into(),
                     "itch.io".into(),
                     "twitch.tv".into(),
@@ -302,6 +1129,160 @@ impl Default for Settings {
                 ],
                 moderator_dids: vec![],
                 influencer_dids: vec![],
+                fold_plurals: true,
+                reply_bait_phrases: vec![
+                    "tag a dev".into(),
+                    "tag an artist".into(),
+                    "tag someone".into(),
+                    "tag a friend".into(),
+                    "tag your".into(),
+                    "drop your".into(),
+                    "reply with your".into(),
+                    "quote this with".into(),
+                    "comment below".into(),
+                ],
+                post_age: PostAge {
+                    max_future_secs: 300,
+                    clamp_future: true,
+                    max_age_hours: 48,
+                },
+                languages: vec![
+                    LanguageLexicon {
+                        code: "pt".into(),
+                        feed_name: "devlogs-pt".into(),
+                        enabled: false,
+                        gamedev_keywords: vec![
+                            "desenvolvimento de jogos".into(),
+                            "desenvolvedor de jogos".into(),
+                            "desenvolvedora de jogos".into(),
+                            "dev de jogos".into(),
+                            "jogo indie".into(),
+                            "jogos indie".into(),
+                            "design de jogos".into(),
+                            "design de níveis".into(),
+                            "arte de jogos".into(),
+                        ],
+                        gamedev_hashtags: vec![
+                            "#gamedevbr".into(),
+                            "#indiedevbr".into(),
+                            "#desenvolvimentodejogos".into(),
+                            "#jogosindie".into(),
+                            "#devlogbr".into(),
+                        ],
+                        blocked_keywords: vec!["cripto".into(), "apostas".into(), "cassino".into()],
+                        promo_domains: vec!["catarse.me".into(), "apoia.se".into()],
+                    },
+                    LanguageLexicon {
+                        code: "es".into(),
+                        feed_name: "devlogs-es".into(),
+                        enabled: false,
+                        gamedev_keywords: vec![
+                            "desarrollo de videojuegos".into(),
+                            "desarrollo de juegos".into(),
+                            "desarrollador de videojuegos".into(),
+                            "desarrolladora de videojuegos".into(),
+                            "videojuego indie".into(),
+                            "juego indie".into(),
+                            "diseño de juegos".into(),
+                            "diseño de niveles".into(),
+                            "arte de videojuegos".into(),
+                        ],
+                        gamedev_hashtags: vec![
+                            "#gamedeves".into(),
+                            "#gamedevlatam".into(),
+                            "#indiedevlatam".into(),
+                            "#desarrolloindie".into(),
+                            "#videojuegosindie".into(),
+                        ],
+                        blocked_keywords: vec!["cripto".into(), "apuestas".into(), "casino".into()],
+                        promo_domains: vec!["verkami.com".into()],
+                    },
+                ],
+            },
+            api: Api {
+                related_default_limit: 10,
+                related_max_limit: 50,
+                search_default_limit: 20,
+                search_max_limit: 100,
+                search_priority_weight: 0.5,
+                search_candidates_per_result: 5,
+                archive_default_limit: 100,
+                archive_max_limit: 500,
+                preview_default_limit: 30,
+                preview_max_limit: 200,
+                preview_excerpt_chars: 140,
+                admin_tokens: vec![],
+            },
+            semantic: Semantic {
+                dedup_similarity: 0.95,
+                cluster_similarity: 0.8,
+                cluster_min_size: 3,
+                index_connections: 16,
+                index_ef_construction: 100,
+                index_ef_search: 64,
+            },
+            authors: Authors {
+                handle_ttl_hours: 24,
+                history_window_days: 30,
+                bio_reference: "indie game developer making my own video game".to_string(),
+                bio_keyword_weight: 0.5,
+                bio_embedding_weight: 0.5,
+                reputation: AuthorReputation {
+                    enabled: true,
+                    window_days: 30,
+                    min_posts: 5,
+                    accept_weight: 0.4,
+                    score_weight: 0.2,
+                    feedback_weight: 0.4,
+                    spam_weight: 1.0,
+                    reference_score: 1.0,
+                    boost: 0.3,
+                    penalty: 0.5,
+                },
+            },
+            firehose: Firehose {
+                silence_timeout_secs: 120,
+                backoff_base_secs: 1,
+                backoff_max_secs: 300,
+                alert_after_failures: 5,
+                dedup_window: 50000,
+            },
+            webhooks: Webhooks {
+                timeout_ms: 5000,
+                endpoints: vec![],
+            },
+            archive: Archive {
+                enabled: true,
+                retention_days: 365,
+            },
+            flush: Flush {
+                min_interval_ms: 5000,
+                max_interval_ms: 60000,
+                idle_max_entries: 20,
+                immediate_threshold: 1000,
+                max_pending_posts: 2000,
+                max_pending_likes: 20000,
+                max_pending_interactions: 10000,
+                max_interactions_per_request: 100,
+                posts_overflow: BufferOverflow::Flush,
+                likes_overflow: BufferOverflow::DropLowest,
+            },
+            rescore: Rescore {
+                enabled: true,
+                interval_mins: 15,
+            },
+            maintenance: Maintenance {
+                enabled: true,
+                interval_mins: 60,
+                max_vacuum_pages: 10000,
+                max_buffer_occupancy: 0.25,
+            },
+            database: Database {
+                write_pool_size: 3,
+                read_pool_size: 6,
+                connection_timeout_ms: 2000,
+                acquire_retries: 2,
+                retry_backoff_ms: 250,
             },
         }
     }
@@ -335,7 +1316,10 @@ impl Settings {
             }
         }
 
-        settings
+        match apply_profile(&settings) {
+            Ok(Some(profiled)) => profiled,
+            _ => settings,
+        }
     }
 
     fn reload() {
@@ -343,6 +1327,7 @@ impl Settings {
         match Self::try_load_from_files() {
             Ok(new_settings) => {
                 swap.store(Arc::new(new_settings));
+                GENERATION.fetch_add(1, Ordering::Relaxed);
                 logs::log_settings_reloaded();
             }
             Err(e) => {
@@ -370,14 +1355,152 @@ impl Settings {
                 ron::from_str::<Settings>(&content).map_err(|e| format!("settings.ron: {e}"))?;
         }
 
-        Ok(settings)
+        Ok(apply_profile(&settings)?.unwrap_or(settings))
     }
+
+    /// A copy without API keys, admin tokens, webhook URLs or webhook secrets, safe to store.
+    pub fn redacted(&self) -> Settings {
+        const REDACTED: &str = "<redacted>";
+        let mut redacted = self.clone();
+        if let Some(api_key) = &mut redacted.ml.translation.api_key {
+            *api_key = REDACTED.to_string();
+        }
+        for admin in &mut redacted.api.admin_tokens {
+            admin.token = REDACTED.to_string();
+        }
+        for endpoint in &mut redacted.webhooks.endpoints {
+            endpoint.url = REDACTED.to_string();
+            if let Some(secret) = &mut endpoint.secret {
+                *secret = REDACTED.to_string();
+            }
+        }
+        redacted
+    }
+}
+
+/// Changes each time the settings are reloaded or overridden, so callers can tell cheaply
+/// whether the settings they last looked at are still the ones in effect.
+pub fn settings_generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+pub fn active_profile() -> Option<String> {
+    std::env::var(PROFILE_ENV).ok().filter(|p| !p.is_empty())
+}
+
+fn apply_profile(settings: &Settings) -> Result<Option<Settings>, String> {
+    let Some(profile) = active_profile() else {
+        return Ok(None);
+    };
+
+    profile_settings(settings, &profile).map(Some)
+}
+
+/// `settings` with the overrides of `profile` from `settings.profiles.ron` merged in.
+pub fn profile_settings(settings: &Settings, profile: &str) -> Result<Settings, String> {
+    let content = fs::read_to_string(PROFILES_PATH).map_err(|e| format!("{PROFILES_PATH}: {e}"))?;
+    let mut profiles: HashMap<String, Value> =
+        ron::from_str(&content).map_err(|e| format!("{PROFILES_PATH}: {e}"))?;
+    let overrides = profiles
+        .remove(profile)
+        .ok_or_else(|| format!("{PROFILES_PATH}: unknown profile \"{profile}\""))?;
+
+    merge_profile(settings, overrides)
+}
+
+fn merge_profile(settings: &Settings, overrides: Value) -> Result<Settings, String> {
+    let serialized = ron::to_string(settings).map_err(|e| e.to_string())?;
+    let mut base: Value = ron::from_str(&serialized).map_err(|e| e.to_string())?;
+    merge_values(&mut base, overrides);
+    base.into_rust()
+        .map_err(|e| format!("{PROFILES_PATH}: {e}"))
+}
+
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Map(base), Value::Map(overlay)) => {
+            for (key, value) in overlay {
+                let merged = match base.remove(&key) {
+                    Some(mut existing) => {
+                        merge_values(&mut existing, value);
+                        existing
+                    }
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// `settings` with `path=value` assignments applied, as in `scoring.rejection.min_priority=0.5`.
+/// Values are RON; text settings also take an unquoted string, and optional ones a bare value.
+pub fn with_overrides(settings: &Settings, assignments: &[String]) -> Result<Settings, String> {
+    let serialized = ron::to_string(settings).map_err(|e| e.to_string())?;
+    let mut base: Value = ron::from_str(&serialized).map_err(|e| e.to_string())?;
+    for assignment in assignments {
+        let (path, raw) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("{assignment}: expected <path>=<value>"))?;
+        let node = setting_mut(&mut base, path.trim())?;
+        let raw = raw.trim();
+        let value = match ron::from_str::<Value>(raw) {
+            _ if matches!(node, Value::String(_)) && !raw.starts_with('"') => {
+                Value::String(raw.to_string())
+            }
+            Ok(Value::Option(value)) => Value::Option(value),
+            Ok(value) if matches!(node, Value::Option(_)) => Value::Option(Some(Box::new(value))),
+            Ok(value) => value,
+            Err(e) => return Err(format!("{assignment}: {e}")),
+        };
+        *node = value;
+    }
+    base.into_rust().map_err(|e| e.to_string())
+}
+
+fn setting_mut<'a>(base: &'a mut Value, path: &str) -> Result<&'a mut Value, String> {
+    let mut node = base;
+    for key in path.split('.') {
+        let Value::Map(map) = node else {
+            return Err(format!("{path}: {key} is not inside a section"));
+        };
+        node = map
+            .iter_mut()
+            .find(|(name, _)| **name == Value::String(key.to_string()))
+            .map(|(_, value)| value)
+            .ok_or_else(|| format!("{path}: no such setting"))?;
+    }
+    Ok(node)
+}
+
+/// Replaces the loaded settings for the rest of the process, for one-off runs of the CLI tools.
+/// A settings file change reloads them from disk again.
+pub fn override_settings(assignments: &[String]) -> Result<(), String> {
+    let overridden = with_overrides(&settings(), assignments)?;
+    SETTINGS
+        .get_or_init(|| ArcSwap::from_pointee(Settings::load_from_files()))
+        .store(Arc::new(overridden));
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    Ok(())
 }
 
 pub fn settings() -> Guard<Arc<Settings>> {
     Settings::load()
 }
 
+fn is_settings_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    name == "settings.default.ron"
+        || name == "settings.ron"
+        || (name == PROFILES_PATH && active_profile().is_some())
+}
+
+/// Reloads the settings when a settings file changes or on SIGHUP. The working directory is
+/// watched rather than the files, so editors that save by renaming and a `settings.ron`
+/// created after startup are both picked up.
 pub fn spawn_settings_watcher() -> notify::Result<()> {
     let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
 
@@ -387,32 +1510,176 @@ pub fn spawn_settings_watcher() -> notify::Result<()> {
                 if matches!(
                     event.kind,
                     EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
-                ) {
+                ) && event.paths.iter().any(|path| is_settings_file(path))
+                {
                     let _ = tx.try_send(());
                 }
             }
         },
         notify::Config::default(),
     )?;
+    watcher.watch(Path::new("."), RecursiveMode::NonRecursive)?;
 
-    let default_path = Path::new("settings.default.ron");
-    let override_path = Path::new("settings.ron");
-
-    if default_path.exists() {
-        watcher.watch(default_path, RecursiveMode::NonRecursive)?;
-    }
-    if override_path.exists() {
-        watcher.watch(override_path, RecursiveMode::NonRecursive)?;
-    }
+    let mut hangup = signal(SignalKind::hangup())?;
 
     tokio::spawn(async move {
         let _watcher = watcher;
-        while rx.recv().await.is_some() {
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            while rx.try_recv().is_ok() {}
+        loop {
+            tokio::select! {
+                changed = rx.recv() => {
+                    if changed.is_none() {
+                        break;
+                    }
+                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                    while rx.try_recv().is_ok() {}
+                }
+                _ = hangup.recv() => {}
+            }
             Settings::reload();
         }
     });
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_routing() {
+        let mut filters = Settings::default().filters;
+        assert_eq!(filters.feed_language(Some("pt-BR")), "en");
+
+        filters.languages[0].enabled = true;
+        assert_eq!(filters.feed_language(Some("pt-BR")), "pt");
+        assert_eq!(filters.feed_language(Some("es")), "en");
+        assert_eq!(
+            filters
+                .language_for_feed("at://did:web:example.com/app.bsky.feed.generator/devlogs-pt"),
+            "pt"
+        );
+        assert_eq!(
+            filters.language_for_feed("at://did:web:example.com/app.bsky.feed.generator/devlogs"),
+            "en"
+        );
+    }
+
+    #[test]
+    fn test_trending_routing() {
+        let mut trending = Settings::default().feed.trending;
+        let uri = "at://did:web:example.com/app.bsky.feed.generator/devlogs-trending";
+        assert!(trending.serves(uri));
+        assert!(!trending.serves("at://did:web:example.com/app.bsky.feed.generator/devlogs"));
+
+        trending.enabled = false;
+        assert!(!trending.serves(uri));
+    }
+
+    #[test]
+    fn test_profile_overrides_nested_fields() {
+        let base = Settings::default();
+        let overrides: Value = ron::from_str(
+            r#"(server: (feed_hostname: "staging.example.com"), feed: (cutoff_hours: 24))"#,
+        )
+        .unwrap();
+
+        let merged = merge_profile(&base, overrides).unwrap();
+        assert_eq!(merged.server.feed_hostname, "staging.example.com");
+        assert_eq!(merged.server.publisher_did, base.server.publisher_did);
+        assert_eq!(merged.feed.cutoff_hours, 24);
+        assert_eq!(merged.feed.max_limit, base.feed.max_limit);
+    }
+
+    #[test]
+    fn test_profile_overrides_ml_backend() {
+        let overrides: Value = ron::from_str(r#"(ml: (backend: "onnx"))"#).unwrap();
+        let merged = merge_profile(&Settings::default(), overrides).unwrap();
+        assert_eq!(merged.ml.backend, MlBackend::Onnx);
+    }
+
+    #[test]
+    fn test_watches_only_settings_files() {
+        assert!(is_settings_file(Path::new("./settings.ron")));
+        assert!(is_settings_file(Path::new(
+            "/opt/feed/settings.default.ron"
+        )));
+        assert!(!is_settings_file(Path::new("./settings.ron.swp")));
+        assert!(!is_settings_file(Path::new("./feed.db-wal")));
+    }
+
+    #[test]
+    fn test_profile_rejects_invalid_types() {
+        let overrides: Value = ron::from_str(r#"(feed: (cutoff_hours: "soon"))"#).unwrap();
+        assert!(merge_profile(&Settings::default(), overrides).is_err());
+    }
+
+    #[test]
+    fn test_webhook_endpoint_defaults() {
+        let endpoint: WebhookEndpoint =
+            ron::from_str(r#"(url: "https://example.com/hook", langs: [], secret: None)"#).unwrap();
+        assert!(!endpoint.strong_only);
+        assert_eq!(endpoint.format, WebhookFormat::Json);
+    }
+
+    #[test]
+    fn test_redacted_hides_secrets() {
+        let mut settings = Settings::default();
+        settings.ml.translation.api_key = Some("deepl-key".to_string());
+        settings.api.admin_tokens.push(AdminToken {
+            name: "ops".to_string(),
+            token: "admin-token".to_string(),
+            role: AdminRole::Owner,
+        });
+        settings.webhooks.endpoints.push(WebhookEndpoint {
+            url: "https://discord.com/api/webhooks/1/hook-token".to_string(),
+            langs: vec![],
+            secret: Some("hook-secret".to_string()),
+            strong_only: true,
+            format: WebhookFormat::Discord,
+        });
+
+        let redacted = ron::to_string(&settings.redacted()).unwrap();
+        for secret in ["deepl-key", "admin-token", "hook-token", "hook-secret"] {
+            assert!(!redacted.contains(secret), "{secret}");
+        }
+        assert!(redacted.contains("ops"));
+    }
+
+    #[test]
+    fn test_overrides_by_path() {
+        let base = Settings::default();
+        let assignments = [
+            "scoring.rejection.min_priority=0.55".to_string(),
+            "server.feed_hostname=staging.example.com".to_string(),
+            "server.record_path=\"events.jsonl\"".to_string(),
+            "feed.cutoff_hours = 24".to_string(),
+        ];
+
+        let overridden = with_overrides(&base, &assignments).unwrap();
+        assert_eq!(overridden.scoring.rejection.min_priority, 0.55);
+        assert_eq!(overridden.server.feed_hostname, "staging.example.com");
+        assert_eq!(
+            overridden.server.record_path.as_deref(),
+            Some("events.jsonl")
+        );
+        assert_eq!(overridden.feed.cutoff_hours, 24);
+        assert_eq!(overridden.feed.max_limit, base.feed.max_limit);
+    }
+
+    #[test]
+    fn test_overrides_reject_unknown_paths_and_types() {
+        let base = Settings::default();
+        for invalid in [
+            "scoring.thresholds.score=0.5",
+            "feed.cutoff_hours=soon",
+            "feed.cutoff_hours.max=1",
+            "feed",
+        ] {
+            assert!(
+                with_overrides(&base, &[invalid.to_string()]).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::db::NewShadowScore;
+use crate::scoring::{calculate_priority_with, PriorityBreakdown, PrioritySignals};
+use crate::settings::{profile_settings, settings, settings_generation, Settings};
+use crate::utils::logs;
+use std::sync::Arc;
+
+/// Scores posts again under `scoring.shadow.profile`, so operators can see what new thresholds
+/// would accept before making them live. Filters and relevance still use the live settings.
+#[derive(Clone, Default)]
+pub struct ShadowScorer {
+    /// The shadow settings and the settings generation they were merged from. `None` when the
+    /// profile failed to load, until the next reload.
+    loaded: Option<(u64, Option<Arc<Settings>>)>,
+}
+
+impl ShadowScorer {
+    fn shadow_settings(&mut self, live: &Settings) -> Option<Arc<Settings>> {
+        let generation = settings_generation();
+        if let Some((loaded_at, shadow)) = &self.loaded {
+            if *loaded_at == generation {
+                return shadow.clone();
+            }
+        }
+        let shadow = match profile_settings(live, &live.scoring.shadow.profile) {
+            Ok(shadow) => Some(Arc::new(shadow)),
+            Err(e) => {
+                logs::log_shadow_profile_failed(&e);
+                None
+            }
+        };
+        self.loaded = Some((generation, shadow.clone()));
+        shadow
+    }
+
+    /// What the shadow profile makes of a post the live settings scored `live`, or `None` when
+    /// shadow scoring is off.
+    pub fn score(
+        &mut self,
+        uri: &str,
+        signals: &PrioritySignals,
+        live: &PriorityBreakdown,
+        now: i64,
+    ) -> Option<NewShadowScore> {
+        let s = settings();
+        if !s.scoring.shadow.enabled {
+            return None;
+        }
+        let shadow = self.shadow_settings(&s)?;
+        let priority = calculate_priority_with(signals, &shadow).priority;
+        let live_accepted = live.priority >= s.scoring.rejection.min_priority;
+        let shadow_accepted = priority >= shadow.scoring.rejection.min_priority;
+        if live_accepted != shadow_accepted && s.server.log_assessments {
+            logs::log_shadow_disagreement(uri, live.priority, priority, shadow_accepted);
+        }
+
+        Some(NewShadowScore {
+            uri: uri.to_string(),
+            profile: s.scoring.shadow.profile.clone(),
+            live_priority: live.priority,
+            shadow_priority: priority,
+            live_accepted: live_accepted as i32,
+            shadow_accepted: shadow_accepted as i32,
+            scored_at: now,
+        })
+    }
+}
@@ -0,0 +1,199 @@
+use crate::db::{self, ArchivedPost, NewPost, NewPostEmbedding, Post, PostSource};
+use crate::scoring::Embedding;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+pub const ARCHIVE_EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub created_at: i64,
+    pub posts: Vec<SnapshotPost>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotPost {
+    pub uri: String,
+    pub text: String,
+    pub timestamp: i64,
+    pub priority: f32,
+    pub has_media: i32,
+    pub is_first_person: i32,
+    pub author_did: Option<String>,
+    pub image_count: i32,
+    pub has_alt_text: i32,
+    pub link_count: i32,
+    pub promo_link_count: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub mention_count: i32,
+    #[serde(default = "default_thread_length")]
+    pub thread_length: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Embedding>,
+}
+
+fn default_thread_length() -> i32 {
+    1
+}
+
+impl SnapshotPost {
+    fn from_post(post: Post, embedding: Option<Embedding>) -> Self {
+        Self {
+            uri: post.uri,
+            text: post.text,
+            timestamp: post.timestamp,
+            priority: post.priority,
+            has_media: post.has_media,
+            is_first_person: post.is_first_person,
+            author_did: post.author_did,
+            image_count: post.image_count,
+            has_alt_text: post.has_alt_text,
+            link_count: post.link_count,
+            promo_link_count: post.promo_link_count,
+            lang: Some(post.lang),
+            mention_count: post.mention_count,
+            thread_length: post.thread_length,
+            embedding,
+        }
+    }
+
+    /// `None` when the URI or author DID is malformed.
+    fn into_new_post(self) -> Option<(NewPost, Option<Embedding>)> {
+        let author_did = match self.author_did {
+            Some(did) => Some(did.parse().ok()?),
+            None => None,
+        };
+        let post = NewPost {
+            uri: self.uri.parse().ok()?,
+            text: self.text,
+            timestamp: self.timestamp,
+            priority: self.priority,
+            has_media: self.has_media,
+            is_first_person: self.is_first_person,
+            author_did,
+            image_count: self.image_count,
+            has_alt_text: self.has_alt_text,
+            link_count: self.link_count,
+            promo_link_count: self.promo_link_count,
+            lang: self.lang.unwrap_or_else(|| "en".to_string()),
+            mention_count: self.mention_count,
+            thread_length: self.thread_length,
+            // Config ids refer to the exporting database's history.
+            config_id: None,
+            source: PostSource::Manual.to_string(),
+        };
+        Some((post, self.embedding))
+    }
+}
+
+pub fn export(conn: &mut SqliteConnection, cutoff_timestamp: i64) -> QueryResult<Snapshot> {
+    let mut embeddings: HashMap<String, Embedding> =
+        db::get_feed_embeddings(conn, cutoff_timestamp)?
+            .into_iter()
+            .collect();
+
+    let posts = db::get_feed(conn, cutoff_timestamp)?
+        .into_iter()
+        .map(|post| {
+            let embedding = embeddings.remove(&post.uri).filter(|e| !e.is_empty());
+            SnapshotPost::from_post(post, embedding)
+        })
+        .collect();
+
+    Ok(Snapshot {
+        version: SNAPSHOT_VERSION,
+        created_at: Utc::now().timestamp(),
+        posts,
+    })
+}
+
+pub fn import(conn: &mut SqliteConnection, snapshot: Snapshot) -> QueryResult<usize> {
+    let now = Utc::now().timestamp();
+    let mut posts = Vec::with_capacity(snapshot.posts.len());
+    let mut embeddings = Vec::new();
+
+    for (post, embedding) in snapshot
+        .posts
+        .into_iter()
+        .filter_map(SnapshotPost::into_new_post)
+    {
+        if let Some(embedding) = embedding {
+            embeddings.push(NewPostEmbedding::new(post.uri.to_string(), &embedding, now));
+        }
+        posts.push(post);
+    }
+
+    conn.transaction(|conn| {
+        let imported = db::insert_posts(conn, posts)?;
+        db::insert_post_embeddings(conn, embeddings)?;
+        Ok(imported)
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveExport {
+    pub version: u32,
+    pub created_at: i64,
+    pub posts: Vec<ArchiveExportPost>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveExportPost {
+    pub uri: String,
+    pub text_hash: String,
+    pub author_did: Option<String>,
+    pub lang: String,
+    pub timestamp: i64,
+    pub priority: f32,
+    pub confidence: String,
+    pub topic: String,
+    pub like_count: i32,
+    pub reply_count: i32,
+    pub repost_count: i32,
+    pub archived_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_id: Option<i32>,
+    #[serde(default)]
+    pub source: String,
+}
+
+impl From<ArchivedPost> for ArchiveExportPost {
+    fn from(post: ArchivedPost) -> Self {
+        Self {
+            uri: post.uri,
+            text_hash: post.text_hash,
+            author_did: post.author_did,
+            lang: post.lang,
+            timestamp: post.timestamp,
+            priority: post.priority,
+            confidence: post.confidence,
+            topic: post.topic,
+            like_count: post.like_count,
+            reply_count: post.reply_count,
+            repost_count: post.repost_count,
+            archived_at: post.archived_at,
+            config_id: post.config_id,
+            source: post.source,
+        }
+    }
+}
+
+pub fn export_archive(conn: &mut SqliteConnection) -> QueryResult<ArchiveExport> {
+    let posts = db::get_archived_posts(conn)?
+        .into_iter()
+        .map(ArchiveExportPost::from)
+        .collect();
+
+    Ok(ArchiveExport {
+        version: ARCHIVE_EXPORT_VERSION,
+        created_at: Utc::now().timestamp(),
+        posts,
+    })
+}
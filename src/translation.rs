@@ -0,0 +1,139 @@
+use crate::settings::settings;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const BUDGET_WINDOW: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Serialize)]
+struct TranslateRequest<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+}
+
+/// Hourly allowance of translation requests, shared by every clone of the translator.
+#[derive(Debug)]
+struct Budget {
+    window_start: Instant,
+    used: u32,
+}
+
+impl Budget {
+    fn try_spend(&mut self, now: Instant, max_per_window: u32) -> bool {
+        if now.duration_since(self.window_start) >= BUDGET_WINDOW {
+            self.window_start = now;
+            self.used = 0;
+        }
+        if self.used >= max_per_window {
+            return false;
+        }
+        self.used += 1;
+        true
+    }
+}
+
+/// Translates non-English posts to English through a LibreTranslate-compatible endpoint,
+/// either self-hosted next to the feed or a hosted API.
+#[derive(Clone)]
+pub struct Translator {
+    client: reqwest::Client,
+    budget: Arc<Mutex<Budget>>,
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Translator {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            budget: Arc::new(Mutex::new(Budget {
+                window_start: Instant::now(),
+                used: 0,
+            })),
+        }
+    }
+
+    /// Gives up after `ml.translation.timeout_ms`, however far the request got.
+    pub async fn translate(&self, text: &str, source_lang: &str) -> Option<String> {
+        let s = settings();
+        let config = &s.ml.translation;
+        if !config.enabled {
+            return None;
+        }
+        let timeout = Duration::from_millis(config.timeout_ms);
+        tokio::time::timeout(timeout, self.request(text, source_lang, timeout))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn request(&self, text: &str, source_lang: &str, timeout: Duration) -> Option<String> {
+        let s = settings();
+        let config = &s.ml.translation;
+
+        let allowed = self
+            .budget
+            .lock()
+            .ok()?
+            .try_spend(Instant::now(), config.max_per_hour);
+        if !allowed {
+            return None;
+        }
+
+        let source = source_lang.split(['-', '_']).next().unwrap_or(source_lang);
+        let response = self
+            .client
+            .post(&config.api_url)
+            .timeout(timeout)
+            .json(&TranslateRequest {
+                q: text,
+                source,
+                target: "en",
+                format: "text",
+                api_key: config.api_key.as_deref(),
+            })
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?;
+
+        let translated = response.json::<TranslateResponse>().await.ok()?;
+        Some(translated.translated_text).filter(|t| !t.trim().is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_resets_each_window() {
+        let start = Instant::now();
+        let mut budget = Budget {
+            window_start: start,
+            used: 0,
+        };
+
+        assert!(budget.try_spend(start, 2));
+        assert!(budget.try_spend(start, 2));
+        assert!(!budget.try_spend(start, 2));
+
+        assert!(budget.try_spend(start + BUDGET_WINDOW, 2));
+        assert_eq!(budget.used, 1);
+    }
+}
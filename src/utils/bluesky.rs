@@ -1,3 +1,5 @@
+use crate::scoring::MediaInfo;
+use crate::utils::ids::AtUri;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -36,6 +38,10 @@ pub struct Facet {
 pub enum FacetFeature {
     #[serde(rename = "app.bsky.richtext.facet#link")]
     Link { uri: String },
+    #[serde(rename = "app.bsky.richtext.facet#tag")]
+    Tag { tag: String },
+    #[serde(rename = "app.bsky.richtext.facet#mention")]
+    Mention { did: String },
     #[serde(other)]
     Other,
 }
@@ -43,16 +49,21 @@ pub enum FacetFeature {
 #[derive(Debug, Clone)]
 pub struct FetchedPost {
     pub text: String,
-    pub has_media: bool,
-    pub has_video: bool,
-    pub image_count: usize,
-    pub external_uri: Option<String>,
-    pub facet_links: Vec<String>,
+    pub media: MediaInfo,
 }
 
-pub fn parse_bluesky_url(input: &str) -> Option<String> {
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    pub did: String,
+    pub handle: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+}
+
+pub fn parse_bluesky_url(input: &str) -> Option<AtUri> {
     if input.starts_with("at://") {
-        return Some(input.to_string());
+        return input.parse().ok();
     }
 
     let url_regex = Regex::new(r"https://bsky\.app/profile/([^/]+)/post/([a-zA-Z0-9]+)").ok()?;
@@ -60,10 +71,9 @@ pub fn parse_bluesky_url(input: &str) -> Option<String> {
     if let Some(caps) = url_regex.captures(input) {
         let did_or_handle = caps.get(1)?.as_str();
         let rkey = caps.get(2)?.as_str();
-        return Some(format!(
-            "at://{}/app.bsky.feed.post/{}",
-            did_or_handle, rkey
-        ));
+        return format!("at://{}/app.bsky.feed.post/{}", did_or_handle, rkey)
+            .parse()
+            .ok();
     }
 
     None
@@ -93,76 +103,93 @@ pub async fn fetch_post(at_uri: &str) -> Result<FetchedPost, String> {
         .await
         .map_err(|e| format!("Failed to parse response: {}", e))?;
 
-    let (has_media, has_video, image_count, external_uri) =
-        extract_media_info(&thread.thread.post.embed);
-
-    let facet_links = extract_facet_links(&thread.thread.post.record.facets);
-
+    let post = thread.thread.post;
     Ok(FetchedPost {
-        text: thread.thread.post.record.text,
-        has_media,
-        has_video,
-        image_count,
-        external_uri,
-        facet_links,
+        media: MediaInfo::from_embed_view(post.embed.as_ref()).with_facets(&post.record.facets),
+        text: post.record.text,
     })
 }
 
-fn extract_media_info(embed: &Option<serde_json::Value>) -> (bool, bool, usize, Option<String>) {
-    let Some(embed) = embed else {
-        return (false, false, 0, None);
-    };
+pub async fn fetch_profile(client: &reqwest::Client, actor: &str) -> Result<Profile, String> {
+    let url = format!(
+        "{}/app.bsky.actor.getProfile?actor={}",
+        PUBLIC_API_BASE,
+        urlencoding::encode(actor)
+    );
+
+    let response = client
+        .get(&url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch profile: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse profile: {}", e))
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowsResponse {
+    follows: Vec<FollowedActor>,
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowedActor {
+    did: String,
+}
 
-    let embed_type = embed.get("$type").and_then(|t| t.as_str()).unwrap_or("");
-
-    match embed_type {
-        "app.bsky.embed.video#view" => (true, true, 0, None),
-        "app.bsky.embed.images#view" => {
-            let count = embed
-                .get("images")
-                .and_then(|i| i.as_array())
-                .map(|arr| arr.len())
-                .unwrap_or(0);
-            (count > 0, false, count, None)
+/// DIDs of the accounts `actor` follows, paging through `getFollows` until the list ends or
+/// `max_follows` have been read.
+pub async fn fetch_follows(
+    client: &reqwest::Client,
+    actor: &str,
+    max_follows: usize,
+) -> Result<Vec<String>, String> {
+    let mut dids = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    while dids.len() < max_follows {
+        let mut url = format!(
+            "{}/app.bsky.graph.getFollows?actor={}&limit=100",
+            PUBLIC_API_BASE,
+            urlencoding::encode(actor)
+        );
+        if let Some(cursor) = &cursor {
+            url.push_str(&format!("&cursor={}", urlencoding::encode(cursor)));
         }
-        "app.bsky.embed.external#view" => {
-            let uri = embed
-                .get("external")
-                .and_then(|e| e.get("uri"))
-                .and_then(|u| u.as_str())
-                .map(|s| s.to_string());
-            (false, false, 0, uri)
+
+        let response = client
+            .get(&url)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch follows: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("API error: {}", response.status()));
         }
-        "app.bsky.embed.recordWithMedia#view" => {
-            let media = embed.get("media");
-            if let Some(media) = media {
-                let media_type = media.get("$type").and_then(|t| t.as_str()).unwrap_or("");
-                match media_type {
-                    "app.bsky.embed.video#view" => (true, true, 0, None),
-                    "app.bsky.embed.images#view" => {
-                        let count = media
-                            .get("images")
-                            .and_then(|i| i.as_array())
-                            .map(|arr| arr.len())
-                            .unwrap_or(0);
-                        (count > 0, false, count, None)
-                    }
-                    "app.bsky.embed.external#view" => {
-                        let uri = media
-                            .get("external")
-                            .and_then(|e| e.get("uri"))
-                            .and_then(|u| u.as_str())
-                            .map(|s| s.to_string());
-                        (false, false, 0, uri)
-                    }
-                    _ => (false, false, 0, None),
-                }
-            } else {
-                (false, false, 0, None)
-            }
+
+        let page: FollowsResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse follows: {}", e))?;
+
+        dids.extend(page.follows.into_iter().map(|f| f.did));
+        match page.cursor {
+            Some(next) if !next.is_empty() => cursor = Some(next),
+            _ => break,
         }
-        _ => (false, false, 0, None),
     }
+
+    dids.truncate(max_follows);
+    Ok(dids)
 }
 
 pub fn extract_facet_links(facets: &Option<Vec<Facet>>) -> Vec<String> {
@@ -175,11 +202,41 @@ pub fn extract_facet_links(facets: &Option<Vec<Facet>>) -> Vec<String> {
         .flat_map(|f| &f.features)
         .filter_map(|feature| match feature {
             FacetFeature::Link { uri } => Some(uri.clone()),
-            FacetFeature::Other => None,
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn extract_facet_mentions(facets: &Option<Vec<Facet>>) -> Vec<String> {
+    let Some(facets) = facets else {
+        return Vec::new();
+    };
+
+    facets
+        .iter()
+        .flat_map(|f| &f.features)
+        .filter_map(|feature| match feature {
+            FacetFeature::Mention { did } => Some(did.clone()),
+            _ => None,
         })
         .collect()
 }
 
+pub fn extract_facet_tags(facets: &Option<Vec<Facet>>) -> Option<Vec<String>> {
+    let facets = facets.as_ref()?;
+
+    Some(
+        facets
+            .iter()
+            .flat_map(|f| &f.features)
+            .filter_map(|feature| match feature {
+                FacetFeature::Tag { tag } => Some(tag.clone()),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
 #[derive(Debug, Serialize)]
 struct CreateSessionRequest {
     identifier: String,
@@ -250,24 +307,48 @@ pub async fn create_session(client: &reqwest::Client) -> Result<String, String>
     Ok(session.access_jwt)
 }
 
+/// Result order for `search_posts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSort {
+    Top,
+    /// Newest first, which lets a time range be paged through by moving `until` back.
+    Latest,
+}
+
+impl SearchSort {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Top => "top",
+            Self::Latest => "latest",
+        }
+    }
+}
+
 pub async fn search_posts(
     client: &reqwest::Client,
     access_token: &str,
     query: &str,
     limit: u32,
     since: Option<&str>,
+    until: Option<&str>,
+    sort: SearchSort,
 ) -> Result<Vec<SearchPost>, String> {
     let mut url = format!(
-        "{}/app.bsky.feed.searchPosts?q={}&limit={}&lang=en&sort=top",
+        "{}/app.bsky.feed.searchPosts?q={}&limit={}&lang=en&sort={}",
         AUTH_API_BASE,
         urlencoding::encode(query),
-        limit
+        limit,
+        sort.as_str()
     );
 
     if let Some(since_ts) = since {
         url.push_str(&format!("&since={}", urlencoding::encode(since_ts)));
     }
 
+    if let Some(until_ts) = until {
+        url.push_str(&format!("&until={}", urlencoding::encode(until_ts)));
+    }
+
     let response = client
         .get(&url)
         .header("Accept", "application/json")
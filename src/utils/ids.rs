@@ -0,0 +1,356 @@
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_DID_LEN: usize = 2048;
+const MAX_RKEY_LEN: usize = 512;
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidId {
+    kind: &'static str,
+    value: String,
+}
+
+impl InvalidId {
+    fn new(kind: &'static str, value: &str) -> Self {
+        Self {
+            kind,
+            value: value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}: {:?}", self.kind, self.value)
+    }
+}
+
+impl std::error::Error for InvalidId {}
+
+/// A `did:<method>:<identifier>`, checked against the atproto DID syntax.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+)]
+#[serde(try_from = "String", into = "String")]
+#[diesel(sql_type = Text)]
+pub struct Did(String);
+
+impl Did {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn method(&self) -> &str {
+        self.0.split(':').nth(1).unwrap_or_default()
+    }
+}
+
+fn is_valid_did(value: &str) -> bool {
+    let mut parts = value.splitn(3, ':');
+    let (Some("did"), Some(method), Some(identifier)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    value.len() <= MAX_DID_LEN
+        && !method.is_empty()
+        && method.bytes().all(|b| b.is_ascii_lowercase())
+        && !identifier.is_empty()
+        && !identifier.ends_with(':')
+        && identifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"._:%-".contains(&b))
+}
+
+/// An `at://<authority>/<collection>/<rkey>` record URI. The authority is a DID or a handle;
+/// collection and rkey are optional, as in profile URIs.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+)]
+#[serde(try_from = "String", into = "String")]
+#[diesel(sql_type = Text)]
+pub struct AtUri(String);
+
+impl AtUri {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0["at://".len()..].split('/')
+    }
+
+    pub fn authority(&self) -> &str {
+        self.segments().next().unwrap_or_default()
+    }
+
+    /// The repo DID, unless the URI names its repo by handle.
+    pub fn did(&self) -> Option<Did> {
+        self.authority().parse().ok()
+    }
+
+    pub fn collection(&self) -> Option<&str> {
+        self.segments().nth(1)
+    }
+
+    pub fn rkey(&self) -> Option<&str> {
+        self.segments().nth(2)
+    }
+
+    /// Parses a post's page on bsky.app, the reverse of [`AtUri::permalink`].
+    #[allow(dead_code)]
+    pub fn from_permalink(url: &str) -> Result<Self, InvalidId> {
+        let invalid = || InvalidId::new("post url", url);
+        let path = url
+            .strip_prefix("https://bsky.app/profile/")
+            .ok_or_else(invalid)?;
+        let (authority, rkey) = path.split_once("/post/").ok_or_else(invalid)?;
+        let rkey = rkey.split(['?', '#']).next().unwrap_or_default();
+        format!("at://{authority}/{POST_COLLECTION}/{rkey}")
+            .parse()
+            .map_err(|_| invalid())
+    }
+
+    /// The post's page on bsky.app, for post URIs.
+    pub fn permalink(&self) -> Option<String> {
+        if self.collection() != Some(POST_COLLECTION) {
+            return None;
+        }
+        let rkey = self.rkey()?;
+        Some(format!(
+            "https://bsky.app/profile/{}/post/{rkey}",
+            self.authority()
+        ))
+    }
+}
+
+fn is_valid_handle(value: &str) -> bool {
+    let labels: Vec<&str> = value.split('.').collect();
+    labels.len() >= 2
+        && value.len() <= 253
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+fn is_valid_nsid(value: &str) -> bool {
+    let segments: Vec<&str> = value.split('.').collect();
+    segments.len() >= 3
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+fn is_valid_rkey(value: &str) -> bool {
+    (1..=MAX_RKEY_LEN).contains(&value.len())
+        && value != "."
+        && value != ".."
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"._:~-".contains(&b))
+}
+
+fn is_valid_at_uri(value: &str) -> bool {
+    let Some(path) = value.strip_prefix("at://") else {
+        return false;
+    };
+    let mut segments = path.split('/');
+    let authority = segments.next().unwrap_or_default();
+    let collection = segments.next();
+    let rkey = segments.next();
+
+    (is_valid_did(authority) || is_valid_handle(authority))
+        && collection.is_none_or(is_valid_nsid)
+        && rkey.is_none_or(is_valid_rkey)
+        && segments.next().is_none()
+}
+
+macro_rules! string_id {
+    ($type:ident, $kind:literal, $is_valid:ident) => {
+        impl FromStr for $type {
+            type Err = InvalidId;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                if $is_valid(value) {
+                    Ok(Self(value.to_string()))
+                } else {
+                    Err(InvalidId::new($kind, value))
+                }
+            }
+        }
+
+        impl TryFrom<String> for $type {
+            type Error = InvalidId;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                if $is_valid(&value) {
+                    Ok(Self(value))
+                } else {
+                    Err(InvalidId::new($kind, &value))
+                }
+            }
+        }
+
+        impl From<$type> for String {
+            fn from(id: $type) -> Self {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $type {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $type {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl PartialEq<str> for $type {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<String> for $type {
+            fn eq(&self, other: &String) -> bool {
+                &self.0 == other
+            }
+        }
+
+        impl ToSql<Text, Sqlite> for $type {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+                <str as ToSql<Text, Sqlite>>::to_sql(&self.0, out)
+            }
+        }
+
+        /// Values read back are trusted: they were validated before being written.
+        impl FromSql<Text, Sqlite> for $type {
+            fn from_sql(bytes: <Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+                <String as FromSql<Text, Sqlite>>::from_sql(bytes).map(Self)
+            }
+        }
+    };
+}
+
+string_id!(Did, "DID", is_valid_did);
+string_id!(AtUri, "AT URI", is_valid_at_uri);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_did_validation() {
+        let did: Did = "did:plc:z72i7hdynmk6r22z27h6tvur".parse().unwrap();
+        assert_eq!(did.method(), "plc");
+        assert!("did:web:example.com".parse::<Did>().is_ok());
+
+        for invalid in [
+            "",
+            "did:plc:",
+            "did::abc",
+            "did:PLC:abc",
+            "plc:abc",
+            "did:plc:a b",
+        ] {
+            assert!(invalid.parse::<Did>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_at_uri_accessors() {
+        let uri: AtUri = "at://did:plc:abc123/app.bsky.feed.post/3kabc2xyz"
+            .parse()
+            .unwrap();
+        assert_eq!(uri.did().unwrap().as_str(), "did:plc:abc123");
+        assert_eq!(uri.collection(), Some("app.bsky.feed.post"));
+        assert_eq!(uri.rkey(), Some("3kabc2xyz"));
+        assert_eq!(
+            uri.permalink().as_deref(),
+            Some("https://bsky.app/profile/did:plc:abc123/post/3kabc2xyz")
+        );
+        assert_eq!(AtUri::from_permalink(&uri.permalink().unwrap()), Ok(uri));
+        assert_eq!(
+            AtUri::from_permalink("https://bsky.app/profile/alice.bsky.social/post/3kabc?ref=x")
+                .unwrap()
+                .as_str(),
+            "at://alice.bsky.social/app.bsky.feed.post/3kabc"
+        );
+        assert!(AtUri::from_permalink("https://bsky.app/profile/alice.bsky.social").is_err());
+
+        let by_handle: AtUri = "at://alice.bsky.social/app.bsky.feed.post/3kabc"
+            .parse()
+            .unwrap();
+        assert_eq!(by_handle.authority(), "alice.bsky.social");
+        assert!(by_handle.did().is_none());
+
+        let profile: AtUri = "at://did:plc:abc123".parse().unwrap();
+        assert_eq!(profile.collection(), None);
+        assert_eq!(profile.permalink(), None);
+    }
+
+    #[test]
+    fn test_at_uri_rejects_malformed() {
+        for invalid in [
+            "",
+            "https://bsky.app/profile/alice",
+            "at://",
+            "at://did:plc:abc/feed/3kabc",
+            "at://did:plc:abc/app.bsky.feed.post/",
+            "at://did:plc:abc/app.bsky.feed.post/3k abc",
+            "at://did:plc:abc/app.bsky.feed.post/3kabc/extra",
+            "at://not a handle/app.bsky.feed.post/3kabc",
+        ] {
+            assert!(invalid.parse::<AtUri>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_serde_validates() {
+        let uri: AtUri = serde_json::from_str("\"at://did:plc:abc/app.bsky.feed.like/1\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&uri).unwrap(),
+            "\"at://did:plc:abc/app.bsky.feed.like/1\""
+        );
+        assert!(serde_json::from_str::<Did>("\"not-a-did\"").is_err());
+    }
+}
@@ -1,8 +1,12 @@
 use console::{measure_text_width, Style};
+use rand::Rng;
+use std::any::Any;
+use std::collections::BTreeMap;
 
+use crate::maintenance::MaintenanceReport;
 use crate::scoring::{
     ContentSignals, Filter, FilterResult, MediaInfo, PriorityBreakdown, PrioritySignals,
-    QualityAssessment,
+    QualityAssessment, TermMatch,
 };
 use crate::settings::settings;
 
@@ -107,6 +111,117 @@ pub fn log_init(hostname: &str, port: u16, backfill_enabled: bool) {
     );
 }
 
+pub fn log_api_listening(addr: &std::net::SocketAddr) {
+    println!(
+        "{} api listening on {}...",
+        init_prefix(),
+        cyan().apply_to(addr),
+    );
+}
+
+pub fn log_gateway_listening(addr: &std::net::SocketAddr, tls: bool) {
+    println!(
+        "{} feed listening on {}{}...",
+        init_prefix(),
+        cyan().apply_to(addr),
+        if tls { " (tls)" } else { "" },
+    );
+}
+
+pub fn log_feed_server_listening(addr: &std::net::SocketAddr) {
+    println!(
+        "{} serving feed endpoints on {}...",
+        init_prefix(),
+        cyan().apply_to(addr),
+    );
+}
+
+pub fn log_access(entry: &crate::gateway::AccessEntry) {
+    if let Ok(json) = serde_json::to_string(entry) {
+        println!("{} {}", dim().apply_to("[ACCESS]"), json);
+    }
+}
+
+pub fn log_migrations_applied(versions: &[String]) {
+    for version in versions {
+        println!(
+            "{} applied migration {}",
+            init_prefix(),
+            cyan().apply_to(version),
+        );
+    }
+}
+
+pub fn log_incremental_vacuum_enabled(database_url: &str, rebuilt: bool) {
+    println!(
+        "{} incremental vacuum is on for {}{}.",
+        init_prefix(),
+        dim().apply_to(database_url),
+        if rebuilt { " after a rebuild" } else { "" },
+    );
+}
+
+pub fn log_migrations_done(database_url: &str) {
+    println!(
+        "{} {} is up to date.",
+        init_prefix(),
+        dim().apply_to(database_url),
+    );
+}
+
+pub fn log_index_loaded(count: usize) {
+    println!(
+        "{} loaded {} embeddings into vector index.",
+        init_prefix(),
+        bold().apply_to(count),
+    );
+}
+
+pub fn log_snapshot_exported(count: usize, path: &str) {
+    println!(
+        "{} exported {} posts to {}",
+        init_prefix(),
+        bold().apply_to(count),
+        dim().apply_to(path),
+    );
+}
+
+pub fn log_snapshot_imported(count: usize, path: &str) {
+    println!(
+        "{} imported {} posts from {}",
+        init_prefix(),
+        bold().apply_to(count),
+        dim().apply_to(path),
+    );
+}
+
+pub fn log_archive_exported(count: usize, path: &str) {
+    println!(
+        "{} exported {} archived posts to {}",
+        init_prefix(),
+        bold().apply_to(count),
+        dim().apply_to(path),
+    );
+}
+
+pub fn log_lint_warning(list: &str, entry: &str, message: &str) {
+    println!(
+        "{} {} \"{}\": {}",
+        yellow().apply_to("warning"),
+        dim().apply_to(list),
+        bold().apply_to(entry),
+        message
+    );
+}
+
+pub fn log_lint_summary(count: usize) {
+    if count == 0 {
+        println!("{} no lexicon issues found", green().apply_to("ok"));
+    } else {
+        println!("{} lexicon warnings", bold().apply_to(count));
+    }
+}
+
 pub fn log_ml_loading() {
     println!("{} loading models...", ml_prefix());
 }
@@ -140,6 +255,71 @@ pub fn log_backfill_start() {
     println!("{} starting backfill...", backfill_prefix());
 }
 
+pub fn log_coverage_gap(started_at: i64, ended_at: i64, reason: &str) {
+    println!(
+        "{} {} {}s without firehose events {}",
+        backfill_prefix(),
+        yellow().apply_to("coverage gap:"),
+        bold().apply_to(ended_at - started_at),
+        dim().apply_to(format!("({reason})"))
+    );
+}
+
+pub fn log_gap_backfill_start(started_at: i64, ended_at: i64, reason: &str) {
+    println!(
+        "{} backfilling {}s coverage gap {}",
+        backfill_prefix(),
+        bold().apply_to(ended_at - started_at),
+        dim().apply_to(format!("({reason})"))
+    );
+}
+
+pub fn log_firehose_reconnect(reason: &str, failures: u32, delay: std::time::Duration) {
+    println!(
+        "{} {} reconnecting in {}s {}",
+        init_prefix(),
+        yellow().apply_to(format!("firehose {reason},")),
+        bold().apply_to(delay.as_secs()),
+        dim().apply_to(format!("(attempt {failures})"))
+    );
+}
+
+pub fn log_jetstream_error(error: &str) {
+    eprintln!(
+        "{} {}",
+        yellow().apply_to("jetstream:"),
+        dim().apply_to(error)
+    );
+}
+
+pub fn log_replay_finished(events: usize, skipped: usize, path: &str) {
+    println!(
+        "{} replayed {} events from {} ({} skipped)",
+        init_prefix(),
+        bold().apply_to(events),
+        dim().apply_to(path),
+        skipped,
+    );
+}
+
+pub fn log_firehose_alert(failures: u32) {
+    eprintln!(
+        "{} {} {failures} consecutive reconnects without events",
+        init_prefix(),
+        red().apply_to(bold().apply_to("firehose down:")),
+    );
+}
+
+pub fn log_webhook_failed(url: &str, error: &str) {
+    eprintln!(
+        "{} {} {} {}",
+        init_prefix(),
+        yellow().apply_to("webhook failed:"),
+        url,
+        dim().apply_to(error)
+    );
+}
+
 pub fn log_backfill_auth_failed(error: &str) {
     println!(
         "{} {} {}",
@@ -193,6 +373,16 @@ pub fn log_backfill_stats(duplicates: usize, filtered: usize, no_relevance: usiz
     );
 }
 
+pub fn log_backfill_query_yield(query: &str, searched: usize, accepted: usize) {
+    println!(
+        "{} {}: {}{} accepted",
+        backfill_prefix(),
+        cyan().apply_to(query),
+        bold().apply_to(accepted),
+        dim().apply_to(format!("/{searched}"))
+    );
+}
+
 pub fn log_backfill_progress(current: usize, total: usize) {
     println!(
         "{} progress: {}{}",
@@ -211,6 +401,32 @@ pub fn log_backfill_complete(total_accepted: usize, total_processed: usize) {
     );
 }
 
+/// Per-outcome counts of a dry backfill, which stored nothing.
+pub fn log_backfill_dry_run(outcomes: &BTreeMap<String, usize>) {
+    println!("{} dry run, nothing stored:", backfill_prefix());
+    for (i, (outcome, count)) in outcomes.iter().enumerate() {
+        let branch = if i + 1 == outcomes.len() {
+            tree_end()
+        } else {
+            tree_branch()
+        };
+        println!(
+            "{}{} {}",
+            branch,
+            pad_label(outcome, 1),
+            dim().apply_to(count)
+        );
+    }
+}
+
+pub fn log_backfill_report_written(path: &str) {
+    println!(
+        "{} report written to {}",
+        backfill_prefix(),
+        dim().apply_to(path)
+    );
+}
+
 #[derive(Debug, Clone)]
 pub enum AssessmentResult {
     Rejected(String),
@@ -220,16 +436,22 @@ pub enum AssessmentResult {
 
 #[derive(Debug, Clone, Default)]
 pub struct PostAssessment {
+    pub text: String,
     pub text_preview: String,
     pub filter_result: Option<FilterResult>,
+    /// The blocked keyword or hashtag that rejected the post, and where it matched.
+    pub blocked_match: Option<TermMatch>,
     pub has_keywords: bool,
     pub has_hashtags: bool,
+    pub keyword_matches: Vec<TermMatch>,
+    pub hashtag_matches: Vec<TermMatch>,
     pub quality: Option<QualityAssessment>,
     pub content_signals: Option<ContentSignals>,
     pub media_info: Option<MediaInfo>,
     pub signals: Option<PrioritySignals>,
     pub priority: Option<PriorityBreakdown>,
     pub result: Option<AssessmentResult>,
+    pub translated_from: Option<String>,
 }
 
 impl PostAssessment {
@@ -240,6 +462,7 @@ impl PostAssessment {
             text.to_string()
         };
         Self {
+            text: text.to_string(),
             text_preview: preview.replace('\n', " "),
             ..Default::default()
         }
@@ -248,16 +471,23 @@ impl PostAssessment {
     pub fn set_filter_result(&mut self, result: FilterResult) {
         if let FilterResult::Reject(ref filter) = result {
             self.result = Some(AssessmentResult::Rejected(format_filter(filter)));
+            self.blocked_match = filter.term_match(&self.text);
         }
         self.filter_result = Some(result);
     }
 
-    pub fn set_relevance(&mut self, keywords: bool, hashtags: bool) {
-        self.has_keywords = keywords;
-        self.has_hashtags = hashtags;
-        if !keywords && !hashtags {
+    pub fn set_translation(&mut self, lang: &str) {
+        self.translated_from = Some(lang.to_string());
+    }
+
+    pub fn set_relevance(&mut self, keywords: Vec<TermMatch>, hashtags: Vec<TermMatch>) {
+        self.has_keywords = !keywords.is_empty();
+        self.has_hashtags = !hashtags.is_empty();
+        if !self.has_keywords && !self.has_hashtags {
             self.result = Some(AssessmentResult::NoRelevance);
         }
+        self.keyword_matches = keywords;
+        self.hashtag_matches = hashtags;
     }
 
     pub fn set_content(&mut self, signals: ContentSignals, media: MediaInfo) {
@@ -280,7 +510,43 @@ impl PostAssessment {
     }
 
     pub fn reject_low_priority(&mut self) {
-        self.result = Some(AssessmentResult::Rejected("low-priority".into()));
+        self.result = Some(AssessmentResult::Rejected(Filter::LowPriority.to_string()));
+    }
+
+    /// The rejection class used to look up its logging rate, without per-post details.
+    pub fn rejection_class(&self) -> Option<String> {
+        match (&self.result, &self.filter_result) {
+            (Some(AssessmentResult::Accepted), _) | (None, _) => None,
+            (Some(AssessmentResult::NoRelevance), _) => Some("no-relevance".to_string()),
+            (_, Some(FilterResult::Reject(filter))) => Some(filter.to_string()),
+            (Some(AssessmentResult::Rejected(reason)), _) => Some(reason.clone()),
+        }
+    }
+
+    pub fn reject_near_duplicate(&mut self) {
+        self.result = Some(AssessmentResult::Rejected(
+            Filter::NearDuplicate.to_string(),
+        ));
+    }
+
+    pub fn log(&self) {
+        let s = settings();
+        if !s.server.log_assessments {
+            return;
+        }
+
+        if let Some(class) = self.rejection_class() {
+            let rate = s
+                .server
+                .rejection_log_rates
+                .get(&class)
+                .copied()
+                .unwrap_or(s.server.default_rejection_log_rate);
+            if rate <= 0.0 || rand::rng().random::<f32>() >= rate {
+                return;
+            }
+        }
+        self.print();
     }
 
     pub fn print(&self) {
@@ -314,18 +580,36 @@ impl PostAssessment {
                 pad_label("status", 1),
                 filter_str
             ));
+            if let Some(snippet) = self.blocked_match.as_ref().and_then(|m| m.snippet.as_ref()) {
+                lines.push(format!(
+                    "{}{} {}",
+                    tree_branch(),
+                    pad_label("matched", 1),
+                    highlight(snippet)
+                ));
+            }
+            if let Some(ref lang) = self.translated_from {
+                lines.push(format!(
+                    "{}{} {}",
+                    tree_branch(),
+                    pad_label("translated", 1),
+                    cyan().apply_to(format!("{lang} → en"))
+                ));
+            }
             lines.push(format!(
                 "{}{} {}",
                 tree_branch(),
                 pad_label("keywords", 1),
                 kw_style.apply_to(self.has_keywords)
             ));
+            lines.extend(match_lines(&self.keyword_matches, &tree_indent()));
             lines.push(format!(
                 "{}{} {}",
                 tree_end(),
                 pad_label("hashtags", 1),
                 ht_style.apply_to(self.has_hashtags)
             ));
+            lines.extend(match_lines(&self.hashtag_matches, "    "));
         }
 
         if let (Some(ref quality), Some(ref priority)) = (&self.quality, &self.priority) {
@@ -417,7 +701,8 @@ impl PostAssessment {
 
             let total_boosts = priority.content_modifier.max(0.0)
                 + priority.engagement_boost
-                + priority.authenticity_boost;
+                + priority.authenticity_boost
+                + priority.author_boost;
             let total_penalties =
                 priority.quality_penalty + priority.content_modifier.min(0.0).abs();
 
@@ -501,6 +786,46 @@ impl PostAssessment {
     }
 }
 
+/// One line per matched term under a `keywords` or `hashtags` line.
+fn match_lines(matches: &[TermMatch], indent: &str) -> Vec<String> {
+    matches
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let branch = if i == matches.len() - 1 {
+                tree_end()
+            } else {
+                tree_branch()
+            };
+            let location = match (&m.snippet, m.in_attached_text) {
+                (Some(snippet), false) => highlight(snippet),
+                (Some(snippet), true) => format!(
+                    "{} {}",
+                    highlight(snippet),
+                    dim().apply_to("(attached text)")
+                ),
+                (None, _) => dim().apply_to("tag facet").to_string(),
+            };
+            format!("{indent}{branch}{} {location}", pad_label(&m.term, 2))
+        })
+        .collect()
+}
+
+/// A match snippet with the `**`-marked match styled instead.
+fn highlight(snippet: &str) -> String {
+    snippet
+        .split("**")
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                yellow().bold().apply_to(part).to_string()
+            } else {
+                dim().apply_to(part).to_string()
+            }
+        })
+        .collect()
+}
+
 fn format_filter(filter: &Filter) -> String {
     match filter {
         Filter::BlockedKeyword(kw) => format!("{} ({})", filter, kw),
@@ -529,6 +854,73 @@ pub fn log_cleanup(deleted: usize) {
     }
 }
 
+pub fn log_rescore(updated: usize) {
+    if updated > 0 {
+        println!(
+            "{} {} posts",
+            dim().apply_to("rescored"),
+            bold().apply_to(updated)
+        );
+    }
+}
+
+pub fn log_maintenance(report: &MaintenanceReport) {
+    if !report.incremental_vacuum {
+        println!(
+            "{} incremental vacuum is off, so no pages are reclaimed; stop the feed and run it \
+             with --enable-incremental-vacuum once",
+            dim().apply_to("maintenance:")
+        );
+    }
+    let kib = |pages: i64| pages * report.page_size / 1024;
+    println!(
+        "{} reclaimed {} pages ({} KiB), checkpointed {} WAL pages{}",
+        dim().apply_to("maintenance:"),
+        bold().apply_to(report.reclaimed_pages),
+        kib(report.reclaimed_pages),
+        bold().apply_to(report.checkpointed_pages),
+        if report.checkpoint_busy {
+            yellow().apply_to(" (busy, WAL not truncated)").to_string()
+        } else {
+            String::new()
+        }
+    );
+}
+
+pub fn log_maintenance_skipped(reason: &str) {
+    println!(
+        "{} {}",
+        dim().apply_to("maintenance skipped:"),
+        dim().apply_to(reason)
+    );
+}
+
+pub fn log_pool_exhausted(job: &str) {
+    eprintln!(
+        "{} skipping {} this cycle",
+        yellow().apply_to("no database connection:"),
+        job,
+    );
+}
+
+pub fn log_maintenance_failed(error: &str) {
+    eprintln!(
+        "{} {}",
+        yellow().apply_to("maintenance failed:"),
+        dim().apply_to(error)
+    );
+}
+
+pub fn log_negative_examples_harvested(added: usize) {
+    if added > 0 {
+        println!(
+            "{} {} negative examples",
+            dim().apply_to("harvested"),
+            bold().apply_to(added)
+        );
+    }
+}
+
 pub fn log_flush(posts: usize, likes: usize) {
     if posts > 0 || likes > 0 {
         println!(
@@ -540,24 +932,126 @@ pub fn log_flush(posts: usize, likes: usize) {
     }
 }
 
-pub fn log_author_blocked(moderator_did: &str, author_did: &str, deleted_posts: usize) {
+pub fn log_flush_failed(error: &str) {
+    eprintln!(
+        "{} {}",
+        red().apply_to("flush failed:"),
+        dim().apply_to(error)
+    );
+}
+
+pub fn log_buffer_dropped(buffer: &str, dropped: usize) {
+    eprintln!(
+        "{} {} {} pending {}",
+        yellow().apply_to("buffer full:"),
+        dim().apply_to("dropped"),
+        bold().apply_to(dropped),
+        buffer
+    );
+}
+
+pub fn log_malformed_record(error: &str) {
+    eprintln!(
+        "{} {}",
+        yellow().apply_to("skipped record:"),
+        dim().apply_to(error)
+    );
+}
+
+pub fn log_post_panicked(uri: &str, panic: &(dyn Any + Send)) {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    eprintln!(
+        "{} {} {}",
+        red().apply_to("skipped post after panic:"),
+        uri,
+        dim().apply_to(message)
+    );
+}
+
+pub fn log_admin_api_listening(addr: &std::net::SocketAddr) {
+    println!(
+        "{} admin API listening on {}...",
+        init_prefix(),
+        cyan().apply_to(addr),
+    );
+}
+
+pub fn log_admin_action(actor: &str, action: &str, target: &str) {
+    println!(
+        "{} {} {} {}",
+        red().apply_to("[ADMIN]"),
+        dim().apply_to(actor),
+        action,
+        bold().apply_to(target)
+    );
+}
+
+/// One line of a `moderate` listing: when the author was flagged or blocked, and why.
+#[allow(dead_code)]
+pub fn log_listed_author(at: i64, did: &str, detail: &str) {
+    let time = chrono::DateTime::from_timestamp(at, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    println!(
+        "{}  {}  {}",
+        dim().apply_to(time),
+        bold().apply_to(did),
+        detail
+    );
+}
+
+#[allow(dead_code)]
+pub fn log_author_posts_deleted(did: &str, deleted: usize) {
+    println!(
+        "{} {} posts by {}",
+        dim().apply_to("deleted"),
+        bold().apply_to(deleted),
+        dim().apply_to(did)
+    );
+}
+
+pub fn log_author_blocked(
+    moderator: (&str, Option<&str>),
+    author: (&str, Option<&str>),
+    deleted_posts: usize,
+) {
     println!(
         "{} {} blocked author {} ({} posts removed)",
         red().apply_to("[MOD]"),
-        dim().apply_to(truncate_did(moderator_did)),
-        bold().apply_to(truncate_did(author_did)),
+        dim().apply_to(display_author(moderator.0, moderator.1)),
+        bold().apply_to(display_author(author.0, author.1)),
         bold().apply_to(deleted_posts)
     );
 }
 
-pub fn log_influencer_accepted(author_did: &str) {
+pub fn log_author_opted_out(did: &str, handle: Option<&str>, deleted_posts: usize) {
+    println!(
+        "{} {} opted out of the feed ({} posts removed)",
+        yellow().apply_to("opt-out"),
+        bold().apply_to(display_author(did, handle)),
+        bold().apply_to(deleted_posts)
+    );
+}
+
+pub fn log_influencer_accepted(author_did: &str, author_handle: Option<&str>) {
     println!(
         "{} post from {} (influencer bypass)",
         green().apply_to("accepted"),
-        dim().apply_to(truncate_did(author_did))
+        dim().apply_to(display_author(author_did, author_handle))
     );
 }
 
+fn display_author(did: &str, handle: Option<&str>) -> String {
+    match handle {
+        Some(handle) => format!("@{}", handle),
+        None => truncate_did(did),
+    }
+}
+
 fn truncate_did(did: &str) -> String {
     if did.len() > 24 {
         format!("{}...", &did[..21])
@@ -566,10 +1060,51 @@ fn truncate_did(did: &str) -> String {
     }
 }
 
+pub fn log_settings_profile(profile: &str) {
+    println!(
+        "{} using profile {}.",
+        blue().apply_to("[SETTINGS]"),
+        bold().apply_to(profile)
+    );
+}
+
 pub fn log_settings_reloaded() {
     println!("{} settings reloaded.", blue().apply_to("[SETTINGS]"),);
 }
 
+pub fn log_shadow_profile_failed(error: &str) {
+    println!(
+        "{} {} {}",
+        yellow().apply_to("[SHADOW]"),
+        red().apply_to("profile failed to load:"),
+        dim().apply_to(error)
+    );
+}
+
+pub fn log_like_affinity_failed(error: &str) {
+    println!(
+        "{} {} {}",
+        yellow().apply_to("[FLUSH]"),
+        red().apply_to("like affinities failed to update:"),
+        dim().apply_to(error)
+    );
+}
+
+pub fn log_shadow_disagreement(uri: &str, live: f32, shadow: f32, shadow_accepted: bool) {
+    let verdict = if shadow_accepted {
+        green().apply_to("would accept")
+    } else {
+        red().apply_to("would reject")
+    };
+    println!(
+        "{} {} {} {}",
+        yellow().apply_to("[SHADOW]"),
+        verdict,
+        uri,
+        dim().apply_to(format!("(live {live:.2}, shadow {shadow:.2})"))
+    );
+}
+
 pub fn log_settings_reload_failed(error: &str) {
     println!(
         "{} {} {}",
@@ -1,2 +1,5 @@
 pub mod bluesky;
+pub mod ids;
 pub mod logs;
+pub mod systemd;
+pub mod time;
@@ -0,0 +1,30 @@
+use sd_notify::NotifyState;
+use std::time::Duration;
+
+pub fn notify_status(status: &str) {
+    let _ = sd_notify::notify(false, &[NotifyState::Status(status)]);
+}
+
+pub fn notify_ready() {
+    let _ = sd_notify::notify(
+        false,
+        &[NotifyState::Ready, NotifyState::Status("serving feed")],
+    );
+}
+
+pub fn notify_stopping() {
+    let _ = sd_notify::notify(false, &[NotifyState::Stopping]);
+}
+
+pub fn ping_watchdog() {
+    let _ = sd_notify::notify(false, &[NotifyState::Watchdog]);
+}
+
+pub fn watchdog_interval() -> Option<Duration> {
+    let mut usec = 0;
+    if sd_notify::watchdog_enabled(false, &mut usec) && usec > 0 {
+        Some(Duration::from_micros(usec / 2))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Source of the current time, injected so tests can pin and advance it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current Unix time in seconds, the unit stored in the database.
+    fn timestamp(&self) -> i64 {
+        self.now().timestamp()
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    timestamp: Arc<AtomicI64>,
+}
+
+impl ManualClock {
+    pub fn new(timestamp: i64) -> Self {
+        Self {
+            timestamp: Arc::new(AtomicI64::new(timestamp)),
+        }
+    }
+
+    pub fn set(&self, timestamp: i64) {
+        self.timestamp.store(timestamp, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.timestamp.fetch_add(secs, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.timestamp(), 0).unwrap_or_default()
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.timestamp.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeError {
+    InvalidRfc3339(String),
+    OutOfRange(i64),
+}
+
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidRfc3339(value) => write!(f, "invalid RFC 3339 time: {value:?}"),
+            Self::OutOfRange(value) => write!(f, "time out of range: {value}"),
+        }
+    }
+}
+
+impl std::error::Error for TimeError {}
+
+pub fn parse_rfc3339(value: &str) -> Result<DateTime<Utc>, TimeError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| TimeError::InvalidRfc3339(value.to_string()))
+}
+
+pub fn from_timestamp(timestamp: i64) -> Result<DateTime<Utc>, TimeError> {
+    DateTime::from_timestamp(timestamp, 0).ok_or(TimeError::OutOfRange(timestamp))
+}
+
+pub fn from_timestamp_micros(micros: i64) -> Result<DateTime<Utc>, TimeError> {
+    DateTime::from_timestamp_micros(micros).ok_or(TimeError::OutOfRange(micros))
+}
+
+/// Second-precision `YYYY-MM-DDTHH:MM:SSZ`, the form the search API accepts for `since`/`until`.
+/// How long a background job waits between runs when set in minutes. At least a minute, so
+/// setting `0` doesn't spin the job; turn it off with its `enabled` flag instead.
+pub fn job_period_mins(mins: u64) -> Duration {
+    Duration::from_secs(mins.max(1) * 60)
+}
+
+/// The same for a period set in seconds.
+pub fn job_period_secs(secs: u64) -> Duration {
+    Duration::from_secs(secs.max(1))
+}
+
+pub fn format_rfc3339(timestamp: i64) -> Result<String, TimeError> {
+    Ok(from_timestamp(timestamp)?
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new(1_000);
+        let shared: SharedClock = Arc::new(clock.clone());
+        clock.advance(60);
+        assert_eq!(shared.timestamp(), 1_060);
+        clock.set(0);
+        assert_eq!(shared.now(), DateTime::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_job_periods_are_never_zero() {
+        assert_eq!(job_period_mins(0), Duration::from_secs(60));
+        assert_eq!(job_period_mins(15), Duration::from_secs(900));
+        assert_eq!(job_period_secs(0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let parsed = parse_rfc3339("2024-09-09T19:46:02.102+02:00").unwrap();
+        assert_eq!(parsed.timestamp(), 1725903962);
+        assert_eq!(
+            format_rfc3339(parsed.timestamp()).unwrap(),
+            "2024-09-09T17:46:02Z"
+        );
+        assert_eq!(
+            parse_rfc3339("yesterday"),
+            Err(TimeError::InvalidRfc3339("yesterday".to_string()))
+        );
+        assert_eq!(
+            from_timestamp(i64::MAX),
+            Err(TimeError::OutOfRange(i64::MAX))
+        );
+        assert_eq!(
+            from_timestamp_micros(1_500_000).unwrap().timestamp_millis(),
+            1_500
+        );
+    }
+}
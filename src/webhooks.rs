@@ -0,0 +1,289 @@
+use crate::db::NewPost;
+use crate::explain;
+use crate::scoring::{ConfidenceTier, PriorityBreakdown};
+use crate::settings::{settings, WebhookEndpoint, WebhookFormat};
+use crate::utils::ids::{AtUri, Did};
+use crate::utils::logs;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const SECRET_HEADER: &str = "X-Webhook-Secret";
+/// Discord's limits on an embed description and on a field value, in characters.
+const DISCORD_DESCRIPTION_MAX: usize = 4096;
+const DISCORD_FIELD_MAX: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AcceptedPostEvent {
+    pub event: &'static str,
+    pub uri: AtUri,
+    pub author_did: Option<Did>,
+    pub text: String,
+    pub lang: String,
+    pub priority: f32,
+    /// Confidence tier: `STRONG`, `MODERATE` or `WEAK`.
+    pub tier: String,
+    /// The post on bsky.app.
+    pub permalink: Option<String>,
+    pub timestamp: i64,
+    pub accepted_at: i64,
+    pub has_media: bool,
+    pub image_count: i32,
+    pub link_count: i32,
+    /// How the priority was reached, as recorded in `post_scores`, with the reasons' terminal
+    /// styling removed.
+    pub breakdown: PriorityBreakdown,
+}
+
+impl AcceptedPostEvent {
+    pub fn from_post(post: &NewPost, breakdown: &PriorityBreakdown) -> Self {
+        Self {
+            event: "post.accepted",
+            uri: post.uri.clone(),
+            author_did: post.author_did.clone(),
+            text: post.text.clone(),
+            lang: post.lang.clone(),
+            priority: post.priority,
+            tier: ConfidenceTier::from_priority(post.priority).to_string(),
+            permalink: post.uri.permalink(),
+            timestamp: post.timestamp,
+            accepted_at: Utc::now().timestamp(),
+            has_media: post.has_media != 0,
+            image_count: post.image_count,
+            link_count: post.link_count,
+            breakdown: PriorityBreakdown {
+                boost_reasons: breakdown
+                    .boost_reasons
+                    .iter()
+                    .map(|r| explain::plain(r))
+                    .collect(),
+                penalty_reasons: breakdown
+                    .penalty_reasons
+                    .iter()
+                    .map(|r| explain::plain(r))
+                    .collect(),
+                ..breakdown.clone()
+            },
+        }
+    }
+
+    fn body(&self, format: WebhookFormat) -> Value {
+        match format {
+            WebhookFormat::Json => serde_json::to_value(self).unwrap_or_default(),
+            WebhookFormat::Discord => self.discord_message(),
+        }
+    }
+
+    /// A Discord webhook message with the post as an embed linking to bsky.app. Mentions are
+    /// disabled so post text can't ping the channel.
+    fn discord_message(&self) -> Value {
+        let reasons: Vec<&str> = self
+            .breakdown
+            .boost_reasons
+            .iter()
+            .chain(&self.breakdown.penalty_reasons)
+            .map(String::as_str)
+            .collect();
+        let mut fields = Vec::new();
+        if let Some(did) = &self.author_did {
+            fields.push(json!({"name": "Author", "value": did.as_str(), "inline": true}));
+        }
+        fields.push(json!({"name": "Language", "value": self.lang, "inline": true}));
+        if !reasons.is_empty() {
+            fields.push(json!({
+                "name": "Score",
+                "value": truncate(&reasons.join("\n"), DISCORD_FIELD_MAX),
+            }));
+        }
+
+        json!({
+            "allowed_mentions": {"parse": []},
+            "embeds": [{
+                "title": format!("{} · {:.2}", self.tier, self.priority),
+                "url": self.permalink,
+                "description": truncate(&self.text, DISCORD_DESCRIPTION_MAX),
+                "timestamp": DateTime::from_timestamp(self.timestamp, 0).map(|t| t.to_rfc3339()),
+                "fields": fields,
+            }],
+        })
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn endpoint_wants(endpoint: &WebhookEndpoint, lang: &str, tier: ConfidenceTier) -> bool {
+    (endpoint.langs.is_empty() || endpoint.langs.iter().any(|l| l.eq_ignore_ascii_case(lang)))
+        && (!endpoint.strong_only || tier == ConfidenceTier::Strong)
+}
+
+/// Posts accepted posts to the configured endpoints so mirrors and search sites can follow the
+/// feed without consuming the firehose. Deliveries run in the background and are not retried.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Whether any endpoint is configured, so accepted posts need to be kept for delivery.
+    pub fn is_enabled(&self) -> bool {
+        !settings().webhooks.endpoints.is_empty()
+    }
+
+    /// Delivers `event` to the endpoints that want it. Called once the post has been stored.
+    pub fn post_accepted(&self, event: &AcceptedPostEvent) {
+        let s = settings();
+        let tier = ConfidenceTier::from_priority(event.priority);
+        let endpoints: Vec<WebhookEndpoint> = s
+            .webhooks
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint_wants(endpoint, &event.lang, tier))
+            .cloned()
+            .collect();
+        if endpoints.is_empty() {
+            return;
+        }
+
+        let timeout = Duration::from_millis(s.webhooks.timeout_ms);
+        for endpoint in endpoints {
+            let client = self.client.clone();
+            let body = event.body(endpoint.format);
+            tokio::spawn(async move {
+                let mut request = client.post(&endpoint.url).timeout(timeout).json(&body);
+                if let Some(secret) = &endpoint.secret {
+                    request = request.header(SECRET_HEADER, secret);
+                }
+                let result = request.send().await.and_then(|r| r.error_for_status());
+                if let Err(e) = result {
+                    logs::log_webhook_failed(&endpoint.url, &e.to_string());
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(langs: &[&str], strong_only: bool) -> WebhookEndpoint {
+        WebhookEndpoint {
+            url: "http://localhost/hook".to_string(),
+            langs: langs.iter().map(|l| l.to_string()).collect(),
+            secret: None,
+            strong_only,
+            format: WebhookFormat::Json,
+        }
+    }
+
+    #[test]
+    fn test_endpoint_language_filter() {
+        let strong = ConfidenceTier::Strong;
+        assert!(endpoint_wants(&endpoint(&[], false), "pt", strong));
+        assert!(endpoint_wants(
+            &endpoint(&["en", "PT"], false),
+            "pt",
+            strong
+        ));
+        assert!(!endpoint_wants(&endpoint(&["en"], false), "es", strong));
+    }
+
+    #[test]
+    fn test_endpoint_strong_only() {
+        assert!(endpoint_wants(
+            &endpoint(&[], true),
+            "en",
+            ConfidenceTier::Strong
+        ));
+        assert!(!endpoint_wants(
+            &endpoint(&[], true),
+            "en",
+            ConfidenceTier::Moderate
+        ));
+        assert!(endpoint_wants(
+            &endpoint(&[], false),
+            "en",
+            ConfidenceTier::Weak
+        ));
+    }
+
+    #[test]
+    fn test_event_reasons_are_plain_text() {
+        let post = NewPost::new(
+            "at://did:plc:dev/app.bsky.feed.post/3kabc".parse().unwrap(),
+            "the boss fight is done".to_string(),
+            0,
+            1.0,
+            &Default::default(),
+            &Default::default(),
+            None,
+        );
+        let breakdown = PriorityBreakdown {
+            boost_reasons: vec!["\u{1b}[2mfirst-person:\u{1b}[0m  +0.2".to_string()],
+            penalty_reasons: vec!["\u{1b}[31mpromo\u{1b}[0m -0.5".to_string()],
+            ..Default::default()
+        };
+        let event = AcceptedPostEvent::from_post(&post, &breakdown);
+        assert_eq!(event.breakdown.boost_reasons, vec!["first-person: +0.2"]);
+        assert_eq!(event.breakdown.penalty_reasons, vec!["promo -0.5"]);
+    }
+
+    #[test]
+    fn test_discord_message() {
+        let event = AcceptedPostEvent {
+            event: "post.accepted",
+            uri: "at://did:plc:dev/app.bsky.feed.post/3kabc".parse().unwrap(),
+            author_did: "did:plc:dev".parse().ok(),
+            text: "@everyone the boss fight is done! ".repeat(200),
+            lang: "en".to_string(),
+            priority: 1.234,
+            tier: "STRONG".to_string(),
+            permalink: Some("https://bsky.app/profile/did:plc:dev/post/3kabc".to_string()),
+            timestamp: 0,
+            accepted_at: 0,
+            has_media: true,
+            image_count: 1,
+            link_count: 0,
+            breakdown: PriorityBreakdown {
+                boost_reasons: vec!["first-person: +0.2".to_string()],
+                ..Default::default()
+            },
+        };
+
+        let message = event.body(WebhookFormat::Discord);
+        let embed = &message["embeds"][0];
+        assert_eq!(message["allowed_mentions"]["parse"], json!([]));
+        assert_eq!(embed["title"], "STRONG · 1.23");
+        assert_eq!(
+            embed["url"],
+            "https://bsky.app/profile/did:plc:dev/post/3kabc"
+        );
+        assert_eq!(
+            embed["description"].as_str().unwrap().chars().count(),
+            DISCORD_DESCRIPTION_MAX
+        );
+        assert_eq!(embed["fields"][2]["value"], "first-person: +0.2");
+
+        assert_eq!(event.body(WebhookFormat::Json)["tier"], "STRONG");
+    }
+}
@@ -0,0 +1,54 @@
+//! Helpers shared by the database-backed integration tests.
+#![allow(dead_code)]
+
+use devlogs_feed::db::{self, establish_pool, DbPool, NewPost};
+use devlogs_feed::scoring::{ContentSignals, MediaInfo};
+use devlogs_feed::settings::settings;
+use diesel::sqlite::SqliteConnection;
+use std::path::PathBuf;
+
+pub const START: i64 = 1_760_000_000;
+pub const AUTHOR: &str = "did:plc:devlogger";
+pub const POST_URI: &str = "at://did:plc:devlogger/app.bsky.feed.post/3kdevlog";
+
+/// A database file that is deleted, with its WAL, when the test ends.
+pub struct TempDb(pub PathBuf);
+
+impl TempDb {
+    pub fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("devlogs-{name}-{}.db", std::process::id())))
+    }
+
+    pub fn pool(&self) -> DbPool {
+        establish_pool(self.0.to_str().unwrap())
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", self.0.display()));
+        }
+    }
+}
+
+pub fn accept_post(conn: &mut SqliteConnection, now: i64) {
+    let post = NewPost::new(
+        POST_URI.parse().unwrap(),
+        "Day 12 of my roguelike devlog: the dungeon generator works!".to_string(),
+        now,
+        1.0,
+        &MediaInfo::default(),
+        &ContentSignals::default(),
+        Some(AUTHOR.parse().unwrap()),
+    );
+    db::insert_posts(conn, vec![post]).unwrap();
+}
+
+pub fn is_served(conn: &mut SqliteConnection, now: i64) -> bool {
+    let cutoff = now - settings().feed.cutoff_hours * 3600;
+    db::get_language_feed(conn, cutoff, "en")
+        .unwrap()
+        .iter()
+        .any(|post| post.uri == POST_URI)
+}
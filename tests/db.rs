@@ -0,0 +1,424 @@
+//! Database queries and engagement bookkeeping, each against a fresh database file.
+
+mod common;
+
+use common::{accept_post, is_served, TempDb, AUTHOR, POST_URI, START};
+use devlogs_feed::db::{
+    self, establish_read_pool, NewAuthorHistory, NewBlockedAuthor, NewConfigSnapshot,
+    NewInteraction, NewLike, NewPost, NewShadowScore, INTERACTION_SEEN,
+};
+use devlogs_feed::engagement::EngagementTracker;
+use devlogs_feed::schema::posts;
+use devlogs_feed::scoring::{ContentSignals, MediaInfo, TopicLabel};
+use devlogs_feed::settings::settings;
+use devlogs_feed::utils::time::{Clock, ManualClock};
+use diesel::prelude::*;
+use std::sync::Arc;
+
+#[test]
+fn test_migrations_apply_once_in_order() {
+    let db = TempDb::new("migrations");
+    let mut conn = SqliteConnection::establish(db.0.to_str().unwrap()).unwrap();
+
+    let applied = db::run_migrations(&mut conn).unwrap();
+    let migration_dirs = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().is_dir())
+        .count();
+    assert_eq!(applied.len(), migration_dirs);
+    assert!(applied.windows(2).all(|pair| pair[0] < pair[1]));
+
+    assert!(db::run_migrations(&mut conn).unwrap().is_empty());
+}
+
+#[test]
+fn test_config_history_reuses_unchanged_configs() {
+    let db = TempDb::new("config");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    let snapshot = |hash: &str, activated_at: i64| NewConfigSnapshot {
+        hash: hash.to_string(),
+        settings: format!("(hash: \"{hash}\")"),
+        profile: None,
+        activated_at,
+    };
+
+    let first = db::record_config_snapshot(&mut conn, snapshot("a", START)).unwrap();
+    let reloaded = db::record_config_snapshot(&mut conn, snapshot("a", START + 60)).unwrap();
+    let changed = db::record_config_snapshot(&mut conn, snapshot("b", START + 120)).unwrap();
+    let reverted = db::record_config_snapshot(&mut conn, snapshot("a", START + 180)).unwrap();
+
+    assert_eq!(first, reloaded);
+    assert_ne!(changed, first);
+    assert!(reverted > changed);
+    let history: Vec<i32> = db::get_config_history(&mut conn, 10, None)
+        .unwrap()
+        .iter()
+        .map(|config| config.id)
+        .collect();
+    assert_eq!(history, [reverted, changed, first]);
+}
+
+#[test]
+fn test_shadow_summary_counts_disagreements() {
+    let db = TempDb::new("shadow");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    let score =
+        |rkey: &str, profile: &str, live: bool, shadow: bool, scored_at: i64| NewShadowScore {
+            uri: format!("at://{AUTHOR}/app.bsky.feed.post/{rkey}"),
+            profile: profile.to_string(),
+            live_priority: if live { 1.0 } else { -1.0 },
+            shadow_priority: if shadow { 1.0 } else { -1.0 },
+            live_accepted: live as i32,
+            shadow_accepted: shadow as i32,
+            scored_at,
+        };
+    let scores = vec![
+        score("both", "strict", true, true, START),
+        score("live", "strict", true, false, START + 10),
+        score("shadow", "strict", false, true, START + 20),
+        score("neither", "strict", false, false, START + 30),
+        score("old", "strict", true, false, START - 3600),
+        score("other", "lenient", false, true, START),
+    ];
+    db::upsert_shadow_scores(&mut conn, scores).unwrap();
+
+    let summary = db::shadow_summary(&mut conn, "strict", START).unwrap();
+    assert_eq!(
+        (
+            summary.scored,
+            summary.live_accepted,
+            summary.shadow_accepted
+        ),
+        (4, 2, 2)
+    );
+    assert_eq!((summary.live_only, summary.shadow_only), (1, 1));
+
+    let disagreements = db::shadow_disagreements(&mut conn, "strict", START, 10).unwrap();
+    let rkeys: Vec<&str> = disagreements
+        .iter()
+        .map(|score| score.uri.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(rkeys, ["shadow", "live"]);
+
+    assert_eq!(db::cleanup_shadow_scores(&mut conn, START).unwrap(), 1);
+}
+
+#[test]
+fn test_like_affinities_count_feed_users_and_decay() {
+    let db = TempDb::new("affinities");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    let half_life = settings().feed.like_affinity.half_life_hours;
+
+    accept_post(&mut conn, START);
+    db::insert_interactions(
+        &mut conn,
+        vec![NewInteraction {
+            user_did: "did:plc:fan0".to_string(),
+            post_uri: POST_URI.to_string(),
+            interaction_type: INTERACTION_SEEN.to_string(),
+            created_at: START,
+        }],
+    )
+    .unwrap();
+
+    // fan1 never used the feed, so only fan0's like counts, once for the topic and the author.
+    let likes: Vec<NewLike> = (0..2)
+        .map(|i| NewLike {
+            post_uri: POST_URI.parse().unwrap(),
+            like_uri: format!("at://did:plc:fan{i}/app.bsky.feed.like/3klike")
+                .parse()
+                .unwrap(),
+            liker_did: Some(format!("did:plc:fan{i}").parse().unwrap()),
+            liked_at: 0,
+        })
+        .collect();
+    let updated = db::record_like_affinities(&mut conn, &likes, START, half_life).unwrap();
+    assert_eq!(updated, 2);
+    assert!(
+        db::get_like_affinities(&mut conn, "did:plc:fan1", START, half_life)
+            .unwrap()
+            .is_empty()
+    );
+
+    // One half-life later the first like counts for half.
+    let later = START + (half_life * 3600.0) as i64;
+    db::record_like_affinities(&mut conn, &likes[..1], later, half_life).unwrap();
+    let affinities = db::get_like_affinities(&mut conn, "did:plc:fan0", later, half_life).unwrap();
+    let author = affinities
+        .iter()
+        .find(|a| a.kind == db::AFFINITY_AUTHOR)
+        .unwrap();
+    assert_eq!(author.value, AUTHOR);
+    assert!((author.score - 1.5).abs() < 1e-4);
+
+    assert_eq!(
+        db::cleanup_like_affinities(&mut conn, later + 1).unwrap(),
+        2
+    );
+}
+
+#[test]
+fn test_post_flood_counts_recent_and_pending_posts() {
+    let db = TempDb::new("flood");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+    let spam = &settings().spam;
+    let limit = (spam.max_posts_per_hour * spam.velocity_window_hours as f32) as usize;
+
+    let history = (1..limit)
+        .map(|i| NewAuthorHistory {
+            post_uri: format!("at://{AUTHOR}/app.bsky.feed.post/{i}"),
+            author_did: AUTHOR.to_string(),
+            accepted_at: clock.timestamp(),
+        })
+        .collect();
+    db::insert_author_history(&mut conn, history).unwrap();
+
+    assert!(engagement.check_post_flood(AUTHOR, 0).is_none());
+    let flood = engagement.check_post_flood(AUTHOR, 1).unwrap();
+    assert_eq!(flood.did, AUTHOR);
+    // The extra posts are only rejected unless `flag_post_flood` is on.
+    assert!(!engagement.is_spammer(AUTHOR));
+
+    clock.advance(spam.velocity_window_hours * 3600 + 1);
+    assert!(engagement.check_post_flood(AUTHOR, 1).is_none());
+}
+
+#[test]
+fn test_read_pool_sees_writes_but_cannot_write() {
+    let db = TempDb::new("read-pool");
+    let pool = db.pool();
+    let read_pool = establish_read_pool(db.0.to_str().unwrap());
+    let now = START;
+
+    accept_post(&mut pool.get().unwrap(), now);
+    let mut reader = read_pool.get().unwrap();
+    assert!(is_served(&mut reader, now));
+    assert!(diesel::delete(posts::table).execute(&mut reader).is_err());
+    assert!(is_served(&mut reader, now));
+}
+
+#[test]
+fn test_search_index_follows_post_changes() {
+    let db = TempDb::new("search");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    let search = |conn: &mut SqliteConnection, query: &str| {
+        db::search_posts(conn, query, 10)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.uri)
+            .collect::<Vec<_>>()
+    };
+
+    accept_post(&mut conn, START);
+    assert_eq!(search(&mut conn, "dungeon generators"), vec![POST_URI]);
+
+    diesel::update(posts::table)
+        .set(posts::text.eq("Rewrote the inventory screen"))
+        .execute(&mut conn)
+        .unwrap();
+    assert!(search(&mut conn, "dungeon").is_empty());
+    assert_eq!(search(&mut conn, "inventory"), vec![POST_URI]);
+
+    db::delete_post(&mut conn, POST_URI).unwrap();
+    assert!(search(&mut conn, "inventory").is_empty());
+    diesel::sql_query("INSERT INTO posts_fts (posts_fts) VALUES ('integrity-check')")
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+fn test_self_reposts_are_not_dev_reposts() {
+    let db = TempDb::new("self-repost");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+
+    accept_post(&mut conn, START);
+    let history = NewAuthorHistory {
+        post_uri: POST_URI.to_string(),
+        author_did: AUTHOR.to_string(),
+        accepted_at: START,
+    };
+    db::insert_author_history(&mut conn, vec![history]).unwrap();
+
+    clock.advance(60);
+    engagement
+        .record_repost(
+            POST_URI,
+            "at://did:plc:devlogger/app.bsky.feed.repost/3kself",
+            AUTHOR,
+        )
+        .unwrap();
+    let weights = &settings().engagement.weights;
+    let velocity = engagement.get_engagement(POST_URI).unwrap().velocity_score;
+    assert!((velocity - weights.repost).abs() < 1e-5, "{velocity}");
+}
+
+#[test]
+fn test_self_likes_are_not_dev_likes() {
+    let db = TempDb::new("self-like");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+
+    accept_post(&mut conn, START);
+    let history = NewAuthorHistory {
+        post_uri: POST_URI.to_string(),
+        author_did: AUTHOR.to_string(),
+        accepted_at: START,
+    };
+    db::insert_author_history(&mut conn, vec![history]).unwrap();
+
+    clock.advance(60);
+    let like = NewLike {
+        post_uri: POST_URI.parse().unwrap(),
+        like_uri: "at://did:plc:devlogger/app.bsky.feed.like/3kself"
+            .parse()
+            .unwrap(),
+        liker_did: Some(AUTHOR.parse().unwrap()),
+        liked_at: clock.timestamp(),
+    };
+    db::insert_likes(&mut conn, vec![like]).unwrap();
+    engagement.record_like(POST_URI).unwrap();
+    let s = settings();
+    let expected = s.engagement.like_multipliers.unknown * s.engagement.weights.like * 0.1;
+    let velocity = engagement.get_engagement(POST_URI).unwrap().velocity_score;
+    assert!((velocity - expected).abs() < 1e-5, "{velocity}");
+}
+
+#[test]
+fn test_topic_matching_agrees_with_post_topic() {
+    let db = TempDb::new("topic-matching");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    let images = MediaInfo {
+        image_count: 1,
+        ..MediaInfo::default()
+    };
+    let posts = [
+        ("first person with media", &images, true, 0),
+        (
+            "which engine should I pick?",
+            &MediaInfo::default(),
+            true,
+            0,
+        ),
+        ("a shader breakdown", &MediaInfo::default(), false, 1),
+        ("I fixed the jump today", &MediaInfo::default(), true, 0),
+        ("pixel art is great", &images, false, 0),
+    ];
+    let new_posts = posts
+        .iter()
+        .enumerate()
+        .map(|(i, (text, media, is_first_person, link_count))| {
+            let content = ContentSignals {
+                is_first_person: *is_first_person,
+                link_count: *link_count,
+                ..ContentSignals::default()
+            };
+            NewPost::new(
+                format!("at://{AUTHOR}/app.bsky.feed.post/3k{i}")
+                    .parse()
+                    .unwrap(),
+                text.to_string(),
+                START,
+                1.0,
+                media,
+                &content,
+                Some(AUTHOR.parse().unwrap()),
+            )
+        })
+        .collect();
+    db::insert_posts(&mut conn, new_posts).unwrap();
+
+    let stored = db::get_posts_containing(&mut conn, "").unwrap();
+    for label in [
+        TopicLabel::SharingWork,
+        TopicLabel::Question,
+        TopicLabel::Link,
+        TopicLabel::Update,
+        TopicLabel::Discussion,
+    ] {
+        let mut expected: Vec<_> = stored
+            .iter()
+            .filter(|post| post.topic() == label)
+            .map(|post| post.uri.clone())
+            .collect();
+        let mut matched = db::get_post_uris_matching(&mut conn, None, Some(label)).unwrap();
+        expected.sort();
+        matched.sort();
+        assert_eq!(matched, expected, "{label}");
+        assert_eq!(matched.len(), 1, "{label}");
+    }
+    assert_eq!(
+        db::get_post_uris_matching(&mut conn, Some("ENGINE"), Some(TopicLabel::Question))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_blocking_many_authors_at_once() {
+    let db = TempDb::new("block-many");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    accept_post(&mut conn, START);
+
+    let dids: Vec<String> = (0..2000)
+        .map(|i| format!("did:plc:spammer{i}"))
+        .chain([AUTHOR.to_string()])
+        .collect();
+    let blocked = dids
+        .iter()
+        .map(|did| NewBlockedAuthor {
+            did: did.clone(),
+            post_uri: String::new(),
+            blocked_at: START,
+        })
+        .collect();
+    assert_eq!(db::block_authors(&mut conn, blocked), Ok(dids.len()));
+    assert_eq!(
+        db::delete_posts_by_authors(&mut conn, &dids),
+        Ok(vec![POST_URI.to_string()])
+    );
+}
+
+#[test]
+fn test_trending_drops_stale_velocity() {
+    let db = TempDb::new("trending");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+
+    accept_post(&mut conn, START);
+    clock.advance(60);
+    engagement
+        .record_repost(
+            POST_URI,
+            "at://did:plc:fan/app.bsky.feed.repost/3krepost",
+            "did:plc:fan",
+        )
+        .unwrap();
+
+    let trending = |conn: &mut SqliteConnection, now: i64| {
+        let s = settings();
+        let cutoff = now - s.feed.trending.cutoff_hours * 3600;
+        let fresh_since = now - s.spam.velocity_window_hours * 3600;
+        db::get_trending_feed(conn, cutoff, fresh_since).unwrap()
+    };
+    assert_eq!(trending(&mut conn, clock.timestamp()).len(), 1);
+
+    clock.advance(settings().spam.velocity_window_hours * 3600 + 1);
+    assert!(trending(&mut conn, clock.timestamp()).is_empty());
+}
@@ -0,0 +1,270 @@
+//! A post's life from acceptance to cleanup, driven by a manual clock.
+
+mod common;
+
+use common::{accept_post, is_served, TempDb, AUTHOR, POST_URI, START};
+use devlogs_feed::archive;
+use devlogs_feed::cleanup::cleanup_expired;
+use devlogs_feed::db::{self, NewBlockedAuthor, NewLike, NewPost};
+use devlogs_feed::engagement::{EngagementTracker, NewReply};
+use devlogs_feed::maintenance::{enable_incremental_vacuum, run_maintenance};
+use devlogs_feed::rescore::rescore_recent_posts;
+use devlogs_feed::schema::{archive_posts, engagement_cache, likes, posts, replies, reposts};
+use devlogs_feed::scoring::{ContentSignals, MediaInfo};
+use devlogs_feed::settings::settings;
+use devlogs_feed::utils::time::{Clock, ManualClock};
+use diesel::prelude::*;
+use std::sync::Arc;
+
+fn like_post(conn: &mut SqliteConnection, likers: usize) {
+    let likes = (0..likers)
+        .map(|i| NewLike {
+            post_uri: POST_URI.parse().unwrap(),
+            like_uri: format!("at://did:plc:fan{i}/app.bsky.feed.like/3klike")
+                .parse()
+                .unwrap(),
+            liker_did: Some(format!("did:plc:fan{i}").parse().unwrap()),
+            liked_at: 0,
+        })
+        .collect();
+    db::insert_likes(conn, likes).unwrap();
+}
+
+fn priority(conn: &mut SqliteConnection) -> f32 {
+    db::get_posts_by_uris(conn, &[POST_URI.to_string()]).unwrap()[0].priority
+}
+
+fn rescore(conn: &mut SqliteConnection, now: i64) -> usize {
+    let since = now - settings().feed.cutoff_hours * 3600;
+    rescore_recent_posts(conn, since, now).unwrap()
+}
+
+#[test]
+fn test_post_lifecycle_leaves_no_orphans() {
+    let db = TempDb::new("lifecycle");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+
+    accept_post(&mut conn, clock.timestamp());
+    assert!(is_served(&mut conn, clock.timestamp()));
+
+    // Engagement arrives after ingest and lifts the stored priority.
+    clock.advance(600);
+    like_post(&mut conn, 25);
+    diesel::insert_into(replies::table)
+        .values(NewReply {
+            post_uri: POST_URI.to_string(),
+            reply_uri: "at://did:plc:fan0/app.bsky.feed.post/3kreply".to_string(),
+            author_did: "did:plc:fan0".to_string(),
+            timestamp: clock.timestamp(),
+        })
+        .execute(&mut conn)
+        .unwrap();
+    engagement
+        .record_repost(
+            POST_URI,
+            "at://did:plc:fan1/app.bsky.feed.repost/3krepost",
+            "did:plc:fan1",
+        )
+        .unwrap();
+    engagement.record_like(POST_URI).unwrap();
+    assert_eq!(rescore(&mut conn, clock.timestamp()), 1);
+    let trending = priority(&mut conn);
+    assert!(trending > 1.0);
+
+    // Once the velocity window passes, the boost settles on the totals and stays put.
+    clock.advance(settings().spam.velocity_window_hours * 3600 + 1);
+    rescore(&mut conn, clock.timestamp());
+    let settled = priority(&mut conn);
+    assert!(settled > 1.0);
+    assert_eq!(rescore(&mut conn, clock.timestamp()), 0);
+    assert_eq!(priority(&mut conn), settled);
+
+    // Just inside the feed window the post is still served; just past it, it isn't, and
+    // rescoring leaves it alone.
+    let expires_at = START + settings().feed.cutoff_hours * 3600;
+    clock.set(expires_at - 1);
+    assert!(is_served(&mut conn, clock.timestamp()));
+    clock.set(expires_at + 1);
+    assert!(!is_served(&mut conn, clock.timestamp()));
+    assert_eq!(rescore(&mut conn, clock.timestamp()), 0);
+
+    let report = cleanup_expired(&mut conn, &engagement, clock.timestamp()).unwrap();
+    assert_eq!(report.posts_deleted, 1);
+    assert_eq!(report.orphans_removed, 0);
+
+    let remaining = |conn: &mut SqliteConnection| {
+        [
+            posts::table.count().get_result::<i64>(conn).unwrap(),
+            likes::table.count().get_result::<i64>(conn).unwrap(),
+            replies::table.count().get_result::<i64>(conn).unwrap(),
+            reposts::table.count().get_result::<i64>(conn).unwrap(),
+            engagement_cache::table
+                .count()
+                .get_result::<i64>(conn)
+                .unwrap(),
+        ]
+    };
+    assert_eq!(remaining(&mut conn), [0; 5]);
+
+    let archived: (i32, i32, i32) = archive_posts::table
+        .filter(archive_posts::uri.eq(POST_URI))
+        .select((
+            archive_posts::like_count,
+            archive_posts::reply_count,
+            archive_posts::repost_count,
+        ))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(archived, (25, 1, 1));
+}
+
+#[test]
+fn test_cleanup_keeps_posts_inside_the_window() {
+    let db = TempDb::new("window");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+
+    accept_post(&mut conn, clock.timestamp());
+    like_post(&mut conn, 3);
+    engagement.record_like(POST_URI).unwrap();
+
+    clock.advance(settings().feed.cutoff_hours * 3600 - 60);
+    cleanup_expired(&mut conn, &engagement, clock.timestamp()).unwrap();
+
+    assert!(is_served(&mut conn, clock.timestamp()));
+    assert_eq!(likes::table.count().get_result::<i64>(&mut conn), Ok(3));
+    assert!(engagement.get_engagement(POST_URI).is_some());
+}
+
+#[test]
+fn test_cleanup_sweeps_orphaned_engagement() {
+    let db = TempDb::new("orphans");
+    let pool = db.pool();
+    let clock = ManualClock::new(START);
+    let engagement = EngagementTracker::with_clock(pool.clone(), Arc::new(clock.clone()));
+    let mut conn = pool.get().unwrap();
+
+    // Rows written without foreign keys enforced can outlive their post.
+    diesel::sql_query("PRAGMA foreign_keys = OFF")
+        .execute(&mut conn)
+        .unwrap();
+    diesel::insert_into(likes::table)
+        .values(NewLike {
+            post_uri: POST_URI.parse().unwrap(),
+            like_uri: "at://did:plc:fan0/app.bsky.feed.like/3klike"
+                .parse()
+                .unwrap(),
+            liker_did: None,
+            liked_at: 0,
+        })
+        .execute(&mut conn)
+        .unwrap();
+    diesel::insert_into(reposts::table)
+        .values((
+            reposts::post_uri.eq(POST_URI),
+            reposts::repost_uri.eq("at://did:plc:fan1/app.bsky.feed.repost/3krepost"),
+            reposts::reposter_did.eq("did:plc:fan1"),
+            reposts::timestamp.eq(clock.timestamp()),
+        ))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::sql_query("PRAGMA foreign_keys = ON")
+        .execute(&mut conn)
+        .unwrap();
+
+    let report = cleanup_expired(&mut conn, &engagement, clock.timestamp()).unwrap();
+    assert_eq!(report.posts_deleted, 0);
+    assert_eq!(report.orphans_removed, 2);
+    assert_eq!(likes::table.count().get_result::<i64>(&mut conn), Ok(0));
+    assert_eq!(reposts::table.count().get_result::<i64>(&mut conn), Ok(0));
+}
+
+#[test]
+fn test_maintenance_reclaims_deleted_posts() {
+    let db = TempDb::new("maintenance");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+
+    // Scheduled runs leave the rebuild incremental vacuuming needs to an explicit request.
+    let report = run_maintenance(&mut conn, 10_000).unwrap();
+    assert!(!report.incremental_vacuum);
+    assert_eq!(report.reclaimed_pages, 0);
+    assert!(enable_incremental_vacuum(&mut conn).unwrap());
+    assert!(!enable_incremental_vacuum(&mut conn).unwrap());
+
+    let posts: Vec<NewPost> = (0..500)
+        .map(|i| {
+            NewPost::new(
+                format!("at://did:plc:devlogger/app.bsky.feed.post/3kpost{i}")
+                    .parse()
+                    .unwrap(),
+                "Another day of tweaking the dungeon generator. ".repeat(20),
+                START,
+                1.0,
+                &MediaInfo::default(),
+                &ContentSignals::default(),
+                Some(AUTHOR.parse().unwrap()),
+            )
+        })
+        .collect();
+    db::insert_posts(&mut conn, posts).unwrap();
+    diesel::delete(posts::table).execute(&mut conn).unwrap();
+
+    let report = run_maintenance(&mut conn, 10_000).unwrap();
+    assert!(report.incremental_vacuum);
+    assert!(report.reclaimed_pages > 0);
+    assert!(!report.checkpoint_busy);
+    let wal = std::fs::metadata(format!("{}-wal", db.0.display())).unwrap();
+    assert_eq!(wal.len(), 0);
+}
+
+#[test]
+fn test_archive_day_leaves_out_blocked_and_deleted_posts() {
+    let db = TempDb::new("archive-day");
+    let pool = db.pool();
+    let mut conn = pool.get().unwrap();
+    let day_start = START - START % 86400;
+    let listed = |conn: &mut SqliteConnection| {
+        let (total, posts) = archive::day_posts(conn, day_start, 0, 10, START).unwrap();
+        (
+            total,
+            posts.into_iter().map(|post| post.uri).collect::<Vec<_>>(),
+        )
+    };
+
+    accept_post(&mut conn, START);
+    archive::archive_expiring_posts(&mut conn, START + 1, 10, START).unwrap();
+    assert_eq!(listed(&mut conn), (1, vec![POST_URI.to_string()]));
+
+    diesel::delete(posts::table).execute(&mut conn).unwrap();
+    assert_eq!(listed(&mut conn), (1, vec![POST_URI.to_string()]));
+    assert_eq!(
+        archive::day_posts(&mut conn, day_start, 1, 10, START)
+            .unwrap()
+            .1
+            .len(),
+        0
+    );
+
+    let blocked = NewBlockedAuthor {
+        did: AUTHOR.to_string(),
+        post_uri: String::new(),
+        blocked_at: START,
+    };
+    db::block_author(&mut conn, blocked).unwrap();
+    assert_eq!(listed(&mut conn), (0, Vec::new()));
+    db::unblock_author(&mut conn, AUTHOR).unwrap();
+    assert_eq!(listed(&mut conn), (1, vec![POST_URI.to_string()]));
+
+    db::delete_post(&mut conn, POST_URI).unwrap();
+    assert_eq!(listed(&mut conn), (0, Vec::new()));
+    assert_eq!(
+        archive_posts::table.count().get_result::<i64>(&mut conn),
+        Ok(0)
+    );
+}